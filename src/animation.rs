@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use egui::{Context, Id};
+
+use crate::Position;
+
+/// Whether walkers should animate things, such as inertia after dragging the map, or
+/// [`AnimatedPosition`]s. Set with [`set_motion_preference`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MotionPreference {
+    Full,
+    /// Skip all animations, jumping straight to their end.
+    Reduced,
+    /// Reduced if egui's animations are disabled, i.e. `animation_time` in its style is zero.
+    /// This is where applications following the platform's preference can plug in.
+    #[default]
+    FollowStyle,
+}
+
+fn motion_preference_id() -> Id {
+    Id::new("walkers_motion_preference")
+}
+
+/// Set the [`MotionPreference`] of all maps and animations using this context.
+pub fn set_motion_preference(ctx: &Context, preference: MotionPreference) {
+    ctx.data_mut(|data| data.insert_temp(motion_preference_id(), preference));
+}
+
+/// Whether animations should be skipped, according to the [`MotionPreference`].
+pub fn reduced_motion(ctx: &Context) -> bool {
+    let preference: MotionPreference =
+        ctx.data(|data| data.get_temp(motion_preference_id()).unwrap_or_default());
+
+    match preference {
+        MotionPreference::Full => false,
+        MotionPreference::Reduced => true,
+        MotionPreference::FollowStyle => ctx.style().animation_time <= 0.,
+    }
+}
+
+/// Longest time step that animations advance by in one frame, so that they do not jump after the
+/// app was stalled or in the background.
+const MAX_FRAME_TIME: f32 = 0.1;
+
+/// Seconds elapsed since the previous frame, by which frame-based animations (such as inertia)
+/// advance, making them behave the same regardless of the frame rate.
+pub(crate) fn frame_time(ctx: &Context) -> f32 {
+    ctx.input(|i| i.stable_dt)
+        .clamp(f32::EPSILON, MAX_FRAME_TIME)
+}
+
+/// Shape of the animation curve.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Easing {
+    Linear,
+    /// Starts fast and slows down when approaching the target.
+    EaseOut,
+    /// Starts and ends slowly.
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map linear progress (from 0 to 1) onto the curve.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4. * t.powi(3)
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+        }
+    }
+}
+
+/// Position which smoothly transitions to a new target instead of jumping there. Useful for
+/// markers fed by infrequent updates, like a GPS fix once per second. It must persist between
+/// frames, and [`AnimatedPosition::get`] gives the [`Position`] to be passed to the map, plugins,
+/// etc.
+///
+/// Timing is based on egui's input time, so all animations in the app share the same clock.
+#[derive(Clone, Debug)]
+pub struct AnimatedPosition {
+    from: Position,
+    to: Position,
+
+    /// When the current transition started, in egui's input time.
+    started: f64,
+
+    duration: Duration,
+    easing: Easing,
+}
+
+impl AnimatedPosition {
+    pub fn new(position: Position) -> Self {
+        Self {
+            from: position,
+            to: position,
+            started: f64::NEG_INFINITY,
+            duration: Duration::from_millis(500),
+            easing: Easing::default(),
+        }
+    }
+
+    /// How long does it take to reach the target. Default is 0.5s.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Start moving towards the new target, from wherever the position currently is. Does nothing
+    /// if the target did not change, so it is fine to call it on each frame.
+    pub fn set_target(&mut self, target: Position, ctx: &Context) {
+        if target != self.to {
+            let now = ctx.input(|i| i.time);
+            self.from = self.at(now);
+            self.to = target;
+            self.started = now;
+        }
+    }
+
+    /// Jump to the position, without animating.
+    pub fn set(&mut self, position: Position) {
+        self.from = position;
+        self.to = position;
+        self.started = f64::NEG_INFINITY;
+    }
+
+    /// Target of the current transition.
+    pub fn target(&self) -> Position {
+        self.to
+    }
+
+    /// Whether the position is still moving.
+    pub fn is_animating(&self, ctx: &Context) -> bool {
+        !reduced_motion(ctx) && self.progress(ctx.input(|i| i.time)) < 1.
+    }
+
+    /// Current position. Requests a repaint while the position is still moving. With
+    /// [`MotionPreference::Reduced`], this is always the target.
+    pub fn get(&self, ctx: &Context) -> Position {
+        if reduced_motion(ctx) {
+            return self.to;
+        }
+
+        let now = ctx.input(|i| i.time);
+        if self.progress(now) < 1. {
+            ctx.request_repaint();
+        }
+        self.at(now)
+    }
+
+    fn progress(&self, now: f64) -> f64 {
+        let duration = self.duration.as_secs_f64();
+        if duration <= 0. {
+            1.
+        } else {
+            ((now - self.started) / duration).clamp(0., 1.)
+        }
+    }
+
+    fn at(&self, now: f64) -> Position {
+        let t = self.easing.apply(self.progress(now));
+        self.from + (self.to - self.from) * t
+    }
+}
@@ -0,0 +1,278 @@
+//! Downloading many tiles in a single request, see [`BatchOptions`].
+
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+
+use crate::{download::Fetcher, TileId};
+
+/// Download tiles in batches from a custom endpoint, instead of one request per tile. This cuts
+/// the request overhead for self-hosted servers on high-latency links. See
+/// [`crate::HttpOptions::batch`].
+///
+/// Tiles requested while a batch is being downloaded are collected into the next one. The
+/// endpoint receives a `POST` with a JSON array of `[zoom, x, y]` triples, and responds with a
+/// `multipart/mixed` body, in which each part is a tile image with a `Content-ID: zoom/x/y`
+/// header. Tiles missing from the response are reported as errors.
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    pub url: String,
+
+    /// Maximum number of tiles in a single batch.
+    pub max_tiles: usize,
+}
+
+impl BatchOptions {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            max_tiles: 64,
+        }
+    }
+}
+
+impl Fetcher {
+    /// Download the tiles in a single request, returning those present in the response.
+    pub(crate) async fn download_batch(
+        &self,
+        batch: &BatchOptions,
+        tiles: &[TileId],
+    ) -> Result<Vec<(TileId, Vec<u8>)>, String> {
+        log::trace!("Downloading a batch of {} tiles.", tiles.len());
+
+        let mut request = self
+            .client
+            .post(&batch.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(request_body(tiles));
+
+        if let Some(user_agent) = &self.user_agent {
+            request = request.header(USER_AGENT, user_agent);
+        }
+
+        let response = self
+            .send(request)
+            .await
+            .map_err(|error| error.to_string())?
+            .error_for_status()
+            .map_err(|error| error.to_string())?;
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+
+        let body = response.bytes().await.map_err(|error| error.to_string())?;
+        parse_multipart(&content_type, &body)
+    }
+}
+
+fn request_body(tiles: &[TileId]) -> String {
+    let triples: Vec<String> = tiles
+        .iter()
+        .map(|tile| format!("[{},{},{}]", tile.zoom, tile.x, tile.y))
+        .collect();
+    format!("[{}]", triples.join(","))
+}
+
+/// Split `multipart/*` body into tiles, identified by their `Content-ID` headers.
+fn parse_multipart(content_type: &str, body: &[u8]) -> Result<Vec<(TileId, Vec<u8>)>, String> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|parameter| parameter.trim().strip_prefix("boundary="))
+        .map(|boundary| boundary.trim_matches('"'))
+        .ok_or_else(|| format!("batch response is not multipart, but '{}'", content_type))?;
+
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut tiles = Vec::new();
+    let mut rest = match find(body, &delimiter) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => return Ok(tiles),
+    };
+
+    // Each part ends where the next delimiter starts, and the last delimiter is followed by "--".
+    while !rest.starts_with(b"--") {
+        let Some(end) = find(rest, &delimiter) else {
+            break;
+        };
+
+        // Lines end with CRLF, as the RFC says, or with LF, as some servers do.
+        let part = &rest[..end];
+        let part = part
+            .strip_prefix(b"\r\n")
+            .or_else(|| part.strip_prefix(b"\n"))
+            .unwrap_or(part);
+        let part = part
+            .strip_suffix(b"\r\n")
+            .or_else(|| part.strip_suffix(b"\n"))
+            .unwrap_or(part);
+
+        if let Some(tile) = parse_part(part) {
+            tiles.push(tile);
+        } else {
+            log::warn!("Skipping a part of the batch response without a valid Content-ID.");
+        }
+
+        rest = &rest[end + delimiter.len()..];
+    }
+
+    Ok(tiles)
+}
+
+fn parse_part(part: &[u8]) -> Option<(TileId, Vec<u8>)> {
+    let (headers_end, separator) = match (find(part, b"\r\n\r\n"), find(part, b"\n\n")) {
+        (Some(crlf), Some(lf)) if lf < crlf => (lf, 2),
+        (Some(crlf), _) => (crlf, 4),
+        (None, Some(lf)) => (lf, 2),
+        (None, None) => return None,
+    };
+    let headers = std::str::from_utf8(&part[..headers_end]).ok()?;
+
+    let content_id = headers.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("content-id")
+            .then(|| value.trim().trim_start_matches('<').trim_end_matches('>'))
+    })?;
+
+    let mut numbers = content_id.split('/').map(|n| n.trim().parse::<u32>().ok());
+    let tile_id = TileId {
+        zoom: u8::try_from(numbers.next()??).ok()?,
+        x: numbers.next()??,
+        y: numbers.next()??,
+    };
+
+    Some((tile_id, part[headers_end + separator..].to_vec()))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/mixed; boundary=\"tiles\"";
+
+    fn tile(zoom: u8, x: u32, y: u32) -> TileId {
+        TileId { x, y, zoom }
+    }
+
+    #[test]
+    fn crlf() {
+        let body = b"--tiles\r\n\
+            Content-Type: image/png\r\n\
+            Content-ID: <3/1/2>\r\n\
+            \r\n\
+            first\r\n\
+            --tiles\r\n\
+            content-id: 3/1/3\r\n\
+            \r\n\
+            second\r\n\
+            --tiles--\r\n";
+        assert_eq!(
+            parse_multipart(CONTENT_TYPE, body),
+            Ok(vec![
+                (tile(3, 1, 2), b"first".to_vec()),
+                (tile(3, 1, 3), b"second".to_vec())
+            ])
+        );
+    }
+
+    #[test]
+    fn lf() {
+        let body = b"--tiles\nContent-ID: 3/1/2\n\nfirst\n--tiles--\n";
+        assert_eq!(
+            parse_multipart(CONTENT_TYPE, body),
+            Ok(vec![(tile(3, 1, 2), b"first".to_vec())])
+        );
+    }
+
+    #[test]
+    fn preamble_and_epilogue() {
+        let body = b"This is a preamble.\r\n\
+            --tiles\r\n\
+            Content-ID: 0/0/0\r\n\
+            \r\n\
+            tile\r\n\
+            --tiles--\r\n\
+            This is an epilogue, with --tiles in it.";
+        assert_eq!(
+            parse_multipart("multipart/mixed;boundary=tiles", body),
+            Ok(vec![(tile(0, 0, 0), b"tile".to_vec())])
+        );
+    }
+
+    #[test]
+    fn binary_content() {
+        let mut body = b"--tiles\r\nContent-ID: 1/0/1\r\n\r\n".to_vec();
+        let image = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0, b'\n', b'\n', 0xff];
+        body.extend_from_slice(&image);
+        body.extend_from_slice(b"\r\n--tiles--");
+        assert_eq!(
+            parse_multipart(CONTENT_TYPE, &body),
+            Ok(vec![(tile(1, 0, 1), image.to_vec())])
+        );
+    }
+
+    #[test]
+    fn missing_headers() {
+        let body = b"--tiles\r\n\
+            Content-Type: image/png\r\n\
+            \r\n\
+            anonymous\r\n\
+            --tiles\r\n\
+            Content-ID: 2/1/x\r\n\
+            \r\n\
+            invalid\r\n\
+            --tiles\r\n\
+            no headers at all\r\n\
+            --tiles\r\n\
+            Content-ID: 300/1/1\r\n\
+            \r\n\
+            too deep\r\n\
+            --tiles\r\n\
+            Content-ID: 2/1/1\r\n\
+            \r\n\
+            valid\r\n\
+            --tiles--";
+        assert_eq!(
+            parse_multipart(CONTENT_TYPE, body),
+            Ok(vec![(tile(2, 1, 1), b"valid".to_vec())])
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let body = b"--tiles\r\n\
+            Content-ID: 2/1/1\r\n\
+            \r\n\
+            complete\r\n\
+            --tiles\r\n\
+            Content-ID: 2/1/2\r\n\
+            \r\n\
+            cut off";
+        assert_eq!(
+            parse_multipart(CONTENT_TYPE, body),
+            Ok(vec![(tile(2, 1, 1), b"complete".to_vec())])
+        );
+        assert_eq!(parse_multipart(CONTENT_TYPE, b"--til"), Ok(Vec::new()));
+        assert_eq!(parse_multipart(CONTENT_TYPE, b""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn not_multipart() {
+        assert!(parse_multipart("image/png", b"--tiles--").is_err());
+    }
+
+    #[test]
+    fn request() {
+        assert_eq!(
+            request_body(&[tile(3, 1, 2), tile(4, 5, 6)]),
+            "[[3,1,2],[4,5,6]]"
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use std::time::Duration;
+
+use egui::{Context, Id, Ui};
+
+use crate::{
+    animation::{reduced_motion, Easing},
+    zoom::Zoom,
+    MapMemory, Position,
+};
+
+/// How long it takes to fly to a [`View`].
+const FLIGHT_DURATION: Duration = Duration::from_secs(1);
+
+/// What the map is showing, i.e. its center, zoom, bearing and tilt.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct View {
+    pub center: Position,
+    pub zoom: f64,
+    /// See [`MapMemory::set_bearing`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bearing: f64,
+    /// See [`MapMemory::set_tilt`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tilt: f64,
+    /// Names of the layers shown along with the view, for applications which let users toggle
+    /// them. The map does not know about layers, so [`View::apply`] leaves them to the
+    /// application.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub layers: Option<Vec<String>>,
+}
+
+impl View {
+    /// Current view of the map. `my_position` must be the same as passed to the map, as it is
+    /// the center until the map gets detached from it.
+    pub fn from_memory(map_memory: &MapMemory, my_position: Position) -> Self {
+        Self {
+            center: map_memory.detached().unwrap_or(my_position),
+            zoom: map_memory.zoom(),
+            bearing: map_memory.bearing(),
+            tilt: map_memory.tilt(),
+            layers: None,
+        }
+    }
+
+    pub fn with_layers(mut self, layers: Vec<String>) -> Self {
+        self.layers = Some(layers);
+        self
+    }
+
+    /// Make the map fly to this view, over a second. The flight is skipped with
+    /// [`crate::MotionPreference::Reduced`], and stops once the map is dragged or zoomed.
+    pub fn apply(&self, map_memory: &mut MapMemory) {
+        map_memory.flight = Some(Flight {
+            to: self.clone(),
+            from: None,
+            zoom: None,
+        });
+    }
+
+    /// Make the map show this view at once.
+    fn jump(&self, map_memory: &mut MapMemory) {
+        map_memory.center_at(self.center);
+        match Zoom::try_from(self.zoom) {
+            Ok(zoom) => map_memory.zoom = zoom,
+            Err(err) => log::warn!("Could not set zoom to {}: {}", self.zoom, err),
+        }
+        map_memory.set_bearing(self.bearing);
+        map_memory.set_tilt(self.tilt);
+    }
+
+    /// View `t` of the way from this one to the other, turning the shorter way around.
+    fn lerp(&self, other: &View, t: f64) -> View {
+        let turn = (other.bearing - self.bearing + 180.).rem_euclid(360.) - 180.;
+        View {
+            center: self.center + (other.center - self.center) * t,
+            zoom: self.zoom + (other.zoom - self.zoom) * t,
+            bearing: self.bearing + turn * t,
+            tilt: self.tilt + (other.tilt - self.tilt) * t,
+            layers: other.layers.clone(),
+        }
+    }
+}
+
+/// Transition of the map towards a [`View`], see [`View::apply`].
+#[derive(Clone)]
+pub(crate) struct Flight {
+    to: View,
+
+    /// View the flight started from, and when, in egui's input time. Known once the map is
+    /// shown, as it may follow `my_position`.
+    from: Option<(View, f64)>,
+
+    /// Zoom set by the flight in the previous frame, to tell whether the user zoomed since.
+    zoom: Option<f64>,
+}
+
+impl MapMemory {
+    /// Advance the flight to a [`View`], if there is one. Returns whether the map moved.
+    pub(crate) fn update_flight(&mut self, ctx: &Context, my_position: Position) -> bool {
+        let Some(flight) = self.flight.take() else {
+            return false;
+        };
+
+        // User took over.
+        if !self.center_mode.is_at_rest() || flight.zoom.is_some_and(|zoom| zoom != self.zoom()) {
+            return false;
+        }
+
+        if reduced_motion(ctx) {
+            flight.to.jump(self);
+            return true;
+        }
+
+        let now = ctx.input(|i| i.time);
+        let (from, started) = flight
+            .from
+            .unwrap_or_else(|| (View::from_memory(self, my_position), now));
+        let progress = (now - started) / FLIGHT_DURATION.as_secs_f64();
+        from.lerp(&flight.to, Easing::EaseInOut.apply(progress))
+            .jump(self);
+
+        if progress < 1. {
+            self.flight = Some(Flight {
+                to: flight.to,
+                from: Some((from, started)),
+                zoom: Some(self.zoom()),
+            });
+            ctx.request_repaint();
+        }
+        true
+    }
+}
+
+/// Named [`View`]s, in the order they were added. With the `serde` feature enabled, it can be
+/// stored e.g. in eframe's storage.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bookmarks {
+    views: Vec<(String, View)>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store the view under the name, replacing the previous one with the same name.
+    pub fn save(&mut self, name: impl Into<String>, view: View) {
+        let name = name.into();
+        match self
+            .views
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, existing)) => *existing = view,
+            None => self.views.push((name, view)),
+        }
+    }
+
+    /// Remove the view, returning it if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<View> {
+        let index = self
+            .views
+            .iter()
+            .position(|(existing, _)| existing == name)?;
+        Some(self.views.remove(index).1)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&View> {
+        self.views
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, view)| view)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &View)> {
+        self.views.iter().map(|(name, view)| (name.as_str(), view))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    /// Make the map show the named view. Returns `false` if there is no such view.
+    pub fn go_to(&self, name: &str, map_memory: &mut MapMemory) -> bool {
+        match self.get(name) {
+            Some(view) => {
+                view.apply(map_memory);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Simple UI listing the bookmarks, with buttons to go to or remove each of them, and a text
+    /// box to save the current view. `my_position` must be the same as passed to the map.
+    pub fn ui(&mut self, ui: &mut Ui, map_memory: &mut MapMemory, my_position: Position) {
+        let mut removed = None;
+
+        for (name, view) in &self.views {
+            ui.horizontal(|ui| {
+                if ui.button(name).clicked() {
+                    view.apply(map_memory);
+                }
+                if ui.small_button("🗑").clicked() {
+                    removed = Some(name.clone());
+                }
+            });
+        }
+
+        if let Some(name) = removed {
+            self.remove(&name);
+        }
+
+        let id = ui.make_persistent_id(Id::new("walkers_bookmarks"));
+        let mut name: String = ui.data_mut(|data| data.get_temp(id).unwrap_or_default());
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut name).hint_text("Name"));
+
+            let enabled = !name.trim().is_empty();
+            if ui.add_enabled(enabled, egui::Button::new("Save")).clicked() {
+                self.save(name.trim(), View::from_memory(map_memory, my_position));
+                name.clear();
+            }
+        });
+
+        ui.data_mut(|data| data.insert_temp(id, name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lon_lat;
+
+    /// Advance the flight in a frame at given time, in seconds.
+    fn frame(ctx: &Context, memory: &mut MapMemory, time: f64, my_position: Position) -> bool {
+        let mut moved = false;
+        let input = egui::RawInput {
+            time: Some(time),
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| moved = memory.update_flight(ctx, my_position));
+        moved
+    }
+
+    fn view(lon: f64, zoom: f64, bearing: f64) -> View {
+        View {
+            center: pos_from_lon_lat(lon, 0.),
+            zoom,
+            bearing,
+            tilt: 0.,
+            layers: None,
+        }
+    }
+
+    #[test]
+    fn current_view_follows_my_position() {
+        let mut memory = MapMemory::default();
+        memory.set_bearing(30.);
+        let my_position = pos_from_lon_lat(21., 52.);
+
+        let current = View::from_memory(&memory, my_position);
+        assert_eq!(current.center, my_position);
+        assert_eq!(current.bearing, 30.);
+
+        memory.center_at(pos_from_lon_lat(1., 2.));
+        let detached = View::from_memory(&memory, my_position).center;
+        assert!((detached.x - 1.).abs() < 1e-9 && (detached.y - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flies_to_the_view() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        view(0., 10., 350.).jump(&mut memory);
+        view(10., 14., 10.).apply(&mut memory);
+
+        let my_position = pos_from_lon_lat(50., 50.);
+        assert!(frame(&ctx, &mut memory, 1., my_position));
+        assert_eq!(memory.zoom(), 10.);
+
+        // Halfway there, turning through north rather than all the way around.
+        assert!(frame(&ctx, &mut memory, 1.5, my_position));
+        let halfway = View::from_memory(&memory, my_position);
+        assert!((halfway.center.x - 5.).abs() < 1e-6);
+        assert!((halfway.zoom - 12.).abs() < 1e-9);
+        assert!(halfway.bearing.abs() < 1e-9 || (halfway.bearing - 360.).abs() < 1e-9);
+
+        assert!(frame(&ctx, &mut memory, 2., my_position));
+        assert!(memory.flight.is_none());
+        assert_eq!(memory.zoom(), 14.);
+        assert!((memory.bearing() - 10.).abs() < 1e-9);
+        assert!(!frame(&ctx, &mut memory, 2.5, my_position));
+    }
+
+    #[test]
+    fn zooming_stops_the_flight() {
+        let ctx = Context::default();
+        let mut memory = MapMemory::default();
+        view(10., 14., 0.).apply(&mut memory);
+
+        let my_position = pos_from_lon_lat(0., 0.);
+        frame(&ctx, &mut memory, 1., my_position);
+        // The way scrolling zooms, bypassing `MapMemory::set_zoom`.
+        memory.camera(my_position).zoom_about(egui::Vec2::ZERO, 1.);
+        assert!(!frame(&ctx, &mut memory, 1.5, my_position));
+        assert!(memory.flight.is_none());
+    }
+}
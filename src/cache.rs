@@ -0,0 +1,427 @@
+//! Storage for downloaded tiles, consulted before reaching the network.
+
+use std::{num::NonZeroUsize, sync::Mutex};
+
+use lru::LruCache;
+
+use crate::{sources::SourceParameters, TileId};
+
+/// Storage of raw (encoded) tile images. Implement it to plug your own storage, like sqlite, into
+/// [`crate::HttpTiles`] via [`crate::HttpOptions::tile_cache`].
+///
+/// Methods are called from the IO thread, hence the `Send + Sync` requirement. Entries are never
+/// considered stale, so unlike [`crate::HttpOptions::cache`], HTTP caching headers are not
+/// respected.
+pub trait TileCache: Send + Sync {
+    /// Get the tile stored under given key.
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    /// Store the tile under given key.
+    fn put(&self, key: &str, data: &[u8]);
+
+    /// Remove all tiles of the source with given [`crate::sources::TileSource::cache_id`]. Keys
+    /// of such tiles start with the id followed by the `U+001F` control character, which never
+    /// appears in keys which are tile URLs. Does nothing by default.
+    fn invalidate_source(&self, _source_id: &str) {}
+}
+
+/// Separates the source's id from the rest of the keys of tiles of sources with
+/// a [`crate::sources::TileSource::cache_id`]. It is a control character, so it never appears in
+/// keys which are tile URLs.
+pub(crate) const SOURCE_SEPARATOR: char = '\u{1f}';
+
+/// Key of the tile from the source with a [`crate::sources::TileSource::cache_id`].
+pub(crate) fn source_key(
+    source_id: &str,
+    tile_id: TileId,
+    parameters: &SourceParameters,
+) -> String {
+    let mut key = format!(
+        "{}{}{}/{}/{}",
+        source_id, SOURCE_SEPARATOR, tile_id.zoom, tile_id.x, tile_id.y
+    );
+    if !parameters.is_empty() {
+        // Percent-encoded, so that names and values containing `=` or `&` are not ambiguous.
+        let mut url = reqwest::Url::parse("key:").expect("valid URL");
+        url.query_pairs_mut().extend_pairs(parameters);
+        key.push('?');
+        key.push_str(url.query().unwrap_or_default());
+    }
+    key
+}
+
+/// Id of the source which the tile stored under given key comes from, or `None` if the key is
+/// the tile's URL.
+pub(crate) fn source_of(key: &str) -> Option<&str> {
+    key.split_once(SOURCE_SEPARATOR)
+        .map(|(source_id, _)| source_id)
+}
+
+/// Keeps the most recently used tiles in memory.
+pub struct MemoryCache {
+    tiles: Mutex<LruCache<String, Vec<u8>>>,
+}
+
+impl MemoryCache {
+    /// Create a cache holding up to `capacity` tiles.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            tiles: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+impl TileCache for MemoryCache {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.tiles.lock().ok()?.get(key).cloned()
+    }
+
+    fn put(&self, key: &str, data: &[u8]) {
+        if let Ok(mut tiles) = self.tiles.lock() {
+            tiles.put(key.to_owned(), data.to_owned());
+        }
+    }
+
+    fn invalidate_source(&self, source_id: &str) {
+        if let Ok(mut tiles) = self.tiles.lock() {
+            let keys: Vec<String> = tiles
+                .iter()
+                .filter(|(key, _)| source_of(key) == Some(source_id))
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            for key in keys {
+                tiles.pop(&key);
+            }
+        }
+    }
+}
+
+/// Stores each tile as a separate file in given directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DiskCache {
+    path: std::path::PathBuf,
+    compressed: bool,
+    max_size: Option<u64>,
+
+    /// Files of the tiles, once they are listed. See [`DiskCache::max_size`].
+    files: Mutex<Option<Files>>,
+}
+
+/// Sizes of the files of a [`DiskCache`], from the least to the most recently used, so that
+/// they need not be listed again to find the ones to remove.
+#[cfg(not(target_arch = "wasm32"))]
+struct Files {
+    sizes: LruCache<std::path::PathBuf, u64>,
+    total: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DiskCache {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            compressed: false,
+            max_size: None,
+            files: Mutex::new(None),
+        }
+    }
+
+    /// Limit the total size of the stored tiles, in bytes. Once it is exceeded, the least
+    /// recently used tiles are removed, judging by their files' modification times, which are
+    /// updated whenever a tile is read. Unlimited by default.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Store new tiles compressed with gzip. Worth it for layers whose images still compress
+    /// well, e.g. PNGs with large flat areas. Compressed and uncompressed tiles can be mixed
+    /// in the same directory, as they are told apart when read.
+    pub fn compressed(mut self, compressed: bool) -> Self {
+        self.compressed = compressed;
+        self
+    }
+
+    /// Tiles of sources with an id are kept in a separate directory, so that they can be removed
+    /// all at once.
+    fn file(&self, key: &str) -> std::path::PathBuf {
+        let file = format!("{:016x}", fnv1a(key.as_bytes()));
+        match source_of(key) {
+            Some(source_id) => self.source_directory(source_id).join(file),
+            None => self.path.join(file),
+        }
+    }
+
+    fn source_directory(&self, source_id: &str) -> std::path::PathBuf {
+        self.path
+            .join(format!("{:016x}", fnv1a(source_id.as_bytes())))
+    }
+
+    /// Files of all tiles, listed once, ordered by their modification times.
+    fn list_files(&self) -> Files {
+        let mut files = Vec::new();
+        let mut directories = vec![self.path.clone()];
+        while let Some(directory) = directories.pop() {
+            let Ok(entries) = std::fs::read_dir(&directory) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    directories.push(entry.path());
+                } else if let Ok(modified) = metadata.modified() {
+                    files.push((entry.path(), modified, metadata.len()));
+                }
+            }
+        }
+
+        files.sort_by_key(|(_, modified, _)| *modified);
+        let mut sizes = LruCache::unbounded();
+        for (path, _, length) in files {
+            sizes.put(path, length);
+        }
+        Files {
+            total: sizes.iter().map(|(_, length)| length).sum(),
+            sizes,
+        }
+    }
+
+    /// Mark the file as the most recently used one.
+    fn touch(&self, file: &std::path::Path) {
+        if let Ok(mut files) = self.files.lock() {
+            if let Some(files) = files.as_mut() {
+                files.sizes.get(file);
+            }
+        }
+    }
+
+    /// Account for the file being written with `length` bytes, and remove the least recently
+    /// used files if the cache got too large. To avoid doing that on every write, it is shrunk
+    /// to 90% of its maximum size.
+    fn grow(&self, file: std::path::PathBuf, length: u64) {
+        let Some(max_size) = self.max_size else {
+            return;
+        };
+        let Ok(mut files) = self.files.lock() else {
+            return;
+        };
+
+        // Listing the files includes the new one already.
+        let files = match files.as_mut() {
+            Some(files) => {
+                let previous = files.sizes.put(file, length).unwrap_or(0);
+                files.total = files.total - previous.min(files.total) + length;
+                files
+            }
+            None => files.insert(self.list_files()),
+        };
+        if files.total <= max_size {
+            return;
+        }
+
+        while files.total > max_size / 10 * 9 {
+            let Some((path, length)) = files.sizes.pop_lru() else {
+                break;
+            };
+            match std::fs::remove_file(&path) {
+                Ok(()) => files.total -= length,
+                Err(error) => log::warn!("Could not remove '{}': {}.", path.display(), error),
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TileCache for DiskCache {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let file = self.file(key);
+        let data = std::fs::read(&file).ok()?;
+
+        if self.max_size.is_some() {
+            // Mark the tile as recently used, also for the next time the files are listed.
+            let _ = std::fs::File::options()
+                .write(true)
+                .open(&file)
+                .and_then(|file| file.set_modified(std::time::SystemTime::now()));
+            self.touch(&file);
+        }
+
+        if data.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            std::io::Read::read_to_end(
+                &mut flate2::read::GzDecoder::new(data.as_slice()),
+                &mut decompressed,
+            )
+            .map_err(|error| log::warn!("Could not decompress '{}': {}.", key, error))
+            .ok()?;
+            Some(decompressed)
+        } else {
+            Some(data)
+        }
+    }
+
+    fn put(&self, key: &str, data: &[u8]) {
+        let data = if self.compressed {
+            compress(data)
+        } else {
+            Ok(data.to_owned())
+        };
+
+        let file = self.file(key);
+        match data.and_then(|data| {
+            if let Some(directory) = file.parent() {
+                std::fs::create_dir_all(directory)?;
+            }
+            std::fs::write(&file, &data)?;
+            Ok(data.len() as u64)
+        }) {
+            Ok(written) => self.grow(file, written),
+            Err(error) => log::warn!("Could not store '{}' in the disk cache: {}.", key, error),
+        }
+    }
+
+    fn invalidate_source(&self, source_id: &str) {
+        let directory = self.source_directory(source_id);
+        if let Err(error) = std::fs::remove_dir_all(&directory) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Could not remove '{}': {}.", directory.display(), error);
+            }
+        }
+
+        if let Ok(mut files) = self.files.lock() {
+            if let Some(files) = files.as_mut() {
+                let removed: Vec<_> = files
+                    .sizes
+                    .iter()
+                    .filter(|(path, _)| path.starts_with(&directory))
+                    .map(|(path, _)| path.clone())
+                    .collect();
+                for path in removed {
+                    files.total -= files.sizes.pop(&path).unwrap_or(0);
+                }
+            }
+        }
+    }
+}
+
+/// First bytes of a gzip stream. Neither PNG nor JPEG images start with them.
+#[cfg(not(target_arch = "wasm32"))]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[cfg(not(target_arch = "wasm32"))]
+fn compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// FNV-1a hash. Unlike [`std::hash::DefaultHasher`], it is guaranteed to be stable, so file names
+/// survive updates of the Rust compiler.
+#[cfg(not(target_arch = "wasm32"))]
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(zoom: u8, x: u32, y: u32) -> TileId {
+        TileId { x, y, zoom }
+    }
+
+    #[test]
+    fn keys() {
+        let key = source_key("osm", tile(3, 1, 2), &SourceParameters::new());
+        assert_eq!(source_of(&key), Some("osm"));
+
+        let parameters = SourceParameters::from([("lang".to_owned(), "pl".to_owned())]);
+        let key = source_key("mapbox/streets", tile(3, 1, 2), &parameters);
+        assert_eq!(source_of(&key), Some("mapbox/streets"));
+        assert!(key.ends_with("3/1/2?lang=pl"));
+
+        let ambiguous = |name: &str, value: &str| {
+            let parameters = SourceParameters::from([(name.to_owned(), value.to_owned())]);
+            source_key("osm", tile(3, 1, 2), &parameters)
+        };
+        assert_ne!(ambiguous("a", "b&c=d"), ambiguous("a=b&c", "d"));
+        assert!(ambiguous("a", "b&c=d").ends_with("?a=b%26c%3Dd"));
+
+        assert_eq!(source_of("https://tile.openstreetmap.org/3/1/2.png"), None);
+        assert_eq!(source_of("relative/3/1/2.png"), None);
+    }
+
+    /// Directory of its own for each test, removed even if the test fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    struct TempDir(std::path::PathBuf);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            Self(std::env::temp_dir().join(format!(
+                "walkers-{}-{}-{}",
+                name,
+                std::process::id(),
+                nanos
+            )))
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn disk_cache_evicts_least_recently_used() {
+        use std::time::{Duration, SystemTime};
+
+        let directory = TempDir::new("disk-cache");
+        let cache = DiskCache::new(&directory.0).max_size(1000);
+        let key = |i: u32| source_key("test", tile(10, i, 0), &SourceParameters::new());
+
+        // Spaced well apart, so that the order is known even on filesystems with coarse
+        // modification times. All are in the past, before any tile gets read.
+        let modified = |i: u32| SystemTime::now() - Duration::from_secs(3600 - 60 * i as u64);
+        let set_modified = |i: u32| {
+            std::fs::File::options()
+                .write(true)
+                .open(cache.file(&key(i)))
+                .and_then(|file| file.set_modified(modified(i)))
+                .unwrap();
+        };
+
+        for i in 0..4 {
+            cache.put(&key(i), &[i as u8; 300]);
+            set_modified(i);
+        }
+
+        // The oldest tile went first.
+        assert_eq!(cache.get(&key(0)), None);
+        assert_eq!(cache.get(&key(1)), Some(vec![1; 300]));
+
+        // Reading the second tile made it recently used, so the third goes instead.
+        cache.put(&key(4), &[4; 300]);
+        assert_eq!(cache.get(&key(1)), Some(vec![1; 300]));
+        assert_eq!(cache.get(&key(2)), None);
+        assert_eq!(cache.get(&key(3)), Some(vec![3; 300]));
+        assert_eq!(cache.get(&key(4)), Some(vec![4; 300]));
+
+        cache.invalidate_source("test");
+        assert_eq!(cache.get(&key(4)), None);
+    }
+}
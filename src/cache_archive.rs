@@ -0,0 +1,218 @@
+//! Moving the contents of a [`TileCache`] between machines as zip archives, e.g. to pre-load
+//! devices going offline.
+
+use std::{
+    io::{self, Read, Seek, Write},
+    ops::RangeInclusive,
+};
+
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    cache::source_key,
+    sources::{source_tile_id, SourceParameters, TileSource},
+    BoundingBox, TileCache, TileId,
+};
+
+/// Write tiles of the source, which cover the area at given zoom levels and are present in the
+/// cache, into a zip archive, as `zoom/x/y` entries. Zoom levels are those of the source's tiles.
+/// Archives of more than 65535 tiles, or larger than 4 GiB, are written as ZIP64. Returns the
+/// number of exported tiles.
+///
+/// [`TileCache`] cannot list its contents, so only tiles within the area are looked up. Keep it
+/// reasonably small, as the number of tiles grows fourfold with each zoom level.
+pub fn export_tiles<S: TileSource>(
+    cache: &dyn TileCache,
+    source: &S,
+    area: BoundingBox,
+    zoom: RangeInclusive<u8>,
+    writer: impl Write,
+) -> io::Result<usize> {
+    let mut archive = ZipWriter::new_stream(writer);
+    // Tiles are already compressed images, so deflating them again would gain little.
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut exported = 0;
+
+    for zoom in zoom {
+        let top_left = TileId::from_position(
+            crate::Position {
+                x: area.min().x,
+                y: area.max().y,
+            },
+            zoom,
+        );
+        let bottom_right = TileId::from_position(
+            crate::Position {
+                x: area.max().x,
+                y: area.min().y,
+            },
+            zoom,
+        );
+
+        for x in top_left.x..=bottom_right.x {
+            for y in top_left.y..=bottom_right.y {
+                let tile_id = TileId { x, y, zoom };
+                let data = cache_key(source, tile_id).and_then(|key| cache.get(&key));
+                if let Some(data) = data {
+                    archive.start_file(format!("{}/{}/{}", zoom, x, y), options)?;
+                    archive.write_all(&data)?;
+                    exported += 1;
+                }
+            }
+        }
+    }
+
+    archive.finish()?.flush()?;
+    Ok(exported)
+}
+
+/// Put tiles from a zip archive, e.g. made by [`export_tiles`], into the cache, as if they were
+/// downloaded from the source. Entries are expected to be named `zoom/x/y`, optionally with a
+/// file extension, and either stored or deflated. Returns the number of imported tiles.
+pub fn import_tiles<S: TileSource>(
+    cache: &dyn TileCache,
+    source: &S,
+    reader: impl Read + Seek,
+) -> io::Result<usize> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut imported = 0;
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let Some(tile_id) = parse_tile_name(entry.name()) else {
+            log::debug!("Skipping '{}', which is not a tile.", entry.name());
+            continue;
+        };
+
+        let Some(key) = cache_key(source, tile_id) else {
+            log::debug!("Skipping '{}', which is not on the map.", entry.name());
+            continue;
+        };
+
+        // Checksum is verified once the entry is read to the end.
+        let mut data = Vec::new();
+        match entry.read_to_end(&mut data) {
+            Ok(_) => {
+                cache.put(&key, &data);
+                imported += 1;
+            }
+            Err(error) => log::warn!("Could not import '{}': {}.", entry.name(), error),
+        }
+    }
+
+    Ok(imported)
+}
+
+/// Key under which the tile is stored, as if downloaded without any source parameters. `None`
+/// if the source cannot number the tile.
+fn cache_key(source: &impl TileSource, tile_id: TileId) -> Option<String> {
+    match source.cache_id() {
+        Some(source_id) => Some(source_key(&source_id, tile_id, &SourceParameters::new())),
+        None => source_tile_id(source, tile_id).map(|tile_id| source.tile_url(tile_id)),
+    }
+}
+
+fn parse_tile_name(name: &str) -> Option<TileId> {
+    let mut segments = name.rsplit('/');
+    let y = segments.next()?;
+    let y = y.split_once('.').map_or(y, |(y, _)| y).parse().ok()?;
+    let x = segments.next()?.parse().ok()?;
+    let zoom = segments.next()?.parse().ok()?;
+    Some(TileId { x, y, zoom })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, num::NonZeroUsize};
+
+    use super::*;
+    use crate::{pos_from_lon_lat, sources::OpenStreetMap, MemoryCache};
+
+    fn cache(capacity: usize) -> MemoryCache {
+        MemoryCache::new(NonZeroUsize::new(capacity).unwrap())
+    }
+
+    fn key(zoom: u8, x: u32, y: u32) -> String {
+        cache_key(&OpenStreetMap, TileId { x, y, zoom }).unwrap()
+    }
+
+    fn world() -> BoundingBox {
+        BoundingBox::new(pos_from_lon_lat(-180., -85.), pos_from_lon_lat(179.99, 85.))
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = cache(16);
+        source.put(&key(1, 0, 1), b"first");
+        source.put(&key(1, 1, 1), b"");
+        source.put(&key(2, 0, 0), b"not exported");
+
+        let mut archive = Vec::new();
+        let exported = export_tiles(&source, &OpenStreetMap, world(), 0..=1, &mut archive);
+        assert_eq!(exported.unwrap(), 2);
+
+        let target = cache(16);
+        let imported = import_tiles(&target, &OpenStreetMap, Cursor::new(archive));
+        assert_eq!(imported.unwrap(), 2);
+        assert_eq!(target.get(&key(1, 0, 1)), Some(b"first".to_vec()));
+        assert_eq!(target.get(&key(1, 1, 1)), Some(Vec::new()));
+        assert_eq!(target.get(&key(2, 0, 0)), None);
+    }
+
+    #[test]
+    fn more_tiles_than_fit_in_plain_zip() {
+        // All tiles of zoom 8 are one more than the 16-bit count of entries can hold.
+        let tiles = 1 << 16;
+        let source = cache(tiles);
+        for x in 0..256 {
+            for y in 0..256 {
+                source.put(&key(8, x, y), b"tile");
+            }
+        }
+
+        let mut archive = Vec::new();
+        let exported = export_tiles(&source, &OpenStreetMap, world(), 8..=8, &mut archive);
+        assert_eq!(exported.unwrap(), tiles);
+
+        let target = cache(tiles);
+        let imported = import_tiles(&target, &OpenStreetMap, Cursor::new(archive));
+        assert_eq!(imported.unwrap(), tiles);
+        assert_eq!(target.get(&key(8, 255, 255)), Some(b"tile".to_vec()));
+    }
+
+    #[test]
+    fn skips_corrupted_tiles() {
+        let source = cache(1);
+        source.put(&key(0, 0, 0), b"tile");
+        let mut archive = Vec::new();
+        export_tiles(&source, &OpenStreetMap, world(), 0..=0, &mut archive).unwrap();
+
+        let data = archive.windows(4).position(|w| w == b"tile").unwrap();
+        archive[data] ^= 0xff;
+
+        let target = cache(1);
+        let imported = import_tiles(&target, &OpenStreetMap, Cursor::new(archive));
+        assert_eq!(imported.unwrap(), 0);
+        assert_eq!(target.get(&key(0, 0, 0)), None);
+    }
+
+    #[test]
+    fn parses_tile_names() {
+        assert_eq!(
+            parse_tile_name("tiles/12/2200/1343.png"),
+            Some(TileId {
+                x: 2200,
+                y: 1343,
+                zoom: 12
+            })
+        );
+        assert_eq!(parse_tile_name("readme.txt"), None);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let import = |bytes: &[u8]| import_tiles(&cache(1), &OpenStreetMap, Cursor::new(bytes));
+        assert!(import(b"not a zip archive at all").is_err());
+        assert!(import(b"").is_err());
+    }
+}
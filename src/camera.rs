@@ -0,0 +1,209 @@
+use egui::Vec2;
+
+use crate::{
+    center::Center,
+    map_memory::MapMemory,
+    projector::ProjectorType,
+    units::{AdjustedPosition, Position},
+};
+
+/// Programmatic control over what the map shows, obtained by [`MapMemory::camera`]. It takes
+/// care of keeping the position accurate while it is moved by pixels and zoomed, which is
+/// otherwise tricky for positions far from the map's origin.
+pub struct Camera<'a> {
+    memory: &'a mut MapMemory,
+    my_position: Position,
+}
+
+impl MapMemory {
+    /// [`Camera`] of the map. `my_position` must be the same as passed to the map, as it is
+    /// the center until the map gets detached from it.
+    pub fn camera(&mut self, my_position: Position) -> Camera<'_> {
+        Camera {
+            memory: self,
+            my_position,
+        }
+    }
+}
+
+impl Camera<'_> {
+    /// Position at the center of the map.
+    pub fn center(&self) -> Position {
+        center(self.memory, self.my_position)
+    }
+
+    /// Center exactly at the position, detaching from `my_position`.
+    pub fn set_center(&mut self, position: Position) {
+        self.memory.center_at(position);
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.memory.zoom()
+    }
+
+    /// Move the map's content by given number of points, the same way dragging does. E.g.
+    /// positive `x` reveals what is to the west (or to the left, for local maps).
+    pub fn translate_pixels(&mut self, offset: Vec2) {
+        let offset = self.memory.screen_transform().invert(offset);
+        let center = self.center();
+        self.memory.center_mode = Center::Exact {
+            pos: AdjustedPosition::from(center).shift(offset),
+        };
+    }
+
+    /// Change the zoom level by `delta`, keeping the position under the `anchor` in place. The
+    /// anchor is relative to the map's center, in points, e.g. it can be the mouse pointer's
+    /// position minus the center of the map widget's rect. Zoom stays unchanged if the result
+    /// would be out of range.
+    pub fn zoom_about(&mut self, anchor: Vec2, delta: f64) {
+        let projection = self.memory.projection_type.clone();
+        let anchor = self.memory.screen_transform().invert(anchor);
+
+        // Move the anchored location to the center, adjust the zoom, and move the location back
+        // to where it was on the screen. Zooming about the center itself does not detach the map
+        // from `my_position`.
+        if anchor != Vec2::ZERO {
+            let center = self.center();
+            self.memory.center_mode = Center::Exact {
+                pos: AdjustedPosition::from(center).shift(-anchor),
+            }
+            .zero_offset(&projection, self.memory.zoom());
+        }
+
+        self.memory.zoom.zoom_by(delta);
+
+        // Offset gets invalidated by zooming, so get rid of it.
+        self.memory.center_mode = self
+            .memory
+            .center_mode
+            .clone()
+            .zero_offset(&projection, self.memory.zoom())
+            .shift(anchor);
+    }
+}
+
+/// Position at the center of the map. See [`Camera::center`].
+pub(crate) fn center(memory: &MapMemory, my_position: Position) -> Position {
+    match memory.center_mode.get_adjusted_position() {
+        Some(pos) => unadjusted_position(&pos, &memory.projection_type, memory.zoom()),
+        None => my_position,
+    }
+}
+
+/// Position which the adjusted one stands for, using the given projection.
+pub(crate) fn unadjusted_position(
+    pos: &AdjustedPosition,
+    projection: &ProjectorType,
+    zoom: f64,
+) -> Position {
+    match projection {
+        ProjectorType::Global => pos.global_unadjusted_position(zoom),
+        ProjectorType::Local(transform) => pos.local_unadjusted_position(zoom, transform),
+        ProjectorType::Custom(projection) => {
+            pos.custom_unadjusted_position(zoom, projection.as_ref())
+        }
+    }
+}
+
+/// Move the offset into the position, using the given projection. Offset is only valid for the
+/// zoom level it was made at, so this must be done before zooming.
+pub(crate) fn zero_offset(
+    pos: AdjustedPosition,
+    projection: &ProjectorType,
+    zoom: f64,
+) -> AdjustedPosition {
+    unadjusted_position(&pos, projection, zoom).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{vec2, Rect};
+
+    use super::*;
+    use crate::{pos_from_lon_lat, LocalTransform, Projector};
+
+    fn assert_near(a: Position, b: Position) {
+        assert!(
+            (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    fn local() -> MapMemory {
+        let mut memory = MapMemory::default();
+        memory.projection_type = ProjectorType::Local(LocalTransform::default());
+        memory
+    }
+
+    /// Position under the point, relative to the map's center.
+    fn under(memory: &mut MapMemory, my_position: Position, anchor: Vec2) -> Position {
+        let rect = Rect::from_center_size(egui::pos2(200., 100.), vec2(400., 200.));
+        Projector::new(memory, rect, my_position).unproject(rect.center() + anchor)
+    }
+
+    #[test]
+    fn center_follows_my_position_until_set() {
+        let my_position = pos_from_lon_lat(21., 52.);
+        let position = pos_from_lon_lat(-73.98, 40.75);
+
+        for mut memory in [MapMemory::default(), local()] {
+            let mut camera = memory.camera(my_position);
+            assert_near(camera.center(), my_position);
+
+            camera.set_center(position);
+            assert_near(camera.center(), position);
+            assert_near(memory.camera(pos_from_lon_lat(0., 0.)).center(), position);
+        }
+    }
+
+    #[test]
+    fn translate_pixels() {
+        let mut memory = MapMemory::default();
+        memory.set_zoom(0.).unwrap();
+        let mut camera = memory.camera(pos_from_lon_lat(0., 0.));
+
+        // Whole world is 256 points wide at zoom 0.
+        camera.translate_pixels(vec2(64., 0.));
+        assert_near(camera.center(), pos_from_lon_lat(-90., 0.));
+        camera.translate_pixels(vec2(-64., 0.));
+        assert_near(camera.center(), pos_from_lon_lat(0., 0.));
+
+        camera.translate_pixels(vec2(0., 64.));
+        assert!(camera.center().y > 0.);
+
+        let mut memory = local();
+        memory.set_zoom(0.).unwrap();
+        let mut camera = memory.camera(pos_from_lon_lat(10., 10.));
+        let before = camera.center();
+        camera.translate_pixels(vec2(10., -10.));
+        let after = camera.center();
+        assert!(after.x < before.x);
+        assert!(after.y < before.y);
+    }
+
+    #[test]
+    fn zoom_about_keeps_the_anchor_in_place() {
+        let my_position = pos_from_lon_lat(21., 52.);
+        let anchor = vec2(120., -40.);
+
+        for mut memory in [MapMemory::default(), local()] {
+            let before = under(&mut memory, my_position, anchor);
+            memory.camera(my_position).zoom_about(anchor, 1.5);
+            assert_near(under(&mut memory, my_position, anchor), before);
+
+            memory.camera(my_position).zoom_about(anchor, -2.25);
+            assert_near(under(&mut memory, my_position, anchor), before);
+        }
+    }
+
+    #[test]
+    fn zooming_about_the_center_stays_attached() {
+        let my_position = pos_from_lon_lat(21., 52.);
+        let mut memory = MapMemory::default();
+        let zoom = memory.zoom();
+        memory.camera(my_position).zoom_about(Vec2::ZERO, 1.);
+
+        assert_eq!(memory.zoom(), zoom + 1.);
+        assert_eq!(memory.detached(), None);
+    }
+}
@@ -1,6 +1,15 @@
-use egui::{Response, Vec2};
+use egui::{Context, Response, Vec2};
 
-use crate::units::{AdjustedPosition, Position};
+use crate::{
+    animation::{frame_time, reduced_motion},
+    camera,
+    map_memory::ScreenTransform,
+    projector::ProjectorType,
+    units::{AdjustedPosition, Position},
+};
+
+/// How quickly inertia fades out, per second. It stops after `1 / INERTIA_DECAY` seconds.
+const INERTIA_DECAY: f32 = 1.8;
 
 /// Position at the map's center. Initially, the map follows `my_position` argument which typically
 /// is meant to be fed by a GPS sensor or other geo-localization method. If user drags the map,
@@ -24,27 +33,48 @@ pub(crate) enum Center {
     /// Map is currently moving due to inertia, and will slow down and stop after a short while.
     Inertia {
         pos: AdjustedPosition,
-        direction: Vec2,
+        /// Points per second.
+        velocity: Vec2,
         amount: f32,
     },
 }
 
 impl Center {
-    pub(crate) fn recalculate_drag(&mut self, response: &Response, my_position: Position) -> bool {
+    /// Follow the dragging. `screen_rotation` is the rotation of the map's content, which the
+    /// drag gets undone by.
+    pub(crate) fn recalculate_drag(
+        &mut self,
+        response: &Response,
+        my_position: Position,
+        screen_transform: ScreenTransform,
+    ) -> bool {
         if response.dragged_by(egui::PointerButton::Primary) {
+            // Under perspective, the same drag moves the map by more the farther it is, so follow
+            // the point under the pointer.
+            let pointer = response
+                .interact_pointer_pos()
+                .map(|pos| pos - response.rect.center())
+                .unwrap_or_default();
+            let direction = screen_transform.invert(pointer)
+                - screen_transform.invert(pointer - response.drag_delta());
+
             *self = Center::Moving {
                 pos: self
                     .get_adjusted_position()
                     .unwrap_or(AdjustedPosition::new(my_position, Default::default())),
-                direction: response.drag_delta(),
+                direction,
             };
             true
         } else if response.drag_stopped() {
             if let Center::Moving { pos, direction } = &self {
-                *self = Center::Inertia {
-                    pos: pos.clone(),
-                    direction: *direction,
-                    amount: 1.0,
+                *self = if reduced_motion(&response.ctx) {
+                    Center::Exact { pos: pos.clone() }
+                } else {
+                    Center::Inertia {
+                        pos: pos.clone(),
+                        velocity: *direction / frame_time(&response.ctx),
+                        amount: 1.0,
+                    }
                 };
             }
             true
@@ -53,7 +83,7 @@ impl Center {
         }
     }
 
-    pub(crate) fn update_movement(&mut self) -> bool {
+    pub(crate) fn update_movement(&mut self, ctx: &Context) -> bool {
         match self {
             Center::Moving { pos, direction } => {
                 *pos = pos.clone().shift(*direction);
@@ -62,7 +92,7 @@ impl Center {
             }
             Center::Inertia {
                 pos,
-                direction,
+                velocity,
                 amount,
             } => {
                 if amount <= &mut 0.0 {
@@ -70,8 +100,9 @@ impl Center {
                         pos: pos.to_owned(),
                     }
                 } else {
-                    *pos = pos.clone().shift(*direction * *amount);
-                    *amount -= 0.03;
+                    let dt = frame_time(ctx);
+                    *pos = pos.clone().shift(*velocity * *amount * dt);
+                    *amount -= INERTIA_DECAY * dt;
                 };
                 true
             }
@@ -79,6 +110,11 @@ impl Center {
         }
     }
 
+    /// Whether the map is neither dragged nor moving by inertia.
+    pub(crate) fn is_at_rest(&self) -> bool {
+        matches!(self, Center::MyPosition | Center::Exact { .. })
+    }
+
     pub(crate) fn get_adjusted_position(&self) -> Option<AdjustedPosition> {
         match self {
             Center::MyPosition => None,
@@ -90,82 +126,32 @@ impl Center {
 
     /// Shift position by given number of pixels, if detached.
     pub(crate) fn shift(self, offset: Vec2) -> Self {
-        match self {
-            Center::MyPosition => Center::MyPosition,
-            Center::Exact { pos } => Center::Exact {
-                pos: pos.shift(offset),
-            },
-            Center::Moving { pos, direction } => Center::Moving {
-                pos: pos.shift(offset),
-                direction,
-            },
-            Center::Inertia {
-                pos,
-                direction,
-                amount,
-            } => Center::Inertia {
-                pos: pos.shift(offset),
-                direction,
-                amount,
-            },
-        }
+        self.map_position(|pos| pos.shift(offset))
     }
 
-    pub(crate) fn global_zero_offset(self, zoom: f64) -> Center {
-        match self {
-            Center::MyPosition => Center::MyPosition,
-            Center::Exact { pos } => Center::Exact {
-                pos: pos.global_zero_offset(zoom),
-            },
-            Center::Moving { pos, direction } => Center::Moving {
-                pos: pos.global_zero_offset(zoom),
-                direction,
-            },
-            Center::Inertia {
-                pos,
-                direction,
-                amount,
-            } => Center::Inertia {
-                pos: pos.global_zero_offset(zoom),
-                direction,
-                amount,
-            },
-        }
+    /// Move the offset into the position, using the given projection. See
+    /// [`camera::zero_offset`].
+    pub(crate) fn zero_offset(self, projection: &ProjectorType, zoom: f64) -> Center {
+        self.map_position(|pos| camera::zero_offset(pos, projection, zoom))
     }
 
-    pub(crate) fn local_zero_offset(self, zoom: f64) -> Center {
+    fn map_position(self, f: impl FnOnce(AdjustedPosition) -> AdjustedPosition) -> Self {
         match self {
             Center::MyPosition => Center::MyPosition,
-            Center::Exact { pos } => Center::Exact {
-                pos: pos.local_zero_offset(zoom),
-            },
+            Center::Exact { pos } => Center::Exact { pos: f(pos) },
             Center::Moving { pos, direction } => Center::Moving {
-                pos: pos.local_zero_offset(zoom),
+                pos: f(pos),
                 direction,
             },
             Center::Inertia {
                 pos,
-                direction,
+                velocity,
                 amount,
             } => Center::Inertia {
-                pos: pos.local_zero_offset(zoom),
-                direction,
+                pos: f(pos),
+                velocity,
                 amount,
             },
         }
     }
-
-    pub(crate) fn global_position(&self, my_position: Position, zoom: f64) -> Position {
-        match self.get_adjusted_position() {
-            Some(adj_pos) => adj_pos.global_unadjusted_position(zoom),
-            None => my_position,
-        }
-    }
-
-    pub(crate) fn local_position(&self, my_position: Position, zoom: f64) -> Position {
-        match self.get_adjusted_position() {
-            Some(adj_pos) => adj_pos.local_unadjusted_position(zoom),
-            None => my_position,
-        }
-    }
 }
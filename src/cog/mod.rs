@@ -0,0 +1,461 @@
+//! Tiles cut from [Cloud Optimized GeoTIFFs](https://cogeo.org/) on the fly.
+
+mod tiff;
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use egui::{pos2, Color32, ColorImage, Context, Rect};
+use futures::{
+    channel::mpsc::{channel, Receiver, Sender},
+    future::try_join_all,
+    SinkExt, StreamExt,
+};
+use lru::LruCache;
+use reqwest::{
+    header::{HeaderValue, RANGE, USER_AGENT},
+    StatusCode,
+};
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::{
+    download::{Repaint, MAX_PARALLEL_DOWNLOADS},
+    io::{http_client, Runtime},
+    sources::Attribution,
+    tiles::LoadedTiles,
+    BoundingBox, HttpOptions, Texture, TextureWithUv, TileId, Tiles, TILE_SIZE,
+};
+use tiff::{Cog, ParseError};
+
+/// How much of the file is read at first, in hope that it contains the whole header.
+const HEADER_SIZE: u64 = 16 * 1024;
+
+/// Headers larger than that are not worth waiting for.
+const MAX_HEADER_SIZE: usize = 16 * 1024 * 1024;
+
+/// Tiles needing more blocks are left empty. Happens when zoomed out on files without overviews.
+const MAX_BLOCKS: usize = 64;
+
+/// Decoded blocks kept in memory, as neighbouring tiles often share them.
+const BLOCK_CACHE_SIZE: usize = 256;
+
+type Block = Option<Arc<Vec<Color32>>>;
+
+enum Message {
+    Opened(Result<BoundingBox, String>),
+    Tile(TileId, Result<Option<ColorImage>, String>),
+}
+
+/// Where the file is read from.
+enum Reader {
+    File(PathBuf),
+    Http {
+        client: ClientWithMiddleware,
+        user_agent: Option<HeaderValue>,
+        url: String,
+    },
+}
+
+impl Reader {
+    /// Read up to `length` bytes from the offset.
+    async fn read(&self, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+        match self {
+            Reader::File(path) => {
+                // Keep the runtime free for other reads while waiting for the disk.
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    let mut data = Vec::new();
+                    File::open(&path)
+                        .and_then(|mut file| {
+                            file.seek(SeekFrom::Start(offset))?;
+                            file.take(length).read_to_end(&mut data)
+                        })
+                        .map_err(|err| format!("{}: {}", path.display(), err))?;
+                    Ok(data)
+                })
+                .await
+                .map_err(|err| err.to_string())?
+            }
+            Reader::Http {
+                client,
+                user_agent,
+                url,
+            } => {
+                log::trace!("Reading {} bytes at {} of '{}'.", length, offset, url);
+                let mut request = client.get(url).header(
+                    RANGE,
+                    format!("bytes={}-{}", offset, offset + length.max(1) - 1),
+                );
+                if let Some(user_agent) = user_agent {
+                    request = request.header(USER_AGENT, user_agent);
+                }
+
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|err| err.to_string())?
+                    .error_for_status()
+                    .map_err(|err| err.to_string())?;
+                let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+                let data = response.bytes().await.map_err(|err| err.to_string())?;
+
+                // Server ignored the range and sent the whole file.
+                if !partial {
+                    let start = (offset as usize).min(data.len());
+                    let end = (offset.saturating_add(length) as usize).min(data.len());
+                    return Ok(data[start..end].to_vec());
+                }
+                Ok(data.to_vec())
+            }
+        }
+    }
+}
+
+/// [`Tiles`] cut from a Cloud Optimized GeoTIFF, such as a drone orthophoto or a satellite
+/// scene, without tiling it beforehand. Only the blocks needed for the visible tiles are read,
+/// from the overview matching the zoom, either from a local file or via HTTP range requests.
+///
+/// Supported are tiled images with 8 bit samples (grayscale, RGB, with or without alpha),
+/// compressed with Deflate, JPEG or not at all, in Web Mercator (EPSG:3857) or WGS 84
+/// (EPSG:4326). Like [`crate::HttpTiles`], it must persist between frames.
+pub struct CogTiles {
+    egui_ctx: Context,
+    attribution: Attribution,
+    /// `None` for tiles which the image does not cover.
+    tiles: LoadedTiles<Option<Texture>>,
+    bounds: Option<BoundingBox>,
+    error: Option<String>,
+    /// Path or URL of the file.
+    source_id: String,
+    request_tx: Sender<TileId>,
+    message_rx: Receiver<Message>,
+
+    #[allow(dead_code)] // Significant Drop
+    runtime: Runtime,
+}
+
+impl CogTiles {
+    /// Read the local file.
+    pub fn open(path: impl Into<PathBuf>, egui_ctx: Context) -> Self {
+        Self::new(Reader::File(path.into()), egui_ctx)
+    }
+
+    /// Read the file from the URL, using HTTP range requests. Of the [`HttpOptions`], only the
+    /// user agent and middleware are used, as the HTTP cache does not handle ranges.
+    pub fn from_url(url: impl Into<String>, http_options: HttpOptions, egui_ctx: Context) -> Self {
+        let user_agent = http_options.user_agent.clone();
+        let client = http_client(HttpOptions {
+            cache: None,
+            ..http_options
+        });
+        Self::new(
+            Reader::Http {
+                client,
+                user_agent,
+                url: url.into(),
+            },
+            egui_ctx,
+        )
+    }
+
+    fn new(reader: Reader, egui_ctx: Context) -> Self {
+        let source_id = match &reader {
+            Reader::File(path) => path.display().to_string(),
+            Reader::Http { url, .. } => url.clone(),
+        };
+        let (request_tx, request_rx) = channel(MAX_PARALLEL_DOWNLOADS);
+        let (message_tx, message_rx) = channel(MAX_PARALLEL_DOWNLOADS);
+
+        // IO thread does not touch egui, other than waking it up.
+        let ctx = egui_ctx.clone();
+        let repaint: Repaint = Box::new(move || ctx.request_repaint());
+        let runtime = Runtime::new(read_continuously(reader, request_rx, message_tx, repaint));
+
+        Self {
+            egui_ctx,
+            attribution: Attribution {
+                text: "",
+                url: "",
+                logo_light: None,
+                logo_dark: None,
+            },
+            tiles: LoadedTiles::new(),
+            bounds: None,
+            error: None,
+            source_id,
+            request_tx,
+            message_rx,
+            runtime,
+        }
+    }
+
+    /// Attribution of the imagery, as required by its license.
+    pub fn attribution(mut self, text: &'static str, url: &'static str) -> Self {
+        self.attribution.text = text;
+        self.attribution.url = url;
+        self
+    }
+
+    /// Geographical area covered by the image, once its header is read.
+    pub fn bounds(&mut self) -> Option<BoundingBox> {
+        self.receive();
+        self.bounds
+    }
+
+    /// Reason why the file could not be read, if so.
+    pub fn error(&mut self) -> Option<&str> {
+        self.receive();
+        self.error.as_deref()
+    }
+
+    fn receive(&mut self) {
+        while let Ok(message) = self.message_rx.try_recv() {
+            match message {
+                Message::Opened(Ok(bounds)) => self.bounds = Some(bounds),
+                Message::Opened(Err(error)) => {
+                    log::warn!("Could not read the GeoTIFF: {}", error);
+                    self.error = Some(error);
+                }
+                Message::Tile(tile_id, Ok(image)) => {
+                    let texture =
+                        image.map(|image| Texture::from_color_image(image, &self.egui_ctx));
+                    self.tiles.loaded(tile_id, texture);
+                }
+                Message::Tile(tile_id, Err(error)) => self.tiles.failed(tile_id, error),
+            }
+        }
+    }
+}
+
+impl Tiles for CogTiles {
+    fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+        self.receive();
+
+        if self.error.is_some() {
+            return None;
+        }
+
+        if let Some(bounds) = self.bounds {
+            if !intersects(bounds, tile_id.bounds()) {
+                return None;
+            }
+        }
+
+        let request_tx = &mut self.request_tx;
+        let texture = self
+            .tiles
+            .get_or_request(tile_id, || request_tx.try_send(tile_id).is_ok())
+            .flatten();
+
+        texture.map(|texture| TextureWithUv {
+            texture,
+            uv: Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+        })
+    }
+
+    fn attribution(&self) -> Attribution {
+        self.attribution.clone()
+    }
+
+    fn tile_size(&self) -> u32 {
+        TILE_SIZE
+    }
+
+    fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        self.tiles.take_errors()
+    }
+
+    fn source_id(&self) -> String {
+        self.source_id.clone()
+    }
+}
+
+fn intersects(a: BoundingBox, b: BoundingBox) -> bool {
+    a.min().x <= b.max().x
+        && b.min().x <= a.max().x
+        && a.min().y <= b.max().y
+        && b.min().y <= a.max().y
+}
+
+/// Read the header, which for cloud optimized files is at the beginning, growing the read part
+/// until it fits.
+async fn read_header(reader: &Reader) -> Result<Cog, String> {
+    let mut length = HEADER_SIZE;
+    loop {
+        let data = reader.read(0, length).await?;
+        match tiff::parse(&data) {
+            Ok(cog) if cog.levels.is_empty() => return Err("no images".to_owned()),
+            Ok(cog) => return Ok(cog),
+            Err(ParseError::Invalid(reason)) => return Err(reason),
+            Err(ParseError::Truncated(_)) if (data.len() as u64) < length => {
+                return Err("file is truncated".to_owned());
+            }
+            Err(ParseError::Truncated(needed)) if needed > MAX_HEADER_SIZE => {
+                return Err("header is too large".to_owned());
+            }
+            Err(ParseError::Truncated(needed)) => length = (needed as u64).max(length * 2),
+        }
+    }
+}
+
+async fn read_continuously(
+    reader: Reader,
+    request_rx: Receiver<TileId>,
+    mut message_tx: Sender<Message>,
+    repaint: Repaint,
+) {
+    let cog = read_header(&reader).await;
+    let opened = cog.as_ref().map(Cog::bounds).map_err(Clone::clone);
+
+    // Main thread is gone if this fails, nothing to do about it.
+    let _ = message_tx.send(Message::Opened(opened)).await;
+    repaint();
+
+    let Ok(cog) = cog else {
+        return;
+    };
+
+    #[allow(clippy::unwrap_used)]
+    let blocks = Mutex::new(LruCache::new(
+        std::num::NonZeroUsize::new(BLOCK_CACHE_SIZE).unwrap(),
+    ));
+    let (cog, reader, blocks) = (&cog, &reader, &blocks);
+
+    request_rx
+        .map(|tile_id| async move { (tile_id, render(cog, reader, blocks, tile_id).await) })
+        .buffer_unordered(MAX_PARALLEL_DOWNLOADS)
+        .for_each(|(tile_id, result)| {
+            let mut message_tx = message_tx.clone();
+            let repaint = &repaint;
+            async move {
+                if let Err(error) = &result {
+                    log::warn!("{:?}: {}", tile_id, error);
+                }
+                let _ = message_tx.send(Message::Tile(tile_id, result)).await;
+                repaint();
+            }
+        })
+        .await;
+}
+
+/// Resample the image into the tile, or `None` if it does not cover it.
+async fn render(
+    cog: &Cog,
+    reader: &Reader,
+    blocks: &Mutex<LruCache<(usize, u32, u32), Block>>,
+    tile_id: TileId,
+) -> Result<Option<ColorImage>, String> {
+    let size = TILE_SIZE as usize;
+    let tiles = (1u64 << tile_id.zoom) as f64;
+
+    // Full resolution pixels at the centers of tile's pixels. Axes are independent in both
+    // supported coordinate systems.
+    let along = |tile: u32, i: usize| (tile as f64 + (i as f64 + 0.5) / size as f64) / tiles;
+    let columns: Vec<f64> = (0..size)
+        .map(|i| cog.pixel(along(tile_id.x, i), 0.).0)
+        .collect();
+    let rows: Vec<f64> = (0..size)
+        .map(|j| cog.pixel(0., along(tile_id.y, j)).1)
+        .collect();
+
+    let footprint = |values: &[f64]| (values[size - 1] - values[0]).abs() / (size - 1) as f64;
+    let index = cog.level_for(footprint(&columns).min(footprint(&rows)));
+    let level = &cog.levels[index];
+
+    // Pixels of the chosen level, for the parts of the tile covered by the image.
+    let full = &cog.levels[0];
+    let to_level = |values: &[f64], full: u32, level: u32| -> Vec<Option<u32>> {
+        let scale = level as f64 / full as f64;
+        values
+            .iter()
+            .map(|value| {
+                let value = value * scale;
+                (value >= 0. && value < level as f64).then_some(value as u32)
+            })
+            .collect()
+    };
+    let columns = to_level(&columns, full.width, level.width);
+    let rows = to_level(&rows, full.height, level.height);
+
+    let range = |values: &[Option<u32>], block: u32| {
+        let mut values = values.iter().flatten();
+        let first = values.next()?;
+        let (min, max) = values.fold((first, first), |(min, max), v| (min.min(v), max.max(v)));
+        Some(min / block..=max / block)
+    };
+    let (Some(block_columns), Some(block_rows)) = (
+        range(&columns, level.tile_width),
+        range(&rows, level.tile_height),
+    ) else {
+        return Ok(None);
+    };
+
+    let needed: Vec<(u32, u32)> = block_rows
+        .flat_map(|row| block_columns.clone().map(move |column| (column, row)))
+        .collect();
+    if needed.len() > MAX_BLOCKS {
+        log::debug!("{:?} needs {} blocks, skipping.", tile_id, needed.len());
+        return Ok(None);
+    }
+
+    let loaded = try_join_all(
+        needed
+            .iter()
+            .map(|&(column, row)| block(cog, reader, blocks, index, column, row)),
+    )
+    .await?;
+    let loaded: HashMap<_, _> = needed.into_iter().zip(loaded).collect();
+
+    let mut pixels = vec![Color32::TRANSPARENT; size * size];
+    for (j, row) in rows.iter().enumerate() {
+        let Some(row) = row else { continue };
+        for (i, column) in columns.iter().enumerate() {
+            let Some(column) = column else { continue };
+            let key = (column / level.tile_width, row / level.tile_height);
+            if let Some(Some(block)) = loaded.get(&key) {
+                let offset = (row % level.tile_height) as usize * level.tile_width as usize
+                    + (column % level.tile_width) as usize;
+                pixels[j * size + i] = block[offset];
+            }
+        }
+    }
+
+    Ok(Some(ColorImage {
+        size: [size, size],
+        pixels,
+    }))
+}
+
+/// Decoded block, read from the file if not cached. `None` for the empty ones.
+async fn block(
+    cog: &Cog,
+    reader: &Reader,
+    blocks: &Mutex<LruCache<(usize, u32, u32), Block>>,
+    index: usize,
+    column: u32,
+    row: u32,
+) -> Result<Block, String> {
+    let key = (index, column, row);
+    let lock = || blocks.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(block) = lock().get(&key) {
+        return Ok(block.clone());
+    }
+
+    let level = &cog.levels[index];
+    let block = match level.block_range(column, row) {
+        Some((offset, length)) => {
+            let data = reader.read(offset, length).await?;
+            Some(Arc::new(level.decode_block(&data)?))
+        }
+        None => None,
+    };
+
+    lock().put(key, block.clone());
+    Ok(block)
+}
@@ -0,0 +1,815 @@
+//! Reading of TIFF and BigTIFF headers and blocks, just enough for Cloud Optimized GeoTIFFs.
+
+use std::{collections::HashMap, f64::consts::PI, io::Read};
+
+use egui::Color32;
+
+use crate::{BoundingBox, Position};
+
+/// Blocks of COGs are usually 256 or 512 pixels wide. Larger ones would take too much memory.
+const MAX_BLOCK_SIZE: u32 = 4096;
+
+/// Length of the Earth's equator in Web Mercator meters.
+const EQUATOR: f64 = 2. * PI * 6_378_137.;
+
+pub(crate) enum ParseError {
+    /// Header continues beyond the data read so far, up to given length.
+    Truncated(usize),
+    Invalid(String),
+}
+
+fn invalid(reason: impl Into<String>) -> ParseError {
+    ParseError::Invalid(reason.into())
+}
+
+/// Coordinate system of the file. Others would need reprojection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Crs {
+    /// EPSG:3857
+    WebMercator,
+    /// EPSG:4326
+    Wgs84,
+}
+
+impl Crs {
+    /// Coordinates of the point given as a fraction of the Web Mercator world.
+    fn coordinates(self, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Crs::WebMercator => ((x - 0.5) * EQUATOR, (0.5 - y) * EQUATOR),
+            Crs::Wgs84 => (
+                x * 360. - 180.,
+                (PI * (1. - 2. * y)).sinh().atan().to_degrees(),
+            ),
+        }
+    }
+
+    fn to_position(self, x: f64, y: f64) -> Position {
+        match self {
+            Crs::WebMercator => Position {
+                x: x / EQUATOR * 360.,
+                y: (y / EQUATOR * 2. * PI).sinh().atan().to_degrees(),
+            },
+            Crs::Wgs84 => Position { x, y },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Compression {
+    None,
+    Deflate,
+    Jpeg,
+}
+
+/// The full resolution image or one of its overviews.
+pub(crate) struct Level {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) tile_width: u32,
+    pub(crate) tile_height: u32,
+    offsets: Vec<u64>,
+    byte_counts: Vec<u64>,
+    compression: Compression,
+    predictor: bool,
+    samples_per_pixel: usize,
+    white_is_zero: bool,
+    /// Whether the extra sample is alpha, and if so, whether it is premultiplied.
+    alpha: Option<bool>,
+    jpeg_tables: Option<Vec<u8>>,
+    nodata: Option<u8>,
+}
+
+impl Level {
+    /// Location of the block in the file, or `None` if it is missing, which means it is empty.
+    pub(crate) fn block_range(&self, column: u32, row: u32) -> Option<(u64, u64)> {
+        let columns = u64::from(self.width.div_ceil(self.tile_width));
+        let index = u64::from(row)
+            .checked_mul(columns)?
+            .checked_add(u64::from(column))?;
+        let index = usize::try_from(index).ok()?;
+        let (offset, length) = (*self.offsets.get(index)?, *self.byte_counts.get(index)?);
+        (length > 0).then_some((offset, length))
+    }
+
+    /// Decompress the block into `tile_width` by `tile_height` pixels.
+    pub(crate) fn decode_block(&self, data: &[u8]) -> Result<Vec<Color32>, String> {
+        let pixels = (self.tile_width as usize)
+            .checked_mul(self.tile_height as usize)
+            .ok_or("block is too large")?;
+
+        let samples = match self.compression {
+            Compression::Jpeg => return self.decode_jpeg(data, pixels),
+            Compression::None => data.to_vec(),
+            Compression::Deflate => {
+                let mut samples = Vec::with_capacity(pixels * self.samples_per_pixel);
+                flate2::read::ZlibDecoder::new(data)
+                    .read_to_end(&mut samples)
+                    .map_err(|err| err.to_string())?;
+                samples
+            }
+        };
+
+        let n = self.samples_per_pixel;
+        if samples.len() < pixels * n {
+            return Err("block is shorter than expected".to_owned());
+        }
+
+        let mut samples = samples;
+        if self.predictor {
+            for row in samples.chunks_mut(self.tile_width as usize * n) {
+                for i in n..row.len() {
+                    row[i] = row[i].wrapping_add(row[i - n]);
+                }
+            }
+        }
+
+        Ok(samples
+            .chunks(n)
+            .take(pixels)
+            .map(|s| self.color(s))
+            .collect())
+    }
+
+    fn color(&self, s: &[u8]) -> Color32 {
+        let bands = if self.alpha.is_some() {
+            s.len() - 1
+        } else {
+            s.len()
+        };
+        if let Some(nodata) = self.nodata {
+            if s[..bands].iter().all(|&sample| sample == nodata) {
+                return Color32::TRANSPARENT;
+            }
+        }
+
+        let (r, g, b) = if bands >= 3 {
+            (s[0], s[1], s[2])
+        } else if self.white_is_zero {
+            (255 - s[0], 255 - s[0], 255 - s[0])
+        } else {
+            (s[0], s[0], s[0])
+        };
+
+        match self.alpha {
+            Some(true) => Color32::from_rgba_premultiplied(r, g, b, s[bands]),
+            Some(false) => Color32::from_rgba_unmultiplied(r, g, b, s[bands]),
+            None => Color32::from_rgb(r, g, b),
+        }
+    }
+
+    fn decode_jpeg(&self, data: &[u8], pixels: usize) -> Result<Vec<Color32>, String> {
+        // Tables shared by all blocks are stored separately, as a JPEG stream of their own.
+        let data = match &self.jpeg_tables {
+            Some(tables) if tables.len() >= 4 && data.len() >= 2 => {
+                let mut joined = tables[..tables.len() - 2].to_vec();
+                joined.extend_from_slice(&data[2..]);
+                joined
+            }
+            _ => data.to_vec(),
+        };
+
+        let image = image::load_from_memory_with_format(&data, image::ImageFormat::Jpeg)
+            .map_err(|err| err.to_string())?
+            .to_rgba8();
+        if image.as_raw().len() < pixels * 4 {
+            return Err("block is smaller than expected".to_owned());
+        }
+
+        Ok(image
+            .as_raw()
+            .chunks(4)
+            .take(pixels)
+            .map(|p| Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]))
+            .collect())
+    }
+}
+
+/// Header of a Cloud Optimized GeoTIFF.
+pub(crate) struct Cog {
+    /// Full resolution image followed by the overviews, from the most detailed.
+    pub(crate) levels: Vec<Level>,
+    georeference: Georeference,
+}
+
+/// Placement of the full resolution image.
+struct Georeference {
+    crs: Crs,
+    /// Coordinates of the top-left corner of the image.
+    origin: (f64, f64),
+    /// Size of a full resolution pixel, in the units of the coordinate system.
+    pixel_size: (f64, f64),
+}
+
+impl Cog {
+    /// Position of the point, given as a fraction of the Web Mercator world, in full
+    /// resolution pixels.
+    pub(crate) fn pixel(&self, x: f64, y: f64) -> (f64, f64) {
+        let Georeference {
+            crs,
+            origin,
+            pixel_size,
+        } = &self.georeference;
+        let (x, y) = crs.coordinates(x, y);
+        ((x - origin.0) / pixel_size.0, (origin.1 - y) / pixel_size.1)
+    }
+
+    /// Geographical area covered by the image.
+    pub(crate) fn bounds(&self) -> BoundingBox {
+        let Georeference {
+            crs,
+            origin: (x, y),
+            pixel_size,
+        } = &self.georeference;
+        let full = &self.levels[0];
+        BoundingBox::new(
+            crs.to_position(*x, *y),
+            crs.to_position(
+                x + full.width as f64 * pixel_size.0,
+                y - full.height as f64 * pixel_size.1,
+            ),
+        )
+    }
+
+    /// Coarsest level whose pixels are not larger than given number of full resolution pixels.
+    pub(crate) fn level_for(&self, footprint: f64) -> usize {
+        let width = self.levels[0].width as f64;
+        (0..self.levels.len())
+            .rev()
+            .find(|&i| width / self.levels[i].width as f64 <= footprint)
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    kind: u16,
+    count: u64,
+    /// Where the values are, either within the entry itself or elsewhere in the file.
+    offset: u64,
+}
+
+fn type_size(kind: u16) -> Option<u64> {
+    match kind {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 => Some(4),
+        5 | 10 | 12 | 16..=18 => Some(8),
+        _ => None,
+    }
+}
+
+struct Bytes<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Bytes<'a> {
+    fn get(&self, offset: u64, length: u64) -> Result<&'a [u8], ParseError> {
+        let end = offset
+            .checked_add(length)
+            .and_then(|end| usize::try_from(end).ok())
+            .ok_or_else(|| invalid("offset out of range"))?;
+        self.data
+            .get(offset as usize..end)
+            .ok_or(ParseError::Truncated(end))
+    }
+
+    fn uint(&self, offset: u64, size: u64) -> Result<u64, ParseError> {
+        let bytes = self.get(offset, size)?;
+        let fold = |value: u64, byte: &u8| value << 8 | u64::from(*byte);
+        Ok(if self.little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        })
+    }
+
+    fn ints(&self, entry: Entry) -> Result<Vec<u64>, ParseError> {
+        let size = match entry.kind {
+            1 | 3 | 4 | 16 => type_size(entry.kind).unwrap_or(1),
+            _ => return Err(invalid("expected an integer tag")),
+        };
+        self.get(entry.offset, entry.count.saturating_mul(size))?;
+        (0..entry.count)
+            .map(|i| self.uint(entry.offset + i * size, size))
+            .collect()
+    }
+
+    fn doubles(&self, entry: Entry) -> Result<Vec<f64>, ParseError> {
+        if entry.kind != 12 {
+            return Err(invalid("expected a floating point tag"));
+        }
+        (0..entry.count)
+            .map(|i| Ok(f64::from_bits(self.uint(entry.offset + i * 8, 8)?)))
+            .collect()
+    }
+
+    fn raw(&self, entry: Entry) -> Result<&'a [u8], ParseError> {
+        let size = type_size(entry.kind).ok_or_else(|| invalid("unknown tag type"))?;
+        self.get(entry.offset, entry.count.saturating_mul(size))
+    }
+
+    /// Entries of the directory at the offset, along with the offset of the next one.
+    fn directory(&self, offset: u64, big: bool) -> Result<(HashMap<u16, Entry>, u64), ParseError> {
+        let (count_size, entry_size, value_size) = if big { (8, 20, 8) } else { (2, 12, 4) };
+        let count = self.uint(offset, count_size)?;
+        if count > 4096 {
+            return Err(invalid("too many tags"));
+        }
+
+        let mut entries = HashMap::new();
+        for i in 0..count {
+            let at = offset + count_size + i * entry_size;
+            let tag = self.uint(at, 2)? as u16;
+            let kind = self.uint(at + 2, 2)? as u16;
+            let count = self.uint(at + 4, value_size)?;
+            let value_at = at + 4 + value_size;
+            let inline =
+                type_size(kind).is_some_and(|size| count.saturating_mul(size) <= value_size);
+            let offset = if inline {
+                value_at
+            } else {
+                self.uint(value_at, value_size)?
+            };
+            entries.insert(
+                tag,
+                Entry {
+                    kind,
+                    count,
+                    offset,
+                },
+            );
+        }
+
+        let next = self.uint(offset + count_size + count * entry_size, value_size)?;
+        Ok((entries, next))
+    }
+}
+
+mod tag {
+    pub const NEW_SUBFILE_TYPE: u16 = 254;
+    pub const IMAGE_WIDTH: u16 = 256;
+    pub const IMAGE_LENGTH: u16 = 257;
+    pub const BITS_PER_SAMPLE: u16 = 258;
+    pub const COMPRESSION: u16 = 259;
+    pub const PHOTOMETRIC: u16 = 262;
+    pub const SAMPLES_PER_PIXEL: u16 = 277;
+    pub const PLANAR_CONFIGURATION: u16 = 284;
+    pub const PREDICTOR: u16 = 317;
+    pub const TILE_WIDTH: u16 = 322;
+    pub const TILE_LENGTH: u16 = 323;
+    pub const TILE_OFFSETS: u16 = 324;
+    pub const TILE_BYTE_COUNTS: u16 = 325;
+    pub const EXTRA_SAMPLES: u16 = 338;
+    pub const JPEG_TABLES: u16 = 347;
+    pub const MODEL_PIXEL_SCALE: u16 = 33550;
+    pub const MODEL_TIEPOINT: u16 = 33922;
+    pub const GEO_KEY_DIRECTORY: u16 = 34735;
+    pub const GDAL_NODATA: u16 = 42113;
+}
+
+/// Parse the header of the file, given its beginning.
+pub(crate) fn parse(data: &[u8]) -> Result<Cog, ParseError> {
+    let little_endian = match data.get(..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        Some(_) => return Err(invalid("not a TIFF file")),
+        None => return Err(ParseError::Truncated(8)),
+    };
+    let bytes = Bytes {
+        data,
+        little_endian,
+    };
+
+    let (big, mut offset) = match bytes.uint(2, 2)? {
+        42 => (false, bytes.uint(4, 4)?),
+        43 => (true, bytes.uint(8, 8)?),
+        _ => return Err(invalid("not a TIFF file")),
+    };
+
+    let mut directories = Vec::new();
+    while offset != 0 {
+        if directories.len() > 64 {
+            return Err(invalid("too many images"));
+        }
+        let (entries, next) = bytes.directory(offset, big)?;
+        directories.push(entries);
+        offset = next;
+    }
+
+    let full = directories.first().ok_or_else(|| invalid("no images"))?;
+    let nodata = match full.get(&tag::GDAL_NODATA) {
+        Some(entry) => String::from_utf8_lossy(bytes.raw(*entry)?)
+            .trim_matches(char::from(0))
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|nodata| (0.0..=255.).contains(nodata) && nodata.fract() == 0.)
+            .map(|nodata| nodata as u8),
+        None => None,
+    };
+    let georeference = georeference(&bytes, full)?;
+
+    let mut levels = Vec::new();
+    for entries in &directories {
+        let int = |tag: u16| -> Result<Option<u64>, ParseError> {
+            match entries.get(&tag) {
+                Some(entry) => Ok(bytes.ints(*entry)?.first().copied()),
+                None => Ok(None),
+            }
+        };
+
+        // Transparency masks are not supported.
+        if int(tag::NEW_SUBFILE_TYPE)?.unwrap_or(0) & 4 != 0 {
+            continue;
+        }
+
+        levels.push(level(&bytes, entries, &int, nodata)?);
+    }
+
+    levels.sort_by_key(|level| std::cmp::Reverse(level.width));
+
+    Ok(Cog {
+        levels,
+        georeference,
+    })
+}
+
+fn level(
+    bytes: &Bytes,
+    entries: &HashMap<u16, Entry>,
+    int: &impl Fn(u16) -> Result<Option<u64>, ParseError>,
+    nodata: Option<u8>,
+) -> Result<Level, ParseError> {
+    let required = |tag: u16, name: &str| int(tag)?.ok_or_else(|| invalid(format!("no {name}")));
+    let list = |tag: u16, name: &str| match entries.get(&tag) {
+        Some(entry) => bytes.ints(*entry),
+        None => Err(invalid(format!("no {name}"))),
+    };
+
+    let (Some(tile_width), Some(tile_height)) = (int(tag::TILE_WIDTH)?, int(tag::TILE_LENGTH)?)
+    else {
+        return Err(invalid("image is not tiled, so it is not cloud optimized"));
+    };
+
+    let samples_per_pixel = int(tag::SAMPLES_PER_PIXEL)?.unwrap_or(1) as usize;
+    if !(1..=16).contains(&samples_per_pixel) {
+        return Err(invalid("unsupported number of samples"));
+    }
+    if let Some(entry) = entries.get(&tag::BITS_PER_SAMPLE) {
+        if bytes.ints(*entry)?.iter().any(|&bits| bits != 8) {
+            return Err(invalid("only 8 bit samples are supported"));
+        }
+    }
+    if int(tag::PLANAR_CONFIGURATION)?.unwrap_or(1) != 1 {
+        return Err(invalid("only interleaved samples are supported"));
+    }
+
+    let compression = match int(tag::COMPRESSION)?.unwrap_or(1) {
+        1 => Compression::None,
+        7 => Compression::Jpeg,
+        8 | 32946 => Compression::Deflate,
+        other => return Err(invalid(format!("unsupported compression {other}"))),
+    };
+    let predictor = match int(tag::PREDICTOR)?.unwrap_or(1) {
+        1 => false,
+        2 => true,
+        other => return Err(invalid(format!("unsupported predictor {other}"))),
+    };
+    let photometric = int(tag::PHOTOMETRIC)?.unwrap_or(1);
+    if photometric == 3 {
+        return Err(invalid("palette images are not supported"));
+    }
+
+    let color_samples = if samples_per_pixel >= 3 { 3 } else { 1 };
+    let alpha = match int(tag::EXTRA_SAMPLES)? {
+        Some(extra @ (1 | 2)) if samples_per_pixel > color_samples => Some(extra == 1),
+        _ => None,
+    };
+
+    let jpeg_tables = match entries.get(&tag::JPEG_TABLES) {
+        Some(entry) => Some(bytes.raw(*entry)?.to_vec()),
+        None => None,
+    };
+
+    let level = Level {
+        width: dimension(required(tag::IMAGE_WIDTH, "width")?)?,
+        height: dimension(required(tag::IMAGE_LENGTH, "height")?)?,
+        tile_width: dimension(tile_width)?,
+        tile_height: dimension(tile_height)?,
+        offsets: list(tag::TILE_OFFSETS, "tile offsets")?,
+        byte_counts: list(tag::TILE_BYTE_COUNTS, "tile byte counts")?,
+        compression,
+        predictor,
+        samples_per_pixel,
+        white_is_zero: photometric == 0,
+        alpha,
+        jpeg_tables,
+        nodata,
+    };
+
+    if level.width == 0 || level.height == 0 || level.tile_width == 0 || level.tile_height == 0 {
+        return Err(invalid("empty image"));
+    }
+    if level.tile_width > MAX_BLOCK_SIZE || level.tile_height > MAX_BLOCK_SIZE {
+        return Err(invalid("blocks are too large"));
+    }
+
+    Ok(level)
+}
+
+fn dimension(value: u64) -> Result<u32, ParseError> {
+    u32::try_from(value).map_err(|_| invalid("image is too large"))
+}
+
+fn georeference(bytes: &Bytes, entries: &HashMap<u16, Entry>) -> Result<Georeference, ParseError> {
+    const MODEL_TYPE: u64 = 1024;
+    const RASTER_TYPE: u64 = 1025;
+    const GEOGRAPHIC_TYPE: u64 = 2048;
+    const PROJECTED_TYPE: u64 = 3072;
+
+    let (Some(scale), Some(tiepoint)) = (
+        entries.get(&tag::MODEL_PIXEL_SCALE),
+        entries.get(&tag::MODEL_TIEPOINT),
+    ) else {
+        return Err(invalid("image is not georeferenced"));
+    };
+    let (scale, tiepoint) = (bytes.doubles(*scale)?, bytes.doubles(*tiepoint)?);
+    let ([sx, sy, ..], [i, j, _, x, y, ..]) = (scale.as_slice(), tiepoint.as_slice()) else {
+        return Err(invalid("invalid georeference"));
+    };
+
+    let keys = match entries.get(&tag::GEO_KEY_DIRECTORY) {
+        Some(entry) => bytes.ints(*entry)?,
+        None => return Err(invalid("no GeoTIFF keys")),
+    };
+    let key = |id: u64| {
+        keys.get(4..)
+            .unwrap_or_default()
+            .chunks_exact(4)
+            .find(|key| key[0] == id && key[1] == 0)
+            .map(|key| key[3])
+    };
+
+    let crs = match (key(MODEL_TYPE), key(PROJECTED_TYPE), key(GEOGRAPHIC_TYPE)) {
+        (_, Some(3857 | 3785 | 900913), _) => Crs::WebMercator,
+        // Model type is often left out when the geographic type says enough.
+        (Some(2) | None, None, Some(4326)) | (Some(2), None, None) => Crs::Wgs84,
+        (_, Some(code), _) | (_, None, Some(code)) => {
+            return Err(invalid(format!(
+                "unsupported coordinate system EPSG:{code}"
+            )));
+        }
+        _ => return Err(invalid("unknown coordinate system")),
+    };
+
+    // Tiepoint refers to the pixel's center, rather than its corner.
+    let shift = if key(RASTER_TYPE) == Some(2) { 0.5 } else { 0. };
+    let origin = (x - (i + shift) * sx, y + (j + shift) * sy);
+
+    Ok(Georeference {
+        crs,
+        origin,
+        pixel_size: (*sx, *sy),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    enum Value {
+        Short(Vec<u16>),
+        Long(Vec<u32>),
+        Double(Vec<f64>),
+    }
+
+    impl Value {
+        fn encode(&self) -> (u16, u32, Vec<u8>) {
+            match self {
+                Value::Short(v) => (
+                    3,
+                    v.len() as u32,
+                    v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+                ),
+                Value::Long(v) => (
+                    4,
+                    v.len() as u32,
+                    v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+                ),
+                Value::Double(v) => (
+                    12,
+                    v.len() as u32,
+                    v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+                ),
+            }
+        }
+    }
+
+    /// Little-endian TIFF with a single image made of the blocks.
+    fn tiff(mut tags: Vec<(u16, Value)>, blocks: &[Vec<u8>]) -> Vec<u8> {
+        tags.push((tag::TILE_OFFSETS, Value::Long(vec![0; blocks.len()])));
+        tags.push((
+            tag::TILE_BYTE_COUNTS,
+            Value::Long(blocks.iter().map(|b| b.len() as u32).collect()),
+        ));
+        tags.sort_by_key(|(tag, _)| *tag);
+
+        let ifd_size = 2 + tags.len() * 12 + 4;
+        let external: usize = tags
+            .iter()
+            .map(|(_, value)| value.encode().2.len())
+            .filter(|length| *length > 4)
+            .sum();
+        let mut block_offset = (8 + ifd_size + external) as u32;
+        let offsets: Vec<u32> = blocks
+            .iter()
+            .map(|block| {
+                let offset = block_offset;
+                block_offset += block.len() as u32;
+                offset
+            })
+            .collect();
+        for (tag, value) in &mut tags {
+            if *tag == tag::TILE_OFFSETS {
+                *value = Value::Long(offsets.clone());
+            }
+        }
+
+        let mut data = b"II".to_vec();
+        data.extend_from_slice(&42u16.to_le_bytes());
+        data.extend_from_slice(&8u32.to_le_bytes());
+        data.extend_from_slice(&(tags.len() as u16).to_le_bytes());
+
+        let mut values = Vec::new();
+        let mut value_offset = (8 + ifd_size) as u32;
+        for (tag, value) in &tags {
+            let (kind, count, bytes) = value.encode();
+            data.extend_from_slice(&tag.to_le_bytes());
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&count.to_le_bytes());
+            if bytes.len() <= 4 {
+                let mut inline = bytes.clone();
+                inline.resize(4, 0);
+                data.extend_from_slice(&inline);
+            } else {
+                data.extend_from_slice(&value_offset.to_le_bytes());
+                value_offset += bytes.len() as u32;
+                values.extend_from_slice(&bytes);
+            }
+        }
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&values);
+        for block in blocks {
+            data.extend_from_slice(block);
+        }
+        data
+    }
+
+    /// Tags of a 16 by 8 pixel RGB image in two 8 by 8 blocks, with given geo keys.
+    fn tags(compression: u16, predictor: u16, geo_keys: &[u16]) -> Vec<(u16, Value)> {
+        let mut keys = vec![1, 1, 0, (geo_keys.len() / 4) as u16];
+        keys.extend_from_slice(geo_keys);
+        vec![
+            (tag::IMAGE_WIDTH, Value::Long(vec![16])),
+            (tag::IMAGE_LENGTH, Value::Long(vec![8])),
+            (tag::BITS_PER_SAMPLE, Value::Short(vec![8, 8, 8])),
+            (tag::COMPRESSION, Value::Short(vec![compression])),
+            (tag::PHOTOMETRIC, Value::Short(vec![2])),
+            (tag::SAMPLES_PER_PIXEL, Value::Short(vec![3])),
+            (tag::PREDICTOR, Value::Short(vec![predictor])),
+            (tag::TILE_WIDTH, Value::Long(vec![8])),
+            (tag::TILE_LENGTH, Value::Long(vec![8])),
+            (tag::MODEL_PIXEL_SCALE, Value::Double(vec![0.5, 0.25, 0.])),
+            (
+                tag::MODEL_TIEPOINT,
+                Value::Double(vec![0., 0., 0., 10., 50., 0.]),
+            ),
+            (tag::GEO_KEY_DIRECTORY, Value::Short(keys)),
+        ]
+    }
+
+    /// EPSG:4326, as written by GDAL.
+    const WGS84_KEYS: [u16; 8] = [1024, 0, 1, 2, 2048, 0, 1, 4326];
+
+    /// Block filled with a single color.
+    fn block(color: [u8; 3]) -> Vec<u8> {
+        color.repeat(64)
+    }
+
+    fn parsed(data: &[u8]) -> Cog {
+        match parse(data) {
+            Ok(cog) => cog,
+            Err(ParseError::Invalid(reason)) => panic!("invalid: {reason}"),
+            Err(ParseError::Truncated(length)) => panic!("truncated at {length}"),
+        }
+    }
+
+    fn invalid_reason(data: &[u8]) -> String {
+        match parse(data) {
+            Err(ParseError::Invalid(reason)) => reason,
+            _ => panic!("expected an invalid file"),
+        }
+    }
+
+    #[test]
+    fn uncompressed() {
+        let data = tiff(
+            tags(1, 1, &WGS84_KEYS),
+            &[block([255, 0, 0]), block([0, 0, 255])],
+        );
+        let cog = parsed(&data);
+        assert_eq!(cog.levels.len(), 1);
+        let level = &cog.levels[0];
+        assert_eq!((level.width, level.height), (16, 8));
+        assert_eq!((level.tile_width, level.tile_height), (8, 8));
+
+        let bounds = cog.bounds();
+        assert_eq!(bounds.min(), Position { x: 10., y: 48. });
+        assert_eq!(bounds.max(), Position { x: 18., y: 50. });
+
+        let (offset, length) = level.block_range(1, 0).unwrap();
+        let block = level
+            .decode_block(&data[offset as usize..(offset + length) as usize])
+            .unwrap();
+        assert_eq!(block.len(), 64);
+        assert!(block.iter().all(|c| *c == Color32::from_rgb(0, 0, 255)));
+
+        assert_eq!(level.block_range(0, 1), None);
+        assert_eq!(level.block_range(u32::MAX, u32::MAX), None);
+    }
+
+    #[test]
+    fn deflate_with_predictor() {
+        // Horizontal differencing of a gradient, as written with PREDICTOR=2.
+        let mut samples = Vec::new();
+        for _ in 0..8 {
+            samples.extend_from_slice(&[10, 20, 30]);
+            samples.extend_from_slice(&[1, 2, 3].repeat(7));
+        }
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&samples).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let data = tiff(tags(8, 2, &WGS84_KEYS), &[compressed.clone(), compressed]);
+        let cog = parsed(&data);
+        let level = &cog.levels[0];
+        let (offset, length) = level.block_range(0, 0).unwrap();
+        let block = level
+            .decode_block(&data[offset as usize..(offset + length) as usize])
+            .unwrap();
+        assert_eq!(block[0], Color32::from_rgb(10, 20, 30));
+        assert_eq!(block[7], Color32::from_rgb(17, 34, 51));
+        assert_eq!(block[63], Color32::from_rgb(17, 34, 51));
+    }
+
+    #[test]
+    fn geographic_without_model_type() {
+        let data = tiff(
+            tags(1, 1, &[2048, 0, 1, 4326]),
+            &[block([0; 3]), block([0; 3])],
+        );
+        assert_eq!(parsed(&data).georeference.crs, Crs::Wgs84);
+    }
+
+    #[test]
+    fn web_mercator() {
+        let data = tiff(
+            tags(1, 1, &[1024, 0, 1, 1, 3072, 0, 1, 3857]),
+            &[block([0; 3]), block([0; 3])],
+        );
+        let cog = parsed(&data);
+        assert_eq!(cog.georeference.crs, Crs::WebMercator);
+        // Close to Null Island, as the coordinates are in meters.
+        assert!(cog.bounds().max().x < 0.001);
+    }
+
+    #[test]
+    fn unsupported_coordinate_system() {
+        let data = tiff(
+            tags(1, 1, &[1024, 0, 1, 1, 3072, 0, 1, 2180]),
+            &[block([0; 3]), block([0; 3])],
+        );
+        assert!(invalid_reason(&data).contains("EPSG:2180"));
+    }
+
+    #[test]
+    fn oversized_blocks() {
+        let mut tags = tags(1, 1, &WGS84_KEYS);
+        tags.retain(|(tag, _)| *tag != tag::TILE_WIDTH);
+        tags.push((tag::TILE_WIDTH, Value::Long(vec![u32::MAX])));
+        let data = tiff(tags, &[block([0; 3])]);
+        assert_eq!(invalid_reason(&data), "blocks are too large");
+    }
+
+    #[test]
+    fn truncated_header() {
+        let data = tiff(tags(1, 1, &WGS84_KEYS), &[block([0; 3]), block([0; 3])]);
+        assert!(matches!(parse(&data[..20]), Err(ParseError::Truncated(_))));
+        assert!(matches!(parse(b"II"), Err(ParseError::Truncated(_))));
+        assert_eq!(invalid_reason(b"PK\x03\x04"), "not a TIFF file");
+    }
+}
@@ -0,0 +1,196 @@
+//! Projections of arbitrary coordinate reference systems, transformed through
+//! [PROJ](https://proj.org).
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use proj::Proj;
+
+use crate::{units::haversine, BoundingBox, Position, Projection, TileId};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid coordinate reference system {crs}: {reason}")]
+pub struct InvalidCrs {
+    pub crs: String,
+    pub reason: String,
+}
+
+/// Transformations from WGS 84 to a CRS and back.
+struct Transformations {
+    forward: Proj,
+    inverse: Proj,
+}
+
+thread_local! {
+    // PROJ objects can not be shared between threads, while `Projection` must be, so each thread
+    // creates its own ones.
+    static TRANSFORMATIONS: RefCell<HashMap<String, Rc<Transformations>>> =
+        RefCell::new(HashMap::new());
+}
+
+fn transformations(crs: &str) -> Result<Rc<Transformations>, InvalidCrs> {
+    TRANSFORMATIONS.with(|cache| {
+        if let Some(transformations) = cache.borrow().get(crs) {
+            return Ok(transformations.clone());
+        }
+
+        let invalid = |err: proj::ProjCreateError| InvalidCrs {
+            crs: crs.to_owned(),
+            reason: err.to_string(),
+        };
+        let transformations = Rc::new(Transformations {
+            forward: Proj::new_known_crs("EPSG:4326", crs, None).map_err(invalid)?,
+            inverse: Proj::new_known_crs(crs, "EPSG:4326", None).map_err(invalid)?,
+        });
+        cache
+            .borrow_mut()
+            .insert(crs.to_owned(), transformations.clone());
+        Ok(transformations)
+    })
+}
+
+/// Projection of a coordinate reference system given by an EPSG code (e.g. `"EPSG:25832"`) or a
+/// PROJ string, for displaying data in national grids without reprojecting it first. Set it with
+/// [`crate::Map::projection`].
+///
+/// Tiles form a square grid over `bounds`, given in the CRS's units, which should be meters.
+/// Bounds which are not square are extended to the right and down, keeping the top-left corner,
+/// which is the usual origin of tile matrices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrsProjection {
+    crs: String,
+    origin: Position,
+    size: f64,
+}
+
+impl CrsProjection {
+    pub fn new(crs: impl Into<String>, bounds: BoundingBox) -> Result<Self, InvalidCrs> {
+        let crs = crs.into();
+        transformations(&crs)?;
+
+        Ok(Self {
+            origin: Position {
+                x: bounds.min().x,
+                y: bounds.max().y,
+            },
+            size: bounds.width().max(bounds.height()),
+            crs,
+        })
+    }
+
+    /// Coordinates in the CRS of the geographical position, or None if PROJ fails to
+    /// transform it.
+    pub fn forward(&self, position: Position) -> Option<Position> {
+        let transformations = transformations(&self.crs).ok()?;
+        let (x, y) = transformations
+            .forward
+            .convert((position.x, position.y))
+            .ok()?;
+        Some(Position { x, y })
+    }
+
+    /// Geographical position of coordinates in the CRS, or None if PROJ fails to transform them.
+    pub fn inverse(&self, position: Position) -> Option<Position> {
+        let transformations = transformations(&self.crs).ok()?;
+        let (x, y) = transformations
+            .inverse
+            .convert((position.x, position.y))
+            .ok()?;
+        Some(Position { x, y })
+    }
+
+    /// Tile of this projection's grid, which contains the position.
+    pub fn tile_id(&self, position: Position, zoom: u8) -> TileId {
+        crate::units::tile_id_of_normalized(self.normalize(position), zoom, 0)
+    }
+}
+
+impl Projection for CrsProjection {
+    fn normalize(&self, position: Position) -> (f64, f64) {
+        match self.forward(position) {
+            Some(projected) => (
+                (projected.x - self.origin.x) / self.size,
+                (self.origin.y - projected.y) / self.size,
+            ),
+            None => (f64::NAN, f64::NAN),
+        }
+    }
+
+    fn denormalize(&self, x: f64, y: f64) -> Position {
+        self.inverse(Position {
+            x: self.origin.x + x * self.size,
+            y: self.origin.y - y * self.size,
+        })
+        .unwrap_or(Position {
+            x: f64::NAN,
+            y: f64::NAN,
+        })
+    }
+
+    fn ground_width(&self, position: Position) -> f64 {
+        // Measure a short distance, since CRSs do not tell their scale factor directly.
+        const STEP: f64 = 0.001;
+        let (a, b) = (
+            Position {
+                x: position.x - STEP,
+                ..position
+            },
+            Position {
+                x: position.x + STEP,
+                ..position
+            },
+        );
+        match (self.forward(a), self.forward(b)) {
+            (Some(pa), Some(pb)) => self.size * haversine(a, b) / (pb.x - pa.x).hypot(pb.y - pa.y),
+            _ => self.size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::PositionTrait;
+
+    /// Half of the width of the Web Mercator's square, in meters.
+    const HALF_WORLD: f64 = 20_037_508.342_789_244;
+
+    fn web_mercator() -> CrsProjection {
+        CrsProjection::new(
+            "EPSG:3857",
+            BoundingBox::new(
+                Position {
+                    x: -HALF_WORLD,
+                    y: -HALF_WORLD,
+                },
+                Position {
+                    x: HALF_WORLD,
+                    y: HALF_WORLD,
+                },
+            ),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn same_as_built_in_mercator() {
+        let projection = web_mercator();
+
+        for (lon, lat) in [(0., 0.), (21.01, 52.23), (-73.98, 40.75), (151.21, -33.87)] {
+            let position = Position { x: lon, y: lat };
+            let (x, y) = projection.normalize(position);
+            let (expected_x, expected_y) = position.mercator_normalized();
+            assert!((x - expected_x).abs() < 1e-9, "{x} != {expected_x}");
+            assert!((y - expected_y).abs() < 1e-9, "{y} != {expected_y}");
+
+            let back = projection.denormalize(x, y);
+            assert!((back.x - lon).abs() < 1e-9 && (back.y - lat).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn invalid_crs() {
+        let bounds = BoundingBox::new(Position { x: 0., y: 0. }, Position { x: 1., y: 1. });
+        assert!(CrsProjection::new("EPSG:0", bounds).is_err());
+        assert!(CrsProjection::new("not a CRS", bounds).is_err());
+    }
+}
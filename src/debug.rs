@@ -0,0 +1,205 @@
+//! Tiles generated procedurally, for testing without the network.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use egui::{pos2, Color32, ColorImage, Context, Rect};
+use lru::LruCache;
+
+use crate::{sources::Attribution, Texture, TextureWithUv, TileId, Tiles};
+
+const SIZE: usize = crate::TILE_SIZE as usize;
+
+/// [`Tiles`] which render a checkerboard with the tile's `zoom/x/y` printed on it. Useful for
+/// debugging projections and for writing tests which do not need the network.
+///
+/// Network conditions can be simulated by [`DebugTiles::latency`] and
+/// [`DebugTiles::failure_rate`]. Both are deterministic - latency is measured using egui's input
+/// time, and failing tiles are chosen by hashing their [`TileId`] with [`DebugTiles::seed`].
+pub struct DebugTiles {
+    egui_ctx: Context,
+    cache: LruCache<TileId, Texture>,
+
+    /// When each pending tile was requested, in egui's input time.
+    requested: HashMap<TileId, f64>,
+
+    /// Tiles which already failed. They are not retried, like in HttpTiles.
+    failed: HashSet<TileId>,
+
+    errors: Vec<(TileId, String)>,
+    latency: Duration,
+    failure_rate: f32,
+    seed: u64,
+}
+
+impl DebugTiles {
+    pub fn new(egui_ctx: Context) -> Self {
+        // Same as in HttpTiles.
+        #[allow(clippy::unwrap_used)]
+        let cache_size = std::num::NonZeroUsize::new(256).unwrap();
+
+        Self {
+            egui_ctx,
+            cache: LruCache::new(cache_size),
+            requested: HashMap::new(),
+            failed: HashSet::new(),
+            errors: Vec::new(),
+            latency: Duration::ZERO,
+            failure_rate: 0.,
+            seed: 0,
+        }
+    }
+
+    /// Delay between requesting a tile and it becoming available.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Fraction of tiles, from 0 to 1, which fail to load.
+    pub fn failure_rate(mut self, failure_rate: f32) -> Self {
+        self.failure_rate = failure_rate.clamp(0., 1.);
+        self
+    }
+
+    /// Seed used to choose which tiles fail.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Whether the tile is one of the failing ones.
+    fn fails(&self, tile_id: TileId) -> bool {
+        // SplitMix64, good enough to spread the failures evenly.
+        let mut z = self.seed
+            ^ ((tile_id.x as u64) << 32 | tile_id.y as u64).wrapping_add(tile_id.zoom as u64);
+        z = z.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z as f64 / u64::MAX as f64) < self.failure_rate as f64
+    }
+}
+
+impl Tiles for DebugTiles {
+    fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+        let uv = Rect::from_min_max(pos2(0., 0.), pos2(1., 1.));
+
+        if let Some(texture) = self.cache.get(&tile_id) {
+            return Some(TextureWithUv {
+                texture: texture.clone(),
+                uv,
+            });
+        }
+
+        if self.failed.contains(&tile_id) {
+            return None;
+        }
+
+        let now = self.egui_ctx.input(|i| i.time);
+        let requested = *self.requested.entry(tile_id).or_insert(now);
+
+        if now - requested < self.latency.as_secs_f64() {
+            self.egui_ctx.request_repaint();
+            return None;
+        }
+
+        self.requested.remove(&tile_id);
+
+        if self.fails(tile_id) {
+            self.failed.insert(tile_id);
+            self.errors.push((tile_id, "simulated failure".to_owned()));
+            return None;
+        }
+
+        let texture = Texture::from_color_image(render(tile_id), &self.egui_ctx);
+        self.cache.put(tile_id, texture.clone());
+        Some(TextureWithUv { texture, uv })
+    }
+
+    fn attribution(&self) -> Attribution {
+        Attribution {
+            text: "Debug tiles",
+            url: "https://github.com/oyhj1801/walkers",
+            logo_light: None,
+            logo_dark: None,
+        }
+    }
+
+    fn tile_size(&self) -> u32 {
+        crate::TILE_SIZE
+    }
+
+    fn source_id(&self) -> String {
+        "debug".to_owned()
+    }
+
+    fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+/// Draw the checkerboard and the label.
+fn render(tile_id: TileId) -> ColorImage {
+    const CELL: usize = SIZE / 8;
+
+    let mut image = ColorImage::new([SIZE, SIZE], Color32::WHITE);
+
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let border = x == 0 || y == 0;
+            let dark = (x / CELL + y / CELL) % 2 == 1;
+            image[(x, y)] = match (border, dark) {
+                (true, _) => Color32::RED,
+                (false, true) => Color32::from_gray(180),
+                (false, false) => Color32::from_gray(220),
+            };
+        }
+    }
+
+    let label = format!("{}/{}/{}", tile_id.zoom, tile_id.x, tile_id.y);
+    draw_text(&mut image, &label, 8, 8, 3);
+    image
+}
+
+/// Draw the text with a tiny 3x5 pixel font, supporting only digits and a slash.
+fn draw_text(image: &mut ColorImage, text: &str, x: usize, y: usize, scale: usize) {
+    for (i, glyph) in text.chars().filter_map(glyph).enumerate() {
+        let glyph_x = x + i * 4 * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let px = glyph_x + column * scale + dx;
+                        let py = y + row * scale + dy;
+                        if px < SIZE && py < SIZE {
+                            image[(px, py)] = Color32::BLACK;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn glyph(c: char) -> Option<[u8; 5]> {
+    Some(match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        _ => return None,
+    })
+}
@@ -1,21 +1,31 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use egui::Context;
+use egui::{ColorImage, TextureFilter};
 use futures::{
+    channel::oneshot,
     future::{select, select_all, Either},
     SinkExt, StreamExt,
 };
 use image::ImageError;
-use reqwest::header::USER_AGENT;
-use reqwest_middleware::ClientWithMiddleware;
+use reqwest::header::{AGE, USER_AGENT};
+use reqwest::{Response, StatusCode};
+use reqwest_middleware::{ClientWithMiddleware, RequestBuilder};
 
 use crate::{
-    io::http_client,
-    sources::TileSource,
-    tiles::{Texture, TileId},
+    batch::BatchOptions,
+    cache::{source_key, TileCache},
+    io::{blocking, http_client, spawn},
+    sources::{source_tile_id, Authorization, SourceParameters, TileSource},
+    tiles::{decode, TileId},
+    validation::{validate, ValidationResult},
 };
 
 pub use reqwest::header::HeaderValue;
+pub use reqwest_middleware::Middleware;
 
 /// Controls how [`crate::HttpTiles`] use the HTTP protocol, such as caching.
 pub struct HttpOptions {
@@ -25,6 +35,8 @@ pub struct HttpOptions {
     /// to respect the HTTP `Expires` header.
     /// <https://operations.osmfoundation.org/policies/tiles/>
     ///
+    /// Tiles are requested with gzip or brotli content encoding, which servers may use.
+    ///
     /// This option is ignored in WASM, as HTTP cache is controlled by the
     /// browser the app is running on.
     pub cache: Option<PathBuf>,
@@ -34,6 +46,125 @@ pub struct HttpOptions {
     /// This should be set only on native targets. The browser sets its own user agent on wasm
     /// targets, and trying to set a different one may upset some servers (e.g. MapBox)
     pub user_agent: Option<HeaderValue>,
+
+    /// Storage consulted before downloading a tile, and filled with downloaded ones. See
+    /// [`crate::MemoryCache`] and [`crate::DiskCache`] for built-in implementations.
+    pub tile_cache: Option<Arc<dyn TileCache>>,
+
+    /// Limits how many downloaded tiles are put in the cache in a single frame.
+    pub upload_budget: UploadBudget,
+
+    /// Download tiles with the browser's `fetch`, using these options, instead of the regular
+    /// HTTP client. Such requests can be intercepted by a service worker, e.g. to serve tiles
+    /// offline in a PWA.
+    ///
+    /// This option is ignored on native targets.
+    pub fetch: Option<FetchOptions>,
+
+    /// Download tiles in batches from a custom endpoint, instead of using the source's URLs.
+    pub batch: Option<BatchOptions>,
+
+    /// Transform applied to each tile after it is decoded, e.g. to crop a watermark, recolor a
+    /// categorical raster or apply gamma. It runs in the IO thread, and only the result is kept
+    /// in memory, while the [`HttpOptions::tile_cache`] holds original images.
+    pub post_process: Option<PostProcess>,
+
+    /// Additional layers of the HTTP client, e.g. for tracing, retrying or refreshing
+    /// credentials. They run in order, after the [`HttpOptions::cache`], so only for requests
+    /// which actually reach the network.
+    ///
+    /// Not used when downloading with [`HttpOptions::fetch`].
+    pub middleware: Vec<Arc<dyn Middleware>>,
+
+    /// How tiles are sampled when drawn at other than their native size, e.g. when zoomed between
+    /// levels, rotated or tilted. [`TextureFilter::Nearest`] is cheaper on weak hardware and keeps
+    /// pixels of categorical rasters crisp. Default is [`TextureFilter::Linear`].
+    pub texture_filter: TextureFilter,
+}
+
+/// See [`HttpOptions::post_process`].
+pub type PostProcess = Arc<dyn Fn(&TileId, ColorImage) -> ColorImage + Send + Sync>;
+
+/// Options of the browser's `fetch`. See [`HttpOptions::fetch`] and
+/// <https://developer.mozilla.org/en-US/docs/Web/API/RequestInit>.
+#[derive(Clone, Debug, Default)]
+pub struct FetchOptions {
+    pub mode: FetchMode,
+    pub credentials: FetchCredentials,
+    pub cache: FetchCache,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FetchMode {
+    #[default]
+    Cors,
+    /// Note that responses to such requests are opaque, so tiles can be read only if they are
+    /// served by a service worker.
+    NoCors,
+    SameOrigin,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FetchCredentials {
+    Omit,
+    #[default]
+    SameOrigin,
+    Include,
+}
+
+/// How the request interacts with the browser's HTTP cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FetchCache {
+    #[default]
+    Default,
+    NoStore,
+    Reload,
+    NoCache,
+    ForceCache,
+    OnlyIfCached,
+}
+
+/// Where a tile came from. See [`crate::HttpTiles::tile_info`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileOrigin {
+    /// [`HttpOptions::tile_cache`].
+    TileCache,
+
+    /// HTTP cache in [`HttpOptions::cache`] directory.
+    HttpCache,
+
+    /// Tile server. In WASM, this includes tiles served from the browser's cache, as there is no
+    /// way to tell them apart.
+    Network,
+}
+
+/// How a tile was obtained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileInfo {
+    pub origin: TileOrigin,
+
+    /// How long the tile has been cached for, according to the `Age` header, if there was one.
+    pub age: Option<Duration>,
+}
+
+/// Per-frame limit of the work spent on putting downloaded tiles in the cache. When a burst of
+/// tiles arrives, the remainder is deferred to the subsequent frames, avoiding hitches.
+#[derive(Clone, Copy, Debug)]
+pub struct UploadBudget {
+    /// Maximum number of tiles per frame.
+    pub max_tiles: usize,
+
+    /// Maximum time spent per frame. Ignored in WASM, where there is no monotonic clock available.
+    pub max_duration: Option<Duration>,
+}
+
+impl Default for UploadBudget {
+    fn default() -> Self {
+        Self {
+            max_tiles: 4,
+            max_duration: Some(Duration::from_millis(4)),
+        }
+    }
 }
 
 impl Default for HttpOptions {
@@ -51,12 +182,19 @@ impl Default for HttpOptions {
         Self {
             cache: None,
             user_agent,
+            tile_cache: None,
+            upload_budget: UploadBudget::default(),
+            fetch: None,
+            batch: None,
+            post_process: None,
+            middleware: Vec::new(),
+            texture_filter: TextureFilter::Linear,
         }
     }
 }
 
 #[derive(Debug, thiserror::Error)]
-enum Error {
+pub(crate) enum Error {
     #[error(transparent)]
     HttpMiddleware(reqwest_middleware::Error),
 
@@ -66,6 +204,22 @@ enum Error {
     #[error(transparent)]
     Image(ImageError),
 
+    #[error("{0}")]
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    Fetch(String),
+
+    #[error("could not authorize the request: {0}")]
+    Authorization(String),
+
+    #[error("batch download failed: {0}")]
+    Batch(String),
+
+    #[error("tile is missing from the batch response")]
+    MissingFromBatch,
+
+    #[error("tile is not on the map")]
+    OutsideMap,
+
     #[error("Tile request channel from the main thread was broken.")]
     RequestChannelBroken,
 
@@ -86,71 +240,314 @@ impl From<futures::channel::mpsc::SendError> for Error {
     }
 }
 
+/// [`SourceParameters`] shared between the main and the IO thread, along with their generation,
+/// which is bumped on each change, so tiles downloaded with outdated parameters can be dropped.
+#[derive(Clone, Default)]
+pub(crate) struct SharedParameters(Arc<Mutex<(SourceParameters, u64)>>);
+
+impl SharedParameters {
+    /// Set the parameter, returning the new generation.
+    pub(crate) fn set(&self, key: String, value: String) -> u64 {
+        match self.0.lock() {
+            Ok(mut guard) => {
+                guard.0.insert(key, value);
+                guard.1 += 1;
+                guard.1
+            }
+            Err(_) => {
+                log::error!("Source parameters are poisoned.");
+                0
+            }
+        }
+    }
+
+    /// URL of the tile given the current parameters, along with their generation, or the failed
+    /// download of a tile which the source cannot number.
+    fn url(&self, source: &impl TileSource, tile_id: TileId) -> Result<(String, u64), Download> {
+        let guard = self.0.lock();
+        let Some(source_tile_id) = source_tile_id(source, tile_id) else {
+            return Err(Download {
+                tile_id,
+                generation: guard.map_or(0, |guard| guard.1),
+                result: Err(Error::OutsideMap),
+            });
+        };
+        Ok(match guard {
+            Ok(guard) => (
+                source.tile_url_with_parameters(source_tile_id, &guard.0),
+                guard.1,
+            ),
+            Err(_) => (source.tile_url(source_tile_id), 0),
+        })
+    }
+
+    /// Key of the tile in the [`TileCache`]. See [`TileSource::cache_id`].
+    fn cache_key(&self, source: &impl TileSource, tile_id: TileId, url: &str) -> String {
+        match (source.cache_id(), self.0.lock()) {
+            (Some(source_id), Ok(guard)) => source_key(&source_id, tile_id, &guard.0),
+            _ => url.to_owned(),
+        }
+    }
+}
+
+/// Request from the main thread to the IO thread.
+pub(crate) enum Request {
+    Tile(TileId),
+
+    /// See [`crate::HttpTiles::validate`].
+    Validate(oneshot::Sender<ValidationResult>),
+}
+
 struct Download {
     tile_id: TileId,
-    result: Result<Texture, Error>,
+    generation: u64,
+    result: Result<(ColorImage, TileInfo), Error>,
 }
 
-/// Download and decode the tile.
-async fn download_and_decode(
-    client: &ClientWithMiddleware,
-    tile_id: TileId,
-    url: String,
-    user_agent: Option<&HeaderValue>,
-    egui_ctx: &Context,
-) -> Download {
-    log::trace!("Downloading '{}'.", url);
-    Download {
-        tile_id,
-        result: download_and_decode_impl(client, url, user_agent, egui_ctx).await,
+/// Everything needed to download a tile, other than its URL.
+pub(crate) struct Fetcher {
+    pub(crate) client: ClientWithMiddleware,
+    pub(crate) user_agent: Option<HeaderValue>,
+    tile_cache: Option<Arc<dyn TileCache>>,
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    fetch: Option<FetchOptions>,
+    batch: Option<BatchOptions>,
+    post_process: Option<PostProcess>,
+    pub(crate) authorization: Option<Arc<dyn Authorization>>,
+}
+
+impl Fetcher {
+    pub(crate) fn new(http_options: HttpOptions) -> Self {
+        Self {
+            user_agent: http_options.user_agent.clone(),
+            tile_cache: http_options.tile_cache.clone(),
+            fetch: http_options.fetch.clone(),
+            batch: http_options.batch.clone(),
+            post_process: http_options.post_process.clone(),
+            authorization: None,
+            // Keep it here to reuse it as much as possible.
+            client: http_client(http_options),
+        }
+    }
+
+    /// Download and decode the tile.
+    async fn download_and_decode(
+        &self,
+        tile_id: TileId,
+        generation: u64,
+        url: String,
+        cache_key: String,
+    ) -> Download {
+        let result = self.download_and_decode_impl(url, cache_key).await;
+        self.post_processed(Download {
+            tile_id,
+            generation,
+            result,
+        })
+    }
+
+    /// Apply [`HttpOptions::post_process`] to successfully decoded tile.
+    fn post_processed(&self, download: Download) -> Download {
+        match (&self.post_process, download.result) {
+            (Some(post_process), Ok((image, info))) => Download {
+                result: Ok((post_process(&download.tile_id, image), info)),
+                ..download
+            },
+            (_, result) => Download { result, ..download },
+        }
+    }
+
+    async fn download_and_decode_impl(
+        &self,
+        url: String,
+        cache_key: String,
+    ) -> Result<(ColorImage, TileInfo), Error> {
+        if let Some(image) = self.cached(&cache_key).await {
+            log::trace!("Found '{}' in the tile cache.", cache_key);
+            let info = TileInfo {
+                origin: TileOrigin::TileCache,
+                age: None,
+            };
+            return Ok((decode(&image).map_err(Error::Image)?, info));
+        }
+
+        let (image, info) = self.download(&url).await?;
+        let decoded = decode(&image).map_err(Error::Image)?;
+
+        // Store only valid images.
+        self.cache(cache_key, image).await;
+
+        Ok((decoded, info))
+    }
+
+    /// Tile stored in the [`TileCache`]. It is read on the blocking pool, as caches are likely
+    /// to do filesystem IO.
+    async fn cached(&self, cache_key: &str) -> Option<Vec<u8>> {
+        let tile_cache = self.tile_cache.clone()?;
+        let cache_key = cache_key.to_owned();
+        blocking(move || tile_cache.get(&cache_key)).await.flatten()
+    }
+
+    /// Store the tile in the [`TileCache`], on the blocking pool like [`Fetcher::cached`].
+    async fn cache(&self, cache_key: String, image: Vec<u8>) {
+        if let Some(tile_cache) = self.tile_cache.clone() {
+            blocking(move || tile_cache.put(&cache_key, &image)).await;
+        }
+    }
+
+    pub(crate) async fn download(&self, url: &str) -> Result<(Vec<u8>, TileInfo), Error> {
+        #[cfg(target_arch = "wasm32")]
+        if let Some(fetch) = &self.fetch {
+            log::trace!("Fetching '{}'.", url);
+            let image = crate::io::fetch(url, fetch).await.map_err(Error::Fetch)?;
+            let info = TileInfo {
+                origin: TileOrigin::Network,
+                age: None,
+            };
+            return Ok((image, info));
+        }
+
+        log::trace!("Downloading '{}'.", url);
+        let mut image_request = self.client.get(url);
+
+        if let Some(user_agent) = &self.user_agent {
+            image_request = image_request.header(USER_AGENT, user_agent);
+        }
+
+        let image = self.send(image_request).await?;
+
+        log::trace!("Downloaded '{}': {:?}.", url, image.status());
+
+        let info = response_info(image.headers());
+        let image = image
+            .error_for_status()
+            .map_err(Error::Http)?
+            .bytes()
+            .await
+            .map_err(Error::Http)?;
+
+        Ok((image.to_vec(), info))
+    }
+
+    /// Validate the source in a task of its own, so that tile downloads go on meanwhile.
+    fn validate(
+        &self,
+        url: String,
+        result_tx: oneshot::Sender<ValidationResult>,
+        repaint: &Arc<Repaint>,
+    ) {
+        let client = self.client.clone();
+        let user_agent = self.user_agent.clone();
+        let repaint = repaint.clone();
+        spawn(async move {
+            let result = validate(&client, user_agent.as_ref(), &url).await;
+
+            // Nobody might be waiting for the result anymore, which is fine.
+            let _ = result_tx.send(result);
+            repaint();
+        });
+    }
+}
+
+impl Fetcher {
+    /// Send the request with the source's [`Authorization`], if any. If the server rejects the
+    /// credentials, they are refreshed and the request is sent again, once.
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let Some(authorization) = &self.authorization else {
+            return request.send().await.map_err(Error::HttpMiddleware);
+        };
+
+        let mut refresh = false;
+        loop {
+            let built = request
+                .try_clone()
+                .ok_or_else(|| Error::Authorization("request cannot be repeated".to_owned()))?
+                .build()
+                .map_err(Error::Http)?;
+            let built = authorization
+                .authorize(built, refresh)
+                .await
+                .map_err(Error::Authorization)?;
+            let response = self
+                .client
+                .execute(built)
+                .await
+                .map_err(Error::HttpMiddleware)?;
+
+            if response.status() == StatusCode::UNAUTHORIZED && !refresh {
+                log::debug!("Credentials rejected, refreshing them.");
+                refresh = true;
+                continue;
+            }
+            return Ok(response);
+        }
     }
 }
 
-async fn download_and_decode_impl(
-    client: &ClientWithMiddleware,
-    url: String,
-    user_agent: Option<&HeaderValue>,
-    egui_ctx: &Context,
-) -> Result<Texture, Error> {
-    let mut image_request = client.get(&url);
+/// Tell whether the response came from the HTTP cache, by the header which the cache middleware
+/// adds to the response.
+fn response_info(headers: &reqwest::header::HeaderMap) -> TileInfo {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
 
-    if let Some(user_agent) = user_agent {
-        image_request = image_request.header(USER_AGENT, user_agent);
+    TileInfo {
+        origin: if header("x-cache") == Some("HIT") {
+            TileOrigin::HttpCache
+        } else {
+            TileOrigin::Network
+        },
+        age: header(AGE.as_str())
+            .and_then(|age| age.trim().parse().ok())
+            .map(Duration::from_secs),
     }
+}
 
-    let image = image_request.send().await.map_err(Error::HttpMiddleware)?;
+/// URL of the top-level tile, which every source is expected to have.
+fn validation_url(source: &impl TileSource, parameters: &SharedParameters) -> String {
+    let tile_id = TileId {
+        x: 0,
+        y: 0,
+        zoom: 0,
+    };
+    match parameters.url(source, tile_id) {
+        Ok((url, _)) => url,
+        // The only tile of zoom 0 is always on the map.
+        Err(_) => source.tile_url(tile_id),
+    }
+}
 
-    log::trace!("Downloaded '{}': {:?}.", url, image.status());
+/// Result of a single download, as delivered to the main thread. Images are only decoded here,
+/// uploading them as textures is up to the main thread.
+pub(crate) struct TileResult {
+    pub tile_id: TileId,
 
-    let image = image
-        .error_for_status()
-        .map_err(Error::Http)?
-        .bytes()
-        .await
-        .map_err(Error::Http)?;
+    /// Generation of the [`SharedParameters`] used to download the tile.
+    pub generation: u64,
 
-    Texture::new(&image, egui_ctx).map_err(Error::Image)
+    pub result: Result<(ColorImage, TileInfo), String>,
 }
 
+/// Called whenever a tile was downloaded, so the main thread can pick it up.
+pub(crate) type Repaint = Box<dyn Fn() + Send + Sync>;
+
 async fn download_complete(
-    mut tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
-    egui_ctx: Context,
+    mut tile_tx: futures::channel::mpsc::Sender<TileResult>,
+    repaint: &Repaint,
     download: Download,
 ) -> Result<(), Error> {
-    match download.result {
-        Ok(tile) => {
-            tile_tx
-                .send((download.tile_id, tile))
-                .await
-                .map_err(Error::from)?;
-            egui_ctx.request_repaint();
-        }
-        Err(e) => {
-            // It would probably be more consistent to push it to the caller, but it's not that
-            // important right now.
-            log::warn!("{}", e);
-        }
-    };
+    let result = download.result.map_err(|e| {
+        log::warn!("{}", e);
+        e.to_string()
+    });
+
+    tile_tx
+        .send(TileResult {
+            tile_id: download.tile_id,
+            generation: download.generation,
+            result,
+        })
+        .await
+        .map_err(Error::from)?;
+    repaint();
 
     Ok(())
 }
@@ -162,66 +559,233 @@ pub(crate) const MAX_PARALLEL_DOWNLOADS: usize = 6;
 async fn download_continuously_impl<S>(
     source: S,
     http_options: HttpOptions,
-    mut request_rx: futures::channel::mpsc::Receiver<TileId>,
-    tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
-    egui_ctx: Context,
+    mut request_rx: futures::channel::mpsc::Receiver<Request>,
+    tile_tx: futures::channel::mpsc::Sender<TileResult>,
+    parameters: SharedParameters,
+    repaint: Arc<Repaint>,
 ) -> Result<(), Error>
 where
     S: TileSource + Send + 'static,
 {
-    let user_agent = http_options.user_agent.clone();
+    let mut fetcher = Fetcher::new(http_options);
+    fetcher.authorization = source.authorization();
+
+    if let Some(batch) = fetcher.batch.clone() {
+        return download_batches_continuously(
+            source, fetcher, batch, request_rx, tile_tx, parameters, repaint,
+        )
+        .await;
+    }
 
-    // Keep outside the loop to reuse it as much as possible.
-    let client = http_client(http_options);
     let mut downloads = Vec::new();
 
     loop {
         if downloads.is_empty() {
             // Only new downloads might be requested.
-            let tile_id = request_rx.next().await.ok_or(Error::RequestChannelBroken)?;
-            let url = source.tile_url(tile_id);
-            let download =
-                download_and_decode(&client, tile_id, url, user_agent.as_ref(), &egui_ctx);
-            downloads.push(Box::pin(download));
+            match request_rx.next().await.ok_or(Error::RequestChannelBroken)? {
+                Request::Tile(tile_id) => match parameters.url(&source, tile_id) {
+                    Ok((url, generation)) => {
+                        let cache_key = parameters.cache_key(&source, tile_id, &url);
+                        let download =
+                            fetcher.download_and_decode(tile_id, generation, url, cache_key);
+                        downloads.push(Box::pin(download));
+                    }
+                    Err(failed) => download_complete(tile_tx.to_owned(), &repaint, failed).await?,
+                },
+                Request::Validate(result_tx) => {
+                    fetcher.validate(validation_url(&source, &parameters), result_tx, &repaint)
+                }
+            }
         } else if downloads.len() < MAX_PARALLEL_DOWNLOADS {
             // New downloads might be requested or ongoing downloads might be completed.
             let download = select_all(downloads.drain(..));
             match select(request_rx.next(), download).await {
                 // New download was requested.
                 Either::Left((request, remaining_downloads)) => {
-                    let tile_id = request.ok_or(Error::RequestChannelBroken)?;
-                    let url = source.tile_url(tile_id);
-                    let download =
-                        download_and_decode(&client, tile_id, url, user_agent.as_ref(), &egui_ctx);
                     downloads = remaining_downloads.into_inner();
-                    downloads.push(Box::pin(download));
+                    match request.ok_or(Error::RequestChannelBroken)? {
+                        Request::Tile(tile_id) => match parameters.url(&source, tile_id) {
+                            Ok((url, generation)) => {
+                                let cache_key = parameters.cache_key(&source, tile_id, &url);
+                                let download = fetcher
+                                    .download_and_decode(tile_id, generation, url, cache_key);
+                                downloads.push(Box::pin(download));
+                            }
+                            Err(failed) => {
+                                download_complete(tile_tx.to_owned(), &repaint, failed).await?
+                            }
+                        },
+                        Request::Validate(result_tx) => fetcher.validate(
+                            validation_url(&source, &parameters),
+                            result_tx,
+                            &repaint,
+                        ),
+                    }
                 }
                 // Ongoing download was completed.
                 Either::Right(((result, _, remaining_downloads), _)) => {
-                    download_complete(tile_tx.to_owned(), egui_ctx.to_owned(), result).await?;
+                    download_complete(tile_tx.to_owned(), &repaint, result).await?;
                     downloads = remaining_downloads;
                 }
             }
         } else {
             // Only ongoing downloads might be completed.
             let (result, _, remaining_downloads) = select_all(downloads.drain(..)).await;
-            download_complete(tile_tx.to_owned(), egui_ctx.to_owned(), result).await?;
+            download_complete(tile_tx.to_owned(), &repaint, result).await?;
             downloads = remaining_downloads;
         }
     }
 }
 
+/// Like [`download_continuously_impl`], but downloading all tiles requested in the meantime in a
+/// single batch.
+async fn download_batches_continuously<S>(
+    source: S,
+    fetcher: Fetcher,
+    batch: BatchOptions,
+    mut request_rx: futures::channel::mpsc::Receiver<Request>,
+    tile_tx: futures::channel::mpsc::Sender<TileResult>,
+    parameters: SharedParameters,
+    repaint: Arc<Repaint>,
+) -> Result<(), Error>
+where
+    S: TileSource + Send + 'static,
+{
+    loop {
+        let mut requests = vec![request_rx.next().await.ok_or(Error::RequestChannelBroken)?];
+        while requests.len() < batch.max_tiles.max(1) {
+            match request_rx.try_recv() {
+                Ok(request) => requests.push(request),
+                Err(futures::channel::mpsc::TryRecvError::Closed) => {
+                    return Err(Error::RequestChannelBroken)
+                }
+                Err(futures::channel::mpsc::TryRecvError::Empty) => break,
+            }
+        }
+
+        // URLs are not downloaded, but they identify the tiles in the tile cache.
+        let mut tiles = Vec::new();
+        for request in requests {
+            match request {
+                Request::Tile(tile_id) => match parameters.url(&source, tile_id) {
+                    Ok((url, generation)) => tiles.push((
+                        tile_id,
+                        generation,
+                        parameters.cache_key(&source, tile_id, &url),
+                    )),
+                    Err(failed) => download_complete(tile_tx.to_owned(), &repaint, failed).await?,
+                },
+                Request::Validate(result_tx) => {
+                    fetcher.validate(validation_url(&source, &parameters), result_tx, &repaint)
+                }
+            }
+        }
+
+        for download in fetcher.download_and_decode_batch(&batch, tiles).await {
+            let download = fetcher.post_processed(download);
+            download_complete(tile_tx.to_owned(), &repaint, download).await?;
+        }
+    }
+}
+
+impl Fetcher {
+    /// Take tiles from the tile cache, and download the rest in a single batch.
+    async fn download_and_decode_batch(
+        &self,
+        batch: &BatchOptions,
+        tiles: Vec<(TileId, u64, String)>,
+    ) -> Vec<Download> {
+        let mut downloads = Vec::new();
+        let mut missing = Vec::new();
+
+        for (tile_id, generation, cache_key) in tiles {
+            match self.cached(&cache_key).await {
+                Some(image) => downloads.push(Download {
+                    tile_id,
+                    generation,
+                    result: decode(&image).map_err(Error::Image).map(|image| {
+                        let info = TileInfo {
+                            origin: TileOrigin::TileCache,
+                            age: None,
+                        };
+                        (image, info)
+                    }),
+                }),
+                None => missing.push((tile_id, generation, cache_key)),
+            }
+        }
+
+        if missing.is_empty() {
+            return downloads;
+        }
+
+        let tile_ids: Vec<TileId> = missing.iter().map(|(tile_id, _, _)| *tile_id).collect();
+        let mut received =
+            match self.download_batch(batch, &tile_ids).await {
+                Ok(received) => received,
+                Err(error) => {
+                    downloads.extend(missing.into_iter().map(|(tile_id, generation, _)| {
+                        Download {
+                            tile_id,
+                            generation,
+                            result: Err(Error::Batch(error.clone())),
+                        }
+                    }));
+                    return downloads;
+                }
+            };
+
+        for (tile_id, generation, cache_key) in missing {
+            let image = received
+                .iter()
+                .position(|(received_id, _)| *received_id == tile_id)
+                .map(|index| received.swap_remove(index).1);
+
+            let result = match image.map(|image| (decode(&image), image)) {
+                Some((Ok(decoded), image)) => {
+                    self.cache(cache_key, image).await;
+                    let info = TileInfo {
+                        origin: TileOrigin::Network,
+                        age: None,
+                    };
+                    Ok((decoded, info))
+                }
+                Some((Err(error), _)) => Err(Error::Image(error)),
+                None => Err(Error::MissingFromBatch),
+            };
+
+            downloads.push(Download {
+                tile_id,
+                generation,
+                result,
+            });
+        }
+
+        downloads
+    }
+}
+
 /// Continuously download tiles requested via request channel.
 pub(crate) async fn download_continuously<S>(
     source: S,
     http_options: HttpOptions,
-    request_rx: futures::channel::mpsc::Receiver<TileId>,
-    tile_tx: futures::channel::mpsc::Sender<(TileId, Texture)>,
-    egui_ctx: Context,
+    request_rx: futures::channel::mpsc::Receiver<Request>,
+    tile_tx: futures::channel::mpsc::Sender<TileResult>,
+    parameters: SharedParameters,
+    repaint: Repaint,
 ) where
     S: TileSource + Send + 'static,
 {
-    match download_continuously_impl(source, http_options, request_rx, tile_tx, egui_ctx).await {
+    match download_continuously_impl(
+        source,
+        http_options,
+        request_rx,
+        tile_tx,
+        parameters,
+        Arc::new(repaint),
+    )
+    .await
+    {
         Ok(()) | Err(Error::TileChannelClosed) | Err(Error::RequestChannelBroken) => {
             log::debug!("Tile download loop finished.");
         }
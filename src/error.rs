@@ -0,0 +1,40 @@
+use crate::{
+    sources::InvalidTileSize, InvalidCoordinates, InvalidUtmZone, InvalidZoom, ValidationError,
+};
+
+/// Any of the errors returned by this crate, for applications which would rather handle them in
+/// one place, e.g. when applying user-provided configuration.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    InvalidZoom(#[from] InvalidZoom),
+
+    #[error(transparent)]
+    InvalidCoordinates(#[from] InvalidCoordinates),
+
+    #[error(transparent)]
+    InvalidTileSize(#[from] InvalidTileSize),
+
+    #[error(transparent)]
+    InvalidUtmZone(#[from] InvalidUtmZone),
+
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+
+    #[cfg(feature = "wmm")]
+    #[error(transparent)]
+    Wmm(#[from] crate::WmmError),
+
+    #[cfg(all(feature = "proj", not(target_arch = "wasm32")))]
+    #[error(transparent)]
+    InvalidCrs(#[from] crate::InvalidCrs),
+
+    #[cfg(all(feature = "mbtiles", not(target_arch = "wasm32")))]
+    #[error(transparent)]
+    MbTiles(#[from] crate::MbTilesError),
+
+    #[cfg(target_arch = "wasm32")]
+    #[error(transparent)]
+    Geolocation(#[from] crate::GeolocationError),
+}
@@ -0,0 +1,90 @@
+use egui::{PointerButton, Response};
+
+use crate::{Position, TileId};
+
+/// Interactions and state changes of the map widget, useful e.g. for analytics.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapEvent {
+    /// User started dragging the map, which was centered at `center`.
+    DragStarted { center: Position },
+
+    /// User released the map, which is now centered at `center`.
+    DragEnded { center: Position },
+
+    /// Map was long pressed at `position`. See [`crate::MapMemory::long_press`].
+    LongPress { position: Position },
+
+    /// Map stopped moving, after being dragged, moved by inertia or sprung back from the
+    /// world's edge. See [`crate::MapMemory::is_settled`].
+    Settled { center: Position },
+
+    /// Zoom level was changed by a gesture.
+    ZoomChanged { from: f64, to: f64 },
+
+    /// Tiles passed to the map come from a different source than in the previous frame.
+    /// Sources are told apart by [`crate::Tiles::source_id`]. Not emitted for the first tiles
+    /// shown by the map.
+    SourceSwitched { attribution: &'static str },
+
+    /// Tile could not be downloaded or decoded.
+    TileError { tile_id: TileId, message: String },
+}
+
+type Listener<'b> = Box<dyn FnMut(&MapEvent) + 'b>;
+
+/// Callbacks subscribed to the map's events. They are called at the end of the frame in which
+/// the event happened.
+#[derive(Default)]
+pub(crate) struct EventListeners<'b> {
+    listeners: Vec<Listener<'b>>,
+    pending: Vec<MapEvent>,
+}
+
+impl<'b> EventListeners<'b> {
+    pub(crate) fn subscribe(&mut self, listener: impl FnMut(&MapEvent) + 'b) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, event: MapEvent) {
+        if !self.is_empty() {
+            self.pending.push(event);
+        }
+    }
+
+    /// Record drag and zoom events which happened in this frame.
+    pub(crate) fn push_gestures(
+        &mut self,
+        response: &Response,
+        zoom_before: f64,
+        zoom_after: f64,
+        center: Position,
+    ) {
+        if response.drag_started_by(PointerButton::Primary) {
+            self.push(MapEvent::DragStarted { center });
+        }
+
+        if response.drag_stopped_by(PointerButton::Primary) {
+            self.push(MapEvent::DragEnded { center });
+        }
+
+        if zoom_before != zoom_after {
+            self.push(MapEvent::ZoomChanged {
+                from: zoom_before,
+                to: zoom_after,
+            });
+        }
+    }
+
+    /// Deliver pending events to all listeners.
+    pub(crate) fn dispatch(&mut self) {
+        for event in self.pending.drain(..) {
+            for listener in &mut self.listeners {
+                listener(&event);
+            }
+        }
+    }
+}
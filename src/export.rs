@@ -0,0 +1,474 @@
+//! Export of the map into SVG, e.g. for report generation, and georeferencing of exported images.
+
+use std::{collections::HashMap, f64::consts::PI, fmt::Write as _};
+
+use egui::{
+    epaint::{
+        ClippedShape, ColorMode, CubicBezierShape, Mesh, PathShape, PathStroke,
+        QuadraticBezierShape, RectShape, TextShape,
+    },
+    Color32, ColorImage, Pos2, Rect, Shape, Stroke, TextureId, Ui, Vec2,
+};
+use image::ImageEncoder as _;
+
+use crate::{projector::ProjectorType, MapMemory, Position, Snapshot};
+
+impl Snapshot {
+    /// Like [`Snapshot::render`], but produces an SVG document. Shapes drawn by plugins are
+    /// exported as vectors, while tiles and other textured meshes are embedded as PNG images.
+    pub fn render_svg(&mut self, add_contents: impl FnMut(&mut Ui)) -> String {
+        let (shapes, _) = self.run(add_contents);
+        let size = self.size();
+
+        let mut svg = Svg::default();
+        for ClippedShape { clip_rect, shape } in shapes {
+            let clip = svg.clip(clip_rect);
+            let _ = write!(svg.body, r#"<g clip-path="url(#{clip})">"#);
+            svg.shape(self, &shape);
+            svg.body.push_str("</g>\n");
+        }
+
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{}" height="{}" viewBox="0 0 {} {}">
+<defs>
+{}</defs>
+{}</svg>
+"#,
+            size.x, size.y, size.x, size.y, svg.defs, svg.body
+        )
+    }
+}
+
+#[derive(Default)]
+struct Svg {
+    defs: String,
+    body: String,
+    clips: HashMap<[u32; 4], String>,
+    images: HashMap<TextureId, String>,
+    triangles: usize,
+}
+
+impl Svg {
+    /// Id of the clip path for given rect, defining it if needed.
+    fn clip(&mut self, rect: Rect) -> String {
+        let key = [rect.min.x, rect.min.y, rect.max.x, rect.max.y].map(f32::to_bits);
+        let next_id = format!("clip{}", self.clips.len());
+        self.clips
+            .entry(key)
+            .or_insert_with(|| {
+                let _ = writeln!(
+                    self.defs,
+                    r#"<clipPath id="{next_id}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                    rect.min.x,
+                    rect.min.y,
+                    rect.width(),
+                    rect.height()
+                );
+                next_id
+            })
+            .clone()
+    }
+
+    /// Id of the image holding given texture, embedding it if needed.
+    fn image(&mut self, id: TextureId, texture: &ColorImage) -> String {
+        let next_id = format!("image{}", self.images.len());
+        self.images
+            .entry(id)
+            .or_insert_with(|| {
+                let _ = writeln!(
+                    self.defs,
+                    r#"<image id="{next_id}" width="{}" height="{}" preserveAspectRatio="none" xlink:href="data:image/png;base64,{}"/>"#,
+                    texture.width(),
+                    texture.height(),
+                    base64(&png(texture))
+                );
+                next_id
+            })
+            .clone()
+    }
+
+    fn shape(&mut self, snapshot: &Snapshot, shape: &Shape) {
+        match shape {
+            Shape::Noop | Shape::Callback(_) => {}
+            Shape::Vec(shapes) => {
+                for shape in shapes {
+                    self.shape(snapshot, shape);
+                }
+            }
+            Shape::Circle(circle) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<circle cx="{}" cy="{}" r="{}" {} {}/>"#,
+                    circle.center.x,
+                    circle.center.y,
+                    circle.radius,
+                    fill(circle.fill),
+                    stroke(&circle.stroke)
+                );
+            }
+            Shape::Ellipse(ellipse) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {} {}/>"#,
+                    ellipse.center.x,
+                    ellipse.center.y,
+                    ellipse.radius.x,
+                    ellipse.radius.y,
+                    fill(ellipse.fill),
+                    stroke(&ellipse.stroke)
+                );
+            }
+            Shape::LineSegment { points, stroke } => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                    points[0].x,
+                    points[0].y,
+                    points[1].x,
+                    points[1].y,
+                    path_stroke(stroke)
+                );
+            }
+            Shape::Path(PathShape {
+                points,
+                closed,
+                fill: fill_color,
+                stroke,
+            }) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<{} points="{}" {} {}/>"#,
+                    if *closed { "polygon" } else { "polyline" },
+                    points_attribute(points),
+                    fill(if *closed {
+                        *fill_color
+                    } else {
+                        Color32::TRANSPARENT
+                    }),
+                    path_stroke(stroke)
+                );
+            }
+            Shape::Rect(rect) => self.rect(snapshot, rect),
+            Shape::Text(text) => self.text(text),
+            Shape::Mesh(mesh) => self.mesh(snapshot, mesh),
+            Shape::QuadraticBezier(QuadraticBezierShape {
+                points: [a, b, c],
+                closed,
+                fill: fill_color,
+                stroke,
+            }) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<path d="M {} {} Q {} {} {} {}{}" {} {}/>"#,
+                    a.x,
+                    a.y,
+                    b.x,
+                    b.y,
+                    c.x,
+                    c.y,
+                    if *closed { " Z" } else { "" },
+                    fill(*fill_color),
+                    path_stroke(stroke)
+                );
+            }
+            Shape::CubicBezier(CubicBezierShape {
+                points: [a, b, c, d],
+                closed,
+                fill: fill_color,
+                stroke,
+            }) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<path d="M {} {} C {} {} {} {} {} {}{}" {} {}/>"#,
+                    a.x,
+                    a.y,
+                    b.x,
+                    b.y,
+                    c.x,
+                    c.y,
+                    d.x,
+                    d.y,
+                    if *closed { " Z" } else { "" },
+                    fill(*fill_color),
+                    path_stroke(stroke)
+                );
+            }
+        }
+    }
+
+    fn rect(&mut self, snapshot: &Snapshot, shape: &RectShape) {
+        if shape.fill_texture_id != TextureId::default() {
+            // Textured rect, paint it as a mesh.
+            let mut mesh = Mesh::with_texture(shape.fill_texture_id);
+            mesh.add_rect_with_uv(shape.rect, shape.uv, shape.fill);
+            self.mesh(snapshot, &mesh);
+            return;
+        }
+
+        let _ = writeln!(
+            self.body,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {} {}/>"#,
+            shape.rect.min.x,
+            shape.rect.min.y,
+            shape.rect.width(),
+            shape.rect.height(),
+            shape.rounding.nw,
+            fill(shape.fill),
+            stroke(&shape.stroke)
+        );
+    }
+
+    fn text(&mut self, shape: &TextShape) {
+        let galley = &shape.galley;
+        for row in &galley.rows {
+            let Some(first) = row.glyphs.first() else {
+                continue;
+            };
+
+            let format = galley
+                .job
+                .sections
+                .get(first.section_index as usize)
+                .map(|section| &section.format);
+
+            let color = match (shape.override_text_color, format) {
+                (Some(color), _) => color,
+                (None, Some(format)) if format.color != Color32::PLACEHOLDER => format.color,
+                _ => shape.fallback_color,
+            };
+
+            let font_size = format.map_or(12., |format| format.font_id.size);
+            let text: String = row.glyphs.iter().map(|glyph| glyph.chr).collect();
+            let position = shape.pos + first.pos.to_vec2();
+
+            let _ = writeln!(
+                self.body,
+                r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" {} xml:space="preserve">{}</text>"#,
+                position.x,
+                position.y,
+                font_size,
+                fill(color.gamma_multiply(shape.opacity_factor)),
+                escape(&text)
+            );
+        }
+    }
+
+    fn mesh(&mut self, snapshot: &Snapshot, mesh: &Mesh) {
+        let texture = snapshot
+            .texture(mesh.texture_id)
+            .filter(|_| mesh.texture_id != TextureId::default());
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            let points = points_attribute(&[a.pos, b.pos, c.pos]);
+
+            let Some(texture) = texture else {
+                // Untextured, e.g. anti-aliasing feathering or a plain colored mesh.
+                let _ = writeln!(
+                    self.body,
+                    r#"<polygon points="{points}" {}/>"#,
+                    fill(a.color)
+                );
+                continue;
+            };
+
+            // Affine transform mapping texels onto the screen, derived from the triangle.
+            let size = egui::vec2(texture.width() as f32, texture.height() as f32);
+            let [ta, tb, tc] = [a.uv, b.uv, c.uv].map(|uv| (uv.to_vec2() * size).to_pos2());
+            let Some(matrix) = affine([ta, tb, tc], [a.pos, b.pos, c.pos]) else {
+                continue;
+            };
+
+            let image = self.image(mesh.texture_id, texture);
+            let clip = format!("tri{}", self.triangles);
+            self.triangles += 1;
+
+            let _ = writeln!(
+                self.defs,
+                r#"<clipPath id="{clip}"><polygon points="{points}"/></clipPath>"#
+            );
+            let _ = writeln!(
+                self.body,
+                r##"<g clip-path="url(#{clip})"><use xlink:href="#{image}" transform="matrix({})" opacity="{}"/></g>"##,
+                matrix.map(|v| v.to_string()).join(" "),
+                a.color.a() as f32 / 255.
+            );
+        }
+    }
+}
+
+/// Affine transform (as SVG's `matrix(a b c d e f)`) mapping `from` triangle onto `to`.
+fn affine(from: [Pos2; 3], to: [Pos2; 3]) -> Option<[f32; 6]> {
+    let (u1, u2) = (from[1] - from[0], from[2] - from[0]);
+    let (v1, v2) = (to[1] - to[0], to[2] - to[0]);
+
+    let det = u1.x * u2.y - u2.x * u1.y;
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    // Solve M * u1 = v1 and M * u2 = v2 for the linear part.
+    let a = (v1.x * u2.y - v2.x * u1.y) / det;
+    let c = (v2.x * u1.x - v1.x * u2.x) / det;
+    let b = (v1.y * u2.y - v2.y * u1.y) / det;
+    let d = (v2.y * u1.x - v1.y * u2.x) / det;
+    let e = to[0].x - a * from[0].x - c * from[0].y;
+    let f = to[0].y - b * from[0].x - d * from[0].y;
+
+    Some([a, b, c, d, e, f])
+}
+
+fn points_attribute(points: &[Pos2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn color_attribute(name: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!(
+        r#"{name}="rgb({r},{g},{b})" {name}-opacity="{}""#,
+        a as f32 / 255.
+    )
+}
+
+fn fill(color: Color32) -> String {
+    if color == Color32::TRANSPARENT {
+        r#"fill="none""#.to_owned()
+    } else {
+        color_attribute("fill", color)
+    }
+}
+
+fn stroke(stroke: &Stroke) -> String {
+    if stroke.is_empty() {
+        r#"stroke="none""#.to_owned()
+    } else {
+        format!(
+            r#"{} stroke-width="{}""#,
+            color_attribute("stroke", stroke.color),
+            stroke.width
+        )
+    }
+}
+
+fn path_stroke(stroke: &PathStroke) -> String {
+    match &stroke.color {
+        ColorMode::Solid(color) => self::stroke(&Stroke::new(stroke.width, *color)),
+        // Gradients are not supported, use the color at the origin.
+        ColorMode::UV(f) => self::stroke(&Stroke::new(
+            stroke.width,
+            f(
+                Rect::from_min_size(Pos2::ZERO, egui::Vec2::splat(1.)),
+                Pos2::ZERO,
+            ),
+        )),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn png(image: &ColorImage) -> Vec<u8> {
+    let pixels: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_srgba_unmultiplied())
+        .collect();
+
+    let mut png = Vec::new();
+    if let Err(error) = image::codecs::png::PngEncoder::new(&mut png).write_image(
+        &pixels,
+        image.width() as u32,
+        image.height() as u32,
+        image::ExtendedColorType::Rgba8,
+    ) {
+        log::warn!("Could not encode the texture: {}.", error);
+    }
+    png
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Georeferencing of an exported image of the global map, as an ESRI world file (e.g. `.pgw` for
+/// PNG images), so GIS tools can place it. Coordinates are in Web Mercator (EPSG:3857), described
+/// by [`WorldFile::PRJ`], to be saved next to the image as a `.prj` file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldFile {
+    /// Size of a pixel, in meters.
+    pub pixel_size: f64,
+
+    /// Web Mercator coordinates of the center of the top-left pixel.
+    pub top_left: Position,
+}
+
+impl WorldFile {
+    /// Web Mercator (EPSG:3857) in the WKT format of `.prj` files.
+    pub const PRJ: &'static str = r#"PROJCS["WGS_1984_Web_Mercator_Auxiliary_Sphere",GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Mercator_Auxiliary_Sphere"],PARAMETER["False_Easting",0.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",0.0],PARAMETER["Standard_Parallel_1",0.0],PARAMETER["Auxiliary_Sphere_Type",0.0],UNIT["Meter",1.0]]"#;
+
+    /// Georeferencing of an image of given size, in pixels, which is fully covered by the map,
+    /// e.g. rendered by a [`Snapshot`]. `my_position` must be the same as passed to the map.
+    /// Returns `None` for maps not in Web Mercator, i.e. local and polar ones, and rotated maps.
+    pub fn new(memory: &MapMemory, my_position: Position, size: Vec2) -> Option<Self> {
+        if !matches!(memory.projection_type, ProjectorType::Global)
+            || memory.bearing() != 0.
+            || memory.tilt() != 0.
+        {
+            return None;
+        }
+
+        const EARTH_RADIUS: f64 = 6_378_137.;
+
+        let zoom = memory.zoom();
+        let center = crate::camera::center(memory, my_position);
+
+        let x = EARTH_RADIUS * center.x.to_radians();
+        let y = EARTH_RADIUS * (PI / 4. + center.y.to_radians() / 2.).tan().ln();
+
+        let pixel_size = 2. * PI * EARTH_RADIUS / crate::total_pixels(zoom);
+
+        Some(Self {
+            pixel_size,
+            top_left: Position {
+                x: x - (size.x as f64 / 2. - 0.5) * pixel_size,
+                y: y + (size.y as f64 / 2. - 0.5) * pixel_size,
+            },
+        })
+    }
+}
+
+impl std::fmt::Display for WorldFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.pixel_size)?;
+        writeln!(f, "0.0")?;
+        writeln!(f, "0.0")?;
+        writeln!(f, "{}", -self.pixel_size)?;
+        writeln!(f, "{}", self.top_left.x)?;
+        writeln!(f, "{}", self.top_left.y)
+    }
+}
@@ -0,0 +1,274 @@
+use egui::{
+    emath::Rot2, epaint::Vertex, Color32, ColorImage, Context, Mesh, Pos2, Response, Shape, Stroke,
+    TextureHandle, TextureOptions, Ui,
+};
+use geo_types::Polygon;
+
+use crate::{Plugin, Position, Projector};
+
+/// How the inside of an [`Area`] is filled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FillPattern {
+    #[default]
+    Solid,
+
+    /// Diagonal lines, e.g. for restricted zones.
+    Hatch,
+
+    CrossHatch,
+
+    Dots,
+}
+
+/// Distance between repetitions of a [`FillPattern`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatternSpacing {
+    /// In points, so the pattern looks the same at any zoom level.
+    Screen(f32),
+
+    /// In meters, so the pattern sticks to the ground and scales along with the map.
+    Ground(f64),
+}
+
+impl Default for PatternSpacing {
+    fn default() -> Self {
+        Self::Screen(8.)
+    }
+}
+
+/// Polygon drawn by [`Areas`]. Its interiors (holes) are not cut out of the fill.
+pub struct Area {
+    polygon: Polygon<f64>,
+    color: Color32,
+    pattern: FillPattern,
+    spacing: PatternSpacing,
+    stroke: Stroke,
+}
+
+impl Area {
+    pub fn new(polygon: Polygon<f64>, color: Color32) -> Self {
+        Self {
+            polygon,
+            color,
+            pattern: FillPattern::default(),
+            spacing: PatternSpacing::default(),
+            stroke: Stroke::new(1.5, color),
+        }
+    }
+
+    pub fn pattern(mut self, pattern: FillPattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    pub fn spacing(mut self, spacing: PatternSpacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+}
+
+/// [`Plugin`] which draws polygons, filled with a solid color or a pattern, such as hatching
+/// conventionally used for restricted or planned areas.
+pub struct Areas {
+    areas: Vec<Area>,
+}
+
+impl Areas {
+    pub fn new(areas: Vec<Area>) -> Self {
+        Self { areas }
+    }
+}
+
+impl Plugin for Areas {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let painter = ui.painter();
+
+        for area in &self.areas {
+            let mut exterior: Vec<Position> = area.polygon.exterior().coords().copied().collect();
+            if exterior.len() > 1 && exterior.first() == exterior.last() {
+                exterior.pop();
+            }
+            if exterior.len() < 3 {
+                continue;
+            }
+
+            let points: Vec<Pos2> = exterior.iter().map(|p| projector.project(*p)).collect();
+            let mut mesh = match area.pattern {
+                FillPattern::Solid => {
+                    let mut mesh = Mesh::default();
+                    for point in &points {
+                        mesh.colored_vertex(*point, area.color);
+                    }
+                    mesh
+                }
+                pattern => {
+                    let texture = pattern_texture(ui.ctx(), pattern, area.color);
+                    let mut mesh = Mesh::with_texture(texture.id());
+                    let uv = uv_mapping(area, points[0], exterior[0], projector);
+                    mesh.vertices.extend(points.iter().map(|point| Vertex {
+                        pos: *point,
+                        uv: uv(*point),
+                        color: Color32::WHITE,
+                    }));
+                    mesh
+                }
+            };
+
+            mesh.indices = triangulate(&points);
+            painter.add(mesh);
+
+            painter.add(Shape::closed_line(points, area.stroke));
+            for interior in area.polygon.interiors() {
+                let points = interior.coords().map(|p| projector.project(*p)).collect();
+                painter.add(Shape::line(points, area.stroke));
+            }
+        }
+    }
+}
+
+/// Texture coordinates of screen positions. In ground space, the pattern is anchored at the
+/// polygon's first vertex and turns with the map, so it does not slide when the map moves.
+fn uv_mapping(
+    area: &Area,
+    anchor: Pos2,
+    anchor_position: Position,
+    projector: &Projector,
+) -> impl Fn(Pos2) -> Pos2 {
+    let (origin, rotation, spacing) = match area.spacing {
+        PatternSpacing::Screen(points) => (Pos2::ZERO, Rot2::IDENTITY, points),
+        PatternSpacing::Ground(meters) => (
+            anchor,
+            projector.rotation().inverse(),
+            (meters as f32) * projector.scale_pixel_per_meter(anchor_position),
+        ),
+    };
+    let spacing = spacing.max(1.);
+
+    move |point| (rotation * (point - origin) / spacing).to_pos2()
+}
+
+/// Side of the pattern texture, in points. The texture holds one repetition of the pattern.
+const PATTERN_SIZE: usize = 32;
+
+/// Texture of the pattern, rasterized for the current pixels per point, so that it stays crisp
+/// when the window moves to a screen of a different scale factor.
+fn pattern_texture(ctx: &Context, pattern: FillPattern, color: Color32) -> TextureHandle {
+    let id = egui::Id::new(("walkers_fill_pattern", pattern, color));
+    let pixels_per_point = ctx.pixels_per_point();
+    if let Some((_, texture)) = ctx
+        .data(|data| data.get_temp::<(f32, TextureHandle)>(id))
+        .filter(|(cached, _)| *cached == pixels_per_point)
+    {
+        return texture;
+    }
+
+    let size = ((PATTERN_SIZE as f32 * pixels_per_point).round() as usize).max(1);
+    let pixel = PATTERN_SIZE as f32 / size as f32;
+    let pixels = (0..size * size)
+        .map(|i| {
+            let (x, y) = (
+                ((i % size) as f32 + 0.5) * pixel,
+                ((i / size) as f32 + 0.5) * pixel,
+            );
+            color.gamma_multiply(coverage(pattern, x, y, pixel))
+        })
+        .collect();
+
+    let image = ColorImage {
+        size: [size; 2],
+        pixels,
+    };
+    let texture = ctx.load_texture("fill_pattern", image, TextureOptions::LINEAR_REPEAT);
+    ctx.data_mut(|data| data.insert_temp(id, (pixels_per_point, texture.clone())));
+    texture
+}
+
+/// How much of the pixel centered at given point of the pattern is covered. Point and pixel size
+/// are in points.
+fn coverage(pattern: FillPattern, x: f32, y: f32, pixel: f32) -> f32 {
+    let size = PATTERN_SIZE as f32;
+
+    // Distance to the nearest of lines repeated along the texture's diagonal.
+    let line = |along: f32| {
+        let offset = along.rem_euclid(size);
+        offset.min(size - offset) / std::f32::consts::SQRT_2
+    };
+    let stroke =
+        |distance: f32, half_width: f32| ((half_width - distance) / pixel + 0.5).clamp(0., 1.);
+
+    match pattern {
+        FillPattern::Solid => 1.,
+        FillPattern::Hatch => stroke(line(x + y), 2.),
+        FillPattern::CrossHatch => stroke(line(x + y), 2.).max(stroke(line(x - y), 2.)),
+        FillPattern::Dots => {
+            let center = size / 2.;
+            stroke((x - center).hypot(y - center), size / 6.)
+        }
+    }
+}
+
+/// Triangles covering a simple polygon, by ear clipping.
+pub(crate) fn triangulate(points: &[Pos2]) -> Vec<u32> {
+    let cross = |a: Pos2, b: Pos2, c: Pos2| (b - a).x * (c - a).y - (b - a).y * (c - a).x;
+
+    let area: f32 = (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    let orientation = area.signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut indices = Vec::with_capacity((points.len() - 2) * 3);
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                points[remaining[(i + n - 1) % n]],
+                points[remaining[i]],
+                points[remaining[(i + 1) % n]],
+            );
+            if cross(a, b, c) * orientation <= 0. {
+                return false;
+            }
+
+            // No other vertex may lie within the ear.
+            remaining.iter().all(|&j| {
+                let p = points[j];
+                p == a
+                    || p == b
+                    || p == c
+                    || cross(a, b, p) * orientation < 0.
+                    || cross(b, c, p) * orientation < 0.
+                    || cross(c, a, p) * orientation < 0.
+            })
+        });
+
+        // Self-intersecting polygons might have no ears left, so just fan out the rest.
+        let Some(i) = ear else { break };
+
+        indices.extend([
+            remaining[(i + n - 1) % n] as u32,
+            remaining[i] as u32,
+            remaining[(i + 1) % n] as u32,
+        ]);
+        remaining.remove(i);
+    }
+
+    for i in 1..remaining.len() - 1 {
+        indices.extend([
+            remaining[0] as u32,
+            remaining[i] as u32,
+            remaining[i + 1] as u32,
+        ]);
+    }
+
+    indices
+}
@@ -0,0 +1,318 @@
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use egui::{Color32, Response, Stroke, Ui};
+
+use crate::{Plugin, Position, Projector};
+
+// Low precision formulas, good to a fraction of a degree, after
+// https://aa.quae.nl/en/reken/zonpositie.html and https://github.com/mourner/suncalc.
+
+const SECONDS_PER_DAY: f64 = 86400.;
+const J1970: f64 = 2440588.;
+const J2000: f64 = 2451545.;
+const OBLIQUITY: f64 = 23.4397 * PI / 180.;
+
+/// Elevation of the sun's center at sunrise and sunset, accounting for refraction and the size
+/// of its disk.
+const SUN_HORIZON: f64 = -0.833;
+
+/// Same as [`SUN_HORIZON`], but for the moon, which is much closer to the Earth.
+const MOON_HORIZON: f64 = 0.133;
+
+/// Direction towards a celestial body, as seen by an observer on the ground.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Horizontal {
+    /// Degrees clockwise from north.
+    pub azimuth: f64,
+
+    /// Degrees above the horizon.
+    pub elevation: f64,
+}
+
+/// When a celestial body rises and sets, in UNIX time. Either can be `None`, e.g. during polar
+/// day or night.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RiseSet {
+    pub rise: Option<f64>,
+    pub set: Option<f64>,
+}
+
+/// Position of the sun in the sky at given UNIX time, in seconds.
+pub fn sun_position(position: Position, time: f64) -> Horizontal {
+    let (right_ascension, declination) = sun_coordinates(days_since_j2000(time));
+    horizontal(position, time, right_ascension, declination)
+}
+
+/// Position of the moon in the sky at given UNIX time, in seconds.
+pub fn moon_position(position: Position, time: f64) -> Horizontal {
+    let (right_ascension, declination) = moon_coordinates(days_since_j2000(time));
+    horizontal(position, time, right_ascension, declination)
+}
+
+/// First sunrise and sunset within 24 hours from given UNIX time.
+pub fn sun_rise_set(position: Position, from: f64) -> RiseSet {
+    rise_set(from, SUN_HORIZON, |time| {
+        sun_position(position, time).elevation
+    })
+}
+
+/// First moonrise and moonset within 24 hours from given UNIX time.
+pub fn moon_rise_set(position: Position, from: f64) -> RiseSet {
+    rise_set(from, MOON_HORIZON, |time| {
+        moon_position(position, time).elevation
+    })
+}
+
+fn days_since_j2000(time: f64) -> f64 {
+    time / SECONDS_PER_DAY - 0.5 + J1970 - J2000
+}
+
+/// Equatorial coordinates of a point on the ecliptic, in radians.
+fn equatorial(longitude: f64, latitude: f64) -> (f64, f64) {
+    let (sin_e, cos_e) = OBLIQUITY.sin_cos();
+    let right_ascension = (longitude.sin() * cos_e - latitude.tan() * sin_e).atan2(longitude.cos());
+    let declination = (latitude.sin() * cos_e + latitude.cos() * sin_e * longitude.sin()).asin();
+    (right_ascension, declination)
+}
+
+fn sun_coordinates(days: f64) -> (f64, f64) {
+    let mean_anomaly = (357.5291 + 0.98560028 * days).to_radians();
+    let center = (1.9148 * mean_anomaly.sin()
+        + 0.02 * (2. * mean_anomaly).sin()
+        + 0.0003 * (3. * mean_anomaly).sin())
+    .to_radians();
+    let perihelion = 102.9372_f64.to_radians();
+    equatorial(mean_anomaly + center + perihelion + PI, 0.)
+}
+
+fn moon_coordinates(days: f64) -> (f64, f64) {
+    let mean_longitude = (218.316 + 13.176396 * days).to_radians();
+    let mean_anomaly = (134.963 + 13.064993 * days).to_radians();
+    let mean_distance = (93.272 + 13.229350 * days).to_radians();
+
+    let longitude = mean_longitude + 6.289_f64.to_radians() * mean_anomaly.sin();
+    let latitude = 5.128_f64.to_radians() * mean_distance.sin();
+    equatorial(longitude, latitude)
+}
+
+fn horizontal(position: Position, time: f64, right_ascension: f64, declination: f64) -> Horizontal {
+    let latitude = position.y.to_radians();
+    let sidereal =
+        (280.16 + 360.9856235 * days_since_j2000(time)).to_radians() + position.x.to_radians();
+    let hour_angle = sidereal - right_ascension;
+
+    // Measured from the south, so turn it around.
+    let azimuth = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos())
+        + PI;
+    let elevation = (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .asin();
+
+    Horizontal {
+        azimuth: azimuth.rem_euclid(TAU).to_degrees(),
+        elevation: elevation.clamp(-FRAC_PI_2, FRAC_PI_2).to_degrees(),
+    }
+}
+
+/// Find when the elevation crosses the horizon, by sampling it every ten minutes.
+fn rise_set(from: f64, horizon: f64, elevation: impl Fn(f64) -> f64) -> RiseSet {
+    const STEP: f64 = 600.;
+
+    let mut result = RiseSet::default();
+    let mut time = from;
+    let mut previous = elevation(time) - horizon;
+
+    while time < from + SECONDS_PER_DAY && (result.rise.is_none() || result.set.is_none()) {
+        let next = elevation(time + STEP) - horizon;
+        if previous.signum() != next.signum() {
+            let crossing = time + STEP * previous / (previous - next);
+            if next > 0. {
+                result.rise.get_or_insert(crossing);
+            } else {
+                result.set.get_or_insert(crossing);
+            }
+        }
+        previous = next;
+        time += STEP;
+    }
+
+    result
+}
+
+/// [`Plugin`] which draws lines from a point towards where the sun rises and sets on given day,
+/// as well as towards the sun itself if it is above the horizon. Optionally does the same for
+/// the moon. Useful for planning photos.
+pub struct SunLines {
+    position: Position,
+    time: f64,
+    length: f32,
+    sunrise: Stroke,
+    sunset: Stroke,
+    sun: Stroke,
+    moon: Option<Stroke>,
+}
+
+impl SunLines {
+    /// Lines from the `position` for the day starting at given UNIX time.
+    pub fn new(position: Position, time: f64) -> Self {
+        Self {
+            position,
+            time,
+            length: 150.,
+            sunrise: Stroke::new(3., Color32::from_rgb(255, 200, 0)),
+            sunset: Stroke::new(3., Color32::from_rgb(255, 100, 0)),
+            sun: Stroke::new(2., Color32::YELLOW),
+            moon: None,
+        }
+    }
+
+    /// Length of the lines, in points.
+    pub fn length(mut self, length: f32) -> Self {
+        self.length = length;
+        self
+    }
+
+    pub fn sunrise(mut self, stroke: Stroke) -> Self {
+        self.sunrise = stroke;
+        self
+    }
+
+    pub fn sunset(mut self, stroke: Stroke) -> Self {
+        self.sunset = stroke;
+        self
+    }
+
+    /// Stroke of the line towards the sun's current position.
+    pub fn sun(mut self, stroke: Stroke) -> Self {
+        self.sun = stroke;
+        self
+    }
+
+    /// Draw lines for the moonrise and moonset too.
+    pub fn moon(mut self, stroke: Stroke) -> Self {
+        self.moon = Some(stroke);
+        self
+    }
+}
+
+impl Plugin for SunLines {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let origin = projector.project(self.position);
+        let painter = ui.painter();
+
+        let line = |azimuth: f64, length: f32, stroke: Stroke| {
+            let direction = projector.screen_direction(azimuth);
+            painter.line_segment([origin, origin + direction * length], stroke);
+        };
+
+        let azimuth_at = |time: Option<f64>, position: fn(Position, f64) -> Horizontal| {
+            time.map(|time| position(self.position, time).azimuth)
+        };
+
+        let sun = sun_rise_set(self.position, self.time);
+        if let Some(azimuth) = azimuth_at(sun.rise, sun_position) {
+            line(azimuth, self.length, self.sunrise);
+        }
+        if let Some(azimuth) = azimuth_at(sun.set, sun_position) {
+            line(azimuth, self.length, self.sunset);
+        }
+
+        let current = sun_position(self.position, self.time);
+        if current.elevation > SUN_HORIZON {
+            line(current.azimuth, self.length * 0.7, self.sun);
+        }
+
+        if let Some(stroke) = self.moon {
+            let moon = moon_rise_set(self.position, self.time);
+            for time in [moon.rise, moon.set] {
+                if let Some(azimuth) = azimuth_at(time, moon_position) {
+                    line(azimuth, self.length, stroke);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2013-03-05 00:00 UTC, used in suncalc's tests.
+    const SUNCALC_TIME: f64 = 1_362_441_600.;
+    const SUNCALC_POSITION: Position = Position { x: 30.5, y: 50.5 };
+
+    /// 2024-03-20 03:06 UTC.
+    const EQUINOX: f64 = 1_710_903_960.;
+
+    /// 2024-06-20 20:51 UTC.
+    const SOLSTICE: f64 = 1_718_916_660.;
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "{actual} != {expected}"
+        );
+    }
+
+    fn declination(time: f64) -> f64 {
+        sun_coordinates(days_since_j2000(time)).1.to_degrees()
+    }
+
+    #[test]
+    fn sun_declination() {
+        assert_near(declination(EQUINOX), 0., 0.25);
+        assert_near(declination(SOLSTICE), 23.44, 0.05);
+        // Half a year later, in the south.
+        assert_near(declination(SOLSTICE + 182.6 * SECONDS_PER_DAY), -23.44, 0.1);
+    }
+
+    #[test]
+    fn subsolar_point() {
+        // Noon at the solstice is at 20:51 UTC, with the equation of time of about -1.6 minutes.
+        let subsolar = Position {
+            x: -15. * (20.85 - 12. - 1.6 / 60.),
+            y: 23.44,
+        };
+        assert!(sun_position(subsolar, SOLSTICE).elevation > 89.5);
+
+        // At the same time, it is midnight on the other side of the Earth.
+        let antipode = Position {
+            x: subsolar.x + 180.,
+            y: -subsolar.y,
+        };
+        assert!(sun_position(antipode, SOLSTICE).elevation < -89.5);
+    }
+
+    #[test]
+    fn sun_against_suncalc() {
+        // Azimuth of -2.5003175907168385 rad from the south and altitude of -0.7000406838781611
+        // rad.
+        let sun = sun_position(SUNCALC_POSITION, SUNCALC_TIME);
+        assert_near(sun.azimuth, 36.7424, 0.01);
+        assert_near(sun.elevation, -40.1094, 0.01);
+
+        // Sunrise at 04:34:56 and sunset at 15:46:57, within the sampling's accuracy.
+        let rise_set = sun_rise_set(SUNCALC_POSITION, SUNCALC_TIME);
+        assert_near(rise_set.rise.unwrap(), SUNCALC_TIME + 16_496., 60.);
+        assert_near(rise_set.set.unwrap(), SUNCALC_TIME + 56_817., 60.);
+    }
+
+    #[test]
+    fn moon_against_suncalc() {
+        // Azimuth of -0.9783999522438226 rad from the south, altitude within a fraction of a
+        // degree of the horizon.
+        let moon = moon_position(SUNCALC_POSITION, SUNCALC_TIME);
+        assert_near(moon.azimuth, 123.9418, 0.01);
+        assert_near(moon.elevation, 0.4, 0.5);
+    }
+
+    #[test]
+    fn polar_night() {
+        let svalbard = Position { x: 15.6, y: 78.2 };
+        // Around the December solstice.
+        let rise_set = sun_rise_set(svalbard, SOLSTICE + 183. * SECONDS_PER_DAY);
+        assert_eq!(rise_set, RiseSet::default());
+    }
+}
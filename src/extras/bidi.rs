@@ -0,0 +1,167 @@
+//! Simplified bidirectional text reordering, since egui lays text out strictly left to right.
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    LeftToRight,
+    RightToLeft,
+    Number,
+    Neutral,
+}
+
+fn is_right_to_left(c: char) -> bool {
+    matches!(c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF)
+}
+
+fn classify(c: char) -> Class {
+    if is_right_to_left(c) {
+        Class::RightToLeft
+    } else if c.is_numeric() {
+        Class::Number
+    } else if c.is_alphabetic() {
+        Class::LeftToRight
+    } else {
+        Class::Neutral
+    }
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => c,
+    }
+}
+
+/// Reorder the text from logical to visual order, so that right-to-left scripts (e.g. Hebrew or
+/// Arabic) and mixed-direction labels read correctly when laid out left to right. Paragraph
+/// direction is taken from the first strong character. This is a simplification of the Unicode
+/// Bidirectional Algorithm, without explicit embeddings, and it does not shape Arabic letters.
+pub fn visual_order(text: &str) -> String {
+    if !text.chars().any(is_right_to_left) {
+        return text.to_owned();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let classes: Vec<Class> = chars.iter().map(|c| classify(*c)).collect();
+
+    let paragraph_rtl = classes
+        .iter()
+        .find(|class| matches!(class, Class::LeftToRight | Class::RightToLeft))
+        == Some(&Class::RightToLeft);
+
+    // Numbers following left-to-right text (or starting a left-to-right paragraph) are part of
+    // it, otherwise they keep their own, higher level.
+    let mut previous_rtl = paragraph_rtl;
+    let classes: Vec<Class> = classes
+        .into_iter()
+        .map(|class| match class {
+            Class::LeftToRight => {
+                previous_rtl = false;
+                class
+            }
+            Class::RightToLeft => {
+                previous_rtl = true;
+                class
+            }
+            Class::Number if !previous_rtl => Class::LeftToRight,
+            _ => class,
+        })
+        .collect();
+
+    // Direction of each character, where numbers count as right-to-left and neutrals take it from
+    // their surroundings.
+    let strong: Vec<Option<bool>> = classes
+        .iter()
+        .map(|class| match class {
+            Class::RightToLeft | Class::Number => Some(true),
+            Class::LeftToRight => Some(false),
+            Class::Neutral => None,
+        })
+        .collect();
+
+    let levels: Vec<u8> = (0..classes.len())
+        .map(|i| {
+            if classes[i] == Class::Number {
+                return 2;
+            }
+
+            let rtl = strong[i].unwrap_or_else(|| {
+                let before = strong[..i].iter().rev().find_map(|s| *s);
+                let after = strong[i + 1..].iter().find_map(|s| *s);
+                match (before, after) {
+                    (Some(before), Some(after)) if before == after => before,
+                    _ => paragraph_rtl,
+                }
+            });
+
+            match (paragraph_rtl, rtl) {
+                (false, false) => 0,
+                (_, true) => 1,
+                (true, false) => 2,
+            }
+        })
+        .collect();
+
+    let mut visual: Vec<(char, u8)> = chars
+        .into_iter()
+        .zip(levels)
+        .map(|(c, level)| (if level % 2 == 1 { mirror(c) } else { c }, level))
+        .collect();
+
+    // From the highest level down, reverse each run of characters at that level or above.
+    let max_level = visual.iter().map(|(_, level)| *level).max().unwrap_or(0);
+    for level in (1..=max_level).rev() {
+        let mut i = 0;
+        while i < visual.len() {
+            if visual[i].1 >= level {
+                let start = i;
+                while i < visual.len() && visual[i].1 >= level {
+                    i += 1;
+                }
+                visual[start..i].reverse();
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    visual.into_iter().map(|(c, _)| c).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn left_to_right_text_is_unchanged() {
+        assert_eq!(visual_order("Warszawa 2"), "Warszawa 2");
+        assert_eq!(visual_order("(Kraków)"), "(Kraków)");
+    }
+
+    #[test]
+    fn right_to_left_text_is_reversed() {
+        assert_eq!(visual_order("שלום"), "םולש");
+        assert_eq!(visual_order("(שלום)"), "(םולש)");
+    }
+
+    #[test]
+    fn numbers_keep_their_order() {
+        assert_eq!(visual_order("רחוב 12"), "12 בוחר");
+        assert_eq!(visual_order("כביש 443 צפון"), "ןופצ 443 שיבכ");
+    }
+
+    #[test]
+    fn mixed_directions() {
+        assert_eq!(visual_order("Tel Aviv תל אביב"), "Tel Aviv ביבא לת");
+        assert_eq!(visual_order("תל אביב Tel Aviv"), "Tel Aviv ביבא לת");
+    }
+}
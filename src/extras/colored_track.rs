@@ -0,0 +1,248 @@
+use egui::{Color32, Rect, Response, Shape, Stroke, Ui};
+
+use super::{LegendContributor, LegendEntry, TrailPoint};
+use crate::{units::haversine, Plugin, Position, Projector};
+
+/// Value by which [`ColoredTrack`] colors its segments.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrackMetric {
+    /// Speed between consecutive points, in km/h.
+    Speed,
+
+    /// Direction of travel, in degrees clockwise from the north.
+    Heading,
+
+    /// Value given for each point, such as elevation or heart rate. Segments get the average of
+    /// their ends.
+    Custom { label: String, values: Vec<f64> },
+}
+
+impl TrackMetric {
+    fn label(&self) -> &str {
+        match self {
+            TrackMetric::Speed => "Speed (km/h)",
+            TrackMetric::Heading => "Heading (°)",
+            TrackMetric::Custom { label, .. } => label,
+        }
+    }
+
+    /// Decimal places of the values shown in the legend.
+    fn precision(&self) -> usize {
+        match self {
+            TrackMetric::Speed | TrackMetric::Custom { .. } => 1,
+            TrackMetric::Heading => 0,
+        }
+    }
+
+    /// Value of the segment between the points at `i` and `i + 1`.
+    fn value(&self, points: &[TrailPoint], i: usize) -> Option<f64> {
+        let (a, b) = (points[i], points[i + 1]);
+        match self {
+            TrackMetric::Speed => {
+                let duration = b.time - a.time;
+                (duration > 0.).then(|| haversine(a.position, b.position) / duration * 3.6)
+            }
+            TrackMetric::Heading => {
+                (a.position != b.position).then(|| heading(a.position, b.position))
+            }
+            TrackMetric::Custom { values, .. } => Some((values.get(i)? + values.get(i + 1)?) / 2.),
+        }
+    }
+}
+
+/// Initial bearing of the great circle from `a` to `b`, in degrees.
+fn heading(a: Position, b: Position) -> f64 {
+    let (lat_a, lat_b) = (a.y.to_radians(), b.y.to_radians());
+    let delta = (b.x - a.x).to_radians();
+    let y = delta.sin() * lat_b.cos();
+    let x = lat_a.cos() * lat_b.sin() - lat_a.sin() * lat_b.cos() * delta.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.)
+}
+
+/// Timestamped track with segments colored by speed, heading or a custom metric. Values and
+/// colors are computed once, so keep it in the application's state rather than rebuilding it
+/// on each frame, and draw it with [`ColoredTrack::plugin`]. It also describes its color ramp
+/// in the [`super::Legend`].
+pub struct ColoredTrack {
+    segments: Vec<(Position, Position, Option<f64>)>,
+    metric: TrackMetric,
+    ramp: Vec<Color32>,
+    range: (f64, f64),
+    colors: Vec<Color32>,
+}
+
+impl ColoredTrack {
+    /// Track through the points, in order. Range of the ramp is chosen automatically: full
+    /// circle for the heading, and the 5th to 95th percentile of values otherwise, so that
+    /// a few GPS glitches do not wash out the colors.
+    pub fn new(points: &[TrailPoint], metric: TrackMetric) -> Self {
+        Self::from_segments(std::iter::once(points), metric)
+    }
+
+    /// Like [`ColoredTrack::new`], but for a track split into parts, which are not joined.
+    /// Values of [`TrackMetric::Custom`] are given for all the points, one part after another.
+    pub fn from_segments<'a>(
+        parts: impl IntoIterator<Item = &'a [TrailPoint]>,
+        metric: TrackMetric,
+    ) -> Self {
+        let mut segments = Vec::new();
+        let mut offset = 0;
+        for part in parts {
+            let shifted = match &metric {
+                TrackMetric::Custom { label, values } => TrackMetric::Custom {
+                    label: label.clone(),
+                    values: values.get(offset..).unwrap_or_default().to_vec(),
+                },
+                metric => metric.clone(),
+            };
+            for i in 0..part.len().saturating_sub(1) {
+                segments.push((
+                    part[i].position,
+                    part[i + 1].position,
+                    shifted.value(part, i),
+                ));
+            }
+            offset += part.len();
+        }
+
+        let (ramp, range) = match metric {
+            TrackMetric::Heading => (
+                vec![
+                    Color32::from_rgb(230, 60, 60),
+                    Color32::from_rgb(230, 200, 40),
+                    Color32::from_rgb(60, 180, 75),
+                    Color32::from_rgb(40, 120, 230),
+                    Color32::from_rgb(230, 60, 60),
+                ],
+                (0., 360.),
+            ),
+            _ => (
+                vec![
+                    Color32::from_rgb(40, 120, 230),
+                    Color32::from_rgb(60, 180, 75),
+                    Color32::from_rgb(230, 200, 40),
+                    Color32::from_rgb(230, 60, 60),
+                ],
+                percentiles(&segments),
+            ),
+        };
+
+        let mut track = Self {
+            segments,
+            metric,
+            ramp,
+            range,
+            colors: Vec::new(),
+        };
+        track.update_colors();
+        track
+    }
+
+    /// Colors from the lowest to the highest value.
+    pub fn ramp(mut self, ramp: Vec<Color32>) -> Self {
+        self.ramp = ramp;
+        self.update_colors();
+        self
+    }
+
+    /// Values at the ends of the ramp, instead of the automatic ones. Values beyond get the
+    /// color of the nearest end.
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.range = (min, max);
+        self.update_colors();
+        self
+    }
+
+    /// [`Plugin`] drawing the track.
+    pub fn plugin(&self) -> ColoredTrail<'_> {
+        ColoredTrail {
+            track: self,
+            width: 4.,
+        }
+    }
+
+    fn update_colors(&mut self) {
+        self.colors = self
+            .segments
+            .iter()
+            .map(|(_, _, value)| match value {
+                Some(value) => self.color(*value),
+                None => Color32::GRAY,
+            })
+            .collect();
+    }
+
+    fn color(&self, value: f64) -> Color32 {
+        match self.ramp.as_slice() {
+            [] => Color32::TRANSPARENT,
+            [color] => *color,
+            ramp => {
+                let (min, max) = self.range;
+                let t = if max > min {
+                    ((value - min) / (max - min)).clamp(0., 1.) as f32
+                } else {
+                    0.
+                };
+                let scaled = t * (ramp.len() - 1) as f32;
+                let i = (scaled.floor() as usize).min(ramp.len() - 2);
+                ramp[i].lerp_to_gamma(ramp[i + 1], scaled - i as f32)
+            }
+        }
+    }
+}
+
+/// 5th and 95th percentile of segments' values.
+fn percentiles(segments: &[(Position, Position, Option<f64>)]) -> (f64, f64) {
+    let mut values: Vec<f64> = segments.iter().filter_map(|(_, _, value)| *value).collect();
+    if values.is_empty() {
+        return (0., 1.);
+    }
+    values.sort_by(f64::total_cmp);
+    let at = |fraction: f64| values[((values.len() - 1) as f64 * fraction).round() as usize];
+    (at(0.05), at(0.95))
+}
+
+impl LegendContributor for ColoredTrack {
+    fn legend_entries(&self) -> Vec<LegendEntry> {
+        vec![LegendEntry::Gradient {
+            label: self.metric.label().to_owned(),
+            colors: self.ramp.clone(),
+            min: self.range.0,
+            max: self.range.1,
+            precision: self.metric.precision(),
+        }]
+    }
+}
+
+/// [`Plugin`] which draws a [`ColoredTrack`].
+pub struct ColoredTrail<'a> {
+    track: &'a ColoredTrack,
+    width: f32,
+}
+
+impl ColoredTrail<'_> {
+    /// Width of the line. Default is 4.
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+}
+
+impl Plugin for ColoredTrail<'_> {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let painter = ui.painter();
+        let clip = painter.clip_rect().expand(self.width);
+
+        for ((a, b, _), color) in self.track.segments.iter().zip(&self.track.colors) {
+            let (a, b) = (projector.project(*a), projector.project(*b));
+            if !clip.intersects(Rect::from_two_pos(a, b)) {
+                continue;
+            }
+
+            painter.line_segment([a, b], Stroke::new(self.width, *color));
+
+            // Round joins, so that thick lines do not show gaps at turns.
+            painter.add(Shape::circle_filled(b, self.width / 2., *color));
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use egui::{vec2, Align2, Color32, FontId, Response, Shape, Stroke, Ui};
+
+use crate::{Plugin, PluginLayer, Projector};
+
+/// [`Plugin`] which draws a small compass rose pointing north, along with the map's bearing in
+/// degrees. For navigation, the magnetic bearing can be shown as well, given the magnetic
+/// declination.
+pub struct CompassRose {
+    anchor: Align2,
+    radius: f32,
+    declination: Option<f64>,
+    font: FontId,
+}
+
+impl Default for CompassRose {
+    fn default() -> Self {
+        Self {
+            anchor: Align2::RIGHT_TOP,
+            radius: 24.,
+            declination: None,
+            font: FontId::proportional(11.),
+        }
+    }
+}
+
+impl CompassRose {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Corner of the map in which the rose is placed. Default is the top right one.
+    pub fn anchor(mut self, anchor: Align2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Magnetic declination in degrees, positive when the magnetic north is east of the true
+    /// one. Makes the rose mark the magnetic north and show the magnetic bearing too.
+    pub fn declination(mut self, declination: f64) -> Self {
+        self.declination = Some(declination);
+        self
+    }
+
+    /// Set the [`CompassRose::declination`] from the model, at the position and date (a decimal
+    /// year, see [`crate::decimal_year`]), e.g. those of the user.
+    #[cfg(feature = "wmm")]
+    pub fn declination_from(
+        self,
+        model: &crate::WorldMagneticModel,
+        position: crate::Position,
+        date: f64,
+    ) -> Self {
+        self.declination(model.declination_at(position, date))
+    }
+}
+
+/// Bearing in whole degrees, from 0 to 359.
+fn whole_degrees(bearing: f64) -> i64 {
+    (bearing.round() as i64).rem_euclid(360)
+}
+
+impl Plugin for CompassRose {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let bearing = projector.bearing();
+        let visuals = ui.visuals();
+        let (background, foreground) = (visuals.extreme_bg_color, visuals.text_color());
+
+        let text_height = self.font.size + 2.;
+        let lines = 1 + self.declination.is_some() as usize;
+        let size = vec2(
+            self.radius * 2.,
+            self.radius * 2. + text_height * lines as f32,
+        );
+        let rect = self
+            .anchor
+            .align_size_within_rect(size, ui.max_rect().shrink(8.));
+        let center = rect.center_top() + vec2(0., self.radius);
+
+        let painter = ui.painter();
+        painter.circle(
+            center,
+            self.radius,
+            background.gamma_multiply(0.8),
+            Stroke::new(1., foreground),
+        );
+
+        let needle = projector.screen_direction(0.) * self.radius * 0.8;
+        let side = vec2(-needle.y, needle.x) * 0.25;
+        painter.add(Shape::convex_polygon(
+            vec![center + needle, center + side, center - side],
+            Color32::RED,
+            Stroke::NONE,
+        ));
+        painter.add(Shape::convex_polygon(
+            vec![center - needle, center - side, center + side],
+            foreground.gamma_multiply(0.6),
+            Stroke::NONE,
+        ));
+        painter.text(
+            center + needle * 1.15,
+            Align2::CENTER_CENTER,
+            "N",
+            self.font.clone(),
+            foreground,
+        );
+
+        if let Some(declination) = self.declination {
+            let magnetic = projector.screen_direction(declination) * self.radius;
+            painter.line_segment(
+                [center + magnetic * 0.6, center + magnetic],
+                Stroke::new(2., Color32::LIGHT_BLUE),
+            );
+        }
+
+        let mut cursor = center + vec2(0., self.radius + 1.);
+        painter.text(
+            cursor,
+            Align2::CENTER_TOP,
+            format!("{}°", whole_degrees(bearing)),
+            self.font.clone(),
+            foreground,
+        );
+
+        if let Some(declination) = self.declination {
+            cursor.y += text_height;
+            painter.text(
+                cursor,
+                Align2::CENTER_TOP,
+                format!("{}° M", whole_degrees(bearing - declination)),
+                self.font.clone(),
+                foreground,
+            );
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bearings_in_whole_degrees() {
+        assert_eq!(whole_degrees(0.), 0);
+        assert_eq!(whole_degrees(359.4), 359);
+        assert_eq!(whole_degrees(359.5), 0);
+        assert_eq!(whole_degrees(359.9), 0);
+        assert_eq!(whole_degrees(-0.4), 0);
+        assert_eq!(whole_degrees(-10.), 350);
+        assert_eq!(whole_degrees(725.), 5);
+    }
+}
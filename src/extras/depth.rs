@@ -0,0 +1,108 @@
+use egui::Color32;
+
+use super::{LegendContributor, LegendEntry};
+
+/// Unit in which depths are shown to the user.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthUnit {
+    #[default]
+    Meters,
+    Feet,
+    Fathoms,
+}
+
+impl DepthUnit {
+    /// Depth given in meters, converted to this unit.
+    pub fn from_meters(&self, meters: f64) -> f64 {
+        match self {
+            DepthUnit::Meters => meters,
+            DepthUnit::Feet => meters / 0.3048,
+            DepthUnit::Fathoms => meters / 1.8288,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DepthUnit::Meters => "m",
+            DepthUnit::Feet => "ft",
+            DepthUnit::Fathoms => "fm",
+        }
+    }
+}
+
+/// Depth bands of a bathymetric layer, described in the [`super::Legend`] in the user's
+/// [`DepthUnit`], e.g. along with the [`crate::sources::OpenSeaMap`] overlay.
+#[derive(Clone)]
+pub struct DepthLegend {
+    /// Shallowest depth of each band, in meters, with its color. Sorted from the shallowest.
+    bands: Vec<(f64, Color32)>,
+    unit: DepthUnit,
+}
+
+impl Default for DepthLegend {
+    /// Shades of blue used by nautical charts, from the shallow water up to 2 m, through 5, 10
+    /// and 20 m, to the deep water beyond.
+    fn default() -> Self {
+        Self {
+            bands: vec![
+                (0., Color32::from_rgb(97, 183, 228)),
+                (2., Color32::from_rgb(140, 204, 237)),
+                (5., Color32::from_rgb(180, 222, 244)),
+                (10., Color32::from_rgb(212, 236, 249)),
+                (20., Color32::from_rgb(240, 248, 253)),
+            ],
+            unit: DepthUnit::default(),
+        }
+    }
+}
+
+impl DepthLegend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the bands, given by their shallowest depth in meters and their color.
+    pub fn bands(mut self, mut bands: Vec<(f64, Color32)>) -> Self {
+        bands.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.bands = bands;
+        self
+    }
+
+    /// Default is [`DepthUnit::Meters`].
+    pub fn unit(mut self, unit: DepthUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Depth in meters, formatted in the legend's unit, e.g. `16 ft`.
+    pub fn format(&self, meters: f64) -> String {
+        let depth = self.unit.from_meters(meters);
+        let precision = if depth.abs() < 10. && (depth - depth.round()).abs() >= 0.05 {
+            1
+        } else {
+            0
+        };
+        format!("{depth:.precision$} {}", self.unit.symbol())
+    }
+}
+
+impl LegendContributor for DepthLegend {
+    fn legend_entries(&self) -> Vec<LegendEntry> {
+        self.bands
+            .iter()
+            .enumerate()
+            .map(|(i, (from, color))| {
+                let label = match self.bands.get(i + 1) {
+                    Some((to, _)) => {
+                        format!("{} – {}", self.format(*from), self.format(*to))
+                    }
+                    None => format!("> {}", self.format(*from)),
+                };
+                LegendEntry::Swatch {
+                    color: *color,
+                    label,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,127 @@
+use std::hash::Hash;
+
+use egui::{Color32, Id, Key, Response, TextEdit, Ui, Widget};
+
+use crate::{units::parse_coordinates, Bookmarks, MapMemory, Position, View};
+
+/// Text box which accepts coordinates (see [`crate::parse_coordinates`]) or a name of one of the
+/// known places, and centers the map there once Enter is pressed. Meant to be put over the map,
+/// e.g. in an [`egui::Area`].
+pub struct GoTo<'a> {
+    id: Id,
+    map_memory: &'a mut MapMemory,
+    places: Vec<(String, Position)>,
+    bookmarks: Bookmarks,
+    zoom: Option<f64>,
+    hint: String,
+    width: f32,
+}
+
+/// State of the text box which must persist between frames.
+#[derive(Clone, Default)]
+struct State {
+    text: String,
+    invalid: bool,
+}
+
+impl<'a> GoTo<'a> {
+    /// Text box keeping what was typed under given id, which must be unique among the text boxes
+    /// in the same [`Ui`].
+    pub fn new(id_salt: impl Hash, map_memory: &'a mut MapMemory) -> Self {
+        Self {
+            id: Id::new(("walkers_go_to", id_salt)),
+            map_memory,
+            places: Vec::new(),
+            bookmarks: Bookmarks::default(),
+            zoom: None,
+            hint: "Go to...".to_owned(),
+            width: 200.,
+        }
+    }
+
+    /// Named place which can be typed instead of coordinates. Names are case-insensitive.
+    pub fn with_place(mut self, name: impl Into<String>, position: Position) -> Self {
+        self.places.push((name.into(), position));
+        self
+    }
+
+    /// Names of the bookmarks can be typed instead of coordinates. Going to a bookmark restores
+    /// its zoom as well.
+    pub fn with_bookmarks(mut self, bookmarks: &Bookmarks) -> Self {
+        self.bookmarks = bookmarks.clone();
+        self
+    }
+
+    /// Zoom level to be set after going somewhere. By default, zoom is left as is.
+    pub fn zoom(mut self, zoom: f64) -> Self {
+        self.zoom = Some(zoom);
+        self
+    }
+
+    /// Text shown when the box is empty.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = hint.into();
+        self
+    }
+
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// View to go to, based on what was typed.
+    fn find(&self, text: &str) -> Option<View> {
+        let text = text.trim();
+        let matches = |name: &str| name.trim().eq_ignore_ascii_case(text);
+
+        if let Some((_, view)) = self.bookmarks.iter().find(|(name, _)| matches(name)) {
+            return Some(view.clone());
+        }
+
+        let center = parse_coordinates(text).ok().or_else(|| {
+            self.places
+                .iter()
+                .find(|(name, _)| matches(name))
+                .map(|(_, position)| *position)
+        })?;
+
+        Some(View {
+            center,
+            zoom: self.zoom.unwrap_or(self.map_memory.zoom()),
+            bearing: self.map_memory.bearing(),
+            tilt: self.map_memory.tilt(),
+            layers: None,
+        })
+    }
+}
+
+impl Widget for GoTo<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let id = ui.make_persistent_id(self.id);
+        let mut state: State = ui.data_mut(|data| data.get_temp(id).unwrap_or_default());
+
+        let mut text_edit = TextEdit::singleline(&mut state.text)
+            .hint_text(self.hint.as_str())
+            .desired_width(self.width);
+
+        if state.invalid {
+            text_edit = text_edit.text_color(Color32::RED);
+        }
+
+        let response = ui.add(text_edit);
+
+        if response.changed() {
+            state.invalid = false;
+        }
+
+        if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            match self.find(&state.text) {
+                Some(view) => view.apply(self.map_memory),
+                None => state.invalid = true,
+            }
+        }
+
+        ui.data_mut(|data| data.insert_temp(id, state));
+        response
+    }
+}
@@ -0,0 +1,132 @@
+use egui::{Color32, Response, Stroke, Ui};
+
+use crate::{Plugin, PluginLayer, Position, Projector};
+
+/// Position rounded to the nearest intersection of a grid with given spacing, e.g. for placing
+/// things on a site plan.
+pub fn snap_to_grid(position: Position, spacing: f64) -> Position {
+    if spacing <= 0. || !spacing.is_finite() {
+        return position;
+    }
+
+    Position {
+        x: (position.x / spacing).round() * spacing,
+        y: (position.y / spacing).round() * spacing,
+    }
+}
+
+/// [`Plugin`] which draws a grid of lines every `spacing` units over a local map. When zoomed
+/// out, lines get sparser (by a 1-2-5 sequence) so that they do not clutter the map. Global maps
+/// are not supported.
+pub struct Grid {
+    spacing: f64,
+    stroke: Stroke,
+    axes: Option<Stroke>,
+    min_distance: f32,
+}
+
+impl Grid {
+    pub fn new(spacing: f64) -> Self {
+        Self {
+            spacing,
+            stroke: Stroke::new(1., Color32::GRAY.gamma_multiply(0.5)),
+            axes: None,
+            min_distance: 16.,
+        }
+    }
+
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+
+    /// Draw the x and y axes with a distinct stroke.
+    pub fn axes(mut self, stroke: Stroke) -> Self {
+        self.axes = Some(stroke);
+        self
+    }
+
+    /// Minimum distance between the lines, in points. Default is 16.
+    pub fn min_distance(mut self, min_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self
+    }
+
+    /// Spacing of the lines actually drawn.
+    fn visible_spacing(&self, pixels_per_unit: f64) -> Option<f64> {
+        if self.spacing <= 0. || !self.spacing.is_finite() || pixels_per_unit <= 0. {
+            return None;
+        }
+
+        let mut spacing = self.spacing;
+        for factor in [2., 2.5, 2.].iter().cycle() {
+            if spacing * pixels_per_unit >= self.min_distance as f64 || !spacing.is_finite() {
+                break;
+            }
+            spacing *= factor;
+        }
+        spacing.is_finite().then_some(spacing)
+    }
+}
+
+impl Plugin for Grid {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        if projector.memory().is_global() {
+            return;
+        }
+
+        let rect = projector.clip_rect();
+        let corners = [
+            rect.left_top(),
+            rect.right_top(),
+            rect.left_bottom(),
+            rect.right_bottom(),
+        ]
+        .map(|corner| projector.unproject(corner));
+
+        let pixels_per_unit = projector.scale_pixel_per_meter(corners[0]) as f64;
+        let Some(spacing) = self.visible_spacing(pixels_per_unit) else {
+            return;
+        };
+
+        // Bounds in local units, which, for a rotated map, go beyond the screen.
+        let (min_x, max_x) = corners.iter().fold((f64::MAX, f64::MIN), |(min, max), c| {
+            (min.min(c.x), max.max(c.x))
+        });
+        let (min_y, max_y) = corners.iter().fold((f64::MAX, f64::MIN), |(min, max), c| {
+            (min.min(c.y), max.max(c.y))
+        });
+
+        let painter = ui.painter_at(rect);
+        let stroke = |i: i64| match self.axes {
+            Some(axes) if i == 0 => axes,
+            _ => self.stroke,
+        };
+
+        for i in (min_x / spacing).ceil() as i64..=(max_x / spacing).floor() as i64 {
+            let x = i as f64 * spacing;
+            painter.line_segment(
+                [
+                    projector.project(Position { x, y: min_y }),
+                    projector.project(Position { x, y: max_y }),
+                ],
+                stroke(i),
+            );
+        }
+
+        for i in (min_y / spacing).ceil() as i64..=(max_y / spacing).floor() as i64 {
+            let y = i as f64 * spacing;
+            painter.line_segment(
+                [
+                    projector.project(Position { x: min_x, y }),
+                    projector.project(Position { x: max_x, y }),
+                ],
+                stroke(i),
+            );
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Background
+    }
+}
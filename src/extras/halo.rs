@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+use egui::{vec2, Galley, Painter, Pos2, Stroke};
+
+/// Paint the galley with an outline around its glyphs, which keeps text legible over imagery of
+/// any color. The outline is made of copies of the text in `halo.color`, shifted by up to
+/// `halo.width` points in every direction.
+pub fn galley_with_halo(painter: &Painter, position: Pos2, galley: Arc<Galley>, halo: Stroke) {
+    if halo.width > 0. && halo.color.a() > 0 {
+        // Enough copies for the outline to look round, without drawing the text too many times.
+        let steps = (halo.width * 4.).ceil().clamp(8., 16.) as usize;
+        for i in 0..steps {
+            let angle = i as f32 / steps as f32 * std::f32::consts::TAU;
+            let offset = vec2(angle.cos(), angle.sin()) * halo.width;
+            painter.galley_with_override_text_color(position + offset, galley.clone(), halo.color);
+        }
+    }
+
+    painter.galley(position, galley, halo.color);
+}
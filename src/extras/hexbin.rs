@@ -0,0 +1,330 @@
+use std::{collections::HashMap, hash::Hash, sync::Arc};
+
+use egui::{Align2, Color32, Context, FontId, Id, Response, Shape, Stroke, Ui};
+
+use crate::{units::Pixel, Plugin, Position, Projector, TimeWindow};
+
+/// Shape of the cells of the [`Hexbin`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BinShape {
+    #[default]
+    Hexagon,
+    Square,
+}
+
+/// [`Plugin`] which aggregates points into cells of a fixed ground size, and draws the cells
+/// colored by the number of points inside. Much cheaper than drawing each point when there are
+/// lots of them.
+pub struct Hexbin {
+    points: Vec<Position>,
+    times: Option<Vec<f64>>,
+    cell_size: f64,
+    shape: BinShape,
+    colors: Vec<Color32>,
+    stroke: Stroke,
+    font: FontId,
+    show_counts: bool,
+    id: Option<Id>,
+}
+
+impl Hexbin {
+    /// Aggregate `points` into cells which are `cell_size` meters wide.
+    pub fn new(points: Vec<Position>, cell_size: f64) -> Self {
+        Self {
+            points,
+            times: None,
+            cell_size,
+            shape: BinShape::default(),
+            colors: vec![
+                Color32::from_rgba_unmultiplied(255, 255, 178, 160),
+                Color32::from_rgba_unmultiplied(253, 141, 60, 180),
+                Color32::from_rgba_unmultiplied(189, 0, 38, 200),
+            ],
+            stroke: Stroke::NONE,
+            font: FontId::proportional(11.),
+            show_counts: true,
+            id: None,
+        }
+    }
+
+    /// Moments of the points, in the same order. Points outside of the map's
+    /// [`crate::TimeWindow`] are not counted.
+    pub fn times(mut self, times: Vec<f64>) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    pub fn shape(mut self, shape: BinShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Color ramp, from the least to the most populated cell.
+    pub fn colors(mut self, colors: Vec<Color32>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+
+    /// Whether to print the number of points in cells which are large enough to fit it.
+    pub fn show_counts(mut self, show_counts: bool) -> Self {
+        self.show_counts = show_counts;
+        self
+    }
+
+    /// Keep the counted cells under given id between frames, so that the points are binned again
+    /// only when the cell size, shape or [`crate::TimeWindow`] changes, rather than on each frame.
+    /// Call [`Hexbin::invalidate`] when the points change.
+    pub fn cache(mut self, id_salt: impl Hash) -> Self {
+        self.id = Some(Id::new(("walkers_hexbin", id_salt)));
+        self
+    }
+
+    /// Forget the cells kept under given id, so that the points are binned in the next frame.
+    pub fn invalidate(ctx: &Context, id_salt: impl Hash) {
+        let id = Id::new(("walkers_hexbin", id_salt));
+        ctx.data_mut(|data| data.remove::<Bins>(id));
+    }
+
+    fn color(&self, count: usize, max: usize) -> Color32 {
+        match self.colors.as_slice() {
+            [] => Color32::TRANSPARENT,
+            [color] => *color,
+            colors => {
+                let t = if max > 1 {
+                    (count - 1) as f32 / (max - 1) as f32
+                } else {
+                    0.
+                };
+                let scaled = t * (colors.len() - 1) as f32;
+                let i = (scaled.floor() as usize).min(colors.len() - 2);
+                colors[i].lerp_to_gamma(colors[i + 1], scaled - i as f32)
+            }
+        }
+    }
+}
+
+/// Cells laid out on the map at zoom 0, see [`Projector::bitmap`]. They do not change as the map
+/// is zoomed, panned or rotated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Grid {
+    shape: BinShape,
+
+    /// Corner (for squares) or center (for hexagons) of one of the cells.
+    origin: Pixel,
+
+    /// For hexagons, distance from the center to a corner. For squares, length of a side.
+    size: f64,
+}
+
+impl Grid {
+    /// Integer coordinates of the cell containing the point.
+    fn cell(&self, point: Pixel) -> (i64, i64) {
+        let p = (point - self.origin) / self.size;
+        match self.shape {
+            BinShape::Square => (p.x.floor() as i64, p.y.floor() as i64),
+            BinShape::Hexagon => {
+                // Axial coordinates of pointy-top hexagons, rounded via cube coordinates.
+                let q = 3f64.sqrt() / 3. * p.x - p.y / 3.;
+                let r = 2. / 3. * p.y;
+                let s = -q - r;
+
+                let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+                let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+                if dq > dr && dq > ds {
+                    rq = -rr - rs;
+                } else if dr > ds {
+                    rr = -rq - rs;
+                }
+                (rq as i64, rr as i64)
+            }
+        }
+    }
+
+    fn center(&self, (q, r): (i64, i64)) -> Pixel {
+        let (q, r) = (q as f64, r as f64);
+        let offset = match self.shape {
+            BinShape::Square => Pixel {
+                x: q + 0.5,
+                y: r + 0.5,
+            },
+            BinShape::Hexagon => Pixel {
+                x: 3f64.sqrt() * (q + r / 2.),
+                y: 1.5 * r,
+            },
+        };
+        self.origin + offset * self.size
+    }
+
+    fn corners(&self, cell: (i64, i64)) -> Vec<Pixel> {
+        let center = self.center(cell);
+        let (count, first, radius) = match self.shape {
+            BinShape::Square => (4, 45., self.size / 2f64.sqrt()),
+            BinShape::Hexagon => (6, -30., self.size),
+        };
+        (0..count)
+            .map(|i| {
+                let angle = (first + 360. / count as f64 * i as f64).to_radians();
+                center
+                    + Pixel {
+                        x: angle.cos(),
+                        y: angle.sin(),
+                    } * radius
+            })
+            .collect()
+    }
+
+    /// Radius of a circle fitting inside the cell.
+    fn inner_radius(&self) -> f64 {
+        match self.shape {
+            BinShape::Square => self.size / 2.,
+            BinShape::Hexagon => self.size * 3f64.sqrt() / 2.,
+        }
+    }
+}
+
+/// Points counted into cells, with what they depend on.
+#[derive(Clone)]
+struct Bins {
+    grid: Grid,
+    time_window: Option<TimeWindow>,
+    counts: Arc<Vec<((i64, i64), usize)>>,
+    max: usize,
+}
+
+impl Hexbin {
+    fn bin(&self, grid: Grid, projector: &Projector) -> Bins {
+        let mut counts: HashMap<(i64, i64), usize> = HashMap::new();
+        for (i, point) in self.points.iter().enumerate() {
+            let time = self.times.as_ref().and_then(|times| times.get(i));
+            if time.map_or(true, |time| projector.shows_time(*time)) {
+                *counts
+                    .entry(grid.cell(projector.bitmap(*point)))
+                    .or_default() += 1;
+            }
+        }
+
+        Bins {
+            grid,
+            time_window: projector.time_window(),
+            max: counts.values().copied().max().unwrap_or(0),
+            counts: Arc::new(counts.into_iter().collect()),
+        }
+    }
+}
+
+impl Plugin for Hexbin {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let Some(first) = self.points.first() else {
+            return;
+        };
+
+        // Grid is anchored at the first point, rather than at the map's center, so that cells keep
+        // their size and place while the map is dragged.
+        let width = self.cell_size * projector.bitmap_per_meter(*first);
+        let grid = Grid {
+            shape: self.shape,
+            origin: projector.bitmap(*first),
+            size: match self.shape {
+                BinShape::Square => width,
+                BinShape::Hexagon => width / 3f64.sqrt(),
+            },
+        };
+
+        // Cells smaller than a pixel would not be visible anyway.
+        let screen_per_bitmap = 2f64.powf(projector.memory().zoom());
+        let size = (grid.size * screen_per_bitmap) as f32;
+        if size.is_nan() || size < 1. {
+            return;
+        }
+
+        let cached = self
+            .id
+            .and_then(|id| ui.data(|data| data.get_temp::<Bins>(id)))
+            .filter(|bins| bins.grid == grid && bins.time_window == projector.time_window());
+        let bins = cached.unwrap_or_else(|| {
+            let bins = self.bin(grid, projector);
+            if let Some(id) = self.id {
+                ui.data_mut(|data| data.insert_temp(id, bins.clone()));
+            }
+            bins
+        });
+
+        let visible = ui.clip_rect().expand(2. * size);
+        let painter = ui.painter();
+        let shown: Vec<_> = bins
+            .counts
+            .iter()
+            .map(|&(cell, count)| (cell, count, projector.project_bitmap(grid.center(cell))))
+            .filter(|(_, _, center)| visible.contains(*center))
+            .collect();
+
+        for &(cell, count, _) in &shown {
+            let corners = grid
+                .corners(cell)
+                .into_iter()
+                .map(|corner| projector.project_bitmap(corner))
+                .collect();
+            painter.add(Shape::convex_polygon(
+                corners,
+                self.color(count, bins.max),
+                self.stroke,
+            ));
+        }
+
+        let inner_radius = grid.inner_radius() * screen_per_bitmap;
+        if self.show_counts && inner_radius > 2. * self.font.size as f64 {
+            for &(_, count, center) in &shown {
+                painter.text(
+                    center,
+                    Align2::CENTER_CENTER,
+                    count.to_string(),
+                    self.font.clone(),
+                    Color32::BLACK,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_contain_their_centers() {
+        for shape in [BinShape::Square, BinShape::Hexagon] {
+            let grid = Grid {
+                shape,
+                origin: Pixel { x: 10., y: -3. },
+                size: 0.5,
+            };
+            for cell in [(0, 0), (1, -2), (-3, 4), (7, 7)] {
+                assert_eq!(grid.cell(grid.center(cell)), cell, "{shape:?}");
+                for corner in grid.corners(cell) {
+                    // Just inside of each corner.
+                    let inside = grid.center(cell) + (corner - grid.center(cell)) * 0.9;
+                    assert_eq!(grid.cell(inside), cell, "{shape:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inner_radius() {
+        let grid = Grid {
+            shape: BinShape::Hexagon,
+            origin: Pixel { x: 0., y: 0. },
+            size: 1.,
+        };
+        let center = grid.center((0, 0));
+        // Neighbouring hexagons are two inner radii apart.
+        let neighbour = grid.center((1, 0));
+        let distance = ((neighbour.x - center.x).powi(2) + (neighbour.y - center.y).powi(2)).sqrt();
+        assert!((distance - 2. * grid.inner_radius()).abs() < 1e-9);
+    }
+}
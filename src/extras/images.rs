@@ -1,53 +1,164 @@
-use egui::{emath::Rot2, Rect, Response, Ui, Vec2};
+use egui::{emath::Rot2, pos2, Mesh, Rect, Response, Ui, Vec2};
 
-use crate::{projector::Projector, tiles::Texture, Plugin, Position};
+use super::Selectable;
+use crate::{projector::Projector, tiles::Texture, BoundingBox, Plugin, Position};
 
 /// An image to be drawn on the map.
 pub struct Image {
     /// Geographical position.
     position: Position,
 
+    /// Geographical area covered by the image, if pinned to it.
+    bounds: Option<BoundingBox>,
+
     scale: Vec2,
     angle: Rot2,
     texture: Texture,
+
+    /// The same image in other resolutions, see [`Image::with_sizes`].
+    sizes: Vec<Texture>,
+
+    /// Width on the ground, in meters, if the image scales along with the map.
+    ground_width: Option<f32>,
+
+    /// Moment the image was taken at, for the [`crate::TimeWindow`].
+    time: Option<f64>,
 }
 
 impl Image {
     pub fn new(texture: Texture, position: Position) -> Self {
         Self {
             position,
+            bounds: None,
             scale: Vec2::splat(1.0),
             angle: Rot2::from_angle(0.0),
             texture,
+            sizes: Vec::new(),
+            ground_width: None,
+            time: None,
+        }
+    }
+
+    /// Image supplied in several resolutions, e.g. an icon rendered at 16, 32 and 64 pixels. The
+    /// first texture sets the image's size in points, and whichever is the closest match for the
+    /// size it is actually drawn at, in physical pixels, gets drawn. This keeps icons sharp on
+    /// high density displays and free of shimmering when scaled down.
+    pub fn with_sizes(textures: Vec<Texture>, position: Position) -> Option<Self> {
+        let mut textures = textures.into_iter();
+        let texture = textures.next()?;
+        Some(Self {
+            sizes: textures.collect(),
+            ..Self::new(texture, position)
+        })
+    }
+
+    /// Image stretched over a geographical area, so it scales along with the map. Since
+    /// [`Texture`] can be created from a [`egui::TextureHandle`], which might be updated on each
+    /// frame, it can be used to display e.g. a georeferenced video feed.
+    pub fn with_bounds(texture: Texture, bounds: BoundingBox) -> Self {
+        Self {
+            bounds: Some(bounds),
+            ..Self::new(texture, bounds.center())
         }
     }
 
-    /// Scale the image.
+    /// Scale the image. Has no effect on images created by [`Image::with_bounds`].
     pub fn scale(&mut self, x: f32, y: f32) {
         self.scale.x = x;
         self.scale.y = y;
     }
 
+    /// Keep the image this wide on the ground, in meters, so it grows and shrinks with zoom,
+    /// like e.g. a footprint of a vehicle. Overrides [`Image::scale`].
+    pub fn ground_width(&mut self, meters: f32) {
+        self.ground_width = Some(meters);
+    }
+
     /// Set the image's angle in radians.
     pub fn angle(&mut self, angle: f32) {
         self.angle = Rot2::from_angle(angle);
     }
 
+    /// Set the moment the image was taken at. Such image is hidden when outside of the map's
+    /// [`crate::TimeWindow`].
+    pub fn time(&mut self, time: f64) {
+        self.time = Some(time);
+    }
+
     pub fn draw(&self, ui: &Ui, projector: &Projector) {
+        if !self.time.map_or(true, |time| projector.shows_time(time)) {
+            return;
+        }
+
         let painter = ui.painter();
-        let rect = Rect::from_center_size(
-            projector.project(self.position),
-            self.texture.size() * self.scale,
-        );
-
-        if painter.clip_rect().intersects(rect) {
-            let mut mesh = self.texture.mesh_with_rect(rect);
-            mesh.rotate(self.angle, rect.center());
+        let mesh = match self.bounds {
+            Some(bounds) => {
+                let rect = self.flat_rect(bounds, projector);
+                let texture = self.texture_for(rect.width() * ui.ctx().pixels_per_point());
+                self.bounded_mesh(texture, rect, projector)
+            }
+            None => {
+                let rect =
+                    Rect::from_center_size(projector.project(self.position), self.size(projector));
+                let texture = self.texture_for(rect.width() * ui.ctx().pixels_per_point());
+                let mut mesh = texture.mesh_with_rect(rect);
+                mesh.rotate(self.angle, rect.center());
+                mesh
+            }
+        };
+
+        if painter.clip_rect().intersects(mesh.calc_bounds()) {
             painter.add(mesh);
         }
     }
 }
 
+impl Image {
+    /// Area covered by the image on the flat, north-up map, see [`Projector::project_flat`].
+    fn flat_rect(&self, bounds: BoundingBox, projector: &Projector) -> Rect {
+        Rect::from_two_pos(
+            projector.project_flat(bounds.min()),
+            projector.project_flat(bounds.max()),
+        )
+    }
+
+    /// Mesh of the image stretched over its bounds, turned and tilted along with the map.
+    fn bounded_mesh(&self, texture: &Texture, rect: Rect, projector: &Projector) -> Mesh {
+        let transform = projector.screen_transform();
+        let uv = Rect::from_min_max(pos2(0., 0.), pos2(1., 1.));
+        let mut mesh = if transform.is_tilted() {
+            // Perspective bends straight lines within a quad, so split it.
+            texture.mesh_with_rect_and_uv_grid(rect, uv, transform.subdivisions)
+        } else {
+            texture.mesh_with_rect_and_uv(rect, uv)
+        };
+        mesh.rotate(self.angle, rect.center());
+        transform.transform_mesh(&mut mesh, projector.clip_rect().center());
+        mesh
+    }
+
+    fn size(&self, projector: &Projector) -> Vec2 {
+        let size = self.texture.size();
+        match self.ground_width {
+            Some(meters) => {
+                size * (meters * projector.scale_pixel_per_meter(self.position) / size.x)
+            }
+            None => size * self.scale,
+        }
+    }
+
+    /// The smallest texture at least as wide as given number of physical pixels, so that it gets
+    /// scaled down rather than up, or the largest one.
+    fn texture_for(&self, width: f32) -> &Texture {
+        let textures = || std::iter::once(&self.texture).chain(&self.sizes);
+        textures()
+            .filter(|texture| texture.size().x >= width)
+            .min_by(|a, b| a.size().x.total_cmp(&b.size().x))
+            .or_else(|| textures().max_by(|a, b| a.size().x.total_cmp(&b.size().x)))
+            .unwrap_or(&self.texture)
+    }
+}
+
 /// [`Plugin`] which draws given list of images on the map.
 pub struct Images {
     images: Vec<Image>,
@@ -66,3 +177,43 @@ impl Plugin for Images {
         }
     }
 }
+
+impl Selectable for Images {
+    fn feature_positions(&self) -> Box<dyn Iterator<Item = Position> + '_> {
+        Box::new(self.images.iter().map(|image| image.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pos_from_lon_lat, MapMemory};
+    use egui::{Color32, ColorImage, Context};
+
+    #[test]
+    fn bounded_image_turns_with_the_map() {
+        let mut memory = MapMemory::default();
+        memory.set_bearing(30.);
+        memory.set_tilt(45.);
+        let screen = Rect::from_min_size(pos2(0., 0.), Vec2::splat(512.));
+        let projector = Projector::new(&mut memory, screen, pos_from_lon_lat(0., 0.));
+
+        let texture =
+            Texture::from_color_image(ColorImage::new([2, 2], Color32::WHITE), &Context::default());
+        let bounds = BoundingBox::new(pos_from_lon_lat(-1., -1.), pos_from_lon_lat(1., 1.));
+        let image = Image::with_bounds(texture.clone(), bounds);
+        let mesh = image.bounded_mesh(&texture, image.flat_rect(bounds, &projector), &projector);
+
+        // Corners of the texture end up where the corners of the bounds are projected to.
+        for (uv, (lon, lat)) in [
+            (pos2(0., 0.), (-1., 1.)),
+            (pos2(1., 0.), (1., 1.)),
+            (pos2(1., 1.), (1., -1.)),
+            (pos2(0., 1.), (-1., -1.)),
+        ] {
+            let vertex = mesh.vertices.iter().find(|vertex| vertex.uv == uv).unwrap();
+            let expected = projector.project(pos_from_lon_lat(lon, lat));
+            assert!(vertex.pos.distance(expected) < 1e-3, "{uv:?}");
+        }
+    }
+}
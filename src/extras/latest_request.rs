@@ -0,0 +1,175 @@
+use egui::{vec2, Align2, Context, Response, Spinner, Ui};
+use futures::{
+    channel::mpsc::{channel, unbounded, Receiver, Sender, UnboundedReceiver, UnboundedSender},
+    future::{select, Either},
+    SinkExt, StreamExt,
+};
+
+use crate::{io::Runtime, Plugin, PluginLayer, Projector};
+
+/// Future of an asynchronous request, see [`LatestRequest`] and
+/// [`super::ViewportDataProvider`]. It needs not be `Send` in WASM, so it can await the
+/// browser's APIs.
+#[cfg(not(target_arch = "wasm32"))]
+pub type ProviderFuture<'a, T> = futures::future::BoxFuture<'a, Result<T, String>>;
+
+/// Future of an asynchronous request, see [`LatestRequest`] and
+/// [`super::ViewportDataProvider`]. It needs not be `Send` in WASM, so it can await the
+/// browser's APIs.
+#[cfg(target_arch = "wasm32")]
+pub type ProviderFuture<'a, T> = futures::future::LocalBoxFuture<'a, Result<T, String>>;
+
+enum Command<T> {
+    Start(u64, ProviderFuture<'static, T>),
+    Cancel,
+}
+
+/// Runs asynchronous requests backing a layer, such as geocoding or routing, keeping only the
+/// latest one. Starting a request cancels the one in flight by dropping its future, so results
+/// of stale requests never arrive. Keep it in the application's state, call
+/// [`LatestRequest::poll`] on each frame, and add [`LatestRequest::spinner`] to the map to show
+/// that a request is in flight. Requests run in its own IO thread.
+pub struct LatestRequest<T> {
+    generation: u64,
+    /// Generation of the request which was last completed or cancelled.
+    settled: u64,
+    command_tx: UnboundedSender<Command<T>>,
+    result_rx: Receiver<(u64, Result<T, String>)>,
+
+    #[allow(dead_code)] // Significant Drop
+    runtime: Runtime,
+}
+
+impl<T: Send + 'static> LatestRequest<T> {
+    pub fn new(egui_ctx: Context) -> Self {
+        let (command_tx, command_rx) = unbounded();
+        let (result_tx, result_rx) = channel(1);
+        let runtime = Runtime::new(run_continuously(command_rx, result_tx, egui_ctx));
+
+        Self {
+            generation: 0,
+            settled: 0,
+            command_tx,
+            result_rx,
+            runtime,
+        }
+    }
+}
+
+impl<T> LatestRequest<T> {
+    /// Start the request, cancelling the one in flight, if any.
+    pub fn start(&mut self, request: ProviderFuture<'static, T>) {
+        self.generation += 1;
+        if self
+            .command_tx
+            .unbounded_send(Command::Start(self.generation, request))
+            .is_err()
+        {
+            self.settled = self.generation;
+        }
+    }
+
+    /// Cancel the request in flight, if any.
+    pub fn cancel(&mut self) {
+        if self.is_loading() {
+            let _ = self.command_tx.unbounded_send(Command::Cancel);
+            self.settled = self.generation;
+        }
+    }
+
+    /// Whether a request is in flight.
+    pub fn is_loading(&self) -> bool {
+        self.generation > self.settled
+    }
+
+    /// Result of the latest request, once it arrives. Returned only once.
+    pub fn poll(&mut self) -> Option<Result<T, String>> {
+        let mut latest = None;
+        while let Ok((generation, result)) = self.result_rx.try_recv() {
+            if generation == self.generation && self.is_loading() {
+                self.settled = generation;
+                latest = Some(result);
+            }
+        }
+        latest
+    }
+
+    /// [`Plugin`] showing a spinner in the corner of the map while a request is in flight.
+    pub fn spinner(&self) -> LoadingSpinner {
+        LoadingSpinner::new(self.is_loading())
+    }
+}
+
+async fn run_continuously<T>(
+    mut command_rx: UnboundedReceiver<Command<T>>,
+    mut result_tx: Sender<(u64, Result<T, String>)>,
+    egui_ctx: Context,
+) {
+    let mut current = None;
+    loop {
+        let command = match current.take() {
+            None => command_rx.next().await,
+            Some((generation, request)) => match select(command_rx.next(), request).await {
+                // Dropping the request cancels it.
+                Either::Left((command, _)) => command,
+                Either::Right((result, _)) => {
+                    // Main thread is gone if this fails, nothing to do about it.
+                    if result_tx.send((generation, result)).await.is_err() {
+                        return;
+                    }
+                    egui_ctx.request_repaint();
+                    continue;
+                }
+            },
+        };
+
+        match command {
+            Some(Command::Start(generation, request)) => current = Some((generation, request)),
+            Some(Command::Cancel) => {}
+            None => return,
+        }
+    }
+}
+
+/// [`Plugin`] showing a spinner while data of a layer is loading. See [`LatestRequest::spinner`].
+pub struct LoadingSpinner {
+    loading: bool,
+    anchor: Align2,
+    size: f32,
+}
+
+impl LoadingSpinner {
+    pub fn new(loading: bool) -> Self {
+        Self {
+            loading,
+            anchor: Align2::RIGHT_TOP,
+            size: 20.,
+        }
+    }
+
+    /// Corner of the map where the spinner is shown. Default is the top right one.
+    pub fn anchor(mut self, anchor: Align2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+impl Plugin for LoadingSpinner {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, _projector: &Projector) {
+        if self.loading {
+            let rect = self
+                .anchor
+                .align_size_within_rect(vec2(self.size, self.size), ui.max_rect().shrink(8.));
+            Spinner::new().size(self.size).paint_at(ui, rect);
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Top
+    }
+}
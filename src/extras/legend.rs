@@ -0,0 +1,400 @@
+use std::sync::Arc;
+
+use egui::{pos2, vec2, Align2, Color32, FontId, Galley, Mesh, Rect, Response, Stroke, Ui};
+
+use crate::{Plugin, PluginLayer, Projector};
+
+/// Single entry of the [`Legend`].
+#[derive(Clone)]
+pub enum LegendEntry {
+    /// Solid color swatch followed by a label.
+    Swatch { color: Color32, label: String },
+
+    /// Color ramp, with the `min` value at its left end and the `max` value at its right end,
+    /// both shown with `precision` decimal places.
+    Gradient {
+        label: String,
+        colors: Vec<Color32>,
+        min: f64,
+        max: f64,
+        precision: usize,
+    },
+}
+
+/// Layers which can describe their symbology in the [`Legend`].
+pub trait LegendContributor {
+    fn legend_entries(&self) -> Vec<LegendEntry>;
+}
+
+/// Visual style of the legend panel.
+#[derive(Clone)]
+pub struct LegendStyle {
+    pub font: FontId,
+    pub text_color: Color32,
+    pub background: Color32,
+    pub stroke: Stroke,
+    pub swatch_size: f32,
+    pub gradient_width: f32,
+    pub padding: f32,
+    pub spacing: f32,
+}
+
+impl Default for LegendStyle {
+    fn default() -> Self {
+        Self {
+            font: FontId::proportional(12.),
+            text_color: Color32::from_gray(200),
+            background: Color32::BLACK.gamma_multiply(0.8),
+            stroke: Stroke::NONE,
+            swatch_size: 12.,
+            gradient_width: 120.,
+            padding: 8.,
+            spacing: 4.,
+        }
+    }
+}
+
+/// [`Plugin`] which draws a panel describing the symbology of other layers. Since the map is
+/// rebuilt on each frame, collect the entries only from the layers which are currently shown.
+pub struct Legend {
+    title: Option<String>,
+    entries: Vec<LegendEntry>,
+    anchor: Align2,
+    style: LegendStyle,
+}
+
+impl Default for Legend {
+    fn default() -> Self {
+        Self {
+            title: None,
+            entries: Vec::new(),
+            anchor: Align2::RIGHT_BOTTOM,
+            style: LegendStyle::default(),
+        }
+    }
+}
+
+impl Legend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Title displayed above the entries.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Corner of the map in which the panel is placed. Default is the bottom right one.
+    pub fn anchor(mut self, anchor: Align2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn style(mut self, style: LegendStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_entry(mut self, entry: LegendEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Append all entries provided by the layer.
+    pub fn with_layer(mut self, layer: &impl LegendContributor) -> Self {
+        self.entries.extend(layer.legend_entries());
+        self
+    }
+}
+
+/// Width and height of a single row.
+fn row_size(ui: &Ui, entry: &LegendEntry, style: &LegendStyle) -> egui::Vec2 {
+    let text_size = |text: &str| {
+        ui.painter()
+            .layout_no_wrap(text.to_owned(), style.font.clone(), style.text_color)
+            .size()
+    };
+
+    match entry {
+        LegendEntry::Swatch { label, .. } => {
+            let text = text_size(label);
+            vec2(
+                style.swatch_size + style.spacing + text.x,
+                text.y.max(style.swatch_size),
+            )
+        }
+        LegendEntry::Gradient { label, .. } => {
+            let text = text_size(label);
+            vec2(
+                text.x.max(style.gradient_width),
+                2. * text.y + style.swatch_size + 2. * style.spacing,
+            )
+        }
+    }
+}
+
+/// Value at the end of a gradient, with given number of decimal places. Values which round to
+/// zero are shown without the sign.
+fn format_value(value: f64, precision: usize) -> String {
+    let text = format!("{value:.precision$}");
+    match text.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => unsigned.to_owned(),
+        _ => text,
+    }
+}
+
+fn draw_gradient(ui: &Ui, rect: Rect, colors: &[Color32]) {
+    match colors {
+        [] => {}
+        [color] => {
+            ui.painter().rect_filled(rect, 0., *color);
+        }
+        colors => {
+            let mut mesh = Mesh::default();
+            let step = rect.width() / (colors.len() - 1) as f32;
+            for (i, color) in colors.iter().enumerate() {
+                let x = rect.left() + i as f32 * step;
+                mesh.colored_vertex(pos2(x, rect.top()), *color);
+                mesh.colored_vertex(pos2(x, rect.bottom()), *color);
+                if i > 0 {
+                    let idx = 2 * i as u32;
+                    mesh.add_triangle(idx - 2, idx - 1, idx);
+                    mesh.add_triangle(idx - 1, idx, idx + 1);
+                }
+            }
+            ui.painter().add(mesh);
+        }
+    }
+}
+
+/// Where the panel and its parts are placed.
+struct Layout {
+    panel: Rect,
+    title: Option<(egui::Pos2, Arc<Galley>)>,
+
+    /// Space taken by each entry.
+    rows: Vec<Rect>,
+}
+
+impl Legend {
+    fn layout(&self, ui: &Ui) -> Layout {
+        let style = &self.style;
+
+        let title = self.title.as_ref().map(|title| {
+            ui.painter()
+                .layout_no_wrap(title.to_owned(), style.font.clone(), style.text_color)
+        });
+
+        let sizes: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| row_size(ui, entry, style))
+            .collect();
+
+        let mut content = title.as_ref().map_or(egui::Vec2::ZERO, |t| t.size());
+        for size in &sizes {
+            content.x = content.x.max(size.x);
+            content.y += size.y;
+        }
+        let items = sizes.len() + title.is_some() as usize;
+        content.y += style.spacing * items.saturating_sub(1) as f32;
+
+        let size = content + vec2(2. * style.padding, 2. * style.padding);
+        let panel = self
+            .anchor
+            .align_size_within_rect(size, ui.max_rect().shrink(style.padding));
+
+        let mut cursor = panel.min + vec2(style.padding, style.padding);
+
+        let title = title.map(|title| {
+            let position = cursor;
+            cursor.y += title.size().y + style.spacing;
+            (position, title)
+        });
+
+        let rows = sizes
+            .into_iter()
+            .map(|size| {
+                let row = Rect::from_min_size(cursor, size);
+                cursor.y += size.y + style.spacing;
+                row
+            })
+            .collect();
+
+        Layout { panel, title, rows }
+    }
+
+    fn draw(&self, ui: &Ui) {
+        let style = &self.style;
+        let painter = ui.painter();
+        let layout = self.layout(ui);
+
+        painter.rect(layout.panel, 4., style.background, style.stroke);
+
+        if let Some((position, title)) = layout.title {
+            painter.galley(position, title, style.text_color);
+        }
+
+        for (entry, row) in self.entries.iter().zip(layout.rows) {
+            match entry {
+                LegendEntry::Swatch { color, label } => {
+                    let swatch = Rect::from_min_size(
+                        pos2(row.left(), row.center().y - style.swatch_size / 2.),
+                        vec2(style.swatch_size, style.swatch_size),
+                    );
+                    painter.rect_filled(swatch, 2., *color);
+                    painter.text(
+                        pos2(swatch.right() + style.spacing, swatch.center().y),
+                        Align2::LEFT_CENTER,
+                        label,
+                        style.font.clone(),
+                        style.text_color,
+                    );
+                }
+                LegendEntry::Gradient {
+                    label,
+                    colors,
+                    min,
+                    max,
+                    precision,
+                } => {
+                    let label = painter.layout_no_wrap(
+                        label.to_owned(),
+                        style.font.clone(),
+                        style.text_color,
+                    );
+                    let text_height = label.size().y;
+                    painter.galley(row.min, label, style.text_color);
+
+                    let ramp = Rect::from_min_size(
+                        pos2(row.left(), row.top() + text_height + style.spacing),
+                        vec2(style.gradient_width, style.swatch_size),
+                    );
+                    draw_gradient(ui, ramp, colors);
+
+                    let values_y = ramp.bottom() + style.spacing;
+                    painter.text(
+                        pos2(ramp.left(), values_y),
+                        Align2::LEFT_TOP,
+                        format_value(*min, *precision),
+                        style.font.clone(),
+                        style.text_color,
+                    );
+                    painter.text(
+                        pos2(ramp.right(), values_y),
+                        Align2::RIGHT_TOP,
+                        format_value(*max, *precision),
+                        style.font.clone(),
+                        style.text_color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for Legend {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, _projector: &Projector) {
+        if !self.entries.is_empty() || self.title.is_some() {
+            self.draw(ui);
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `f` with a [`Ui`] covering a 400 by 300 screen.
+    fn with_ui(f: impl FnOnce(&Ui)) {
+        let ctx = egui::Context::default();
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(egui::Pos2::ZERO, vec2(400., 300.))),
+            ..Default::default()
+        };
+        let mut f = Some(f);
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                if let Some(f) = f.take() {
+                    f(ui);
+                }
+            });
+        });
+    }
+
+    fn legend() -> Legend {
+        Legend::new()
+            .title("Depth")
+            .with_entry(LegendEntry::Swatch {
+                color: Color32::BLUE,
+                label: "Deep water".to_owned(),
+            })
+            .with_entry(LegendEntry::Gradient {
+                label: "Speed (km/h)".to_owned(),
+                colors: vec![Color32::GREEN, Color32::RED],
+                min: 0.,
+                max: 42.5,
+                precision: 1,
+            })
+    }
+
+    #[test]
+    fn rows_are_stacked_within_the_panel() {
+        with_ui(|ui| {
+            let legend = legend();
+            let style = &legend.style;
+            let layout = legend.layout(ui);
+
+            let (title, _) = layout.title.unwrap();
+            assert_eq!(title, layout.panel.min + vec2(style.padding, style.padding));
+
+            assert_eq!(layout.rows.len(), 2);
+            assert!(layout.rows[0].top() > title.y);
+            assert_eq!(
+                layout.rows[1].top(),
+                layout.rows[0].bottom() + style.spacing
+            );
+            for row in &layout.rows {
+                assert!(layout.panel.shrink(style.padding).contains_rect(*row));
+            }
+
+            // Gradient's row fits the ramp, even if its label is shorter.
+            assert!(layout.rows[1].width() >= style.gradient_width);
+            assert_eq!(
+                layout.panel.bottom(),
+                layout.rows[1].bottom() + style.padding
+            );
+        });
+    }
+
+    #[test]
+    fn panel_is_placed_in_the_anchor_corner() {
+        with_ui(|ui| {
+            let screen = ui.max_rect();
+
+            let layout = legend().layout(ui);
+            assert_eq!(
+                layout.panel.right_bottom(),
+                screen.shrink(8.).right_bottom()
+            );
+
+            let layout = legend().anchor(Align2::LEFT_TOP).layout(ui);
+            assert_eq!(layout.panel.left_top(), screen.shrink(8.).left_top());
+        });
+    }
+
+    #[test]
+    fn gradient_values() {
+        assert_eq!(format_value(42.5, 1), "42.5");
+        assert_eq!(format_value(0.1 + 0.2, 2), "0.30");
+        assert_eq!(format_value(1234.5678, 0), "1235");
+        assert_eq!(format_value(-12.3456, 3), "-12.346");
+        assert_eq!(format_value(-0.004, 2), "0.00");
+        assert_eq!(format_value(-0., 0), "0");
+    }
+}
@@ -4,3 +4,51 @@ pub use places::{Place, Places, Style};
 mod images;
 pub use crate::tiles::Texture;
 pub use images::{Image, Images};
+mod legend;
+pub use legend::{Legend, LegendContributor, LegendEntry, LegendStyle};
+mod go_to;
+pub use go_to::GoTo;
+mod selection;
+pub use selection::{RectangleSelection, Selectable, Selected};
+mod hexbin;
+pub use hexbin::{BinShape, Hexbin};
+mod time_slider;
+pub use time_slider::TimeSlider;
+mod tile_layer;
+pub use tile_layer::TileLayer;
+mod bidi;
+pub use bidi::visual_order;
+mod grid;
+pub use grid::{snap_to_grid, Grid};
+mod ruler;
+pub use ruler::Ruler;
+mod route_editor;
+pub use route_editor::RouteEditor;
+mod polylabel;
+pub use polylabel::{polylabel, PolylabelCache};
+mod compass;
+pub use compass::CompassRose;
+mod astronomy;
+pub use astronomy::{
+    moon_position, moon_rise_set, sun_position, sun_rise_set, Horizontal, RiseSet, SunLines,
+};
+mod offscreen;
+pub use offscreen::OffscreenIndicators;
+mod halo;
+pub use halo::galley_with_halo;
+mod areas;
+#[cfg(feature = "mvt")]
+pub(crate) use areas::triangulate;
+pub use areas::{Area, Areas, FillPattern, PatternSpacing};
+mod trail;
+pub use trail::{Trail, TrailPoint, TrailRecorder};
+mod depth;
+pub use depth::{DepthLegend, DepthUnit};
+mod colored_track;
+pub use colored_track::{ColoredTrack, ColoredTrail, TrackMetric};
+mod latest_request;
+pub use latest_request::{LatestRequest, LoadingSpinner, ProviderFuture};
+mod viewport_data;
+pub use viewport_data::{ViewportData, ViewportDataProvider, ViewportLayer};
+mod range_rings;
+pub use range_rings::{DistanceUnit, RangeRings};
@@ -0,0 +1,127 @@
+use egui::{Align2, Color32, FontId, Response, Shape, Stroke, Ui, Vec2};
+
+use crate::{units::haversine, Plugin, PluginLayer, Position, Projector};
+
+/// [`Plugin`] which points to positions outside of the view with arrows along the edge of the
+/// map, labeled with the distance from the map's center, e.g. to keep track of vehicles.
+pub struct OffscreenIndicators {
+    targets: Vec<Position>,
+    color: Color32,
+    size: f32,
+    font: FontId,
+}
+
+impl OffscreenIndicators {
+    pub fn new(targets: Vec<Position>) -> Self {
+        Self {
+            targets,
+            color: Color32::from_rgb(230, 80, 40),
+            size: 12.,
+            font: FontId::proportional(11.),
+        }
+    }
+
+    pub fn color(mut self, color: Color32) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Length of the arrows, in points.
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn font(mut self, font: FontId) -> Self {
+        self.font = font;
+        self
+    }
+}
+
+impl Plugin for OffscreenIndicators {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let clip_rect = projector.clip_rect();
+        let center = clip_rect.center();
+        let center_position = projector.unproject(center);
+        let inner = clip_rect.shrink(self.size + 4.);
+        let painter = ui.painter();
+
+        for target in &self.targets {
+            let screen = projector.project(*target);
+            if clip_rect.contains(screen) {
+                continue;
+            }
+
+            let direction = screen - center;
+            let Some(tip) = edge_point(inner.size() / 2., direction) else {
+                continue;
+            };
+            let tip = center + tip;
+            let unit = direction.normalized();
+            let forward = unit * self.size;
+            let side = forward.rot90() * 0.5;
+            let base = tip - forward;
+
+            painter.add(Shape::convex_polygon(
+                vec![tip, base + side, base - side],
+                self.color,
+                Stroke::new(1., Color32::WHITE),
+            ));
+
+            let distance = if projector.memory().is_global() {
+                format_distance(haversine(center_position, *target))
+            } else {
+                format!(
+                    "{:.2}",
+                    (target.x - center_position.x).hypot(target.y - center_position.y)
+                )
+            };
+
+            // Keep the label inside, next to the arrow's base.
+            let galley = painter.layout_no_wrap(distance, self.font.clone(), Color32::WHITE);
+            let anchor = Align2([align(-unit.x), align(-unit.y)]);
+            let rect = anchor
+                .anchor_size(base - forward * 0.3, galley.size())
+                .expand(2.);
+            painter.rect_filled(rect, 3., Color32::from_black_alpha(180));
+            painter.galley(rect.shrink(2.).min, galley, Color32::WHITE);
+        }
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Top
+    }
+}
+
+/// Point where the ray from the center of a rectangle, with given half size, leaves it.
+fn edge_point(half_size: Vec2, direction: Vec2) -> Option<Vec2> {
+    let scale = [
+        half_size.x / direction.x.abs(),
+        half_size.y / direction.y.abs(),
+    ]
+    .into_iter()
+    .filter(|scale| scale.is_finite())
+    .reduce(f32::min)?;
+    (scale >= 0.).then(|| direction * scale)
+}
+
+/// Alignment of the label which makes it extend along given component of a unit vector.
+pub(super) fn align(component: f32) -> egui::Align {
+    if component > 0.5 {
+        egui::Align::Min
+    } else if component < -0.5 {
+        egui::Align::Max
+    } else {
+        egui::Align::Center
+    }
+}
+
+fn format_distance(meters: f64) -> String {
+    if meters < 1000. {
+        format!("{:.0} m", meters)
+    } else if meters < 10_000. {
+        format!("{:.1} km", meters / 1000.)
+    } else {
+        format!("{:.0} km", meters / 1000.)
+    }
+}
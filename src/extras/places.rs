@@ -1,5 +1,6 @@
 use egui::{vec2, Align2, Color32, FontId, Response, Stroke, Ui};
 
+use super::{galley_with_halo, visual_order, Selectable};
 use crate::{Plugin, Position};
 
 /// Visual style of the place.
@@ -8,6 +9,10 @@ pub struct Style {
     pub label_font: FontId,
     pub label_color: Color32,
     pub label_background: Color32,
+
+    /// Outline around the label's text, see [`super::galley_with_halo`]. Usually paired with a
+    /// transparent `label_background`, for labels drawn straight over imagery.
+    pub label_halo: Option<Stroke>,
     pub symbol_font: FontId,
     pub symbol_color: Color32,
     pub symbol_background: Color32,
@@ -20,6 +25,7 @@ impl Default for Style {
             label_font: FontId::proportional(12.),
             label_color: Color32::from_gray(200),
             label_background: Color32::BLACK.gamma_multiply(0.8),
+            label_halo: None,
             symbol_font: FontId::proportional(14.),
             symbol_color: Color32::BLACK.gamma_multiply(0.8),
             symbol_background: Color32::WHITE.gamma_multiply(0.8),
@@ -49,26 +55,32 @@ impl Place {
         let screen_position = projector.project(self.position);
         let painter = ui.painter();
 
-        let label = painter.layout_no_wrap(
-            self.label.to_owned(),
-            self.style.label_font.clone(),
-            self.style.label_color,
-        );
-
-        // Offset of the label, relative to the circle.
-        let offset = vec2(8., 8.);
-
-        painter.rect_filled(
-            label
-                .rect
-                .translate(screen_position.to_vec2())
-                .translate(offset)
-                .expand(5.),
-            10.,
-            self.style.label_background,
-        );
-
-        painter.galley(screen_position + offset, label, Color32::BLACK);
+        // Marker is always drawn, but the label might be thinned out by the label budget.
+        if projector.place_label(screen_position) {
+            let label = painter.layout_no_wrap(
+                visual_order(&self.label),
+                self.style.label_font.clone(),
+                self.style.label_color,
+            );
+
+            // Offset of the label, relative to the circle.
+            let offset = vec2(8., 8.);
+
+            painter.rect_filled(
+                label
+                    .rect
+                    .translate(screen_position.to_vec2())
+                    .translate(offset)
+                    .expand(5.),
+                10.,
+                self.style.label_background,
+            );
+
+            match self.style.label_halo {
+                Some(halo) => galley_with_halo(painter, screen_position + offset, label, halo),
+                None => painter.galley(screen_position + offset, label, Color32::BLACK),
+            }
+        }
 
         painter.circle(
             screen_position,
@@ -87,7 +99,8 @@ impl Place {
     }
 }
 
-/// [`Plugin`] which draws list of places on the map.
+/// [`Plugin`] which draws list of places on the map. When there is a [`crate::LabelBudget`],
+/// places earlier in the list take precedence.
 pub struct Places {
     places: Vec<Place>,
 }
@@ -105,3 +118,9 @@ impl Plugin for Places {
         }
     }
 }
+
+impl Selectable for Places {
+    fn feature_positions(&self) -> Box<dyn Iterator<Item = Position> + '_> {
+        Box::new(self.places.iter().map(|place| place.position))
+    }
+}
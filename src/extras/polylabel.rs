@@ -0,0 +1,301 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+};
+
+use geo_types::Polygon;
+
+use crate::Position;
+
+/// Pole of inaccessibility of the polygon, i.e. the point inside which is the farthest from its
+/// edges, found with the [polylabel](https://github.com/mapbox/polylabel) algorithm. Unlike the
+/// centroid, it stays inside concave shapes, which makes it a good place for a label. Search
+/// stops once the result is within `precision` from the optimum, in the polygon's units.
+///
+/// Coordinates are treated as planar, which is fine for labels of polygons which are not
+/// enormous, even in latitude and longitude.
+pub fn polylabel(polygon: &Polygon<f64>, precision: f64) -> Position {
+    let exterior = polygon.exterior();
+    let Some(first) = exterior.0.first() else {
+        return Position::default();
+    };
+
+    let (min, max) = exterior.0.iter().fold((*first, *first), |(min, max), c| {
+        (
+            Position {
+                x: min.x.min(c.x),
+                y: min.y.min(c.y),
+            },
+            Position {
+                x: max.x.max(c.x),
+                y: max.y.max(c.y),
+            },
+        )
+    });
+
+    let cell_size = (max.x - min.x).min(max.y - min.y);
+    if cell_size <= 0. {
+        return min;
+    }
+
+    let precision = precision.max(cell_size * 1e-6);
+    let mut queue = BinaryHeap::new();
+    let mut x = min.x;
+    while x < max.x {
+        let mut y = min.y;
+        while y < max.y {
+            queue.push(Cell::new(
+                x + cell_size / 2.,
+                y + cell_size / 2.,
+                cell_size / 2.,
+                polygon,
+            ));
+            y += cell_size;
+        }
+        x += cell_size;
+    }
+
+    // Centroid is often a good guess, so start with it.
+    let centroid = centroid(polygon).unwrap_or(*first);
+    let mut best = Cell::new(centroid.x, centroid.y, 0., polygon);
+    let center = Cell::new((min.x + max.x) / 2., (min.y + max.y) / 2., 0., polygon);
+    if center.distance > best.distance {
+        best = center;
+    }
+
+    while let Some(cell) = queue.pop() {
+        // No point in splitting cells which cannot contain a better one.
+        if cell.max_distance - best.distance.max(cell.distance) > precision {
+            let half = cell.half / 2.;
+            for (dx, dy) in [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)] {
+                queue.push(Cell::new(
+                    cell.center.x + dx * half,
+                    cell.center.y + dy * half,
+                    half,
+                    polygon,
+                ));
+            }
+        }
+
+        if cell.distance > best.distance {
+            best = cell;
+        }
+    }
+
+    best.center
+}
+
+/// Remembers [`polylabel`] results, so that they are computed once per geometry rather than
+/// on each frame. Geometries are identified by keys, e.g. feature ids, and must be invalidated
+/// with [`PolylabelCache::remove`] or [`PolylabelCache::clear`] when they change.
+pub struct PolylabelCache<K> {
+    positions: HashMap<K, Position>,
+    precision: f64,
+}
+
+impl<K: Hash + Eq> PolylabelCache<K> {
+    pub fn new(precision: f64) -> Self {
+        Self {
+            positions: HashMap::new(),
+            precision,
+        }
+    }
+
+    /// Label position of the polygon stored under the key, computing it if needed.
+    pub fn get(&mut self, key: K, polygon: &Polygon<f64>) -> Position {
+        let precision = self.precision;
+        *self
+            .positions
+            .entry(key)
+            .or_insert_with(|| polylabel(polygon, precision))
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        self.positions.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+    }
+}
+
+struct Cell {
+    center: Position,
+    half: f64,
+
+    /// Distance from the center to the polygon's outline, negative if outside.
+    distance: f64,
+
+    /// Maximum distance to the outline of any point within the cell.
+    max_distance: f64,
+}
+
+impl Cell {
+    fn new(x: f64, y: f64, half: f64, polygon: &Polygon<f64>) -> Self {
+        let center = Position { x, y };
+        let distance = signed_distance(center, polygon);
+        Self {
+            center,
+            half,
+            distance,
+            max_distance: distance + half * std::f64::consts::SQRT_2,
+        }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_distance == other.max_distance
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.max_distance.total_cmp(&other.max_distance)
+    }
+}
+
+/// Distance from the point to the nearest edge of any ring, negative if the point is outside.
+fn signed_distance(point: Position, polygon: &Polygon<f64>) -> f64 {
+    let mut inside = false;
+    let mut min_distance_sq = f64::INFINITY;
+
+    for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+        for line in ring.lines() {
+            let (a, b) = (line.start, line.end);
+
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+            {
+                inside = !inside;
+            }
+
+            min_distance_sq = min_distance_sq.min(segment_distance_sq(point, a, b));
+        }
+    }
+
+    let distance = min_distance_sq.sqrt();
+    if inside {
+        distance
+    } else {
+        -distance
+    }
+}
+
+fn segment_distance_sq(p: Position, a: Position, b: Position) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0. {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_sq).clamp(0., 1.)
+    } else {
+        0.
+    };
+    let (x, y) = (a.x + dx * t - p.x, a.y + dy * t - p.y);
+    x * x + y * y
+}
+
+/// Area-weighted centroid of the exterior ring.
+fn centroid(polygon: &Polygon<f64>) -> Option<Position> {
+    let mut area = 0.;
+    let (mut x, mut y) = (0., 0.);
+
+    for line in polygon.exterior().lines() {
+        let (a, b) = (line.start, line.end);
+        let f = a.x * b.y - b.x * a.y;
+        x += (a.x + b.x) * f;
+        y += (a.y + b.y) * f;
+        area += f * 3.;
+    }
+
+    (area != 0.).then(|| Position {
+        x: x / area,
+        y: y / area,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::{polygon, LineString};
+
+    use super::*;
+
+    #[test]
+    fn square() {
+        let square = polygon![(x: 0., y: 0.), (x: 10., y: 0.), (x: 10., y: 10.), (x: 0., y: 10.)];
+        let label = polylabel(&square, 0.01);
+        assert!((label.x - 5.).abs() < 0.01 && (label.y - 5.).abs() < 0.01);
+    }
+
+    #[test]
+    fn concave() {
+        // U shape, whose centroid is within the gap between its arms.
+        let u = polygon![
+            (x: 0., y: 0.),
+            (x: 10., y: 0.),
+            (x: 10., y: 10.),
+            (x: 7., y: 10.),
+            (x: 7., y: 3.),
+            (x: 3., y: 3.),
+            (x: 3., y: 10.),
+            (x: 0., y: 10.),
+        ];
+        let centroid = centroid(&u).unwrap();
+        assert!(signed_distance(centroid, &u) < 0.);
+
+        let label = polylabel(&u, 0.01);
+        assert!(signed_distance(label, &u) > 1.4);
+    }
+
+    #[test]
+    fn hole() {
+        let frame = Polygon::new(
+            LineString::from(vec![(0., 0.), (10., 0.), (10., 10.), (0., 10.)]),
+            vec![LineString::from(vec![
+                (3., 3.),
+                (7., 3.),
+                (7., 7.),
+                (3., 7.),
+            ])],
+        );
+        let label = polylabel(&frame, 0.01);
+
+        // Anywhere within the frame, which is 3 wide, but not in the hole.
+        assert!(signed_distance(label, &frame) > 1.49);
+        assert!(!(3. ..=7.).contains(&label.x) || !(3. ..=7.).contains(&label.y));
+    }
+
+    #[test]
+    fn degenerate() {
+        let empty = Polygon::new(LineString::<f64>::new(Vec::new()), Vec::new());
+        assert_eq!(polylabel(&empty, 0.01), Position::default());
+
+        let point = polygon![(x: 1., y: 2.), (x: 1., y: 2.), (x: 1., y: 2.)];
+        assert_eq!(polylabel(&point, 0.01), Position { x: 1., y: 2. });
+
+        let line = polygon![(x: 0., y: 0.), (x: 10., y: 0.), (x: 5., y: 0.)];
+        let label = polylabel(&line, 0.01);
+        assert!(label.x.is_finite() && label.y.is_finite());
+    }
+
+    #[test]
+    fn cache() {
+        let square = polygon![(x: 0., y: 0.), (x: 2., y: 0.), (x: 2., y: 2.), (x: 0., y: 2.)];
+        let moved = polygon![(x: 4., y: 0.), (x: 6., y: 0.), (x: 6., y: 2.), (x: 4., y: 2.)];
+        let mut cache = PolylabelCache::new(0.01);
+
+        let label = cache.get(1, &square);
+        // Stale until removed.
+        assert_eq!(cache.get(1, &moved), label);
+        cache.remove(&1);
+        assert!(cache.get(1, &moved).x > 4.);
+    }
+}
@@ -0,0 +1,242 @@
+use egui::{Align2, Color32, FontId, Pos2, Response, Shape, Stroke, Ui};
+
+use super::{galley_with_halo, offscreen::align};
+use crate::{units::EARTH_RADIUS, Plugin, Position, Projector};
+
+/// Unit of the spacing of [`RangeRings`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceUnit {
+    #[default]
+    Kilometers,
+    NauticalMiles,
+}
+
+impl DistanceUnit {
+    pub fn to_meters(&self, distance: f64) -> f64 {
+        match self {
+            DistanceUnit::Kilometers => distance * 1000.,
+            DistanceUnit::NauticalMiles => distance * 1852.,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DistanceUnit::Kilometers => "km",
+            DistanceUnit::NauticalMiles => "nm",
+        }
+    }
+}
+
+/// Smallest angle between the radial lines of [`RangeRings`], in degrees. Denser ones would
+/// not be told apart anyway.
+const MIN_RADIAL_STEP: f64 = 1.;
+
+/// [`Plugin`] which draws rings of equal great-circle distance around an origin, along with
+/// radial lines of constant initial bearing, e.g. for the coverage of a radio station or an
+/// airport's approach area. They are traced on the sphere, so they get stretched towards the
+/// poles just like the map. Local maps are not supported.
+pub struct RangeRings {
+    origin: Position,
+    spacing: f64,
+    unit: DistanceUnit,
+    rings: usize,
+    radials: Option<f64>,
+    stroke: Stroke,
+    font: FontId,
+    halo: Option<Stroke>,
+}
+
+impl RangeRings {
+    /// Rings every `spacing` units around the origin.
+    pub fn new(origin: Position, spacing: f64, unit: DistanceUnit) -> Self {
+        Self {
+            origin,
+            spacing,
+            unit,
+            rings: 5,
+            radials: Some(30.),
+            stroke: Stroke::new(1.5, Color32::from_rgb(0, 200, 255)),
+            font: FontId::proportional(12.),
+            halo: Some(Stroke::new(1.5, Color32::BLACK)),
+        }
+    }
+
+    /// Number of rings. Default is 5.
+    pub fn rings(mut self, rings: usize) -> Self {
+        self.rings = rings;
+        self
+    }
+
+    /// Angle between the radial lines, in degrees, starting from the north, or `None` for no
+    /// radial lines. Default is 30. Angles below 1 degree are raised to it, and ones which are
+    /// not finite and positive mean no radial lines.
+    pub fn radials(mut self, step: Option<f64>) -> Self {
+        self.radials = step
+            .filter(|step| step.is_finite() && *step > 0.)
+            .map(|step| step.max(MIN_RADIAL_STEP));
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+
+    pub fn font(mut self, font: FontId) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Outline around the labels, see [`super::galley_with_halo`].
+    pub fn halo(mut self, halo: Option<Stroke>) -> Self {
+        self.halo = halo;
+        self
+    }
+
+    fn label(&self, ui: &Ui, position: Pos2, text: String, anchor: Align2) {
+        let painter = ui.painter();
+        let galley = painter.layout_no_wrap(text, self.font.clone(), self.stroke.color);
+        let position = anchor.anchor_size(position, galley.size()).min;
+        match self.halo {
+            Some(halo) => galley_with_halo(painter, position, galley, halo),
+            None => painter.galley(position, galley, self.stroke.color),
+        }
+    }
+}
+
+/// Position at the distance, in meters, along the great circle starting at the bearing, in
+/// degrees clockwise from the north.
+fn destination(origin: Position, bearing: f64, distance: f64) -> Position {
+    let (lat, lon) = (origin.y.to_radians(), origin.x.to_radians());
+    let (bearing, angle) = (bearing.to_radians(), distance / EARTH_RADIUS);
+    let dest_lat = (lat.sin() * angle.cos() + lat.cos() * angle.sin() * bearing.cos()).asin();
+    let dest_lon = lon
+        + (bearing.sin() * angle.sin() * lat.cos()).atan2(angle.cos() - lat.sin() * dest_lat.sin());
+    Position {
+        x: dest_lon.to_degrees(),
+        y: dest_lat.to_degrees(),
+    }
+}
+
+/// Screen points of the path. Longitudes are unwrapped, so that it stays continuous across the
+/// antimeridian, and latitudes are kept off the poles, where Mercator goes to infinity. Second
+/// value tells whether the path went around a pole.
+fn project_path(
+    projector: &Projector,
+    positions: impl Iterator<Item = Position>,
+) -> (Vec<Pos2>, bool) {
+    let mut previous: Option<Position> = None;
+    let mut first_x = None;
+    let points = positions
+        .map(|position| {
+            let x = match previous {
+                Some(previous) => position.x - 360. * ((position.x - previous.x) / 360.).round(),
+                None => position.x,
+            };
+            let unwrapped = Position {
+                x,
+                y: position.y.clamp(-85.05, 85.05),
+            };
+            first_x.get_or_insert(x);
+            previous = Some(unwrapped);
+            projector.project(unwrapped)
+        })
+        .collect();
+
+    let around_pole = match (first_x, previous) {
+        (Some(first), Some(last)) => (last.x - first).abs() > 180.,
+        _ => false,
+    };
+    (points, around_pole)
+}
+
+impl Plugin for RangeRings {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        if !projector.memory().is_global() || self.spacing <= 0. || !self.spacing.is_finite() {
+            return;
+        }
+
+        // Beyond half of the Earth's circumference, rings start shrinking again.
+        let max_distance = std::f64::consts::PI * EARTH_RADIUS;
+        let painter = ui.painter();
+
+        let mut outermost = 0.;
+        for i in 1..=self.rings {
+            let distance = self.unit.to_meters(self.spacing * i as f64);
+            if distance >= max_distance {
+                break;
+            }
+            outermost = distance;
+
+            let (points, around_pole) = project_path(
+                projector,
+                (0..=360).map(|bearing| destination(self.origin, bearing as f64, distance)),
+            );
+            if around_pole {
+                painter.add(Shape::line(points, self.stroke));
+            } else {
+                painter.add(Shape::closed_line(points, self.stroke));
+            }
+
+            let label_at = projector.project(destination(self.origin, 0., distance));
+            let value = self.spacing * i as f64;
+            let label = if value.fract() == 0. {
+                format!("{:.0} {}", value, self.unit.symbol())
+            } else {
+                format!("{:.1} {}", value, self.unit.symbol())
+            };
+            self.label(ui, label_at, label, Align2::LEFT_BOTTOM);
+        }
+
+        let Some(step) = self.radials else {
+            return;
+        };
+        if outermost == 0. {
+            return;
+        }
+
+        let count = (360. / step).round() as usize;
+        for bearing in (0..count).map(|i| i as f64 * step) {
+            let (points, _) = project_path(
+                projector,
+                (0..=64).map(|i| destination(self.origin, bearing, outermost * i as f64 / 64.)),
+            );
+            let [.., before, end] = points[..] else {
+                continue;
+            };
+            painter.add(Shape::line(points, self.stroke));
+
+            // Keep the label outside of the ring, off the line's end.
+            let direction = (end - before).normalized();
+            let anchor = Align2([align(direction.x), align(direction.y)]);
+            self.label(
+                ui,
+                end + direction * 4.,
+                format!("{:03.0}°", bearing),
+                anchor,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lon_lat;
+
+    fn radials(step: f64) -> Option<f64> {
+        RangeRings::new(pos_from_lon_lat(0., 0.), 1., DistanceUnit::Kilometers)
+            .radials(Some(step))
+            .radials
+    }
+
+    #[test]
+    fn radial_steps() {
+        assert_eq!(radials(45.), Some(45.));
+        assert_eq!(radials(0.0001), Some(MIN_RADIAL_STEP));
+        assert_eq!(radials(0.), None);
+        assert_eq!(radials(-10.), None);
+        assert_eq!(radials(f64::NAN), None);
+        assert_eq!(radials(f64::INFINITY), None);
+    }
+}
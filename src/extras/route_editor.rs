@@ -0,0 +1,237 @@
+use egui::{Align2, Color32, FontId, Id, Pos2, Rect, Response, Sense, Stroke, Ui, Vec2};
+
+use super::grid::snap_to_grid;
+use crate::{Plugin, Position, Projector};
+
+/// [`Plugin`] for editing an ordered list of waypoints, e.g. to be passed to a routing service.
+///
+/// - Clicking the map appends a waypoint, unless it is close to the line between two of them, in
+///   which case the waypoint is inserted there.
+/// - Waypoints can be dragged around.
+/// - Secondary click removes a waypoint.
+///
+/// Use [`RouteEditor::list`] to show the waypoints in a side panel, where they can be reordered.
+pub struct RouteEditor<'a> {
+    waypoints: &'a mut Vec<Position>,
+    stroke: Stroke,
+    fill: Color32,
+    radius: f32,
+    snap: Option<f64>,
+}
+
+impl<'a> RouteEditor<'a> {
+    pub fn new(waypoints: &'a mut Vec<Position>) -> Self {
+        Self {
+            waypoints,
+            stroke: Stroke::new(3., Color32::from_rgb(0, 120, 215)),
+            fill: Color32::WHITE,
+            radius: 10.,
+            snap: None,
+        }
+    }
+
+    /// Stroke of the line between waypoints and the outline of the waypoints.
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+
+    pub fn fill(mut self, fill: Color32) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Radius of the waypoint markers, in points.
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Snap added and dragged waypoints to a grid with given spacing, in the map's units, e.g.
+    /// the same as of [`super::Grid`].
+    pub fn snap(mut self, spacing: f64) -> Self {
+        self.snap = Some(spacing);
+        self
+    }
+
+    /// Numbered list of the waypoints, which can be reordered by dragging and removed. At most
+    /// one edit is applied per frame, as the indices of the others would be stale.
+    pub fn list(ui: &mut Ui, waypoints: &mut Vec<Position>) {
+        let mut edit = None;
+
+        for (index, waypoint) in waypoints.iter().enumerate() {
+            let row = ui.horizontal(|ui| {
+                ui.dnd_drag_source(Id::new("walkers_waypoint").with(index), index, |ui| {
+                    ui.label(format!(
+                        "{}. {:.5}, {:.5}",
+                        index + 1,
+                        waypoint.y,
+                        waypoint.x
+                    ));
+                });
+
+                if ui.small_button("🗑").clicked() {
+                    edit = edit.or(Some(ListEdit::Remove(index)));
+                }
+            });
+
+            if let Some(from) = row.response.dnd_release_payload::<usize>() {
+                edit = edit.or(Some(ListEdit::Move(*from, index)));
+            }
+        }
+
+        if let Some(edit) = edit {
+            edit.apply(waypoints);
+        }
+    }
+
+    fn snapped(&self, position: Position) -> Position {
+        match self.snap {
+            Some(spacing) => snap_to_grid(position, spacing),
+            None => position,
+        }
+    }
+
+    /// Index at which a waypoint clicked at `pos` should be inserted.
+    fn insertion_index(&self, pos: Pos2, projector: &Projector) -> usize {
+        let screen: Vec<Pos2> = self
+            .waypoints
+            .iter()
+            .map(|waypoint| projector.project(*waypoint))
+            .collect();
+
+        screen
+            .windows(2)
+            .enumerate()
+            .map(|(index, segment)| (index, distance_to_segment(pos, segment[0], segment[1])))
+            .filter(|(_, distance)| *distance <= self.radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index + 1)
+            .unwrap_or(self.waypoints.len())
+    }
+}
+
+impl Plugin for RouteEditor<'_> {
+    fn run(self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        let mut removed = None;
+        let mut moved = None;
+
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            let center = projector.project(*waypoint);
+            let marker = ui.interact(
+                Rect::from_center_size(center, Vec2::splat(self.radius * 2.)),
+                ui.id().with("walkers_route_editor").with(index),
+                Sense::click_and_drag(),
+            );
+
+            if marker.dragged() {
+                if let Some(pos) = marker.interact_pointer_pos() {
+                    moved = Some((index, self.snapped(projector.unproject(pos))));
+                }
+            }
+
+            if marker.secondary_clicked() {
+                removed = Some(index);
+            }
+        }
+
+        if let Some((index, position)) = moved {
+            self.waypoints[index] = position;
+        }
+
+        if let Some(index) = removed {
+            self.waypoints.remove(index);
+        }
+
+        if response.clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let index = self.insertion_index(pos, projector);
+                let waypoint = self.snapped(projector.unproject(pos));
+                self.waypoints.insert(index, waypoint);
+            }
+        }
+
+        let screen: Vec<Pos2> = self
+            .waypoints
+            .iter()
+            .map(|waypoint| projector.project(*waypoint))
+            .collect();
+
+        let painter = ui.painter();
+        painter.line(screen.clone(), self.stroke);
+
+        for (index, center) in screen.into_iter().enumerate() {
+            painter.circle(center, self.radius, self.fill, self.stroke);
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                index + 1,
+                FontId::proportional(self.radius),
+                self.stroke.color,
+            );
+        }
+    }
+}
+
+/// Change made in [`RouteEditor::list`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ListEdit {
+    Move(usize, usize),
+    Remove(usize),
+}
+
+impl ListEdit {
+    /// Apply the edit, unless its indices are out of bounds, e.g. for a payload dragged while
+    /// the list was longer.
+    fn apply(self, waypoints: &mut Vec<Position>) {
+        match self {
+            ListEdit::Move(from, to) if from < waypoints.len() && to < waypoints.len() => {
+                let waypoint = waypoints.remove(from);
+                waypoints.insert(to, waypoint);
+            }
+            ListEdit::Remove(index) if index < waypoints.len() => {
+                waypoints.remove(index);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn distance_to_segment(pos: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0. {
+        ((pos - a).dot(ab) / ab.length_sq()).clamp(0., 1.)
+    } else {
+        0.
+    };
+    pos.distance(a + ab * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lon_lat;
+
+    fn waypoints(count: usize) -> Vec<Position> {
+        (0..count).map(|x| pos_from_lon_lat(x as f64, 0.)).collect()
+    }
+
+    fn longitudes(waypoints: &[Position]) -> Vec<f64> {
+        waypoints.iter().map(|waypoint| waypoint.x).collect()
+    }
+
+    #[test]
+    fn list_edits() {
+        let mut route = waypoints(4);
+        ListEdit::Move(0, 2).apply(&mut route);
+        assert_eq!(longitudes(&route), [1., 2., 0., 3.]);
+
+        ListEdit::Remove(3).apply(&mut route);
+        assert_eq!(longitudes(&route), [1., 2., 0.]);
+
+        // Stale indices are ignored.
+        ListEdit::Move(3, 0).apply(&mut route);
+        ListEdit::Remove(3).apply(&mut route);
+        assert_eq!(longitudes(&route), [1., 2., 0.]);
+    }
+}
@@ -0,0 +1,198 @@
+use egui::{Align2, Color32, FontId, Modifiers, Response, Sense, Stroke, Ui};
+
+use crate::{Plugin, PluginLayer, Position, Projector};
+
+use super::grid::snap_to_grid;
+
+/// [`Plugin`] which measures the distance between two points on a local map, dragged while
+/// holding a modifier key (<kbd>alt</kbd> by default). The measurement stays on the map until
+/// it is clicked. Distances are in the map's local units.
+///
+/// While the modifier is held, the ruler takes the drag for itself, so the map does not pan.
+pub struct Ruler {
+    modifiers: Modifiers,
+    snap: Option<f64>,
+    units: String,
+    stroke: Stroke,
+    font: FontId,
+}
+
+impl Default for Ruler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ruler {
+    pub fn new() -> Self {
+        Self {
+            modifiers: Modifiers::ALT,
+            snap: None,
+            units: String::new(),
+            stroke: Stroke::new(2., Color32::YELLOW),
+            font: FontId::proportional(13.),
+        }
+    }
+
+    /// Modifier keys which must be held to start measuring.
+    pub fn modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Snap both ends to a grid with given spacing, e.g. the same as of [`super::Grid`].
+    pub fn snap(mut self, spacing: f64) -> Self {
+        self.snap = Some(spacing);
+        self
+    }
+
+    /// Name of the units, printed after the distance, e.g. "m".
+    pub fn units(mut self, units: impl Into<String>) -> Self {
+        self.units = units.into();
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+
+    fn snapped(&self, position: Position) -> Position {
+        match self.snap {
+            Some(spacing) => snap_to_grid(position, spacing),
+            None => position,
+        }
+    }
+
+    fn label(&self, distance: f64) -> String {
+        if self.units.is_empty() {
+            format!("{:.2}", distance)
+        } else {
+            format!("{:.2} {}", distance, self.units)
+        }
+    }
+}
+
+/// Measurement kept between frames.
+#[derive(Clone, Copy)]
+struct Measurement {
+    start: Position,
+    end: Position,
+    dragging: bool,
+}
+
+impl Plugin for Ruler {
+    fn run(self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        if projector.memory().is_global() {
+            return;
+        }
+
+        let id = ui.id().with("walkers_ruler");
+        let mut measurement: Option<Measurement> = ui.data(|data| data.get_temp(id)).flatten();
+
+        let held = ui.input(|i| i.modifiers.matches_logically(self.modifiers));
+        let dragging = measurement.is_some_and(|m| m.dragging);
+
+        // Placed over the map, so that it gets the drag instead of the map. Like other widgets,
+        // it is hit since the next frame, so holding the modifier before pressing is enough.
+        let drag =
+            (held || dragging).then(|| ui.interact(response.rect, id.with("drag"), Sense::drag()));
+        let started = drag
+            .as_ref()
+            .filter(|drag| held && drag.drag_started())
+            .and_then(|drag| drag.interact_pointer_pos());
+
+        if let Some(pos) = started {
+            let start = self.snapped(projector.unproject(pos));
+            measurement = Some(Measurement {
+                start,
+                end: start,
+                dragging: true,
+            });
+        } else if response.clicked() {
+            measurement = None;
+        }
+
+        if let Some(m) = &mut measurement {
+            if m.dragging {
+                if let Some(pos) = ui.input(|i| i.pointer.latest_pos()) {
+                    m.end = self.snapped(projector.unproject(pos));
+                }
+                m.dragging = drag.as_ref().is_some_and(|drag| drag.dragged());
+            }
+
+            let start = projector.project(m.start);
+            let end = projector.project(m.end);
+            let painter = ui.painter();
+            painter.line_segment([start, end], self.stroke);
+            painter.circle_filled(start, self.stroke.width * 2., self.stroke.color);
+            painter.circle_filled(end, self.stroke.width * 2., self.stroke.color);
+
+            let distance = (m.end.x - m.start.x).hypot(m.end.y - m.start.y);
+            let text = self.label(distance);
+            let galley = painter.layout_no_wrap(text, self.font.clone(), Color32::WHITE);
+            let rect = Align2::CENTER_BOTTOM
+                .anchor_size(start.lerp(end, 0.5), galley.size())
+                .expand(3.);
+            painter.rect_filled(rect, 3., Color32::from_black_alpha(180));
+            painter.galley(rect.shrink(3.).min, galley, Color32::WHITE);
+        }
+
+        ui.data_mut(|data| data.insert_temp(id, measurement));
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{pos2, Context, Event, PointerButton, Pos2, RawInput, Rect};
+
+    use super::*;
+    use crate::{LocalMap, MapMemory};
+
+    /// Drag over a local map with the ruler, while holding given modifiers.
+    fn drag(memory: &mut MapMemory, modifiers: Modifiers) {
+        let ctx = Context::default();
+        let (from, to) = (pos2(100., 100.), pos2(200., 150.));
+        let button = |pos, pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers,
+        };
+
+        let mut frames = vec![vec![Event::PointerMoved(from)], vec![button(from, true)]];
+        frames.extend((1..=8).map(|i| vec![Event::PointerMoved(from.lerp(to, i as f32 / 8.))]));
+        frames.push(vec![button(to, false)]);
+
+        for (i, events) in frames.into_iter().enumerate() {
+            let input = RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(256., 256.))),
+                time: Some(i as f64 / 60.),
+                modifiers,
+                events,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let origin = Position { x: 0., y: 0. };
+                    ui.add(LocalMap::new(memory, origin).with_plugin(Ruler::new()));
+                });
+            });
+        }
+    }
+
+    #[test]
+    fn measuring_does_not_pan_the_map() {
+        let mut memory = MapMemory::default();
+        drag(&mut memory, Modifiers::ALT);
+        assert!(memory.detached().is_none());
+
+        let mut memory = MapMemory::default();
+        drag(&mut memory, Modifiers::NONE);
+        assert!(memory.detached().is_some());
+    }
+}
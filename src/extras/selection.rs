@@ -0,0 +1,168 @@
+use egui::{Color32, Modifiers, Pos2, Rect, Response, Stroke, Ui};
+
+use crate::{Plugin, PluginLayer, Position, Projector};
+
+/// Layers whose features can be selected with the [`RectangleSelection`].
+pub trait Selectable {
+    /// Positions of the features, in the order in which they are reported as selected. It is
+    /// only called once the user finishes a selection.
+    fn feature_positions(&self) -> Box<dyn Iterator<Item = Position> + '_>;
+}
+
+impl Selectable for [Position] {
+    fn feature_positions(&self) -> Box<dyn Iterator<Item = Position> + '_> {
+        Box::new(self.iter().copied())
+    }
+}
+
+type Layer<'a> = Box<dyn Fn() -> Box<dyn Iterator<Item = Position> + 'a> + 'a>;
+
+/// Feature picked by the [`RectangleSelection`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selected {
+    /// Index of the layer, in the order they were added with [`RectangleSelection::with_layer`].
+    pub layer: usize,
+
+    /// Index of the feature within the layer.
+    pub feature: usize,
+}
+
+/// [`Plugin`] which lets the user drag a rectangle while holding a modifier key (<kbd>shift</kbd>
+/// by default), and selects all features of the given layers which are inside of it.
+///
+/// Since the map would get dragged as well, disable its drag gesture while the modifier is held:
+///
+/// ```ignore
+/// let selecting = ui.input(|i| i.modifiers.shift);
+/// Map::new(Some(&mut tiles), &mut memory, position)
+///     .drag_gesture(!selecting)
+///     .with_plugin(RectangleSelection::new(&mut selected).with_layer(&places));
+/// ```
+pub struct RectangleSelection<'a> {
+    layers: Vec<Layer<'a>>,
+    selected: &'a mut Vec<Selected>,
+    modifiers: Modifiers,
+    stroke: Stroke,
+    fill: Color32,
+}
+
+impl<'a> RectangleSelection<'a> {
+    /// Once the user finishes dragging, `selected` gets replaced by the features inside the
+    /// rectangle.
+    pub fn new(selected: &'a mut Vec<Selected>) -> Self {
+        Self {
+            layers: Vec::new(),
+            selected,
+            modifiers: Modifiers::SHIFT,
+            stroke: Stroke::new(1., Color32::LIGHT_BLUE),
+            fill: Color32::LIGHT_BLUE.gamma_multiply(0.2),
+        }
+    }
+
+    pub fn with_layer(mut self, layer: &'a (impl Selectable + ?Sized)) -> Self {
+        self.layers
+            .push(Box::new(move || layer.feature_positions()));
+        self
+    }
+
+    /// Modifier keys which must be held to start the selection.
+    pub fn modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+
+    pub fn fill(mut self, fill: Color32) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    fn select(&mut self, rect: Rect, projector: &Projector) {
+        self.selected.clear();
+        for (layer, positions) in self.layers.iter().enumerate() {
+            for (feature, position) in positions().enumerate() {
+                if rect.contains(projector.project(position)) {
+                    self.selected.push(Selected { layer, feature });
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for RectangleSelection<'_> {
+    fn run(mut self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        // Where the rectangle was started, if it is being dragged.
+        let id = ui.id().with("walkers_rectangle_selection");
+        let mut start: Option<Pos2> = ui.data(|data| data.get_temp(id)).flatten();
+
+        if response.drag_started() && ui.input(|i| i.modifiers.matches_logically(self.modifiers)) {
+            start = response.interact_pointer_pos();
+        }
+
+        if let (Some(start), Some(end)) = (start, ui.input(|i| i.pointer.latest_pos())) {
+            let rect = Rect::from_two_pos(start, end);
+            ui.painter().rect(rect, 0., self.fill, self.stroke);
+
+            if response.drag_stopped() {
+                self.select(rect, projector);
+            }
+        }
+
+        if !response.dragged() {
+            start = None;
+        }
+
+        ui.data_mut(|data| data.insert_temp(id, start));
+    }
+
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{pos2, vec2};
+
+    use super::*;
+    use crate::{pos_from_lon_lat, MapMemory};
+
+    #[test]
+    fn selects_features_inside_the_rectangle() {
+        let mut memory = MapMemory::default();
+        let screen = Rect::from_min_size(Pos2::ZERO, vec2(256., 256.));
+        let projector = Projector::new(&mut memory, screen, pos_from_lon_lat(0., 0.));
+
+        let first = [pos_from_lon_lat(0., 0.), pos_from_lon_lat(100., 0.)];
+        let second = vec![pos_from_lon_lat(100., 0.), pos_from_lon_lat(0., 0.)];
+        let mut selected = Vec::new();
+        let mut selection = RectangleSelection::new(&mut selected)
+            .with_layer(first.as_slice())
+            .with_layer(second.as_slice());
+
+        // Around the center of the screen, where the map is centered.
+        selection.select(
+            Rect::from_two_pos(pos2(120., 120.), pos2(136., 136.)),
+            &projector,
+        );
+        drop(selection);
+
+        assert_eq!(
+            selected,
+            [
+                Selected {
+                    layer: 0,
+                    feature: 0
+                },
+                Selected {
+                    layer: 1,
+                    feature: 1
+                }
+            ]
+        );
+    }
+}
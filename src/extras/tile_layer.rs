@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use egui::{Color32, Response, Ui};
+
+use crate::{
+    tiles::{center_tile, detail_zoom, flood_fill_tiles},
+    Plugin, Projector, Tiles,
+};
+
+/// [`Plugin`] which draws additional [`Tiles`] over the map's own ones, e.g. transparent road
+/// or seamark overlays on top of satellite imagery. Each layer has its own source and cache,
+/// while sharing the map's view. Local maps are not supported.
+pub struct TileLayer<'a> {
+    tiles: &'a mut dyn Tiles,
+    opacity: f32,
+    hidpi_tiles: bool,
+}
+
+impl<'a> TileLayer<'a> {
+    pub fn new(tiles: &'a mut dyn Tiles) -> Self {
+        Self {
+            tiles,
+            opacity: 1.,
+            hidpi_tiles: true,
+        }
+    }
+
+    /// Opacity of the layer, from 0 to 1.
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0., 1.);
+        self
+    }
+
+    /// Set whether the zoom level of tiles follows the display's pixels per point. Should be the
+    /// same as [`crate::Map::hidpi_tiles`] for layers to line up in detail. Enabled by default.
+    pub fn hidpi_tiles(mut self, enabled: bool) -> Self {
+        self.hidpi_tiles = enabled;
+        self
+    }
+}
+
+impl Plugin for TileLayer<'_> {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        if !projector.memory().is_global() {
+            return;
+        }
+
+        let zoom = projector.memory().zoom();
+        let map_center = projector.center();
+        let mut meshes = HashMap::new();
+        let tile_zoom = if self.hidpi_tiles {
+            detail_zoom(zoom, ui.ctx().pixels_per_point(), self.tiles)
+        } else {
+            zoom.round() as u8
+        };
+
+        let (tile_id, map_center_projected_position) = center_tile(
+            &projector.memory().projection_type,
+            map_center,
+            zoom,
+            tile_zoom,
+            self.tiles.zoom_offset(),
+        );
+
+        flood_fill_tiles(
+            projector.clip_rect(),
+            projector.screen_transform(),
+            &projector.memory().projection_type,
+            tile_id,
+            map_center_projected_position,
+            zoom,
+            self.tiles,
+            &mut meshes,
+        );
+
+        let tint = Color32::WHITE.gamma_multiply(self.opacity);
+        for mut mesh in meshes.into_values().flatten() {
+            for vertex in &mut mesh.vertices {
+                vertex.color = tint;
+            }
+            ui.painter().add(mesh);
+        }
+    }
+}
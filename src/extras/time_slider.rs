@@ -0,0 +1,64 @@
+use std::ops::RangeInclusive;
+
+use egui::{Response, Slider, Ui, Widget};
+
+use crate::{MapMemory, TimeWindow};
+
+/// Pair of sliders controlling the map's [`TimeWindow`], along with a checkbox turning it off.
+/// Meant to be put over or next to the map.
+pub struct TimeSlider<'a> {
+    map_memory: &'a mut MapMemory,
+    range: RangeInclusive<f64>,
+    formatter: Box<dyn Fn(f64) -> String + 'a>,
+}
+
+impl<'a> TimeSlider<'a> {
+    /// `range` is the period of time covered by the data.
+    pub fn new(map_memory: &'a mut MapMemory, range: RangeInclusive<f64>) -> Self {
+        Self {
+            map_memory,
+            range,
+            formatter: Box::new(|time| format!("{time:.0}")),
+        }
+    }
+
+    /// How the time is displayed, e.g. as a date. By default, raw seconds are shown.
+    pub fn formatter(mut self, formatter: impl Fn(f64) -> String + 'a) -> Self {
+        self.formatter = Box::new(formatter);
+        self
+    }
+}
+
+impl Widget for TimeSlider<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let mut time_window = self.map_memory.time_window();
+
+        let mut response = ui
+            .horizontal(|ui| {
+                let mut enabled = time_window.is_some();
+                ui.checkbox(&mut enabled, "");
+
+                let mut window =
+                    time_window.unwrap_or(TimeWindow::new(*self.range.start(), *self.range.end()));
+
+                ui.add_enabled_ui(enabled, |ui| {
+                    for value in [&mut window.start, &mut window.end] {
+                        ui.add(
+                            Slider::new(value, self.range.clone())
+                                .custom_formatter(|time, _| (self.formatter)(time)),
+                        );
+                    }
+                });
+
+                time_window = enabled.then(|| TimeWindow::new(window.start, window.end));
+            })
+            .response;
+
+        if time_window != self.map_memory.time_window() {
+            self.map_memory.set_time_window(time_window);
+            response.mark_changed();
+        }
+
+        response
+    }
+}
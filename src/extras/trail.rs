@@ -0,0 +1,287 @@
+use std::fmt::Write as _;
+
+use egui::{Color32, Response, Shape, Stroke, Ui};
+
+use super::{ColoredTrack, TrackMetric};
+use crate::{units::haversine, Plugin, Position, Projector};
+
+/// Position recorded by [`TrailRecorder`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrailPoint {
+    pub position: Position,
+
+    /// UNIX time, in seconds.
+    pub time: f64,
+}
+
+/// Records `my_position` over time, e.g. as fed by `Geolocation` on the web, turning the map into
+/// a simple track recorder. Positions come too often and too noisy to keep them all, so the ones
+/// recorded too soon or too close to the previous one are dropped. Keep the recorder in the
+/// application's state, feed it on every frame and draw it with [`TrailRecorder::plugin`].
+///
+/// Pausing splits the trail into segments, which are kept apart in the exported files.
+pub struct TrailRecorder {
+    segments: Vec<Vec<TrailPoint>>,
+    recording: bool,
+    min_interval: f64,
+    min_distance: f64,
+}
+
+impl Default for TrailRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrailRecorder {
+    /// New recorder, which starts recording right away.
+    pub fn new() -> Self {
+        Self {
+            segments: vec![Vec::new()],
+            recording: true,
+            min_interval: 1.,
+            min_distance: 2.,
+        }
+    }
+
+    /// Minimum time between recorded positions, in seconds. Default is 1.
+    pub fn min_interval(mut self, seconds: f64) -> Self {
+        self.min_interval = seconds;
+        self
+    }
+
+    /// Minimum distance between recorded positions, in meters. Default is 2.
+    pub fn min_distance(mut self, meters: f64) -> Self {
+        self.min_distance = meters;
+        self
+    }
+
+    /// Record the position at given UNIX time, in seconds, unless the recorder is paused or the
+    /// position is too close to the previous one. Returns whether it was recorded.
+    pub fn record(&mut self, position: Position, time: f64) -> bool {
+        if !self.recording {
+            return false;
+        }
+
+        let segment = self.segments.last_mut().expect("there is always a segment");
+        if let Some(last) = segment.last() {
+            if time - last.time < self.min_interval
+                || haversine(last.position, position) < self.min_distance
+            {
+                return false;
+            }
+        }
+
+        segment.push(TrailPoint { position, time });
+        true
+    }
+
+    /// Stop recording until [`TrailRecorder::resume`] is called.
+    pub fn pause(&mut self) {
+        self.recording = false;
+    }
+
+    /// Continue recording in a new segment.
+    pub fn resume(&mut self) {
+        if !self.recording {
+            self.recording = true;
+            if self.segments.last().is_some_and(|s| !s.is_empty()) {
+                self.segments.push(Vec::new());
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Forget everything recorded so far.
+    pub fn clear(&mut self) {
+        self.segments = vec![Vec::new()];
+    }
+
+    /// Recorded segments, skipping empty ones.
+    pub fn segments(&self) -> impl Iterator<Item = &[TrailPoint]> {
+        self.segments
+            .iter()
+            .filter(|segment| !segment.is_empty())
+            .map(Vec::as_slice)
+    }
+
+    /// Length of the trail in meters, not counting the gaps between segments.
+    pub fn length(&self) -> f64 {
+        self.segments()
+            .flat_map(|segment| segment.windows(2))
+            .map(|pair| haversine(pair[0].position, pair[1].position))
+            .sum()
+    }
+
+    /// The trail as a GPX 1.1 document, with a single track of given name.
+    pub fn to_gpx(&self, name: &str) -> String {
+        let mut gpx = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <gpx version=\"1.1\" creator=\"walkers\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+        );
+        let _ = writeln!(gpx, "<trk><name>{}</name>", escape_xml(name));
+        for segment in self.segments() {
+            gpx.push_str("<trkseg>\n");
+            for point in segment {
+                let _ = writeln!(
+                    gpx,
+                    r#"<trkpt lat="{}" lon="{}"><time>{}</time></trkpt>"#,
+                    point.position.y,
+                    point.position.x,
+                    iso8601(point.time)
+                );
+            }
+            gpx.push_str("</trkseg>\n");
+        }
+        gpx.push_str("</trk>\n</gpx>\n");
+        gpx
+    }
+
+    /// The trail as a GeoJSON feature with a `MultiLineString`, one line per segment. Times are
+    /// put in the `coordTimes` property, in the same layout as coordinates.
+    pub fn to_geojson(&self) -> String {
+        let segments: Vec<&[TrailPoint]> = self.segments().collect();
+        let list = |item: &dyn Fn(&TrailPoint) -> String| {
+            segments
+                .iter()
+                .map(|segment| {
+                    let items: Vec<String> = segment.iter().map(item).collect();
+                    format!("[{}]", items.join(","))
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        format!(
+            r#"{{"type":"Feature","geometry":{{"type":"MultiLineString","coordinates":[{}]}},"properties":{{"coordTimes":[{}]}}}}"#,
+            list(&|p| format!("[{},{}]", p.position.x, p.position.y)),
+            list(&|p| format!("\"{}\"", iso8601(p.time))),
+        )
+    }
+
+    /// Trail recorded so far, with segments colored by the metric.
+    pub fn colored(&self, metric: TrackMetric) -> ColoredTrack {
+        ColoredTrack::from_segments(self.segments(), metric)
+    }
+
+    /// [`Plugin`] drawing the trail recorded so far.
+    pub fn plugin(&self) -> Trail<'_> {
+        Trail {
+            recorder: self,
+            stroke: Stroke::new(3., Color32::from_rgb(40, 120, 230)),
+        }
+    }
+}
+
+/// [`Plugin`] which draws a [`TrailRecorder`]'s trail.
+pub struct Trail<'a> {
+    recorder: &'a TrailRecorder,
+    stroke: Stroke,
+}
+
+impl Trail<'_> {
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke = stroke;
+        self
+    }
+}
+
+impl Plugin for Trail<'_> {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        let painter = ui.painter();
+        for segment in self.recorder.segments() {
+            let points = segment
+                .iter()
+                .map(|point| projector.project(point.position))
+                .collect();
+            painter.add(Shape::line(points, self.stroke));
+        }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// UTC date and time of the UNIX time, e.g. `2024-05-01T12:30:00Z`.
+fn iso8601(time: f64) -> String {
+    let seconds = time.floor() as i64;
+    let (days, seconds) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lon_lat;
+
+    #[test]
+    fn iso8601_dates() {
+        assert_eq!(iso8601(0.), "1970-01-01T00:00:00Z");
+        assert_eq!(iso8601(-1.), "1969-12-31T23:59:59Z");
+        assert_eq!(iso8601(951_782_400.), "2000-02-29T00:00:00Z");
+        assert_eq!(iso8601(1_709_251_199.), "2024-02-29T23:59:59Z");
+        assert_eq!(iso8601(1_714_566_600.9), "2024-05-01T12:30:00Z");
+        assert_eq!(iso8601(4_107_542_399.5), "2100-02-28T23:59:59Z");
+    }
+
+    #[test]
+    fn recording() {
+        let mut recorder = TrailRecorder::new();
+        assert!(recorder.record(pos_from_lon_lat(0., 0.), 0.));
+        // Too soon, then too close.
+        assert!(!recorder.record(pos_from_lon_lat(0.001, 0.), 0.5));
+        assert!(!recorder.record(pos_from_lon_lat(0.00001, 0.), 10.));
+        assert!(recorder.record(pos_from_lon_lat(0.001, 0.), 10.));
+
+        recorder.pause();
+        assert!(!recorder.record(pos_from_lon_lat(0.002, 0.), 20.));
+        recorder.resume();
+        assert!(recorder.record(pos_from_lon_lat(0.003, 0.), 30.));
+
+        assert_eq!(recorder.segments().count(), 2);
+        assert!((recorder.length() - 111.2).abs() < 0.1);
+    }
+
+    #[test]
+    fn exports() {
+        let mut recorder = TrailRecorder::new();
+        recorder.record(pos_from_lon_lat(21., 52.), 0.);
+        recorder.record(pos_from_lon_lat(21.5, 52.), 60.);
+
+        assert!(recorder.to_gpx("Tom & Jerry").contains(
+            "<trk><name>Tom &amp; Jerry</name>\n<trkseg>\n\
+             <trkpt lat=\"52\" lon=\"21\"><time>1970-01-01T00:00:00Z</time></trkpt>\n\
+             <trkpt lat=\"52\" lon=\"21.5\"><time>1970-01-01T00:01:00Z</time></trkpt>\n\
+             </trkseg>\n</trk>"
+        ));
+        assert_eq!(
+            recorder.to_geojson(),
+            r#"{"type":"Feature","geometry":{"type":"MultiLineString","coordinates":[[[21,52],[21.5,52]]]},"properties":{"coordTimes":[["1970-01-01T00:00:00Z","1970-01-01T00:01:00Z"]]}}"#
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use egui::{Align2, Context, Response, Ui};
+
+use super::{LatestRequest, LoadingSpinner, ProviderFuture};
+use crate::{BoundingBox, Plugin, Position, Projector};
+
+/// Source of features for the visible part of the map, e.g. points of interest queried from a
+/// server. See [`ViewportData`].
+pub trait ViewportDataProvider: Send + Sync + 'static {
+    type Feature: Send + 'static;
+
+    /// Features within the bounds, in the map's coordinates, at given zoom.
+    fn fetch(&self, bounds: BoundingBox, zoom: f64) -> ProviderFuture<'_, Vec<Self::Feature>>;
+}
+
+/// Area of a query, along with its rounded zoom.
+type Area = (BoundingBox, i32);
+
+/// Features of a [`ViewportDataProvider`] for what the map shows. Once the map settles after
+/// moving, and stays still for the debounce time, the provider is asked for the visible area,
+/// enlarged by a margin, so that small moves do not need another fetch. If the view leaves the
+/// area before the features arrive, the query is cancelled. Like [`crate::HttpTiles`], it must
+/// persist between frames, and fetches in its own IO thread. Draw it with
+/// [`ViewportData::plugin`].
+pub struct ViewportData<P: ViewportDataProvider> {
+    provider: Arc<P>,
+    request: LatestRequest<Vec<P::Feature>>,
+    features: Vec<P::Feature>,
+    error: Option<String>,
+    debounce: f64,
+    margin: f64,
+
+    /// Area which the features are for.
+    shown: Option<Area>,
+    /// Area of the query in flight.
+    pending: Option<Area>,
+    still_since: Option<f64>,
+}
+
+impl<P: ViewportDataProvider> ViewportData<P> {
+    pub fn new(provider: P, egui_ctx: Context) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            request: LatestRequest::new(egui_ctx),
+            features: Vec::new(),
+            error: None,
+            debounce: 0.3,
+            margin: 0.25,
+            shown: None,
+            pending: None,
+            still_since: None,
+        }
+    }
+
+    /// How long the map must stay still before fetching, in seconds. Default is 0.3.
+    pub fn debounce(mut self, seconds: f64) -> Self {
+        self.debounce = seconds;
+        self
+    }
+
+    /// How much the visible area is enlarged on each side, as a fraction of its size. Default
+    /// is 0.25.
+    pub fn margin(mut self, margin: f64) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Features of the latest completed fetch.
+    pub fn features(&self) -> &[P::Feature] {
+        &self.features
+    }
+
+    /// Whether a fetch is in progress.
+    pub fn is_loading(&self) -> bool {
+        self.request.is_loading()
+    }
+
+    /// Reason why the latest fetch failed, if it did.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Fetch the features again, e.g. after the provider's data changed.
+    pub fn refresh(&mut self) {
+        self.shown = None;
+        self.pending = None;
+        self.request.cancel();
+    }
+
+    /// [`Plugin`] which keeps the features up to date with the view, and draws each of them
+    /// with the function. While fetching, it shows a spinner in the corner of the map.
+    pub fn plugin<D>(&mut self, draw: D) -> ViewportLayer<'_, P, D>
+    where
+        D: FnMut(&Ui, &Projector, &P::Feature),
+    {
+        ViewportLayer {
+            data: self,
+            draw,
+            spinner: Some(Align2::RIGHT_TOP),
+        }
+    }
+
+    fn receive(&mut self) {
+        match self.request.poll() {
+            Some(Ok(features)) => {
+                self.features = features;
+                self.error = None;
+                self.shown = self.pending.take();
+            }
+            Some(Err(error)) => {
+                log::warn!("Could not fetch viewport data: {}", error);
+                self.error = Some(error);
+                self.pending = None;
+            }
+            None => {}
+        }
+    }
+
+    fn update(&mut self, ui: &Ui, projector: &Projector) {
+        self.receive();
+
+        let memory = projector.memory();
+        let visible = projector.visible_bounds();
+        let zoom = memory.zoom();
+        let covers = |area: Option<Area>| {
+            area.is_some_and(|(bounds, area_zoom)| {
+                area_zoom == zoom.round() as i32 && contains(bounds, visible)
+            })
+        };
+
+        // The view moved away before the features arrived.
+        if self.pending.is_some() && !covers(self.pending) {
+            self.request.cancel();
+            self.pending = None;
+        }
+
+        if covers(self.pending) || covers(self.shown) || !memory.is_settled() {
+            self.still_since = None;
+            return;
+        }
+
+        let now = ui.input(|i| i.time);
+        let still_since = *self.still_since.get_or_insert(now);
+        let remaining = still_since + self.debounce - now;
+        if remaining > 0. {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f64(remaining));
+            return;
+        }
+
+        let bounds = enlarged(visible, self.margin);
+        let provider = self.provider.clone();
+        self.request
+            .start(Box::pin(async move { provider.fetch(bounds, zoom).await }));
+        self.pending = Some((bounds, zoom.round() as i32));
+        self.still_since = None;
+    }
+}
+
+fn contains(outer: BoundingBox, inner: BoundingBox) -> bool {
+    outer.min().x <= inner.min().x
+        && outer.min().y <= inner.min().y
+        && inner.max().x <= outer.max().x
+        && inner.max().y <= outer.max().y
+}
+
+fn enlarged(bounds: BoundingBox, margin: f64) -> BoundingBox {
+    let (min, max) = (bounds.min(), bounds.max());
+    let (dx, dy) = ((max.x - min.x) * margin, (max.y - min.y) * margin);
+    BoundingBox::new(
+        Position {
+            x: min.x - dx,
+            y: min.y - dy,
+        },
+        Position {
+            x: max.x + dx,
+            y: max.y + dy,
+        },
+    )
+}
+
+/// [`Plugin`] drawing the features of [`ViewportData`], see [`ViewportData::plugin`].
+pub struct ViewportLayer<'a, P: ViewportDataProvider, D> {
+    data: &'a mut ViewportData<P>,
+    draw: D,
+    spinner: Option<Align2>,
+}
+
+impl<P: ViewportDataProvider, D> ViewportLayer<'_, P, D> {
+    /// Corner of the map where the spinner is shown while fetching, or `None` to not show it.
+    /// Default is the top right one.
+    pub fn spinner(mut self, anchor: Option<Align2>) -> Self {
+        self.spinner = anchor;
+        self
+    }
+}
+
+impl<P, D> Plugin for ViewportLayer<'_, P, D>
+where
+    P: ViewportDataProvider,
+    D: FnMut(&Ui, &Projector, &P::Feature),
+{
+    fn run(mut self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        self.data.update(ui, projector);
+
+        for feature in &self.data.features {
+            (self.draw)(ui, projector, feature);
+        }
+
+        if let Some(anchor) = self.spinner {
+            Box::new(LoadingSpinner::new(self.data.is_loading()).anchor(anchor))
+                .run(ui, response, projector);
+        }
+    }
+}
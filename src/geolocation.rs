@@ -0,0 +1,165 @@
+//! Browser's Geolocation API, available only in WASM.
+
+use std::{cell::RefCell, rc::Rc};
+
+use egui::Context;
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{PositionError, PositionOptions};
+
+use crate::{pos_from_lat_lon, Position};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum GeolocationError {
+    #[error("geolocation is not supported by the browser")]
+    NotSupported,
+
+    #[error("permission to use geolocation was denied")]
+    PermissionDenied,
+
+    #[error("position is unavailable: {0}")]
+    PositionUnavailable(String),
+
+    #[error("timed out while waiting for the position")]
+    Timeout,
+}
+
+impl From<PositionError> for GeolocationError {
+    fn from(error: PositionError) -> Self {
+        match error.code() {
+            PositionError::PERMISSION_DENIED => Self::PermissionDenied,
+            PositionError::TIMEOUT => Self::Timeout,
+            _ => Self::PositionUnavailable(error.message()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    position: Option<Position>,
+    accuracy: Option<f64>,
+    error: Option<GeolocationError>,
+}
+
+type Callbacks = (
+    Closure<dyn FnMut(web_sys::Position)>,
+    Closure<dyn FnMut(PositionError)>,
+);
+
+/// Position reported by the browser, meant to be fed to the map as `my_position`. The browser
+/// asks the user for permission when the position is requested for the first time.
+///
+/// ```ignore
+/// let geolocation = Geolocation::watch(ctx.clone());
+/// // On each frame:
+/// let my_position = geolocation.position().unwrap_or(fallback);
+/// ```
+pub struct Geolocation {
+    state: Rc<RefCell<State>>,
+    watch_id: Option<i32>,
+
+    // Must be kept alive for as long as the browser might call them.
+    _callbacks: Option<Callbacks>,
+}
+
+impl Geolocation {
+    /// Request the position once.
+    pub fn once(egui_ctx: Context) -> Self {
+        Self::start(egui_ctx, false)
+    }
+
+    /// Keep receiving the position whenever it changes, until dropped.
+    pub fn watch(egui_ctx: Context) -> Self {
+        Self::start(egui_ctx, true)
+    }
+
+    fn start(egui_ctx: Context, watch: bool) -> Self {
+        let state = Rc::new(RefCell::new(State::default()));
+
+        let Some(geolocation) = web_sys::window().and_then(|w| w.navigator().geolocation().ok())
+        else {
+            state.borrow_mut().error = Some(GeolocationError::NotSupported);
+            return Self {
+                state,
+                watch_id: None,
+                _callbacks: None,
+            };
+        };
+
+        let on_position = {
+            let state = state.clone();
+            let egui_ctx = egui_ctx.clone();
+            Closure::<dyn FnMut(web_sys::Position)>::new(move |position: web_sys::Position| {
+                let coords = position.coords();
+                let mut state = state.borrow_mut();
+                state.position = Some(pos_from_lat_lon(coords.latitude(), coords.longitude()));
+                state.accuracy = Some(coords.accuracy());
+                state.error = None;
+                egui_ctx.request_repaint();
+            })
+        };
+
+        let on_error = {
+            let state = state.clone();
+            Closure::<dyn FnMut(PositionError)>::new(move |error: PositionError| {
+                log::warn!("Geolocation error: {}", error.message());
+                state.borrow_mut().error = Some(error.into());
+                egui_ctx.request_repaint();
+            })
+        };
+
+        let options = PositionOptions::new();
+        options.set_enable_high_accuracy(true);
+
+        let success = on_position.as_ref().unchecked_ref();
+        let error = on_error.as_ref().unchecked_ref();
+
+        let watch_id = if watch {
+            geolocation
+                .watch_position_with_error_callback_and_options(success, Some(error), &options)
+                .map_err(|err| log::error!("Could not watch the position: {:?}", err))
+                .ok()
+        } else {
+            if let Err(err) = geolocation.get_current_position_with_error_callback_and_options(
+                success,
+                Some(error),
+                &options,
+            ) {
+                log::error!("Could not get the position: {:?}", err);
+            }
+            None
+        };
+
+        Self {
+            state,
+            watch_id,
+            _callbacks: Some((on_position, on_error)),
+        }
+    }
+
+    /// Most recent position, if any was received yet.
+    pub fn position(&self) -> Option<Position> {
+        self.state.borrow().position
+    }
+
+    /// Accuracy of the most recent position, in meters.
+    pub fn accuracy(&self) -> Option<f64> {
+        self.state.borrow().accuracy
+    }
+
+    /// Error reported by the browser after the most recent position, if any.
+    pub fn error(&self) -> Option<GeolocationError> {
+        self.state.borrow().error.clone()
+    }
+}
+
+impl Drop for Geolocation {
+    fn drop(&mut self) {
+        if let Some(watch_id) = self.watch_id {
+            if let Some(geolocation) =
+                web_sys::window().and_then(|w| w.navigator().geolocation().ok())
+            {
+                geolocation.clear_watch(watch_id);
+            }
+        }
+    }
+}
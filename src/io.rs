@@ -8,8 +8,11 @@ pub(crate) use web::*;
 
 #[cfg(target_arch = "wasm32")]
 mod web {
-    use crate::HttpOptions;
+    use crate::{FetchCache, FetchCredentials, FetchMode, FetchOptions, HttpOptions};
     use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{Request, RequestCache, RequestCredentials, RequestInit, RequestMode, Response};
 
     pub struct Runtime;
 
@@ -23,11 +26,93 @@ mod web {
         }
     }
 
+    /// Run the future alongside the one of the [`Runtime`] it is called from.
+    pub fn spawn<F>(f: F)
+    where
+        F: std::future::Future<Output = ()> + 'static,
+    {
+        wasm_bindgen_futures::spawn_local(f);
+    }
+
+    /// Run the function. There are no threads to run it on, and no blocking IO in the browser.
+    pub async fn blocking<F, T>(f: F) -> Option<T>
+    where
+        F: FnOnce() -> T,
+    {
+        Some(f())
+    }
+
     pub fn http_client(http_options: HttpOptions) -> ClientWithMiddleware {
         if http_options.cache.is_some() {
             log::warn!("HTTP cache directory set, but ignored because, in WASM, caching is handled by the browser.");
         }
-        ClientBuilder::new(reqwest::Client::new()).build()
+        http_options
+            .middleware
+            .into_iter()
+            .fold(
+                ClientBuilder::new(reqwest::Client::new()),
+                ClientBuilder::with_arc,
+            )
+            .build()
+    }
+
+    /// Download using the browser's `fetch`.
+    pub async fn fetch(url: &str, options: &FetchOptions) -> Result<Vec<u8>, String> {
+        let init = RequestInit::new();
+        init.set_mode(match options.mode {
+            FetchMode::Cors => RequestMode::Cors,
+            FetchMode::NoCors => RequestMode::NoCors,
+            FetchMode::SameOrigin => RequestMode::SameOrigin,
+        });
+        init.set_credentials(match options.credentials {
+            FetchCredentials::Omit => RequestCredentials::Omit,
+            FetchCredentials::SameOrigin => RequestCredentials::SameOrigin,
+            FetchCredentials::Include => RequestCredentials::Include,
+        });
+        init.set_cache(match options.cache {
+            FetchCache::Default => RequestCache::Default,
+            FetchCache::NoStore => RequestCache::NoStore,
+            FetchCache::Reload => RequestCache::Reload,
+            FetchCache::NoCache => RequestCache::NoCache,
+            FetchCache::ForceCache => RequestCache::ForceCache,
+            FetchCache::OnlyIfCached => RequestCache::OnlyIfCached,
+        });
+
+        let request = Request::new_with_str_and_init(url, &init).map_err(js_error)?;
+        let window = web_sys::window().ok_or("no window to fetch from")?;
+
+        let response: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+
+        if !response.ok() {
+            return Err(format!("HTTP status {} for '{}'", response.status(), url));
+        }
+
+        let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+            .await
+            .map_err(js_error)?;
+
+        Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+    }
+
+    /// Measures time using the browser's clock, as [`std::time::Instant`] is not available.
+    pub struct Stopwatch(f64);
+
+    impl Stopwatch {
+        pub fn start() -> Self {
+            Self(js_sys::Date::now())
+        }
+
+        pub fn elapsed(&self) -> std::time::Duration {
+            std::time::Duration::from_secs_f64((js_sys::Date::now() - self.0).max(0.) / 1000.)
+        }
+    }
+
+    fn js_error(error: JsValue) -> String {
+        error.as_string().unwrap_or_else(|| format!("{:?}", error))
     }
 }
 
@@ -67,6 +152,24 @@ mod native {
         }
     }
 
+    /// Run the future alongside the one of the [`Runtime`] it is called from.
+    pub fn spawn<F>(f: F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(f);
+    }
+
+    /// Run the blocking function, e.g. one doing filesystem IO, on Tokio's blocking pool, so that
+    /// it does not hold up the [`Runtime`]'s thread. `None` if the function panicked.
+    pub async fn blocking<F, T>(f: F) -> Option<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        tokio::task::spawn_blocking(f).await.ok()
+    }
+
     impl Drop for Runtime {
         fn drop(&mut self) {
             // Tokio thread might be dead, nothing to do in this case.
@@ -82,10 +185,26 @@ mod native {
         }
     }
 
+    pub struct Stopwatch(std::time::Instant);
+
+    impl Stopwatch {
+        pub fn start() -> Self {
+            Self(std::time::Instant::now())
+        }
+
+        pub fn elapsed(&self) -> std::time::Duration {
+            self.0.elapsed()
+        }
+    }
+
     pub fn http_client(http_options: HttpOptions) -> ClientWithMiddleware {
+        if http_options.fetch.is_some() {
+            log::warn!("Fetch options set, but ignored because they are WASM-only.");
+        }
+
         let builder = ClientBuilder::new(reqwest::Client::new());
 
-        if let Some(cache) = http_options.cache {
+        let builder = if let Some(cache) = http_options.cache {
             builder.with(Cache(HttpCache {
                 mode: CacheMode::Default,
                 manager: CACacheManager { path: cache },
@@ -93,7 +212,12 @@ mod native {
             }))
         } else {
             builder
-        }
-        .build()
+        };
+
+        http_options
+            .middleware
+            .into_iter()
+            .fold(builder, ClientBuilder::with_arc)
+            .build()
     }
 }
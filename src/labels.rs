@@ -0,0 +1,48 @@
+use std::{cell::RefCell, collections::HashSet};
+
+use egui::Pos2;
+
+/// Limit of how densely labels are drawn on the map, shared by all plugins. The screen is split
+/// into squares, and each of them can hold at most one label, so when zooming out, crowded
+/// labels get thinned. Labels claimed first win, so plugins should try them in the order of
+/// priority. See [`crate::MapMemory::set_label_budget`] and [`crate::Projector::place_label`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LabelBudget {
+    spacing: f32,
+}
+
+impl LabelBudget {
+    /// At most one label per `spacing` × `spacing` square of the screen, in points.
+    pub fn new(spacing: f32) -> Self {
+        Self {
+            spacing: spacing.max(1.),
+        }
+    }
+}
+
+/// Squares of the screen already taken by labels in the current frame.
+pub(crate) struct LabelSlots {
+    budget: Option<LabelBudget>,
+    taken: RefCell<HashSet<(i32, i32)>>,
+}
+
+impl LabelSlots {
+    pub fn new(budget: Option<LabelBudget>) -> Self {
+        Self {
+            budget,
+            taken: Default::default(),
+        }
+    }
+
+    pub fn claim(&self, anchor: Pos2) -> bool {
+        let Some(budget) = self.budget else {
+            return true;
+        };
+
+        let cell = (
+            (anchor.x / budget.spacing).floor() as i32,
+            (anchor.y / budget.spacing).floor() as i32,
+        );
+        self.taken.borrow_mut().insert(cell)
+    }
+}
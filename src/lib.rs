@@ -1,24 +1,109 @@
 #![doc = include_str!("../README.md")]
 
+mod animation;
+mod batch;
+mod bookmarks;
+mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+mod cache_archive;
+mod camera;
 mod center;
+#[cfg(all(feature = "cog", not(target_arch = "wasm32")))]
+mod cog;
+#[cfg(all(feature = "proj", not(target_arch = "wasm32")))]
+mod crs;
+mod debug;
 mod download;
+mod error;
+mod events;
+#[cfg(feature = "export")]
+mod export;
 pub mod extras;
+#[cfg(target_arch = "wasm32")]
+mod geolocation;
 mod io;
+mod labels;
+#[cfg(feature = "wmm")]
+mod magnetic;
 mod map_memory;
 mod maps;
+#[cfg(all(feature = "mbtiles", not(target_arch = "wasm32")))]
+mod mbtiles;
+mod placeholder;
+mod plate_carree;
+mod polar;
+mod position_format;
 mod projector;
+mod shared_tiles;
+#[cfg(any(feature = "test-support", feature = "export"))]
+mod snapshot;
 pub mod sources;
 mod tiles;
+mod time;
 mod units;
+mod utm;
+mod validation;
+#[cfg(feature = "mvt")]
+mod vector;
 mod zoom;
 
-pub use download::{HeaderValue, HttpOptions};
-pub use maps::{LocalMap, Map, Maps, Plugin};
+pub use animation::{
+    reduced_motion, set_motion_preference, AnimatedPosition, Easing, MotionPreference,
+};
+pub use batch::BatchOptions;
+pub use bookmarks::{Bookmarks, View};
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::DiskCache;
+pub use cache::{MemoryCache, TileCache};
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache_archive::{export_tiles, import_tiles};
+pub use camera::Camera;
+#[cfg(all(feature = "proj", not(target_arch = "wasm32")))]
+pub use crs::{CrsProjection, InvalidCrs};
+pub use debug::DebugTiles;
+pub use download::{
+    FetchCache, FetchCredentials, FetchMode, FetchOptions, HeaderValue, HttpOptions, Middleware,
+    PostProcess, TileInfo, TileOrigin, UploadBudget,
+};
+pub use error::Error;
+pub use events::MapEvent;
+#[cfg(feature = "export")]
+pub use export::WorldFile;
+#[cfg(target_arch = "wasm32")]
+pub use geolocation::{Geolocation, GeolocationError};
+pub use labels::LabelBudget;
+#[cfg(feature = "wmm")]
+pub use magnetic::{decimal_year, WmmError, WorldMagneticModel};
+pub use maps::{
+    CachedLayer, Gesture, GesturePhase, InteractionOptions, LocalMap, Map, MapBuilder, Maps,
+    Plugin, PluginLayer, ScrollPolicy, WorldBounds, ZoomRange, ZoomSensitivity,
+};
 
+#[cfg(all(feature = "cog", not(target_arch = "wasm32")))]
+pub use cog::CogTiles;
 pub use map_memory::MapMemory;
-pub use projector::Projector;
-pub use tiles::{HttpTiles, Texture, TextureWithUv, TileId, Tiles};
-pub use units::{pos_from_lat_lon, pos_from_lon_lat, Position};
+#[cfg(all(feature = "mbtiles", not(target_arch = "wasm32")))]
+pub use mbtiles::{MbTiles, MbTilesError};
+pub use placeholder::Placeholder;
+pub use plate_carree::PlateCarree;
+pub use polar::{PolarStereographic, Pole};
+pub use position_format::PositionFormat;
+pub use projector::{InvalidUtmZone, LocalTransform, Projection, Projector, UtmProjector, UtmZone};
+pub use shared_tiles::SharedTiles;
+#[cfg(any(feature = "test-support", feature = "export"))]
+pub use snapshot::Snapshot;
+pub use tiles::{HttpTiles, Texture, TextureWithUv, TileId, TileStats, Tiles};
+pub use time::TimeWindow;
+pub use units::{
+    parse_coordinates, pos_from_lat_lon, pos_from_lon_lat, try_pos_from_lat_lon,
+    try_pos_from_lon_lat, BoundingBox, InvalidCoordinates, Position,
+};
+pub use validation::{SourceReport, Validation, ValidationError};
+#[cfg(feature = "mvt")]
+pub use vector::{
+    parse_maplibre_style, Comparison, Filter, InvalidStyle, LabelStyle, LayerStyle, PropertyValue,
+    StyleSource, Styled, VectorStyle, VectorTileLayer, VectorTiles, Zoomed,
+};
 pub use zoom::InvalidZoom;
 
 const TILE_SIZE: u32 = 256;
@@ -0,0 +1,258 @@
+//! Magnetic declination computed with the [World Magnetic Model](https://www.ncei.noaa.gov/products/world-magnetic-model).
+
+use crate::Position;
+
+/// WGS 84 semi-major axis, in kilometers.
+const WGS84_A: f64 = 6378.137;
+
+/// WGS 84 flattening.
+const WGS84_F: f64 = 1. / 298.257_223_563;
+
+/// Geomagnetic reference radius, in kilometers.
+const REFERENCE_RADIUS: f64 = 6371.2;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum WmmError {
+    #[error("missing the header with the model's epoch")]
+    MissingEpoch,
+
+    #[error("invalid coefficient in line {0}")]
+    InvalidLine(usize),
+
+    #[error("model has no coefficients")]
+    Empty,
+}
+
+/// Spherical harmonic model of the Earth's magnetic field, used to convert between true and
+/// magnetic bearings.
+///
+/// Coefficients are not bundled, as they are replaced every five years. Load them from the
+/// `WMM.COF` file published by NOAA, e.g. with `include_str!`.
+#[derive(Clone, Debug)]
+pub struct WorldMagneticModel {
+    epoch: f64,
+    degree: usize,
+
+    /// Main field and secular variation coefficients, indexed by `[n][m]`.
+    g: Vec<Vec<(f64, f64)>>,
+    h: Vec<Vec<(f64, f64)>>,
+}
+
+impl WorldMagneticModel {
+    /// Parse coefficients in the format of NOAA's `WMM.COF` file.
+    pub fn from_cof(cof: &str) -> Result<Self, WmmError> {
+        let mut lines = cof.lines().enumerate();
+        let epoch = lines
+            .next()
+            .and_then(|(_, header)| header.split_whitespace().next()?.parse().ok())
+            .ok_or(WmmError::MissingEpoch)?;
+
+        let mut coefficients = Vec::new();
+        for (index, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("9999") {
+                continue;
+            }
+
+            let numbers: Vec<f64> = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| WmmError::InvalidLine(index + 1))?;
+
+            match numbers[..] {
+                [n, m, g, h, g_dot, h_dot] if n >= 1. && m >= 0. && m <= n => {
+                    coefficients.push((n as usize, m as usize, (g, g_dot), (h, h_dot)))
+                }
+                _ => return Err(WmmError::InvalidLine(index + 1)),
+            }
+        }
+
+        let degree = coefficients
+            .iter()
+            .map(|(n, ..)| *n)
+            .max()
+            .ok_or(WmmError::Empty)?;
+
+        let mut g = vec![vec![(0., 0.); degree + 1]; degree + 1];
+        let mut h = g.clone();
+        for (n, m, g_nm, h_nm) in coefficients {
+            g[n][m] = g_nm;
+            h[n][m] = h_nm;
+        }
+
+        Ok(Self {
+            epoch,
+            degree,
+            g,
+            h,
+        })
+    }
+
+    /// Epoch of the model, as a decimal year. The model is valid for five years from it.
+    pub fn epoch(&self) -> f64 {
+        self.epoch
+    }
+
+    /// Magnetic declination in degrees at the sea level, positive when the magnetic north is
+    /// east of the true one. `date` is a decimal year, see [`decimal_year`].
+    pub fn declination_at(&self, position: Position, date: f64) -> f64 {
+        let (north, east) = self.horizontal_field(position, 0., date);
+        east.atan2(north).to_degrees()
+    }
+
+    /// North and east components of the field, in nanoteslas, at given altitude in kilometers.
+    fn horizontal_field(&self, position: Position, altitude: f64, date: f64) -> (f64, f64) {
+        let dt = date - self.epoch;
+        let latitude = position.y.to_radians();
+        let longitude = position.x.to_radians();
+
+        // Geodetic to geocentric coordinates.
+        let e2 = WGS84_F * (2. - WGS84_F);
+        let rc = WGS84_A / (1. - e2 * latitude.sin().powi(2)).sqrt();
+        let p = (rc + altitude) * latitude.cos();
+        let z = (rc * (1. - e2) + altitude) * latitude.sin();
+        let r = p.hypot(z);
+        let geocentric = (z / r).asin();
+
+        let (s, c) = geocentric.sin_cos();
+        let (p_nm, dp_nm) = schmidt_legendre(self.degree, s, c);
+
+        let mut x = 0.;
+        let mut y = 0.;
+        let mut z = 0.;
+        for n in 1..=self.degree {
+            let ratio = (REFERENCE_RADIUS / r).powi(n as i32 + 2);
+            for m in 0..=n {
+                let (g, g_dot) = self.g[n][m];
+                let (h, h_dot) = self.h[n][m];
+                let (g, h) = (g + dt * g_dot, h + dt * h_dot);
+                let (sin_ml, cos_ml) = (m as f64 * longitude).sin_cos();
+
+                let gh = g * cos_ml + h * sin_ml;
+                x -= ratio * gh * dp_nm[n][m];
+                y += ratio * m as f64 * (g * sin_ml - h * cos_ml) * p_nm[n][m];
+                z -= ratio * (n + 1) as f64 * gh * p_nm[n][m];
+            }
+        }
+
+        // Declination is undefined at the geographic poles anyway.
+        y /= c.max(1e-10);
+
+        // Rotate back from geocentric to geodetic frame.
+        let psi = geocentric - latitude;
+        (x * psi.cos() - z * psi.sin(), y)
+    }
+}
+
+/// Schmidt semi-normalized associated Legendre functions of `sin(φ)` and their derivatives with
+/// respect to `φ`.
+fn schmidt_legendre(degree: usize, s: f64, c: f64) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let mut p = vec![vec![0.; degree + 1]; degree + 1];
+    let mut dp = p.clone();
+    p[0][0] = 1.;
+
+    for n in 1..=degree {
+        for m in 0..=n {
+            if n == m {
+                let k = if n == 1 {
+                    1.
+                } else {
+                    ((2 * n - 1) as f64 / (2 * n) as f64).sqrt()
+                };
+                p[n][n] = k * c * p[n - 1][n - 1];
+                dp[n][n] = k * (c * dp[n - 1][n - 1] - s * p[n - 1][n - 1]);
+            } else {
+                let a = (2 * n - 1) as f64;
+                let b = (((n - 1) * (n - 1)) as f64 - (m * m) as f64).max(0.).sqrt();
+                let d = ((n * n - m * m) as f64).sqrt();
+                let (p2, dp2) = if n >= 2 {
+                    (p[n - 2][m], dp[n - 2][m])
+                } else {
+                    (0., 0.)
+                };
+                p[n][m] = (a * s * p[n - 1][m] - b * p2) / d;
+                dp[n][m] = (a * (s * dp[n - 1][m] + c * p[n - 1][m]) - b * dp2) / d;
+            }
+        }
+    }
+
+    (p, dp)
+}
+
+/// Date as a decimal year, as expected by [`WorldMagneticModel::declination_at`]. `month` and
+/// `day` start from 1.
+pub fn decimal_year(year: i32, month: u32, day: u32) -> f64 {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_before_month = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let month = month.clamp(1, 12) as usize;
+    let day_of_year = days_before_month[month - 1] + day.max(1) - 1 + (leap && month > 2) as u32;
+    let days_in_year = if leap { 366. } else { 365. };
+    year as f64 + day_of_year as f64 / days_in_year
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lat_lon;
+
+    fn model() -> WorldMagneticModel {
+        WorldMagneticModel::from_cof(include_str!("../assets/WMM2020.COF")).unwrap()
+    }
+
+    /// Test values published with WMM2020: date, altitude in kilometers, latitude, longitude,
+    /// north and east components in nanoteslas, and declination in degrees.
+    const TEST_VALUES: [(f64, f64, f64, f64, f64, f64, f64); 8] = [
+        (2020., 0., 80., 0., 6570.4, -146.3, -1.28),
+        (2020., 0., 0., 120., 39624.3, 109.9, 0.16),
+        (2020., 0., -80., 240., 5940.6, 15772.1, 69.36),
+        (2020., 100., 80., 0., 6261.8, -185.5, -1.70),
+        (2022.5, 0., 80., 0., 6529.9, 1.1, 0.01),
+        (2022.5, 0., 0., 120., 39684.7, -42.2, -0.06),
+        (2022.5, 0., -80., 240., 6016.5, 15776.7, 69.13),
+        (2022.5, 100., 80., 0., 6224.0, -44.5, -0.41),
+    ];
+
+    #[test]
+    fn noaa_test_values() {
+        let model = model();
+        assert_eq!(model.epoch(), 2020.);
+
+        for (date, altitude, lat, lon, north, east, declination) in TEST_VALUES {
+            let (x, y) = model.horizontal_field(pos_from_lat_lon(lat, lon), altitude, date);
+            assert!((x - north).abs() < 0.06, "{x} at {lat}, {lon}, {date}");
+            assert!((y - east).abs() < 0.06, "{y} at {lat}, {lon}, {date}");
+            assert!(
+                (y.atan2(x).to_degrees() - declination).abs() < 0.006,
+                "declination at {lat}, {lon}, {date}"
+            );
+        }
+
+        let declination = model.declination_at(pos_from_lat_lon(-80., 240.), 2020.);
+        assert!((declination - 69.36).abs() < 0.006);
+    }
+
+    #[test]
+    fn invalid_models() {
+        assert_eq!(
+            WorldMagneticModel::from_cof("").unwrap_err(),
+            WmmError::MissingEpoch
+        );
+        assert_eq!(
+            WorldMagneticModel::from_cof("2020.0 WMM-2020\n").unwrap_err(),
+            WmmError::Empty
+        );
+        assert_eq!(
+            WorldMagneticModel::from_cof("2020.0 WMM-2020\n  1  0  -29404.5  0.0  6.7\n")
+                .unwrap_err(),
+            WmmError::InvalidLine(2)
+        );
+    }
+
+    #[test]
+    fn decimal_years() {
+        assert_eq!(decimal_year(2020, 1, 1), 2020.);
+        assert_eq!(decimal_year(2022, 7, 2), 2022. + 182. / 365.);
+        assert_eq!(decimal_year(2024, 3, 1), 2024. + 60. / 366.);
+    }
+}
@@ -1,7 +1,18 @@
+use std::f64::consts::FRAC_PI_2;
+
+use egui::{emath::Rot2, vec2, Mesh, Pos2, Rect, Vec2};
+
 use crate::{
+    bookmarks::Flight,
+    camera,
     center::Center,
-    projector::ProjectorType,
-    units::{AdjustedPosition, Position},
+    labels::LabelBudget,
+    maps::Gesture,
+    position_format::PositionFormat,
+    projector::{LocalTransform, Projection, ProjectorType},
+    sources::Attribution,
+    time::TimeWindow,
+    units::{try_pos_from_lat_lon, AdjustedPosition, InvalidCoordinates, Position},
     zoom::{InvalidZoom, Zoom},
 };
 
@@ -11,13 +22,60 @@ pub struct MapMemory {
     pub(crate) projection_type: ProjectorType,
     pub(crate) center_mode: Center,
     pub(crate) zoom: Zoom,
+
+    /// [`crate::Tiles::source_id`] of the tiles shown in the previous frame, used to detect
+    /// source changes.
+    pub(crate) source: Option<String>,
+
+    /// See [`MapMemory::attributions`].
+    pub(crate) attributions: Vec<Attribution>,
+
+    time_window: Option<TimeWindow>,
+
+    label_budget: Option<LabelBudget>,
+
+    /// Set by the map widget on each frame.
+    pub(crate) position_format: PositionFormat,
+
+    pub(crate) scroll_consumed: bool,
+
+    local_heading: Option<f64>,
+
+    /// In degrees, see [`MapMemory::set_bearing`].
+    rotation: f64,
+
+    /// In degrees, see [`MapMemory::set_tilt`].
+    tilt: f64,
+
+    /// See [`MapMemory::set_warp_subdivisions`].
+    warp_subdivisions: Option<usize>,
+
+    /// Height of the map widget in the most recent frame, which sets the perspective of the tilt.
+    pub(crate) viewport_height: f32,
+
+    pub(crate) gesture: Option<Gesture>,
+
+    pub(crate) long_press: Option<Position>,
+
+    /// Whether the map was moving by itself in the most recent frame.
+    in_motion: bool,
+
+    /// See [`crate::View::apply`].
+    pub(crate) flight: Option<Flight>,
 }
 
 impl MapMemory {
+    /// Attributions of the tiles which may have been visible in the most recent frame,
+    /// including those of regional sources, such as of a [`crate::sources::MosaicSource`].
+    /// Attribution widgets should show all of them. See [`crate::Tiles::attributions`].
+    pub fn attributions(&self) -> &[Attribution] {
+        &self.attributions
+    }
+
     pub fn is_global(&self) -> bool {
-        match self.projection_type {
-            ProjectorType::Global => true,
-            ProjectorType::Local => false,
+        match &self.projection_type {
+            ProjectorType::Global | ProjectorType::Custom(_) => true,
+            ProjectorType::Local(_) => false,
         }
     }
 
@@ -27,62 +85,307 @@ impl MapMemory {
     }
 
     pub fn zoom_in(&mut self) -> Result<(), InvalidZoom> {
-        let zoom = self.zoom();
-        self.center_mode = match self.projection_type {
-            ProjectorType::Global => self.center_mode.clone().global_zero_offset(zoom),
-            ProjectorType::Local => self.center_mode.clone().local_zero_offset(zoom),
-        };
+        self.center_mode = self
+            .center_mode
+            .clone()
+            .zero_offset(&self.projection_type, self.zoom());
         self.zoom.zoom_in()
     }
 
     /// Try to zoom out, returning `Err(InvalidZoom)` if already at minimum.
     pub fn zoom_out(&mut self) -> Result<(), InvalidZoom> {
-        let zoom = self.zoom();
-        self.center_mode = match self.projection_type {
-            ProjectorType::Global => self.center_mode.clone().global_zero_offset(zoom),
-            ProjectorType::Local => self.center_mode.clone().local_zero_offset(zoom),
-        };
+        self.center_mode = self
+            .center_mode
+            .clone()
+            .zero_offset(&self.projection_type, self.zoom());
         self.zoom.zoom_out()
     }
 
     /// Set exact zoom level
     pub fn set_zoom(&mut self, new_zoom: f64) -> Result<(), InvalidZoom> {
-        let zoom = self.zoom();
-        self.center_mode = match self.projection_type {
-            ProjectorType::Global => self.center_mode.clone().global_zero_offset(zoom),
-            ProjectorType::Local => self.center_mode.clone().local_zero_offset(zoom),
-        };
+        self.flight = None;
+        self.center_mode = self
+            .center_mode
+            .clone()
+            .zero_offset(&self.projection_type, self.zoom());
         self.zoom = Zoom::try_from(new_zoom)?;
         Ok(())
     }
 
     /// Center exactly at the given position.
     pub fn center_at(&mut self, pos: Position) {
+        self.flight = None;
         self.center_mode = Center::Exact {
             pos: AdjustedPosition::new(pos, Default::default()),
         };
     }
 
+    /// Like [`MapMemory::center_at`], but refuses positions which the map cannot show: non-finite
+    /// ones and, for geographical maps, those outside of valid latitude and longitude.
+    pub fn try_center_at(&mut self, pos: Position) -> Result<(), InvalidCoordinates> {
+        let valid = match &self.projection_type {
+            ProjectorType::Global | ProjectorType::Custom(_) => {
+                try_pos_from_lat_lon(pos.y, pos.x).is_ok()
+            }
+            ProjectorType::Local(_) => pos.x.is_finite() && pos.y.is_finite(),
+        };
+
+        if valid {
+            self.center_at(pos);
+            Ok(())
+        } else {
+            Err(InvalidCoordinates)
+        }
+    }
+
     /// Follow `my_position`.
     pub fn follow_my_position(&mut self) {
+        self.flight = None;
         self.center_mode = Center::MyPosition;
     }
 
     /// Get the true position of the map center if following my position else None
     pub fn detached(&self) -> Option<Position> {
         let adj_pos = self.center_mode.get_adjusted_position()?;
+        Some(camera::unadjusted_position(
+            &adj_pos,
+            &self.projection_type,
+            self.zoom(),
+        ))
+    }
+
+    /// How positions are shown to the user, see [`crate::Map::position_format`].
+    pub fn position_format(&self) -> PositionFormat {
+        self.position_format
+    }
+
+    /// Limit how densely plugins draw labels, or draw all of them if `None`.
+    pub fn set_label_budget(&mut self, label_budget: Option<LabelBudget>) {
+        self.label_budget = label_budget;
+    }
+
+    pub fn label_budget(&self) -> Option<LabelBudget> {
+        self.label_budget
+    }
+
+    /// Limit temporal layers to the given period of time, or show everything if `None`.
+    pub fn set_time_window(&mut self, time_window: Option<TimeWindow>) {
+        self.time_window = time_window;
+    }
+
+    /// Period of time which temporal layers should show data from. `None` means no limit.
+    pub fn time_window(&self) -> Option<TimeWindow> {
+        self.time_window
+    }
+
+    /// Whether the map used the mouse wheel, touchpad scroll or pinch in the most recent frame,
+    /// taking it away from the surrounding [`egui::ScrollArea`], if any. See
+    /// [`crate::ScrollPolicy`].
+    pub fn scroll_consumed(&self) -> bool {
+        self.scroll_consumed
+    }
+
+    /// Pointer being dragged over the map in the most recent frame, if any.
+    pub fn gesture(&self) -> Option<&Gesture> {
+        self.gesture.as_ref()
+    }
+
+    /// Whether the map stays where it is, that is it is not being dragged, moving by inertia,
+    /// flying to a [`crate::View`] or springing back from the world's edge. Useful to wait with what should happen after a move,
+    /// e.g. opening a popup at the destination. See also [`crate::MapEvent::Settled`]. Moves
+    /// animated by the application, e.g. with [`crate::AnimatedPosition`], are not tracked.
+    pub fn is_settled(&self) -> bool {
+        !self.in_motion
+    }
+
+    /// Record whether the map moved by itself in this frame, besides being dragged or moved by
+    /// inertia. Returns whether it has just settled.
+    pub(crate) fn update_motion(&mut self, moving: bool) -> bool {
+        let was_in_motion = self.in_motion;
+        self.in_motion = moving || !self.center_mode.is_at_rest() || self.flight.is_some();
+        was_in_motion && !self.in_motion
+    }
 
-        match self.projection_type {
-            ProjectorType::Global => Some(adj_pos.global_unadjusted_position(self.zoom())),
-            ProjectorType::Local => Some(adj_pos.local_unadjusted_position(self.zoom())),
+    /// Where the map was long pressed, if that happened in the most recent frame. Typically used
+    /// on touch screens, e.g. to open a context menu or drop a pin, where desktop apps would use
+    /// a secondary click. See [`crate::InteractionOptions::long_press`].
+    pub fn long_press(&self) -> Option<Position> {
+        self.long_press
+    }
+
+    /// Rotate local maps so that the direction at `heading` (in radians, counter-clockwise from
+    /// the x axis) points up, e.g. a robot's yaw for a "forward is up" view. Positions, including
+    /// those given to plugins, stay in the unrotated frame. `None`, the default, keeps the y axis
+    /// pointing up, unless rotated with [`MapMemory::set_bearing`]. Global maps are not affected.
+    pub fn set_local_heading(&mut self, heading: Option<f64>) {
+        self.local_heading = heading;
+    }
+
+    /// See [`MapMemory::set_local_heading`].
+    pub fn local_heading(&self) -> Option<f64> {
+        self.local_heading
+    }
+
+    /// Rotate the map so that the direction at `bearing` (in degrees clockwise from north, or
+    /// from the y axis for local maps) points up, e.g. a vehicle's heading for a "heading up"
+    /// navigation view. Positions, including those given to plugins, are not affected. For local
+    /// maps, [`MapMemory::set_local_heading`] takes precedence.
+    pub fn set_bearing(&mut self, bearing: f64) {
+        self.rotation = bearing;
+    }
+
+    /// Direction which points up on the screen, in degrees clockwise from north (or from the y
+    /// axis, for local maps).
+    pub fn bearing(&self) -> f64 {
+        let bearing = match (&self.projection_type, self.local_heading) {
+            (ProjectorType::Local(_), Some(heading)) => (FRAC_PI_2 - heading).to_degrees(),
+            _ => self.rotation,
+        };
+        bearing.rem_euclid(360.)
+    }
+
+    /// Rotation of the map's content on the screen.
+    pub(crate) fn screen_rotation(&self) -> Rot2 {
+        match self.bearing() {
+            0. => Rot2::IDENTITY,
+            bearing => Rot2::from_angle(-bearing.to_radians() as f32),
+        }
+    }
+
+    /// Tilt the map away from the viewer by given angle in degrees, for a "2.5D" perspective
+    /// view, e.g. in flight dashboards. The top of the map gets farther and smaller, while the
+    /// center stays in place. It is clamped to 0..=60, 0 being the flat, top-down view.
+    pub fn set_tilt(&mut self, degrees: f64) {
+        self.tilt = if degrees.is_finite() {
+            degrees.clamp(0., MAX_TILT)
+        } else {
+            0.
+        };
+    }
+
+    /// See [`MapMemory::set_tilt`].
+    pub fn tilt(&self) -> f64 {
+        self.tilt
+    }
+
+    /// Split each tile into a grid of `n` by `n` quads when the map is tilted. Denser grids keep
+    /// the imagery straighter under perspective, while sparser ones are cheaper to draw, e.g. on
+    /// weak hardware. It is clamped to 1..=32, default is 8. Flat maps, even when rotated, are not
+    /// affected, as they are drawn exactly with a single quad per tile.
+    pub fn set_warp_subdivisions(&mut self, n: usize) {
+        self.warp_subdivisions = Some(n.clamp(1, 32));
+    }
+
+    /// See [`MapMemory::set_warp_subdivisions`].
+    pub fn warp_subdivisions(&self) -> usize {
+        self.warp_subdivisions.unwrap_or(8)
+    }
+
+    /// Rotation and tilt of the map's content on the screen.
+    pub(crate) fn screen_transform(&self) -> ScreenTransform {
+        let tilt = (self.tilt as f32).to_radians();
+        ScreenTransform {
+            rotation: self.screen_rotation(),
+            sin: tilt.sin(),
+            cos: tilt.cos(),
+            focal_length: PERSPECTIVE * self.viewport_height,
+            subdivisions: self.warp_subdivisions(),
         }
     }
 
     pub fn scale_pixel_per_meter(&self, pos: Position) -> f32 {
-        let zoom = self.zoom();
-        match self.projection_type {
+        self.scale_pixel_per_meter_at(pos, self.zoom())
+    }
+
+    pub(crate) fn scale_pixel_per_meter_at(&self, pos: Position, zoom: f64) -> f32 {
+        match &self.projection_type {
             ProjectorType::Global => global_scale_pixel_per_meter(pos, zoom),
-            ProjectorType::Local => local_scale_pixel_per_meter(zoom),
+            ProjectorType::Local(transform) => local_scale_pixel_per_meter(zoom, transform),
+            ProjectorType::Custom(projection) => {
+                custom_scale_pixel_per_meter(pos, zoom, projection.as_ref())
+            }
+        }
+    }
+}
+
+/// Largest tilt, in degrees. Tiles of a single zoom level are drawn all the way to the top of the
+/// map, so steeper views would need too many of them.
+const MAX_TILT: f64 = 60.;
+
+/// Distance of the viewer from the map, relative to its height. Larger distances flatten the
+/// perspective, and this one keeps the horizon above the map at [`MAX_TILT`].
+const PERSPECTIVE: f32 = 2.;
+
+/// Rotation and perspective applied to the map's content, relative to its center on the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ScreenTransform {
+    pub(crate) rotation: Rot2,
+    sin: f32,
+    cos: f32,
+    focal_length: f32,
+
+    /// See [`MapMemory::set_warp_subdivisions`].
+    pub(crate) subdivisions: usize,
+}
+
+impl ScreenTransform {
+    pub(crate) fn is_tilted(&self) -> bool {
+        self.sin != 0. && self.focal_length > 0.
+    }
+
+    pub(crate) fn is_identity(&self) -> bool {
+        self.rotation == Rot2::IDENTITY && !self.is_tilted()
+    }
+
+    /// Screen offset from the map's center of the offset in the flat, north-up map.
+    pub(crate) fn apply(&self, offset: Vec2) -> Vec2 {
+        let offset = self.rotation * offset;
+        if !self.is_tilted() {
+            return offset;
+        }
+
+        // Points behind the viewer have no image, so they are pushed far away instead.
+        let f = self.focal_length;
+        let depth = (f - offset.y * self.sin).max(f * 1e-3);
+        vec2(offset.x, offset.y * self.cos) * f / depth
+    }
+
+    /// Inverse of [`ScreenTransform::apply`]. Points above the horizon resolve to ones far away
+    /// on the map.
+    pub(crate) fn invert(&self, offset: Vec2) -> Vec2 {
+        let flat = if self.is_tilted() {
+            let f = self.focal_length;
+            let denominator = (f * self.cos + offset.y * self.sin).max(f * 1e-3);
+            let y = offset.y * f / denominator;
+            vec2(offset.x * (f - y * self.sin) / f, y)
+        } else {
+            offset
+        };
+        self.rotation.inverse() * flat
+    }
+
+    /// Part of the flat map which ends up within the viewport.
+    pub(crate) fn visible_area(&self, viewport: Rect) -> Rect {
+        if self.is_identity() {
+            return viewport;
+        }
+
+        let center = viewport.center();
+        Rect::from_points(
+            &[
+                viewport.left_top(),
+                viewport.right_top(),
+                viewport.left_bottom(),
+                viewport.right_bottom(),
+            ]
+            .map(|corner| center + self.invert(corner - center)),
+        )
+    }
+
+    /// Move the mesh, drawn on the flat map, to the screen.
+    pub(crate) fn transform_mesh(&self, mesh: &mut Mesh, center: Pos2) {
+        for vertex in &mut mesh.vertices {
+            vertex.pos = center + self.apply(vertex.pos - center);
         }
     }
 }
@@ -97,6 +400,14 @@ pub(crate) fn global_scale_pixel_per_meter(pos: Position, zoom: f64) -> f32 {
     pixel_per_meter_equator as f32
 }
 
-pub(crate) fn local_scale_pixel_per_meter(zoom: f64) -> f32 {
-    (1. / crate::local_units_per_point(zoom)) as f32
+pub(crate) fn local_scale_pixel_per_meter(zoom: f64, transform: &LocalTransform) -> f32 {
+    (transform.scale() / crate::local_units_per_point(zoom)) as f32
+}
+
+pub(crate) fn custom_scale_pixel_per_meter(
+    pos: Position,
+    zoom: f64,
+    projection: &dyn Projection,
+) -> f32 {
+    (crate::total_pixels(zoom) / projection.ground_width(pos)) as f32
 }
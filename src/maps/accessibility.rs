@@ -0,0 +1,101 @@
+use egui::{EventFilter, Key, Response, Ui, Vec2, WidgetInfo, WidgetType};
+
+use crate::{camera, map_memory::MapMemory, Position};
+
+/// How far the arrow keys move the map, in points.
+const KEYBOARD_PAN_STEP: f32 = 64.;
+
+/// Let the map be focused by clicking or dragging it, in addition to tabbing into it, and keep
+/// the arrow keys from moving the focus away.
+pub(crate) fn handle_focus(ui: &Ui, response: &Response) {
+    if response.clicked() || response.drag_started() {
+        response.request_focus();
+    }
+
+    if response.has_focus() {
+        ui.memory_mut(|memory| {
+            memory.set_focus_lock_filter(
+                response.id,
+                EventFilter {
+                    horizontal_arrows: true,
+                    vertical_arrows: true,
+                    ..Default::default()
+                },
+            )
+        });
+    }
+}
+
+/// Pan with arrow keys, and zoom with <kbd>+</kbd> and <kbd>-</kbd>, while the map is focused.
+/// Returns `true` if the map was moved.
+pub(crate) fn handle_keyboard(
+    ui: &Ui,
+    response: &Response,
+    memory: &mut MapMemory,
+    my_position: Position,
+) -> bool {
+    if !response.has_focus() {
+        return false;
+    }
+
+    let (pan, zoom_in, zoom_out) = ui.input(|i| {
+        let mut pan = Vec2::ZERO;
+        if i.key_pressed(Key::ArrowLeft) {
+            pan.x += KEYBOARD_PAN_STEP;
+        }
+        if i.key_pressed(Key::ArrowRight) {
+            pan.x -= KEYBOARD_PAN_STEP;
+        }
+        if i.key_pressed(Key::ArrowUp) {
+            pan.y += KEYBOARD_PAN_STEP;
+        }
+        if i.key_pressed(Key::ArrowDown) {
+            pan.y -= KEYBOARD_PAN_STEP;
+        }
+        (
+            pan,
+            i.key_pressed(Key::Plus) || i.key_pressed(Key::Equals),
+            i.key_pressed(Key::Minus),
+        )
+    });
+
+    let mut changed = false;
+
+    if pan != Vec2::ZERO {
+        memory.camera(my_position).translate_pixels(pan);
+        changed = true;
+    }
+
+    // Failing to zoom beyond the limits is fine.
+    if zoom_in {
+        changed |= memory.zoom_in().is_ok();
+    }
+
+    if zoom_out {
+        changed |= memory.zoom_out().is_ok();
+    }
+
+    changed
+}
+
+/// Describe the map for screen readers.
+pub(crate) fn describe(
+    response: &Response,
+    memory: &MapMemory,
+    my_position: Position,
+    description: Option<&str>,
+) {
+    let pos = camera::center(memory, my_position);
+    let mut label = format!(
+        "Map centered at {}, zoom {:.1}",
+        memory.position_format().format(pos),
+        memory.zoom()
+    );
+
+    if let Some(description) = description {
+        label.push_str(", ");
+        label.push_str(description);
+    }
+
+    response.widget_info(|| WidgetInfo::labeled(WidgetType::Other, true, &label));
+}
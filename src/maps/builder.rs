@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use crate::{MapMemory, Plugin, Position, PositionFormat, Projection, Tiles};
+
+use super::{InteractionOptions, Map};
+
+/// Builder of the [`Map`], for when options are computed by the application (e.g. loaded from
+/// settings) rather than chained. Everything not set keeps the same default as with [`Map::new`].
+///
+/// ```ignore
+/// let map = MapBuilder::new(&mut memory, my_position)
+///     .tiles(&mut tiles)
+///     .interaction(InteractionOptions {
+///         zoom_with_ctrl: false,
+///         ..Default::default()
+///     })
+///     .build();
+/// ```
+pub struct MapBuilder<'a, 'b, 'c> {
+    memory: &'a mut MapMemory,
+    my_position: Position,
+    tiles: Option<&'c mut dyn Tiles>,
+    plugins: Vec<Box<dyn Plugin + 'b>>,
+    interaction: InteractionOptions,
+    position_format: PositionFormat,
+    hidpi_tiles: bool,
+    description: Option<String>,
+    projection: Option<Arc<dyn Projection>>,
+}
+
+impl<'a, 'b, 'c> MapBuilder<'a, 'b, 'c> {
+    pub fn new(memory: &'a mut MapMemory, my_position: Position) -> Self {
+        Self {
+            memory,
+            my_position,
+            tiles: None,
+            plugins: Vec::new(),
+            interaction: InteractionOptions::default(),
+            position_format: PositionFormat::default(),
+            hidpi_tiles: true,
+            description: None,
+            projection: None,
+        }
+    }
+
+    /// Tiles to draw. Without them, only plugins are drawn.
+    pub fn tiles(mut self, tiles: &'c mut dyn Tiles) -> Self {
+        self.tiles = Some(tiles);
+        self
+    }
+
+    pub fn plugin(mut self, plugin: impl Plugin + 'b) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// See [`Map::interaction`].
+    pub fn interaction(mut self, options: InteractionOptions) -> Self {
+        self.interaction = options;
+        self
+    }
+
+    /// See [`Map::position_format`].
+    pub fn position_format(mut self, format: PositionFormat) -> Self {
+        self.position_format = format;
+        self
+    }
+
+    /// See [`Map::hidpi_tiles`].
+    pub fn hidpi_tiles(mut self, enabled: bool) -> Self {
+        self.hidpi_tiles = enabled;
+        self
+    }
+
+    /// See [`Map::description`].
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// See [`Map::projection`].
+    pub fn projection(mut self, projection: impl Projection + 'static) -> Self {
+        self.projection = Some(Arc::new(projection));
+        self
+    }
+
+    pub fn build(self) -> Map<'a, 'b, 'c> {
+        let mut map = Map::new(self.tiles, self.memory, self.my_position)
+            .interaction(self.interaction)
+            .position_format(self.position_format)
+            .hidpi_tiles(self.hidpi_tiles);
+
+        if let Some(description) = self.description {
+            map = map.description(description);
+        }
+
+        if let Some(projection) = self.projection {
+            map = map.with_shared_projection(projection);
+        }
+
+        self.plugins
+            .into_iter()
+            .fold(map, |map, plugin| map.with_boxed_plugin(plugin))
+    }
+}
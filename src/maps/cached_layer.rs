@@ -0,0 +1,172 @@
+use std::{hash::Hash, ops::RangeInclusive, sync::Arc};
+
+use egui::{layers::ShapeIdx, Context, Id, Pos2, Response, Shape, Ui, UiBuilder, Vec2};
+
+use crate::{Plugin, PluginLayer, Position, Projector};
+
+/// Shapes drawn by the wrapped plugin, with what they depend on.
+#[derive(Clone)]
+struct Cache {
+    zoom: f64,
+    bearing: f64,
+    size: Vec2,
+    pixels_per_point: f32,
+    anchor: Position,
+    anchor_screen: Pos2,
+    shapes: Arc<Vec<Shape>>,
+}
+
+/// Wraps an expensive, static [`Plugin`], e.g. thousands of administrative boundaries, so that
+/// it is run only when the map gets zoomed, rotated or resized, or moves to a screen of
+/// a different scale factor. While the map is just panned,
+/// shapes it drew last time are moved along with the map instead.
+///
+/// It is not a tile-aligned texture cache: the shapes are kept as they were drawn, for the whole
+/// view at once, and nothing is rasterized. Under perspective, panning does not just move the
+/// shapes, so the plugin is run on every frame while the map is tilted.
+///
+/// The wrapped plugin should only paint, as its widgets and interactions would not be recreated
+/// from the cache. Call [`CachedLayer::invalidate`] when its content changes.
+pub struct CachedLayer<P> {
+    plugin: P,
+    id: Id,
+}
+
+impl<P: Plugin> CachedLayer<P> {
+    /// Wrap the plugin, keeping its shapes under given id, which must be unique among cached
+    /// layers.
+    pub fn new(id_salt: impl Hash, plugin: P) -> Self {
+        Self {
+            plugin,
+            id: Id::new(("walkers_cached_layer", id_salt)),
+        }
+    }
+
+    /// Forget the shapes of the layer with given id, so that the plugin is run in the next frame.
+    pub fn invalidate(ctx: &Context, id_salt: impl Hash) {
+        let id = Id::new(("walkers_cached_layer", id_salt));
+        ctx.data_mut(|data| data.remove::<Cache>(id));
+    }
+}
+
+impl<P: Plugin> Plugin for CachedLayer<P> {
+    fn run(self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        if projector.memory().screen_transform().is_tilted() {
+            ui.data_mut(|data| data.remove::<Cache>(self.id));
+            Box::new(self.plugin).run(ui, response, projector);
+            return;
+        }
+
+        let zoom = projector.memory().zoom();
+        let bearing = projector.bearing();
+        let size = projector.clip_rect().size();
+        let pixels_per_point = ui.ctx().pixels_per_point();
+
+        let cache = ui.data(|data| data.get_temp::<Cache>(self.id));
+        if let Some(cache) = cache.filter(|cache| {
+            cache.zoom == zoom
+                && cache.bearing == bearing
+                && cache.size == size
+                && cache.pixels_per_point == pixels_per_point
+        }) {
+            let offset = projector.project(cache.anchor) - cache.anchor_screen;
+            ui.painter().extend(cache.shapes.iter().map(|shape| {
+                let mut shape = shape.clone();
+                shape.translate(offset);
+                shape
+            }));
+            return;
+        }
+
+        let layer_id = ui.layer_id();
+        let start = ui.ctx().graphics_mut(|g| g.entry(layer_id).next_idx());
+
+        let mut child_ui = ui.new_child(UiBuilder::new().max_rect(ui.max_rect()));
+        Box::new(self.plugin).run(&mut child_ui, response, projector);
+
+        let shapes = ui.ctx().graphics_mut(|g| {
+            let ShapeIdx(start) = start;
+            g.entry(layer_id)
+                .all_entries()
+                .skip(start)
+                .map(|clipped| clipped.shape.clone())
+                .collect()
+        });
+
+        let anchor_screen = projector.clip_rect().center();
+        let cache = Cache {
+            zoom,
+            bearing,
+            size,
+            pixels_per_point,
+            anchor: projector.unproject(anchor_screen),
+            anchor_screen,
+            shapes: Arc::new(shapes),
+        };
+        ui.data_mut(|data| data.insert_temp(self.id, cache));
+    }
+
+    fn layer(&self) -> PluginLayer {
+        self.plugin.layer()
+    }
+
+    fn zoom_range(&self) -> RangeInclusive<f64> {
+        self.plugin.zoom_range()
+    }
+}
+
+#[cfg(all(test, feature = "test-support"))]
+mod tests {
+    use std::cell::Cell;
+
+    use egui::Vec2;
+
+    use super::*;
+    use crate::{pos_from_lon_lat, Map, MapMemory, Snapshot};
+
+    /// Counts its runs.
+    struct Counter<'a>(&'a Cell<usize>);
+
+    impl Plugin for Counter<'_> {
+        fn run(self: Box<Self>, _ui: &mut Ui, _response: &Response, _projector: &Projector) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    fn render(snapshot: &mut Snapshot, memory: &mut MapMemory, runs: &Cell<usize>) {
+        snapshot.render(|ui| {
+            ui.add(
+                Map::new(None, memory, pos_from_lon_lat(0., 0.))
+                    .with_plugin(CachedLayer::new("counter", Counter(runs))),
+            );
+        });
+    }
+
+    #[test]
+    fn runs_once_while_the_map_stays() {
+        let mut snapshot = Snapshot::new(Vec2::splat(256.));
+        let mut memory = MapMemory::default();
+        let runs = Cell::new(0);
+
+        render(&mut snapshot, &mut memory, &runs);
+        render(&mut snapshot, &mut memory, &runs);
+        assert_eq!(runs.get(), 1);
+
+        memory.zoom_in().unwrap();
+        render(&mut snapshot, &mut memory, &runs);
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn runs_on_every_frame_while_tilted() {
+        let mut snapshot = Snapshot::new(Vec2::splat(256.));
+        let mut memory = MapMemory::default();
+        memory.set_tilt(30.);
+        let runs = Cell::new(0);
+
+        render(&mut snapshot, &mut memory, &runs);
+        let first = runs.get();
+        render(&mut snapshot, &mut memory, &runs);
+        assert!(runs.get() > first);
+    }
+}
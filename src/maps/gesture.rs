@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use egui::{PointerButton, Pos2, Response, Ui};
+
+use crate::{Position, Projector};
+
+/// Phase of the [`Gesture`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GesturePhase {
+    Started,
+    Dragging,
+    Ended,
+}
+
+/// Pointer dragged over the map, with positions already projected to the map's coordinates.
+/// Useful for custom gestures, like a lasso, typically along with disabling the map's own drag
+/// gesture. See [`crate::MapMemory::gesture`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gesture {
+    pub phase: GesturePhase,
+    pub button: PointerButton,
+
+    /// Where the drag started.
+    pub start: Position,
+
+    /// Where the pointer is now.
+    pub current: Position,
+}
+
+/// Follow the drag gesture from the previous frame.
+pub(crate) fn track_gesture(
+    previous: Option<Gesture>,
+    response: &Response,
+    projector: &Projector,
+) -> Option<Gesture> {
+    let current = response
+        .ctx
+        .input(|i| i.pointer.latest_pos())
+        .map(|pos| projector.unproject(pos))?;
+
+    for button in [
+        PointerButton::Primary,
+        PointerButton::Secondary,
+        PointerButton::Middle,
+    ] {
+        let start = previous
+            .as_ref()
+            .filter(|previous| previous.button == button)
+            .map(|previous| previous.start);
+
+        let phase = if response.drag_started_by(button) {
+            GesturePhase::Started
+        } else if response.dragged_by(button) {
+            GesturePhase::Dragging
+        } else if response.drag_stopped_by(button) {
+            GesturePhase::Ended
+        } else {
+            continue;
+        };
+
+        let start = match phase {
+            GesturePhase::Started => response
+                .ctx
+                .input(|i| i.pointer.press_origin())
+                .map(|pos| projector.unproject(pos))
+                .unwrap_or(current),
+            _ => start.unwrap_or(current),
+        };
+
+        return Some(Gesture {
+            phase,
+            button,
+            start,
+            current,
+        });
+    }
+
+    None
+}
+
+/// How far, in points, the pointer may move while still being long pressed.
+const LONG_PRESS_DISTANCE: f32 = 10.;
+
+/// Screen position where the primary button has been held for `duration` seconds without moving.
+/// Reported once per press, in the frame in which it reaches the duration.
+pub(crate) fn detect_long_press(ui: &Ui, response: &Response, duration: f64) -> Option<Pos2> {
+    let (time, primary_down, origin, start, latest) = ui.input(|i| {
+        (
+            i.time,
+            i.pointer.primary_down(),
+            i.pointer.press_origin(),
+            i.pointer.press_start_time(),
+            i.pointer.latest_pos(),
+        )
+    });
+    if !response.is_pointer_button_down_on() || !primary_down {
+        return None;
+    }
+
+    let (origin, start) = (origin?, start?);
+    if latest?.distance(origin) > LONG_PRESS_DISTANCE {
+        return None;
+    }
+
+    // Remember which press was reported, by its start time.
+    let id = response.id.with("long_press");
+    if ui.data(|data| data.get_temp::<f64>(id)) == Some(start) {
+        return None;
+    }
+
+    let remaining = duration - (time - start);
+    if remaining > 0. {
+        ui.ctx()
+            .request_repaint_after(Duration::from_secs_f64(remaining));
+        return None;
+    }
+
+    ui.data_mut(|data| data.insert_temp(id, start));
+    Some(origin)
+}
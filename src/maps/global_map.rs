@@ -1,12 +1,28 @@
-use egui::{PointerButton, Response, Sense, Ui, UiBuilder, Vec2, Widget};
+use std::{collections::HashMap, sync::Arc};
+
+use egui::{Response, Sense, Ui, Widget};
 
 use crate::{
-    center::Center,
+    events::{EventListeners, MapEvent},
     map_memory::MapMemory,
     projector::{Projector, ProjectorType},
-    tiles::flood_fill_tiles,
-    units::{AdjustedPosition, Position, PositionTrait},
-    Plugin, Tiles,
+    tiles::{center_tile, detail_zoom, flood_fill_tiles},
+    units::Position,
+    Plugin, PositionFormat, Projection, Tiles,
+};
+
+use super::{
+    accessibility::{describe, handle_focus, handle_keyboard},
+    builder::MapBuilder,
+    gesture::{detect_long_press, track_gesture},
+    handle_gestures,
+    options::InteractionOptions,
+    rotation::draw_snap_indicator,
+    run_plugins,
+    scroll::ScrollPolicy,
+    split_into_layers,
+    world_bounds::{keep_within_world, WorldBounds},
+    zoom_input::ZoomSensitivity,
 };
 
 /// The actual map widget. Instances are to be created on each frame, as all necessary state is
@@ -16,13 +32,12 @@ pub struct Map<'a, 'b, 'c> {
     memory: &'a mut MapMemory,
     my_position: Position,
     plugins: Vec<Box<dyn Plugin + 'b>>,
+    events: EventListeners<'b>,
 
-    zoom_gesture_enabled: bool,
-    drag_gesture_enabled: bool,
-    zoom_speed: f64,
-    double_click_to_zoom: bool,
-    double_click_to_zoom_out: bool,
-    zoom_with_ctrl: bool,
+    interaction: InteractionOptions,
+
+    description: Option<String>,
+    hidpi_tiles: bool,
 }
 
 impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
@@ -32,57 +47,111 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
         my_position: Position,
     ) -> Self {
         memory.projection_type = ProjectorType::Global;
+        memory.position_format = PositionFormat::default();
 
         Self {
             tiles,
             memory,
             my_position,
             plugins: Vec::default(),
-            zoom_gesture_enabled: true,
-            drag_gesture_enabled: true,
-            zoom_speed: 2.0,
-            double_click_to_zoom: false,
-            double_click_to_zoom_out: false,
-            zoom_with_ctrl: true,
+            events: EventListeners::default(),
+            interaction: InteractionOptions::default(),
+            description: None,
+            hidpi_tiles: true,
         }
     }
 
+    /// Alternative to [`Map::new`] and chained options, see [`MapBuilder`].
+    pub fn builder(memory: &'a mut MapMemory, my_position: Position) -> MapBuilder<'a, 'b, 'c> {
+        MapBuilder::new(memory, my_position)
+    }
+
     pub fn with_plugin(mut self, plugin: impl Plugin + 'b) -> Self {
         self.plugins.push(Box::new(plugin));
         self
     }
 
+    pub(crate) fn with_boxed_plugin(mut self, plugin: Box<dyn Plugin + 'b>) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    /// Subscribe to [`MapEvent`]s emitted by this widget.
+    pub fn with_event_listener(mut self, listener: impl FnMut(&MapEvent) + 'b) -> Self {
+        self.events.subscribe(listener);
+        self
+    }
+
+    /// Set all options of how the map reacts to the user's input at once.
+    pub fn interaction(mut self, options: InteractionOptions) -> Self {
+        self.interaction = options;
+        self
+    }
+
     /// Set whether map should perform zoom gesture.
     ///
     /// Zoom is typically triggered by the mouse wheel while holding <kbd>ctrl</kbd> key on native
     /// and web, and by pinch gesture on Android.
     pub fn zoom_gesture(mut self, enabled: bool) -> Self {
-        self.zoom_gesture_enabled = enabled;
+        self.interaction.zoom_gesture = enabled;
         self
     }
 
     /// Set whether map should perform drag gesture.
     pub fn drag_gesture(mut self, enabled: bool) -> Self {
-        self.drag_gesture_enabled = enabled;
+        self.interaction.drag_gesture = enabled;
         self
     }
 
     /// Change how far to zoom in/out.
     /// Default value is 2.0
     pub fn zoom_speed(mut self, speed: f64) -> Self {
-        self.zoom_speed = speed;
+        self.interaction.zoom_speed = speed;
+        self
+    }
+
+    /// Tune the zoom speed separately for the mouse wheel, trackpad and touch screen.
+    pub fn zoom_sensitivity(mut self, sensitivity: ZoomSensitivity) -> Self {
+        self.interaction.zoom_sensitivity = sensitivity;
         self
     }
 
     /// Set whether to enable double click primary mouse button to zoom
     pub fn double_click_to_zoom(mut self, enabled: bool) -> Self {
-        self.double_click_to_zoom = enabled;
+        self.interaction.double_click_to_zoom = enabled;
         self
     }
 
     /// Set whether to enable double click secondary mouse button to zoom out
     pub fn double_click_to_zoom_out(mut self, enabled: bool) -> Self {
-        self.double_click_to_zoom_out = enabled;
+        self.interaction.double_click_to_zoom_out = enabled;
+        self
+    }
+
+    /// Set whether tapping twice and dragging vertically zooms the map. See
+    /// [`InteractionOptions::double_tap_drag_zoom`].
+    pub fn double_tap_drag_zoom(mut self, enabled: bool) -> Self {
+        self.interaction.double_tap_drag_zoom = enabled;
+        self
+    }
+
+    /// Set whether the map can be rotated. See [`InteractionOptions::rotate_gesture`].
+    pub fn rotate_gesture(mut self, enabled: bool) -> Self {
+        self.interaction.rotate_gesture = enabled;
+        self
+    }
+
+    /// Set within how many degrees of a cardinal direction rotation snaps to it, or `None` to
+    /// disable snapping. Default is 10.
+    pub fn rotation_snap(mut self, threshold: Option<f64>) -> Self {
+        self.interaction.rotation_snap = threshold;
+        self
+    }
+
+    /// Set how long the map must be pressed to report a long press, or `None` to disable it. See
+    /// [`InteractionOptions::long_press`].
+    pub fn long_press(mut self, duration: Option<f64>) -> Self {
+        self.interaction.long_press = duration;
         self
     }
 
@@ -96,108 +165,63 @@ impl<'a, 'b, 'c> Map<'a, 'b, 'c> {
     ///
     /// Has no effect on Android
     pub fn zoom_with_ctrl(mut self, enabled: bool) -> Self {
-        self.zoom_with_ctrl = enabled;
+        self.interaction.zoom_with_ctrl = enabled;
         self
     }
-}
-
-impl Map<'_, '_, '_> {
-    /// Handle zoom and drag inputs, and recalculate everything accordingly.
-    /// Returns `false` if no gesture handled.
-    fn handle_gestures(&mut self, ui: &mut Ui, response: &Response) -> bool {
-        let mut zoom_delta = ui.input(|input| input.zoom_delta()) as f64;
-
-        if self.double_click_to_zoom
-            && ui.ui_contains_pointer()
-            && response.double_clicked_by(PointerButton::Primary)
-        {
-            zoom_delta = 2.0;
-        }
 
-        if self.double_click_to_zoom_out
-            && ui.ui_contains_pointer()
-            && response.double_clicked_by(PointerButton::Secondary)
-        {
-            zoom_delta = 0.0;
-        }
+    /// Set when the map reacts to the mouse wheel, e.g. to let it scroll the surrounding
+    /// [`egui::ScrollArea`] instead. Default is [`ScrollPolicy::Always`].
+    pub fn scroll_policy(mut self, policy: ScrollPolicy) -> Self {
+        self.interaction.scroll_policy = policy;
+        self
+    }
 
-        if !self.zoom_with_ctrl && zoom_delta == 1.0 {
-            // We only use the raw scroll values, if we are zooming without ctrl,
-            // and zoom_delta is not already over/under 1.0 (eg. a ctrl + scroll event or a pinch zoom)
-            // These values seem to corrospond to the same values as one would get in `zoom_delta()`
-            zoom_delta = ui.input(|input| (1.0 + input.smooth_scroll_delta.y / 200.0)) as f64
-        };
-
-        let mut changed = false;
-
-        // Zooming and dragging need to be exclusive, otherwise the map will get dragged when
-        // pinch gesture is used.
-        if !(0.99..=1.01).contains(&zoom_delta)
-            && ui.ui_contains_pointer()
-            && self.zoom_gesture_enabled
-        {
-            // Displacement of mouse pointer relative to widget center
-            let offset = response.hover_pos().map(|p| p - response.rect.center());
-
-            let pos = self
-                .memory
-                .center_mode
-                .global_position(self.my_position, self.memory.zoom());
-
-            // While zooming, we want to keep the location under the mouse pointer fixed on the
-            // screen. To achieve this, we first move the location to the widget's center,
-            // then adjust zoom level, finally move the location back to the original screen
-            // position.
-            if let Some(offset) = offset {
-                self.memory.center_mode = Center::Exact {
-                    pos: (AdjustedPosition::from(pos).shift(-offset))
-                        .global_zero_offset(self.memory.zoom()),
-                };
-            }
+    /// Set what happens when the map is panned past the top or bottom of the world. Default is
+    /// [`WorldBounds::Free`].
+    pub fn world_bounds(mut self, bounds: WorldBounds) -> Self {
+        self.interaction.world_bounds = bounds;
+        self
+    }
 
-            // Shift by 1 because of the values given by zoom_delta(). Multiple by zoom_speed(defaults to 2.0),
-            // because then it felt right with both mouse wheel, and an Android phone.
-            self.memory
-                .zoom
-                .zoom_by((zoom_delta - 1.) * self.zoom_speed);
+    /// Project the map with given projection instead of Web Mercator, e.g.
+    /// [`crate::PolarStereographic`] to show polar tile sets such as NASA GIBS EPSG:3413. The
+    /// tiles must follow the projection's grid.
+    pub fn projection(self, projection: impl Projection + 'static) -> Self {
+        self.with_shared_projection(Arc::new(projection))
+    }
 
-            // Recalculate the AdjustedPosition's offset, since it gets invalidated by zooming.
-            self.memory.center_mode = self
-                .memory
-                .center_mode
-                .clone()
-                .global_zero_offset(self.memory.zoom());
+    pub(crate) fn with_shared_projection(self, projection: Arc<dyn Projection>) -> Self {
+        self.memory.projection_type = ProjectorType::Custom(projection);
+        self
+    }
 
-            if let Some(offset) = offset {
-                self.memory.center_mode = self.memory.center_mode.clone().shift(offset);
-            }
+    /// Set how positions are shown by readouts, e.g. in plugins and descriptions for screen
+    /// readers. Default is [`PositionFormat::DecimalDegrees`].
+    pub fn position_format(self, format: PositionFormat) -> Self {
+        self.memory.position_format = format;
+        self
+    }
 
-            changed = true;
-        } else if self.drag_gesture_enabled {
-            changed = self
-                .memory
-                .center_mode
-                .recalculate_drag(response, self.my_position);
-        }
+    /// Set whether the zoom level of tiles follows the display's pixels per point, keeping the
+    /// apparent detail the same across displays. E.g. a 2x display fetches tiles one level
+    /// deeper, which means about four times as many downloads. Enabled by default.
+    pub fn hidpi_tiles(mut self, enabled: bool) -> Self {
+        self.hidpi_tiles = enabled;
+        self
+    }
 
-        // Only enable panning with mouse_wheel if we are zooming with ctrl. But always allow touch devices to pan
-        let panning_enabled = ui.input(|i| i.any_touches()) || self.zoom_with_ctrl;
-
-        if ui.ui_contains_pointer() && panning_enabled {
-            // Panning by scrolling, e.g. two-finger drag on a touchpad:
-            let scroll_delta = ui.input(|i| i.smooth_scroll_delta);
-            if scroll_delta != Vec2::ZERO {
-                let pos = self
-                    .memory
-                    .center_mode
-                    .global_position(self.my_position, self.memory.zoom());
-                self.memory.center_mode = Center::Exact {
-                    pos: AdjustedPosition::from(pos).shift(scroll_delta),
-                };
-            }
-        }
+    /// Set whether the focused map can be panned with arrow keys and zoomed with <kbd>+</kbd> and
+    /// <kbd>-</kbd>. Enabled by default.
+    pub fn keyboard_navigation(mut self, enabled: bool) -> Self {
+        self.interaction.keyboard_navigation = enabled;
+        self
+    }
 
-        changed
+    /// Additional text for screen readers, appended to the map's center and zoom, e.g. "12
+    /// markers visible".
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
     }
 }
 
@@ -206,8 +230,28 @@ impl Widget for Map<'_, '_, '_> {
         let (rect, mut response) =
             ui.allocate_exact_size(ui.available_size(), Sense::click_and_drag());
 
-        let mut moved = self.handle_gestures(ui, &response);
-        moved |= self.memory.center_mode.update_movement();
+        let zoom_before = self.memory.zoom();
+        handle_focus(ui, &response);
+        let mut moved = handle_gestures(
+            ui,
+            &response,
+            self.memory,
+            self.my_position,
+            &self.interaction,
+        );
+        if self.interaction.keyboard_navigation {
+            moved |= handle_keyboard(ui, &response, self.memory, self.my_position);
+        }
+        moved |= self.memory.update_flight(ui.ctx(), self.my_position);
+        moved |= self.memory.center_mode.update_movement(ui.ctx());
+        let bounced = keep_within_world(
+            ui.ctx(),
+            rect,
+            self.memory,
+            self.my_position,
+            self.interaction.world_bounds,
+        );
+        moved |= bounced;
 
         if moved {
             response.mark_changed();
@@ -215,34 +259,106 @@ impl Widget for Map<'_, '_, '_> {
         }
 
         let zoom = self.memory.zoom();
-        let map_center = self
-            .memory
-            .center_mode
-            .global_position(self.my_position, zoom);
+        let map_center = self.memory.camera(self.my_position).center();
         let painter = ui.painter().with_clip_rect(rect);
 
+        self.events
+            .push_gestures(&response, zoom_before, zoom, map_center);
+        if self.memory.update_motion(bounced) {
+            self.events.push(MapEvent::Settled { center: map_center });
+        }
+
+        let mut meshes = HashMap::new();
         if let Some(tiles) = self.tiles {
-            let mut meshes = Default::default();
+            let source_id = tiles.source_id();
+            let switched = self
+                .memory
+                .source
+                .as_ref()
+                .is_some_and(|previous| *previous != source_id);
+            if switched {
+                self.events.push(MapEvent::SourceSwitched {
+                    attribution: tiles.attribution().text,
+                });
+            }
+            self.memory.source = Some(source_id);
+
+            for (tile_id, message) in tiles.take_errors() {
+                self.events.push(MapEvent::TileError { tile_id, message });
+            }
+
+            let tile_zoom = if self.hidpi_tiles {
+                detail_zoom(zoom, ui.ctx().pixels_per_point(), tiles)
+            } else {
+                self.memory.zoom.round()
+            };
+
+            let (tile_id, map_center_projected_position) = center_tile(
+                &self.memory.projection_type,
+                map_center,
+                zoom,
+                tile_zoom,
+                tiles.zoom_offset(),
+            );
+
             flood_fill_tiles(
                 painter.clip_rect(),
-                map_center.tile_id(self.memory.zoom.round(), tiles.tile_size()),
-                map_center.global_bitmap_project(zoom),
+                self.memory.screen_transform(),
+                &self.memory.projection_type,
+                tile_id,
+                map_center_projected_position,
                 zoom,
                 tiles,
                 &mut meshes,
             );
 
-            for shape in meshes.drain().filter_map(|(_, mesh)| mesh) {
-                painter.add(shape);
-            }
+            let visible = Projector::new(self.memory, rect, self.my_position).visible_bounds();
+            self.memory.attributions = tiles.attributions(visible);
+        } else {
+            self.memory.attributions.clear();
+        }
+
+        let [background, foreground, top] = split_into_layers(self.plugins);
+        describe(
+            &response,
+            self.memory,
+            self.my_position,
+            self.description.as_deref(),
+        );
+
+        let previous_gesture = self.memory.gesture.take();
+        self.memory.gesture = track_gesture(
+            previous_gesture,
+            &response,
+            &Projector::new(self.memory, rect, self.my_position),
+        );
+
+        self.memory.long_press = self
+            .interaction
+            .long_press
+            .and_then(|duration| detect_long_press(ui, &response, duration))
+            .map(|pos| Projector::new(self.memory, rect, self.my_position).unproject(pos));
+        if let Some(position) = self.memory.long_press {
+            self.events.push(MapEvent::LongPress { position });
         }
 
         let projector = Projector::new(self.memory, rect, self.my_position);
-        for (idx, plugin) in self.plugins.into_iter().enumerate() {
-            let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect).id_salt(idx));
-            plugin.run(&mut child_ui, &response, &projector);
+
+        run_plugins(background, ui, rect, &response, &projector);
+
+        for shape in meshes.drain().filter_map(|(_, mesh)| mesh) {
+            painter.add(shape);
         }
 
+        run_plugins(foreground, ui, rect, &response, &projector);
+        run_plugins(top, ui, rect, &response, &projector);
+
+        if self.interaction.rotate_gesture {
+            draw_snap_indicator(ui, &response, rect);
+        }
+
+        self.events.dispatch();
+
         response
     }
 }
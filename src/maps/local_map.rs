@@ -1,177 +1,152 @@
-use egui::{PointerButton, Response, Sense, Ui, UiBuilder, Vec2, Widget};
+use egui::{Response, Sense, Ui, Widget};
 
 use crate::{
-    center::Center,
+    camera,
+    events::{EventListeners, MapEvent},
     projector::{Projector, ProjectorType},
-    units::{AdjustedPosition, Position},
-    MapMemory, Plugin,
+    units::Position,
+    LocalTransform, MapMemory, Plugin, PositionFormat,
+};
+
+use super::{
+    accessibility::{describe, handle_focus, handle_keyboard},
+    gesture::{detect_long_press, track_gesture},
+    handle_gestures,
+    options::InteractionOptions,
+    rotation::draw_snap_indicator,
+    run_plugins,
+    scroll::ScrollPolicy,
+    split_into_layers,
+    zoom_input::ZoomSensitivity,
 };
 
 /// Actual map widget, but with a blank map and in arbitrary coordinates. Instances
 /// are to be created on each frame, as all necessary state is stored in [`MapMemory`].
 pub struct LocalMap<'a, 'b> {
     plugins: Vec<Box<dyn Plugin + 'b>>,
+    events: EventListeners<'b>,
+
+    interaction: InteractionOptions,
 
     my_position: Position,
     memory: &'a mut MapMemory,
-    zoom_gesture_enabled: bool,
-    drag_gesture_enabled: bool,
-    zoom_speed: f64,
-    double_click_to_zoom: bool,
-    double_click_to_zoom_out: bool,
-    zoom_with_ctrl: bool,
+    description: Option<String>,
 }
 
 impl<'a, 'b> LocalMap<'a, 'b> {
     pub fn new(memory: &'a mut MapMemory, my_position: Position) -> Self {
-        memory.projection_type = ProjectorType::Local;
+        memory.projection_type = ProjectorType::Local(LocalTransform::default());
+        memory.position_format = PositionFormat::Local;
 
         Self {
             plugins: Vec::default(),
+            events: EventListeners::default(),
+            interaction: InteractionOptions::default(),
             memory,
             my_position,
-            zoom_gesture_enabled: true,
-            drag_gesture_enabled: true,
-            zoom_speed: 2.0,
-            double_click_to_zoom: false,
-            double_click_to_zoom_out: false,
-            zoom_with_ctrl: true,
+            description: None,
         }
     }
 
+    /// Transform positions before they are projected, e.g. for a floor plan whose coordinate
+    /// system is rotated relative to the screen or has different scales along its axes.
+    pub fn transform(self, transform: LocalTransform) -> Self {
+        self.memory.projection_type = ProjectorType::Local(transform);
+        self
+    }
+
     pub fn with_plugin(mut self, plugin: impl Plugin + 'b) -> Self {
         self.plugins.push(Box::new(plugin));
         self
     }
 
+    /// Subscribe to [`MapEvent`]s emitted by this widget.
+    pub fn with_event_listener(mut self, listener: impl FnMut(&MapEvent) + 'b) -> Self {
+        self.events.subscribe(listener);
+        self
+    }
+
+    /// Set all options of how the map reacts to the user's input at once.
+    pub fn interaction(mut self, options: InteractionOptions) -> Self {
+        self.interaction = options;
+        self
+    }
+
     pub fn zoom_gesture(mut self, enabled: bool) -> Self {
-        self.zoom_gesture_enabled = enabled;
+        self.interaction.zoom_gesture = enabled;
         self
     }
 
     pub fn drag_gesture(mut self, enabled: bool) -> Self {
-        self.drag_gesture_enabled = enabled;
+        self.interaction.drag_gesture = enabled;
         self
     }
 
     pub fn zoom_speed(mut self, speed: f64) -> Self {
-        self.zoom_speed = speed;
+        self.interaction.zoom_speed = speed;
+        self
+    }
+
+    /// Tune the zoom speed separately for the mouse wheel, trackpad and touch screen.
+    pub fn zoom_sensitivity(mut self, sensitivity: ZoomSensitivity) -> Self {
+        self.interaction.zoom_sensitivity = sensitivity;
         self
     }
 
     pub fn double_click_to_zoom(mut self, enabled: bool) -> Self {
-        self.double_click_to_zoom = enabled;
+        self.interaction.double_click_to_zoom = enabled;
         self
     }
 
     pub fn double_click_to_zoom_out(mut self, enabled: bool) -> Self {
-        self.double_click_to_zoom_out = enabled;
+        self.interaction.double_click_to_zoom_out = enabled;
         self
     }
 
-    pub fn zoom_with_ctrl(mut self, enabled: bool) -> Self {
-        self.zoom_with_ctrl = enabled;
+    pub fn double_tap_drag_zoom(mut self, enabled: bool) -> Self {
+        self.interaction.double_tap_drag_zoom = enabled;
         self
     }
-}
 
-impl LocalMap<'_, '_> {
-    /// Handle zoom and drag inputs, and recalculate everything accordingly.
-    /// Returns `false` if no gesture handled.
-    fn handle_gestures(&mut self, ui: &mut Ui, response: &Response) -> bool {
-        let mut zoom_delta = ui.input(|input| input.zoom_delta()) as f64;
-
-        if self.double_click_to_zoom
-            && ui.ui_contains_pointer()
-            && response.double_clicked_by(PointerButton::Primary)
-        {
-            zoom_delta = 2.0;
-        }
+    pub fn rotate_gesture(mut self, enabled: bool) -> Self {
+        self.interaction.rotate_gesture = enabled;
+        self
+    }
 
-        if self.double_click_to_zoom_out
-            && ui.ui_contains_pointer()
-            && response.double_clicked_by(PointerButton::Secondary)
-        {
-            zoom_delta = 0.0;
-        }
+    pub fn rotation_snap(mut self, threshold: Option<f64>) -> Self {
+        self.interaction.rotation_snap = threshold;
+        self
+    }
 
-        if !self.zoom_with_ctrl && zoom_delta == 1.0 {
-            // We only use the raw scroll values, if we are zooming without ctrl,
-            // and zoom_delta is not already over/under 1.0 (eg. a ctrl + scroll event or a pinch zoom)
-            // These values seem to corrospond to the same values as one would get in `zoom_delta()`
-            zoom_delta = ui.input(|input| (1.0 + input.smooth_scroll_delta.y / 200.0)) as f64
-        };
-
-        let mut changed = false;
-
-        // Zooming and dragging need to be exclusive, otherwise the map will get dragged when
-        // pinch gesture is used.
-        if !(0.99..=1.01).contains(&zoom_delta)
-            && ui.ui_contains_pointer()
-            && self.zoom_gesture_enabled
-        {
-            // Displacement of mouse pointer relative to widget center
-            let offset = response.hover_pos().map(|p| p - response.rect.center());
-
-            let pos = self
-                .memory
-                .center_mode
-                .local_position(self.my_position, self.memory.zoom());
-
-            // While zooming, we want to keep the location under the mouse pointer fixed on the
-            // screen. To achieve this, we first move the location to the widget's center,
-            // then adjust zoom level, finally move the location back to the original screen
-            // position.
-            if let Some(offset) = offset {
-                self.memory.center_mode = Center::Exact {
-                    pos: (AdjustedPosition::from(pos).shift(-offset))
-                        .local_zero_offset(self.memory.zoom()),
-                };
-            }
-
-            // Shift by 1 because of the values given by zoom_delta(). Multiple by zoom_speed(defaults to 2.0),
-            // because then it felt right with both mouse wheel, and an Android phone.
-            self.memory
-                .zoom
-                .zoom_by((zoom_delta - 1.) * self.zoom_speed);
-
-            // Recalculate the AdjustedPosition's offset, since it gets invalidated by zooming.
-            self.memory.center_mode = self
-                .memory
-                .center_mode
-                .clone()
-                .local_zero_offset(self.memory.zoom());
-
-            if let Some(offset) = offset {
-                self.memory.center_mode = self.memory.center_mode.clone().shift(offset);
-            }
-
-            changed = true;
-        } else if self.drag_gesture_enabled {
-            changed = self
-                .memory
-                .center_mode
-                .recalculate_drag(response, self.my_position);
-        }
+    pub fn long_press(mut self, duration: Option<f64>) -> Self {
+        self.interaction.long_press = duration;
+        self
+    }
 
-        // Only enable panning with mouse_wheel if we are zooming with ctrl. But always allow touch devices to pan
-        let panning_enabled = ui.input(|i| i.any_touches()) || self.zoom_with_ctrl;
-
-        if ui.ui_contains_pointer() && panning_enabled {
-            // Panning by scrolling, e.g. two-finger drag on a touchpad:
-            let scroll_delta = ui.input(|i| i.smooth_scroll_delta);
-            if scroll_delta != Vec2::ZERO {
-                let pos = self
-                    .memory
-                    .center_mode
-                    .local_position(self.my_position, self.memory.zoom());
-                self.memory.center_mode = Center::Exact {
-                    pos: AdjustedPosition::from(pos).shift(scroll_delta),
-                };
-            }
-        }
+    pub fn zoom_with_ctrl(mut self, enabled: bool) -> Self {
+        self.interaction.zoom_with_ctrl = enabled;
+        self
+    }
+
+    /// Set when the map reacts to the mouse wheel, e.g. to let it scroll the surrounding
+    /// [`egui::ScrollArea`] instead. Default is [`ScrollPolicy::Always`].
+    pub fn scroll_policy(mut self, policy: ScrollPolicy) -> Self {
+        self.interaction.scroll_policy = policy;
+        self
+    }
 
-        changed
+    /// Set whether the focused map can be panned with arrow keys and zoomed with <kbd>+</kbd> and
+    /// <kbd>-</kbd>. Enabled by default.
+    pub fn keyboard_navigation(mut self, enabled: bool) -> Self {
+        self.interaction.keyboard_navigation = enabled;
+        self
+    }
+
+    /// Additional text for screen readers, appended to the map's center and zoom, e.g. "12
+    /// markers visible".
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
     }
 }
 
@@ -180,20 +155,68 @@ impl Widget for LocalMap<'_, '_> {
         let (rect, mut response) =
             ui.allocate_exact_size(ui.available_size(), Sense::click_and_drag());
 
-        let mut moved = self.handle_gestures(ui, &response);
-        moved |= self.memory.center_mode.update_movement();
+        let zoom_before = self.memory.zoom();
+        handle_focus(ui, &response);
+        let mut moved = handle_gestures(
+            ui,
+            &response,
+            self.memory,
+            self.my_position,
+            &self.interaction,
+        );
+        if self.interaction.keyboard_navigation {
+            moved |= handle_keyboard(ui, &response, self.memory, self.my_position);
+        }
+        moved |= self.memory.update_flight(ui.ctx(), self.my_position);
+        moved |= self.memory.center_mode.update_movement(ui.ctx());
+
+        let zoom = self.memory.zoom();
+        let center = camera::center(self.memory, self.my_position);
+        self.events
+            .push_gestures(&response, zoom_before, zoom, center);
+        if self.memory.update_motion(false) {
+            self.events.push(MapEvent::Settled { center });
+        }
 
         if moved {
             response.mark_changed();
             ui.ctx().request_repaint();
         }
 
+        describe(
+            &response,
+            self.memory,
+            self.my_position,
+            self.description.as_deref(),
+        );
+
+        let previous_gesture = self.memory.gesture.take();
+        self.memory.gesture = track_gesture(
+            previous_gesture,
+            &response,
+            &Projector::new(self.memory, rect, self.my_position),
+        );
+
+        self.memory.long_press = self
+            .interaction
+            .long_press
+            .and_then(|duration| detect_long_press(ui, &response, duration))
+            .map(|pos| Projector::new(self.memory, rect, self.my_position).unproject(pos));
+        if let Some(position) = self.memory.long_press {
+            self.events.push(MapEvent::LongPress { position });
+        }
+
         let projector = Projector::new(self.memory, rect, self.my_position);
-        for (idx, plugin) in self.plugins.into_iter().enumerate() {
-            let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect).id_salt(idx));
-            plugin.run(&mut child_ui, &response, &projector);
+        for layer in split_into_layers(self.plugins) {
+            run_plugins(layer, ui, rect, &response, &projector);
         }
 
+        if self.interaction.rotate_gesture {
+            draw_snap_indicator(ui, &response, rect);
+        }
+
+        self.events.dispatch();
+
         response
     }
 }
@@ -1,10 +1,49 @@
+mod accessibility;
+mod builder;
+mod cached_layer;
+mod gesture;
 mod global_map;
 mod local_map;
+mod options;
+mod rotation;
+mod scroll;
+mod world_bounds;
+mod zoom_input;
 
+pub use builder::MapBuilder;
+pub use cached_layer::CachedLayer;
+pub use gesture::{Gesture, GesturePhase};
 pub use global_map::Map;
 pub use local_map::LocalMap;
+pub use options::InteractionOptions;
+pub use scroll::ScrollPolicy;
+pub use world_bounds::WorldBounds;
+pub use zoom_input::ZoomSensitivity;
 
-use crate::Projector;
+use std::ops::RangeInclusive;
+
+use egui::{PointerButton, Rect, Response, Ui, UiBuilder, Vec2};
+
+use crate::{MapEvent, MapMemory, Position, PositionFormat, Projector};
+
+use rotation::handle_rotation;
+use scroll::{captures_scroll, consume_scroll};
+use zoom_input::double_tap_drag_zoom;
+
+/// Where the plugin is drawn, relative to the tiles and other plugins. Within the same layer,
+/// plugins are drawn in the order they were added.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PluginLayer {
+    /// Below the tiles, e.g. a solid color fill showing through transparent tiles.
+    Background,
+
+    /// Above the tiles. This is where most of the plugins belong.
+    #[default]
+    Foreground,
+
+    /// Above everything else, e.g. controls.
+    Top,
+}
 
 /// Plugins allow drawing custom shapes on the map. After implementing this trait for your type,
 /// you can add it to the map with [`Map::with_plugin`]
@@ -19,6 +58,79 @@ pub trait Plugin {
     /// The provided [`Response`] is the response of the map widget itself and can be used to test
     /// if the mouse is hovering or clicking on the map.
     fn run(self: Box<Self>, ui: &mut egui::Ui, response: &egui::Response, projector: &Projector);
+
+    /// Layer in which this plugin is drawn. Default is [`PluginLayer::Foreground`].
+    fn layer(&self) -> PluginLayer {
+        PluginLayer::Foreground
+    }
+
+    /// Zoom levels at which this plugin is drawn. It is skipped outside of them, e.g. so that
+    /// detailed overlays do not clutter the map when zoomed out. Default is all of them. See
+    /// [`ZoomRange`] for limiting existing plugins.
+    fn zoom_range(&self) -> RangeInclusive<f64> {
+        f64::NEG_INFINITY..=f64::INFINITY
+    }
+}
+
+/// Wraps a [`Plugin`], drawing it only within given zoom levels.
+pub struct ZoomRange<P> {
+    plugin: P,
+    range: RangeInclusive<f64>,
+}
+
+impl<P: Plugin> ZoomRange<P> {
+    pub fn new(plugin: P, range: RangeInclusive<f64>) -> Self {
+        Self { plugin, range }
+    }
+}
+
+impl<P: Plugin> Plugin for ZoomRange<P> {
+    fn run(self: Box<Self>, ui: &mut Ui, response: &Response, projector: &Projector) {
+        Box::new(self.plugin).run(ui, response, projector);
+    }
+
+    fn layer(&self) -> PluginLayer {
+        self.plugin.layer()
+    }
+
+    fn zoom_range(&self) -> RangeInclusive<f64> {
+        self.range.clone()
+    }
+}
+
+type IndexedPlugins<'b> = Vec<(usize, Box<dyn Plugin + 'b>)>;
+
+/// Split plugins into background, foreground and top layers, remembering their original index
+/// for stable ui ids.
+pub(crate) fn split_into_layers(plugins: Vec<Box<dyn Plugin + '_>>) -> [IndexedPlugins<'_>; 3] {
+    let mut layers: [IndexedPlugins; 3] = Default::default();
+    for (idx, plugin) in plugins.into_iter().enumerate() {
+        let layer = match plugin.layer() {
+            PluginLayer::Background => 0,
+            PluginLayer::Foreground => 1,
+            PluginLayer::Top => 2,
+        };
+        layers[layer].push((idx, plugin));
+    }
+    layers
+}
+
+pub(crate) fn run_plugins(
+    plugins: IndexedPlugins,
+    ui: &mut Ui,
+    rect: Rect,
+    response: &Response,
+    projector: &Projector,
+) {
+    let zoom = projector.memory().zoom();
+    for (idx, plugin) in plugins {
+        if !plugin.zoom_range().contains(&zoom) {
+            continue;
+        }
+
+        let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect).id_salt(idx));
+        plugin.run(&mut child_ui, response, projector);
+    }
 }
 
 /// Wrap your map in the Maps enum to be able to return
@@ -46,6 +158,22 @@ impl<'b> Maps<'_, 'b, '_> {
         }
     }
 
+    /// Subscribe to [`MapEvent`]s emitted by the map.
+    pub fn with_event_listener(self, listener: impl FnMut(&MapEvent) + 'b) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.with_event_listener(listener)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.with_event_listener(listener)),
+        }
+    }
+
+    /// Set all options of how the map reacts to the user's input at once.
+    pub fn interaction(self, options: InteractionOptions) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.interaction(options)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.interaction(options)),
+        }
+    }
+
     /// Set whether map should perform zoom gesture.
     ///
     /// Zoom is typically triggered by the mouse wheel while holding <kbd>ctrl</kbd> key on native
@@ -57,6 +185,14 @@ impl<'b> Maps<'_, 'b, '_> {
         }
     }
 
+    /// Tune the zoom speed separately for the mouse wheel, trackpad and touch screen.
+    pub fn zoom_sensitivity(self, sensitivity: ZoomSensitivity) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.zoom_sensitivity(sensitivity)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.zoom_sensitivity(sensitivity)),
+        }
+    }
+
     /// Set whether map should perform drag gesture.
     pub fn drag_gesture(self, enabled: bool) -> Self {
         match self {
@@ -92,6 +228,41 @@ impl<'b> Maps<'_, 'b, '_> {
         }
     }
 
+    /// Set whether tapping twice and dragging vertically zooms the map. See
+    /// [`InteractionOptions::double_tap_drag_zoom`].
+    pub fn double_tap_drag_zoom(self, enabled: bool) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.double_tap_drag_zoom(enabled)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.double_tap_drag_zoom(enabled)),
+        }
+    }
+
+    /// Set whether the map can be rotated. See [`InteractionOptions::rotate_gesture`].
+    pub fn rotate_gesture(self, enabled: bool) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.rotate_gesture(enabled)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.rotate_gesture(enabled)),
+        }
+    }
+
+    /// Set within how many degrees of a cardinal direction rotation snaps to it, or `None` to
+    /// disable snapping.
+    pub fn rotation_snap(self, threshold: Option<f64>) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.rotation_snap(threshold)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.rotation_snap(threshold)),
+        }
+    }
+
+    /// Set how long the map must be pressed to report a long press, or `None` to disable it. See
+    /// [`InteractionOptions::long_press`].
+    pub fn long_press(self, duration: Option<f64>) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.long_press(duration)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.long_press(duration)),
+        }
+    }
+
     /// Sets the zoom behaviour
     ///
     /// When enabled zoom is done with mouse wheel while holding <kbd>ctrl</kbd> key on native
@@ -107,4 +278,154 @@ impl<'b> Maps<'_, 'b, '_> {
             Maps::LocalMap(local_map) => Maps::LocalMap(local_map.zoom_with_ctrl(enabled)),
         }
     }
+
+    /// Set whether the focused map can be panned with arrow keys and zoomed with <kbd>+</kbd> and
+    /// <kbd>-</kbd>. Enabled by default.
+    pub fn keyboard_navigation(self, enabled: bool) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.keyboard_navigation(enabled)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.keyboard_navigation(enabled)),
+        }
+    }
+
+    /// Set when the map reacts to the mouse wheel. Default is [`ScrollPolicy::Always`].
+    pub fn scroll_policy(self, policy: ScrollPolicy) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.scroll_policy(policy)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.scroll_policy(policy)),
+        }
+    }
+
+    /// Set how positions are shown by readouts. Has no effect on local maps, which always use
+    /// [`PositionFormat::Local`].
+    pub fn position_format(self, format: PositionFormat) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.position_format(format)),
+            local_map => local_map,
+        }
+    }
+
+    /// Set whether the zoom level of tiles follows the display's pixels per point. Has no
+    /// effect on local maps, as they have no tiles.
+    pub fn hidpi_tiles(self, enabled: bool) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.hidpi_tiles(enabled)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map),
+        }
+    }
+
+    /// Set what happens when the map is panned past the world's edge. Has no effect on local
+    /// maps, as they have no edge.
+    pub fn world_bounds(self, bounds: WorldBounds) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.world_bounds(bounds)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map),
+        }
+    }
+
+    /// Additional text for screen readers, appended to the map's center and zoom.
+    pub fn description(self, description: impl Into<String>) -> Self {
+        match self {
+            Maps::Map(map) => Maps::Map(map.description(description)),
+            Maps::LocalMap(local_map) => Maps::LocalMap(local_map.description(description)),
+        }
+    }
+}
+
+/// Handle zoom and drag inputs of both [`Map`] and [`LocalMap`], and recalculate everything
+/// accordingly.
+/// Returns `false` if no gesture handled.
+fn handle_gestures(
+    ui: &mut Ui,
+    response: &Response,
+    memory: &mut MapMemory,
+    my_position: Position,
+    interaction: &InteractionOptions,
+) -> bool {
+    if interaction.double_tap_drag_zoom && interaction.zoom_gesture {
+        if let Some((anchor, delta)) = double_tap_drag_zoom(ui, response) {
+            memory.camera(my_position).zoom_about(anchor, delta);
+            return delta != 0.;
+        }
+    }
+
+    let captures_scroll = captures_scroll(ui, response, interaction.scroll_policy);
+    let mut zoom_delta = if captures_scroll {
+        ui.input(|input| input.zoom_delta()) as f64
+    } else {
+        1.0
+    };
+    let mut sensitivity = interaction.zoom_sensitivity.for_zoom_delta(ui);
+
+    if interaction.double_click_to_zoom
+        && ui.ui_contains_pointer()
+        && response.double_clicked_by(PointerButton::Primary)
+    {
+        zoom_delta = 2.0;
+        sensitivity = 1.0;
+    }
+
+    if interaction.double_click_to_zoom_out
+        && ui.ui_contains_pointer()
+        && response.double_clicked_by(PointerButton::Secondary)
+    {
+        zoom_delta = 0.0;
+        sensitivity = 1.0;
+    }
+
+    if !interaction.zoom_with_ctrl && zoom_delta == 1.0 && captures_scroll {
+        // We only use the raw scroll values, if we are zooming without ctrl,
+        // and zoom_delta is not already over/under 1.0 (eg. a ctrl + scroll event or a pinch zoom)
+        // These values seem to corrospond to the same values as one would get in `zoom_delta()`
+        zoom_delta = ui.input(|input| 1.0 + input.smooth_scroll_delta.y / 200.0) as f64;
+        sensitivity = interaction.zoom_sensitivity.wheel;
+    };
+
+    let mut changed = false;
+
+    // Zooming and dragging need to be exclusive, otherwise the map will get dragged when
+    // pinch gesture is used.
+    if !(0.99..=1.01).contains(&zoom_delta) && ui.ui_contains_pointer() && interaction.zoom_gesture
+    {
+        // Displacement of mouse pointer relative to widget center
+        let anchor = response
+            .hover_pos()
+            .map(|p| p - response.rect.center())
+            .unwrap_or_default();
+
+        // Shift by 1 because of the values given by zoom_delta(). Multiple by zoom_speed(defaults to 2.0),
+        // because then it felt right with both mouse wheel, and an Android phone.
+        memory.camera(my_position).zoom_about(
+            anchor,
+            (zoom_delta - 1.) * interaction.zoom_speed * sensitivity,
+        );
+
+        changed = true;
+    } else if interaction.drag_gesture {
+        let screen_transform = memory.screen_transform();
+        changed = memory
+            .center_mode
+            .recalculate_drag(response, my_position, screen_transform);
+    }
+
+    if interaction.rotate_gesture {
+        changed |= handle_rotation(ui, response, memory, interaction.rotation_snap);
+    }
+
+    // Only enable panning with mouse_wheel if we are zooming with ctrl. But always allow touch devices to pan
+    let panning_enabled = ui.input(|i| i.any_touches()) || interaction.zoom_with_ctrl;
+
+    if captures_scroll && panning_enabled {
+        // Panning by scrolling, e.g. two-finger drag on a touchpad:
+        let scroll_delta = ui.input(|i| i.smooth_scroll_delta);
+        if scroll_delta != Vec2::ZERO {
+            memory.camera(my_position).translate_pixels(scroll_delta);
+        }
+    }
+
+    memory.scroll_consumed = captures_scroll
+        && (interaction.zoom_gesture || panning_enabled)
+        && (consume_scroll(ui) || ui.input(|i| i.zoom_delta()) != 1.0);
+
+    changed
 }
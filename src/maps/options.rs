@@ -0,0 +1,73 @@
+use super::{ScrollPolicy, WorldBounds, ZoomSensitivity};
+
+/// How the map reacts to the user's input. Can be set all at once with
+/// [`crate::Map::interaction`], or field by field with the map's other builder methods.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InteractionOptions {
+    /// Whether the map zooms in reaction to the mouse wheel, pinch, etc.
+    pub zoom_gesture: bool,
+
+    /// Whether the map can be dragged.
+    pub drag_gesture: bool,
+
+    /// How far to zoom in/out.
+    pub zoom_speed: f64,
+
+    pub zoom_sensitivity: ZoomSensitivity,
+
+    /// Zoom in with double click of the primary mouse button.
+    pub double_click_to_zoom: bool,
+
+    /// Zoom out with double click of the secondary mouse button.
+    pub double_click_to_zoom_out: bool,
+
+    /// Zoom by tapping twice and dragging vertically with the second tap, for one-handed use on
+    /// touch screens. Dragging down zooms in about the tapped point, dragging up zooms out.
+    pub double_tap_drag_zoom: bool,
+
+    /// Whether the map rotates by twisting two fingers on touch screens, or by dragging around
+    /// its center with the secondary mouse button.
+    pub rotate_gesture: bool,
+
+    /// While rotating, stick to the nearest cardinal direction when within this many degrees of
+    /// it. `None` disables snapping.
+    pub rotation_snap: Option<f64>,
+
+    /// How long, in seconds, the map must be pressed without moving to report a long press, the
+    /// touch counterpart of a secondary click. `None` disables it. See
+    /// [`crate::MapMemory::long_press`].
+    pub long_press: Option<f64>,
+
+    /// Zoom with the mouse wheel only while <kbd>ctrl</kbd> is held, and pan with it otherwise.
+    pub zoom_with_ctrl: bool,
+
+    /// Pan with arrow keys and zoom with <kbd>+</kbd> and <kbd>-</kbd> when focused.
+    pub keyboard_navigation: bool,
+
+    pub scroll_policy: ScrollPolicy,
+
+    /// What happens when the map is panned past the top or bottom of the world. Only Web
+    /// Mercator maps have such an edge.
+    pub world_bounds: WorldBounds,
+}
+
+impl Default for InteractionOptions {
+    fn default() -> Self {
+        Self {
+            zoom_gesture: true,
+            drag_gesture: true,
+            zoom_speed: 2.0,
+            zoom_sensitivity: ZoomSensitivity::default(),
+            double_click_to_zoom: false,
+            double_click_to_zoom_out: false,
+            double_tap_drag_zoom: false,
+            rotate_gesture: false,
+            rotation_snap: Some(10.),
+            long_press: Some(0.5),
+            zoom_with_ctrl: true,
+            keyboard_navigation: true,
+            scroll_policy: ScrollPolicy::default(),
+            world_bounds: WorldBounds::default(),
+        }
+    }
+}
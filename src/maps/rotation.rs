@@ -0,0 +1,118 @@
+use egui::{Align2, FontId, PointerButton, Rect, Response, Stroke, Ui};
+
+use crate::MapMemory;
+
+/// How long the snap indicator takes to fade, in seconds.
+const INDICATOR_FADE: f32 = 0.25;
+
+/// State of the rotation gesture in progress.
+#[derive(Clone, Copy, Default)]
+struct Rotating {
+    /// Bearing the gesture would set without snapping, in degrees.
+    bearing: f64,
+
+    /// Cardinal direction, in degrees, the bearing is snapped to.
+    snapped: Option<f64>,
+}
+
+/// Rotate the map by twisting two fingers, or by dragging around the map's center with the
+/// secondary button. Within `snap` degrees of a cardinal direction, the bearing sticks to it.
+/// Returns whether the map was rotated.
+pub(crate) fn handle_rotation(
+    ui: &Ui,
+    response: &Response,
+    memory: &mut MapMemory,
+    snap: Option<f64>,
+) -> bool {
+    let id = rotating_id(response);
+
+    // Radians, clockwise on the screen.
+    let delta = ui
+        .input(|i| i.multi_touch())
+        .map(|touch| touch.rotation_delta)
+        .or_else(|| {
+            response
+                .dragged_by(PointerButton::Secondary)
+                .then(|| response.interact_pointer_pos())
+                .flatten()
+                .map(|pos| {
+                    let to = pos - response.rect.center();
+                    let from = to - response.drag_delta();
+                    to.angle() - from.angle()
+                })
+        });
+
+    let Some(delta) = delta else {
+        ui.data_mut(|data| data.remove::<Rotating>(id));
+        return false;
+    };
+
+    let mut state = ui
+        .data(|data| data.get_temp::<Rotating>(id))
+        .unwrap_or(Rotating {
+            bearing: memory.bearing(),
+            snapped: None,
+        });
+
+    // Turning the content clockwise brings what was to the left up.
+    state.bearing = (state.bearing - (delta as f64).to_degrees()).rem_euclid(360.);
+    state.snapped = snap.and_then(|threshold| {
+        let cardinal = (state.bearing / 90.).round() * 90.;
+        ((state.bearing - cardinal).abs() <= threshold).then_some(cardinal % 360.)
+    });
+
+    ui.data_mut(|data| data.insert_temp(id, state));
+    memory.set_bearing(state.snapped.unwrap_or(state.bearing));
+    delta != 0.
+}
+
+/// Briefly show which cardinal direction the rotation snapped to.
+pub(crate) fn draw_snap_indicator(ui: &Ui, response: &Response, rect: Rect) {
+    let snapped = ui
+        .data(|data| data.get_temp::<Rotating>(rotating_id(response)))
+        .and_then(|state| state.snapped);
+    if let Some(snapped) = snapped {
+        ui.data_mut(|data| data.insert_temp(response.id.with("snapped_to"), snapped));
+    }
+
+    let opacity = ui.ctx().animate_bool_with_time(
+        response.id.with("snap"),
+        snapped.is_some(),
+        INDICATOR_FADE,
+    );
+    if opacity == 0. {
+        return;
+    }
+
+    let Some(cardinal) = ui.data(|data| data.get_temp::<f64>(response.id.with("snapped_to")))
+    else {
+        return;
+    };
+    let label = match cardinal as u32 {
+        90 => "E",
+        180 => "S",
+        270 => "W",
+        _ => "N",
+    };
+
+    let visuals = ui.visuals();
+    let center = rect.center_top() + egui::vec2(0., 24.);
+    let painter = ui.painter().with_clip_rect(rect);
+    painter.circle(
+        center,
+        12.,
+        visuals.extreme_bg_color.gamma_multiply(opacity),
+        Stroke::new(2., visuals.selection.bg_fill.gamma_multiply(opacity)),
+    );
+    painter.text(
+        center,
+        Align2::CENTER_CENTER,
+        label,
+        FontId::proportional(13.),
+        visuals.text_color().gamma_multiply(opacity),
+    );
+}
+
+fn rotating_id(response: &Response) -> egui::Id {
+    response.id.with("rotating")
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use egui::{Id, Modifiers, Response, Ui, Vec2};
+
+/// When the map takes the mouse wheel (or touchpad scroll) for itself. Maps placed inside a
+/// [`egui::ScrollArea`] may want to let the wheel scroll the page instead, unless the user
+/// clearly means to interact with the map.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollPolicy {
+    /// Whenever the pointer is over the map.
+    #[default]
+    Always,
+
+    /// Only while these modifiers are held, e.g. [`Modifiers::CTRL`].
+    WithModifiers(Modifiers),
+
+    /// Only when the map is focused, or the pointer has been resting on it for this long.
+    WhenFocusedOrHovered(Duration),
+}
+
+/// Whether the map should react to scrolling in this frame.
+pub(crate) fn captures_scroll(ui: &Ui, response: &Response, policy: ScrollPolicy) -> bool {
+    if !ui.ui_contains_pointer() {
+        forget_hover(ui, response);
+        return false;
+    }
+
+    // Pinching and two-finger panning on touch screens is meant for the map.
+    if ui.input(|i| i.any_touches()) {
+        return true;
+    }
+
+    match policy {
+        ScrollPolicy::Always => true,
+        ScrollPolicy::WithModifiers(modifiers) => ui.input(|i| i.modifiers.contains(modifiers)),
+        ScrollPolicy::WhenFocusedOrHovered(delay) => {
+            let now = ui.input(|i| i.time);
+            let hover_started = ui.data_mut(|data| *data.get_temp_mut_or(hover_id(response), now));
+            let hovered_long_enough = now - hover_started >= delay.as_secs_f64();
+
+            if !hovered_long_enough {
+                ui.ctx()
+                    .request_repaint_after_secs((hover_started + delay.as_secs_f64() - now) as f32);
+            }
+
+            response.has_focus() || hovered_long_enough
+        }
+    }
+}
+
+/// Take scrolling away from the input, so that a surrounding [`egui::ScrollArea`] does not scroll
+/// along with the map.
+pub(crate) fn consume_scroll(ui: &Ui) -> bool {
+    ui.input_mut(|i| {
+        let consumed = i.smooth_scroll_delta != Vec2::ZERO || i.raw_scroll_delta != Vec2::ZERO;
+        i.smooth_scroll_delta = Vec2::ZERO;
+        i.raw_scroll_delta = Vec2::ZERO;
+        consumed
+    })
+}
+
+fn forget_hover(ui: &Ui, response: &Response) {
+    ui.data_mut(|data| data.remove::<f64>(hover_id(response)));
+}
+
+fn hover_id(response: &Response) -> Id {
+    response.id.with("scroll_hover_started")
+}
@@ -0,0 +1,90 @@
+use egui::{vec2, Context, Rect};
+
+use crate::{
+    animation::{frame_time, reduced_motion},
+    center::Center,
+    projector::ProjectorType,
+    total_pixels,
+    units::PositionTrait,
+    MapMemory, Position,
+};
+
+/// How quickly the map springs back after being pulled past the world's edge, per second.
+const BOUNCE_RATE: f32 = 12.;
+
+/// How far past the world's edge the map can be pulled, as a fraction of the viewport's height.
+const MAX_STRETCH: f32 = 0.25;
+
+/// What happens when the map is panned past the top or bottom of the Web Mercator world, which
+/// would otherwise reveal the void beyond the poles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WorldBounds {
+    /// Nothing, the map can be panned anywhere.
+    #[default]
+    Free,
+
+    /// The map stops at the edge.
+    Clamp,
+
+    /// The map can be pulled a bit past the edge, and springs back when released. Behaves like
+    /// [`WorldBounds::Clamp`] when reduced motion is requested.
+    Bounce,
+}
+
+/// Move the map's center back within the world, if needed. Returns whether it moved.
+pub(crate) fn keep_within_world(
+    ctx: &Context,
+    rect: Rect,
+    memory: &mut MapMemory,
+    my_position: Position,
+    bounds: WorldBounds,
+) -> bool {
+    if bounds == WorldBounds::Free
+        || !matches!(memory.projection_type, ProjectorType::Global)
+        || matches!(memory.center_mode, Center::MyPosition)
+    {
+        return false;
+    }
+
+    let zoom = memory.zoom();
+    let center = memory
+        .camera(my_position)
+        .center()
+        .global_bitmap_project(zoom)
+        .y as f32;
+
+    // How much of the flat map is visible above and below the center.
+    let area = memory.screen_transform().visible_area(rect);
+    let (above, below) = (
+        rect.center().y - area.top(),
+        area.bottom() - rect.center().y,
+    );
+    let (top, bottom) = (above, total_pixels(zoom) as f32 - below);
+    let target = if top > bottom {
+        // The whole world fits, so keep it in the middle.
+        (top + bottom) / 2.
+    } else {
+        center.clamp(top, bottom)
+    };
+
+    let overshoot = center - target;
+    if overshoot.abs() < 0.5 {
+        return false;
+    }
+
+    let correction = match bounds {
+        WorldBounds::Bounce if !reduced_motion(ctx) => {
+            if matches!(memory.center_mode, Center::Moving { .. }) {
+                let max = rect.height() * MAX_STRETCH;
+                overshoot - overshoot.clamp(-max, max)
+            } else {
+                ctx.request_repaint();
+                overshoot * (BOUNCE_RATE * frame_time(ctx)).min(1.)
+            }
+        }
+        _ => overshoot,
+    };
+
+    memory.center_mode = memory.center_mode.clone().shift(vec2(0., correction));
+    correction != 0.
+}
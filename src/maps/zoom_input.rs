@@ -0,0 +1,113 @@
+use egui::{PointerButton, Pos2, Response, Ui, Vec2};
+
+/// How fast each kind of input device zooms the map, relative to the map's `zoom_speed`. One
+/// speed rarely feels right on every device, e.g. trackpads send many small zoom steps.
+///
+/// Devices are told apart by the input egui receives, which is not always possible. In
+/// particular, some browsers report trackpad pinch as a mouse wheel with <kbd>ctrl</kbd> held.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZoomSensitivity {
+    /// Mouse wheel, with <kbd>ctrl</kbd> held unless zooming without it is enabled.
+    pub wheel: f64,
+
+    /// Pinch on a trackpad.
+    pub trackpad: f64,
+
+    /// Pinch on a touch screen.
+    pub touch: f64,
+}
+
+impl Default for ZoomSensitivity {
+    fn default() -> Self {
+        Self {
+            wheel: 1.,
+            trackpad: 1.,
+            touch: 1.,
+        }
+    }
+}
+
+impl ZoomSensitivity {
+    /// Multiplier for egui's zoom delta in this frame.
+    pub(crate) fn for_zoom_delta(&self, ui: &Ui) -> f64 {
+        ui.input(|i| {
+            if i.any_touches() {
+                self.touch
+            } else if i.modifiers.ctrl || i.modifiers.command {
+                // Egui turns scrolling with ctrl held into zooming.
+                self.wheel
+            } else {
+                self.trackpad
+            }
+        })
+    }
+}
+
+/// Longest time between the tap and the next press, in seconds.
+const DOUBLE_TAP_TIME: f64 = 0.3;
+
+/// Largest distance between the tap and the next press, in points.
+const DOUBLE_TAP_DISTANCE: f32 = 30.;
+
+/// Zoom levels per point of vertical drag.
+const DRAG_ZOOM_PER_POINT: f64 = 1. / 100.;
+
+#[derive(Clone, Copy)]
+enum DoubleTapDrag {
+    Tapped { time: f64, pos: Pos2 },
+    Zooming { anchor: Pos2 },
+}
+
+/// Follow the "double tap, then drag vertically" gesture, which zooms in when dragging down and
+/// out when dragging up, about the tapped point. While it lasts, returns the anchor, relative to
+/// the map's center, and the change of zoom in this frame.
+pub(crate) fn double_tap_drag_zoom(ui: &Ui, response: &Response) -> Option<(Vec2, f64)> {
+    let id = response.id.with("double_tap_drag_zoom");
+    let state = ui.data(|data| data.get_temp::<DoubleTapDrag>(id));
+    let (time, press, press_time) = ui.input(|i| {
+        (
+            i.time,
+            i.pointer.press_origin(),
+            i.pointer.press_start_time(),
+        )
+    });
+    let soon_after = |tapped: f64| {
+        time - tapped < DOUBLE_TAP_TIME
+            || press_time.is_some_and(|pressed| pressed - tapped < DOUBLE_TAP_TIME)
+    };
+
+    let next = match state {
+        Some(DoubleTapDrag::Zooming { anchor }) if response.dragged_by(PointerButton::Primary) => {
+            Some(DoubleTapDrag::Zooming { anchor })
+        }
+        Some(DoubleTapDrag::Tapped { time: tapped, pos })
+            if response.drag_started_by(PointerButton::Primary)
+                && soon_after(tapped)
+                && press.is_some_and(|press| press.distance(pos) < DOUBLE_TAP_DISTANCE) =>
+        {
+            Some(DoubleTapDrag::Zooming { anchor: pos })
+        }
+        _ => response
+            .clicked_by(PointerButton::Primary)
+            .then(|| response.interact_pointer_pos())
+            .flatten()
+            .map(|pos| DoubleTapDrag::Tapped { time, pos })
+            .or(state.filter(|state| match state {
+                DoubleTapDrag::Tapped { time: tapped, .. } => soon_after(*tapped),
+                DoubleTapDrag::Zooming { .. } => false,
+            })),
+    };
+
+    ui.data_mut(|data| match next {
+        Some(next) => data.insert_temp(id, next),
+        None => data.remove::<DoubleTapDrag>(id),
+    });
+
+    match next {
+        Some(DoubleTapDrag::Zooming { anchor }) => Some((
+            anchor - response.rect.center(),
+            response.drag_delta().y as f64 * DRAG_ZOOM_PER_POINT,
+        )),
+        _ => None,
+    }
+}
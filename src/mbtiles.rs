@@ -0,0 +1,296 @@
+//! Tiles read from [MBTiles](https://github.com/mapbox/mbtiles-spec) files.
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use egui::{pos2, ColorImage, Context, Rect};
+use futures::{
+    channel::mpsc::{channel, Receiver, Sender},
+    SinkExt, StreamExt,
+};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+
+use crate::{
+    download::{Repaint, MAX_PARALLEL_DOWNLOADS},
+    io::Runtime,
+    sources::Attribution,
+    tiles::{decode, LoadedTiles},
+    Texture, TextureWithUv, TileId, Tiles,
+};
+
+type TileResult = (TileId, Result<Option<ColorImage>, String>);
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("could not open MBTiles file {path}: {reason}")]
+pub struct MbTilesError {
+    pub path: String,
+    pub reason: String,
+}
+
+/// [`Tiles`] read from an MBTiles file, i.e. an SQLite archive of raster tiles, which is how
+/// offline maps are commonly shipped. Tiles are read and decoded as the map needs them, in an
+/// IO thread, without going through the network or [`crate::HttpTiles`]. Like them, it must
+/// persist between frames.
+pub struct MbTiles {
+    connection: Arc<Mutex<Connection>>,
+    egui_ctx: Context,
+    /// `None` for tiles which the file does not have.
+    tiles: LoadedTiles<Option<Texture>>,
+    attribution: Attribution,
+    tile_size: u32,
+    /// Path of the file.
+    source_id: String,
+    request_tx: Sender<TileId>,
+    tile_rx: Receiver<TileResult>,
+
+    #[allow(dead_code)] // Significant Drop
+    runtime: Runtime,
+}
+
+impl MbTiles {
+    /// Open the file for reading.
+    pub fn open(path: impl AsRef<Path>, egui_ctx: Context) -> Result<Self, MbTilesError> {
+        let path = path.as_ref();
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .and_then(|connection| {
+                // Fail early if it is not an MBTiles file.
+                connection.prepare("SELECT tile_data FROM tiles LIMIT 1")?;
+                Ok(connection)
+            })
+            .map_err(|err| MbTilesError {
+                path: path.display().to_string(),
+                reason: err.to_string(),
+            })?;
+
+        let connection = Arc::new(Mutex::new(connection));
+        let (request_tx, request_rx) = channel(MAX_PARALLEL_DOWNLOADS);
+        let (tile_tx, tile_rx) = channel(MAX_PARALLEL_DOWNLOADS);
+
+        // IO thread does not touch egui, other than waking it up.
+        let ctx = egui_ctx.clone();
+        let repaint: Repaint = Box::new(move || ctx.request_repaint());
+        let runtime = Runtime::new(read_continuously(
+            connection.clone(),
+            request_rx,
+            tile_tx,
+            repaint,
+        ));
+
+        Ok(Self {
+            connection,
+            egui_ctx,
+            tiles: LoadedTiles::new(),
+            attribution: Attribution {
+                text: "",
+                url: "",
+                logo_light: None,
+                logo_dark: None,
+            },
+            tile_size: crate::TILE_SIZE,
+            source_id: path.display().to_string(),
+            request_tx,
+            tile_rx,
+            runtime,
+        })
+    }
+
+    /// Attribution of the tiles, as required by their license. MBTiles keep it in their metadata,
+    /// but as HTML, which the map cannot show.
+    pub fn attribution(mut self, text: &'static str, url: &'static str) -> Self {
+        self.attribution.text = text;
+        self.attribution.url = url;
+        self
+    }
+
+    /// Size of the tiles in the file. Default is 256.
+    pub fn tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// Value from the file's metadata, e.g. `name` or `bounds`.
+    pub fn metadata(&self, name: &str) -> Option<String> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .query_row(
+                "SELECT value FROM metadata WHERE name = ?1",
+                [name],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    /// Upload the tiles which were read in the meantime.
+    fn receive(&mut self) {
+        while let Ok((tile_id, result)) = self.tile_rx.try_recv() {
+            match result {
+                Ok(image) => {
+                    let texture =
+                        image.map(|image| Texture::from_color_image(image, &self.egui_ctx));
+                    self.tiles.loaded(tile_id, texture);
+                }
+                Err(error) => self.tiles.failed(tile_id, error),
+            }
+        }
+    }
+}
+
+/// Row of the tile in the file. Rows are numbered from the south, as in TMS. `None` if the tile
+/// is not on the map.
+fn tms_row(tile_id: TileId) -> Option<u32> {
+    1u32.checked_shl(tile_id.zoom.into())?
+        .checked_sub(1)?
+        .checked_sub(tile_id.y)
+}
+
+/// Read and decode the tile, or `None` if the file does not have it.
+fn read(connection: &Mutex<Connection>, tile_id: TileId) -> Result<Option<ColorImage>, String> {
+    let Some(row) = tms_row(tile_id) else {
+        return Ok(None);
+    };
+
+    let data = connection
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .query_row(
+            "SELECT tile_data FROM tiles \
+             WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+            (tile_id.zoom, tile_id.x, row),
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+        .optional()
+        .map_err(|err| err.to_string())?;
+
+    data.map(|data| decode(&data).map_err(|err| err.to_string()))
+        .transpose()
+}
+
+async fn read_continuously(
+    connection: Arc<Mutex<Connection>>,
+    request_rx: Receiver<TileId>,
+    tile_tx: Sender<TileResult>,
+    repaint: Repaint,
+) {
+    request_rx
+        .map(|tile_id| {
+            let connection = connection.clone();
+            async move {
+                // Keep the runtime free for other tiles while waiting for the disk.
+                let result = tokio::task::spawn_blocking(move || read(&connection, tile_id))
+                    .await
+                    .unwrap_or_else(|err| Err(err.to_string()));
+                (tile_id, result)
+            }
+        })
+        .buffer_unordered(MAX_PARALLEL_DOWNLOADS)
+        .for_each(|(tile_id, result)| {
+            let mut tile_tx = tile_tx.clone();
+            let repaint = &repaint;
+            async move {
+                if let Err(error) = &result {
+                    log::warn!("{:?}: {}", tile_id, error);
+                }
+                // Main thread is gone if this fails, nothing to do about it.
+                let _ = tile_tx.send((tile_id, result)).await;
+                repaint();
+            }
+        })
+        .await;
+}
+
+impl Tiles for MbTiles {
+    fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+        self.receive();
+
+        let request_tx = &mut self.request_tx;
+        let texture = self
+            .tiles
+            .get_or_request(tile_id, || request_tx.try_send(tile_id).is_ok())
+            .flatten();
+
+        texture.map(|texture| TextureWithUv {
+            texture,
+            uv: Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+        })
+    }
+
+    fn attribution(&self) -> Attribution {
+        self.attribution.clone()
+    }
+
+    fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        self.tiles.take_errors()
+    }
+
+    fn source_id(&self) -> String {
+        self.source_id.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(zoom: u8, x: u32, y: u32) -> TileId {
+        TileId { x, y, zoom }
+    }
+
+    fn png() -> Vec<u8> {
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    fn connection() -> Mutex<Connection> {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, \
+                 tile_row INTEGER, tile_data BLOB);",
+            )
+            .unwrap();
+        let mut insert = connection
+            .prepare("INSERT INTO tiles VALUES (?1, ?2, ?3, ?4)")
+            .unwrap();
+        insert.execute((1, 0, 1, png())).unwrap();
+        insert.execute((1, 1, 1, b"not an image".to_vec())).unwrap();
+        drop(insert);
+        Mutex::new(connection)
+    }
+
+    #[test]
+    fn rows() {
+        assert_eq!(tms_row(tile(0, 0, 0)), Some(0));
+        assert_eq!(tms_row(tile(1, 0, 0)), Some(1));
+        assert_eq!(tms_row(tile(3, 2, 5)), Some(2));
+        assert_eq!(tms_row(tile(31, 0, 0)), Some((1 << 31) - 1));
+        assert_eq!(tms_row(tile(1, 0, 2)), None);
+        assert_eq!(tms_row(tile(32, 0, 0)), None);
+        assert_eq!(tms_row(tile(255, 0, 0)), None);
+    }
+
+    #[test]
+    fn reading() {
+        let connection = connection();
+
+        // Row 1 is the northern one at zoom 1.
+        let image = read(&connection, tile(1, 0, 0)).unwrap().unwrap();
+        assert_eq!(image.size, [2, 2]);
+        assert_eq!(image.pixels[0], egui::Color32::RED);
+
+        assert_eq!(read(&connection, tile(1, 0, 1)), Ok(None));
+        assert_eq!(read(&connection, tile(40, 0, 0)), Ok(None));
+        assert!(read(&connection, tile(1, 1, 0)).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use egui::{Color32, ColorImage, Context};
+
+use crate::Texture;
+
+/// What is drawn in place of a tile which is not available yet. See
+/// [`crate::HttpTiles::set_placeholder`].
+#[derive(Clone, Default)]
+pub enum Placeholder {
+    /// Nothing, so that whatever is below the tiles shows through.
+    None,
+
+    /// Part of a tile of a lower zoom level, upscaled and therefore blurry, if one is in the
+    /// cache. Nothing otherwise.
+    #[default]
+    Parent,
+
+    /// Solid color.
+    Color(Color32),
+
+    /// Checkerboard of two colors, making it obvious that the tile is missing.
+    Checkerboard { light: Color32, dark: Color32 },
+
+    /// User-provided image, stretched over the tile.
+    Texture(Texture),
+}
+
+impl Placeholder {
+    /// Texture to draw in place of missing tiles, if it does not depend on the tile.
+    pub(crate) fn texture(&self, ctx: &Context) -> Option<Texture> {
+        match self {
+            Placeholder::None | Placeholder::Parent => None,
+            Placeholder::Color(color) => Some(Texture::from_color_image(
+                ColorImage::new([1, 1], *color),
+                ctx,
+            )),
+            Placeholder::Checkerboard { light, dark } => {
+                Some(Texture::from_color_image(checkerboard(*light, *dark), ctx))
+            }
+            Placeholder::Texture(texture) => Some(texture.clone()),
+        }
+    }
+}
+
+/// Image of 8 by 8 squares.
+fn checkerboard(light: Color32, dark: Color32) -> ColorImage {
+    const SIZE: usize = 64;
+    const SQUARE: usize = SIZE / 8;
+
+    let pixels = (0..SIZE * SIZE)
+        .map(|i| {
+            let (x, y) = (i % SIZE / SQUARE, i / SIZE / SQUARE);
+            if (x + y) % 2 == 0 {
+                light
+            } else {
+                dark
+            }
+        })
+        .collect();
+
+    ColorImage {
+        size: [SIZE, SIZE],
+        pixels,
+    }
+}
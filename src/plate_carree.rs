@@ -0,0 +1,135 @@
+use crate::{BoundingBox, Position, Projection, TileId};
+
+const EARTH_CIRCUMFERENCE: f64 = 40_075_016.686;
+
+/// Equirectangular projection (EPSG:4326), in which longitude and latitude map directly to x and
+/// y. Many WMS servers only serve imagery in it. Set it with [`crate::Map::projection`].
+///
+/// The map is a square spanning 360° on both axes, with the world in its upper half. This makes
+/// tiles at zoom `z` the same as those at level `z - 1` of the usual EPSG:4326 tile matrix, which
+/// starts with two tiles side by side, so the source's zoom needs to be shifted accordingly.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlateCarree;
+
+impl PlateCarree {
+    /// Tile of this projection's grid, which contains the position.
+    pub fn tile_id(&self, position: Position, zoom: u8) -> TileId {
+        crate::units::tile_id_of_normalized(self.normalize(position), zoom, 0)
+    }
+
+    /// Geographical area covered by the tile, e.g. for the `BBOX` of a WMS `GetMap` request.
+    pub fn tile_bounds(&self, tile_id: TileId) -> BoundingBox {
+        let number_of_tiles = 2u32.pow(tile_id.zoom as u32) as f64;
+        let corner = |x: u32, y: u32| {
+            self.denormalize(x as f64 / number_of_tiles, y as f64 / number_of_tiles)
+        };
+        BoundingBox::new(
+            corner(tile_id.x, tile_id.y),
+            corner(tile_id.x + 1, tile_id.y + 1),
+        )
+    }
+}
+
+impl Projection for PlateCarree {
+    fn normalize(&self, position: Position) -> (f64, f64) {
+        ((position.x + 180.) / 360., (90. - position.y) / 360.)
+    }
+
+    fn denormalize(&self, x: f64, y: f64) -> Position {
+        Position {
+            x: x * 360. - 180.,
+            y: 90. - y * 360.,
+        }
+    }
+
+    fn ground_width(&self, position: Position) -> f64 {
+        EARTH_CIRCUMFERENCE * position.y.to_radians().cos()
+    }
+
+    fn has_tile(&self, tile_id: TileId) -> bool {
+        // Lower half of the map is below the south pole.
+        tile_id.zoom == 0 || tile_id.y < 2u32.pow(tile_id.zoom as u32 - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(zoom: u8, x: u32, y: u32) -> TileId {
+        TileId { x, y, zoom }
+    }
+
+    fn corners(bounds: BoundingBox) -> [f64; 4] {
+        [
+            bounds.min().x,
+            bounds.min().y,
+            bounds.max().x,
+            bounds.max().y,
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for (lon, lat) in [
+            (0., 0.),
+            (-180., 90.),
+            (180., -90.),
+            (21.01, 52.23),
+            (-73.98, 40.75),
+        ] {
+            let position = Position { x: lon, y: lat };
+            let (x, y) = PlateCarree.normalize(position);
+            let back = PlateCarree.denormalize(x, y);
+            assert!((back.x - lon).abs() < 1e-12 && (back.y - lat).abs() < 1e-12);
+        }
+
+        // The world is in the upper half of the square map.
+        assert_eq!(
+            PlateCarree.normalize(Position { x: -180., y: 90. }),
+            (0., 0.)
+        );
+        assert_eq!(
+            PlateCarree.normalize(Position { x: 180., y: -90. }),
+            (1., 0.5)
+        );
+    }
+
+    #[test]
+    fn tile_bounds() {
+        // Single tile of zoom 0 reaches below the south pole.
+        assert_eq!(
+            corners(PlateCarree.tile_bounds(tile(0, 0, 0))),
+            [-180., -270., 180., 90.]
+        );
+
+        // Tiles of zoom 1 are the two of the first level of the EPSG:4326 tile matrix.
+        assert_eq!(
+            corners(PlateCarree.tile_bounds(tile(1, 0, 0))),
+            [-180., -90., 0., 90.]
+        );
+        assert_eq!(
+            corners(PlateCarree.tile_bounds(tile(1, 1, 0))),
+            [0., -90., 180., 90.]
+        );
+        assert_eq!(
+            corners(PlateCarree.tile_bounds(tile(2, 3, 1))),
+            [90., -90., 180., 0.]
+        );
+
+        assert_eq!(
+            PlateCarree.tile_id(Position { x: 21.01, y: 52.23 }, 1),
+            tile(1, 1, 0)
+        );
+    }
+
+    #[test]
+    fn has_tile() {
+        assert!(PlateCarree.has_tile(tile(0, 0, 0)));
+        assert!(PlateCarree.has_tile(tile(1, 1, 0)));
+        assert!(!PlateCarree.has_tile(tile(1, 0, 1)));
+        assert!(PlateCarree.has_tile(tile(2, 3, 1)));
+        assert!(!PlateCarree.has_tile(tile(2, 0, 2)));
+        assert!(!PlateCarree.has_tile(tile(2, 0, 3)));
+    }
+}
@@ -0,0 +1,285 @@
+//! Polar stereographic projection on the WGS 84 ellipsoid, after Snyder's "Map Projections: A
+//! Working Manual".
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use crate::{Position, Projection, TileId};
+
+const A: f64 = 6378137.;
+const E: f64 = 0.081_819_190_842_622;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pole {
+    North,
+    South,
+}
+
+/// Polar stereographic projection, for maps of the Arctic and Antarctic, where Web Mercator does
+/// not reach. Set it with [`crate::Map::projection`].
+///
+/// Tiles form a square grid over `-extent..extent` meters on both axes, with a single tile at
+/// zoom 0 and four times as many with each next level, like in Web Mercator. Tile sets with more
+/// tiles at their first level need the zoom shifted in [`crate::sources::TileSource::tile_url`],
+/// e.g. NASA GIBS EPSG:3413 tile matrices start with 2x2 tiles, so their level is `zoom - 1`
+/// for 512 px tiles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PolarStereographic {
+    pub pole: Pole,
+
+    /// Latitude of true scale, in degrees, e.g. 70 for EPSG:3413. Use ±90 for a projection true
+    /// at the pole.
+    pub standard_parallel: f64,
+
+    /// Longitude which points down from the north pole (or up from the south one), in degrees.
+    pub central_meridian: f64,
+
+    /// Half of the width of the tile grid, in meters.
+    pub extent: f64,
+}
+
+impl PolarStereographic {
+    /// NSIDC Sea Ice Polar Stereographic North, as used by NASA GIBS Arctic tiles.
+    pub fn epsg3413() -> Self {
+        Self {
+            pole: Pole::North,
+            standard_parallel: 70.,
+            central_meridian: -45.,
+            extent: 4_194_304.,
+        }
+    }
+
+    /// Antarctic Polar Stereographic, as used by NASA GIBS Antarctic tiles.
+    pub fn epsg3031() -> Self {
+        Self {
+            pole: Pole::South,
+            standard_parallel: -71.,
+            central_meridian: 0.,
+            extent: 4_194_304.,
+        }
+    }
+
+    /// Projected coordinates of the geographical position, in meters.
+    pub fn forward(&self, position: Position) -> Position {
+        let sign = self.sign();
+        let phi = (sign * position.y).to_radians();
+        let delta = (sign * (position.x - self.central_meridian)).to_radians();
+        let rho = self.rho(phi);
+
+        Position {
+            x: sign * rho * delta.sin(),
+            y: -sign * rho * delta.cos(),
+        }
+    }
+
+    /// Geographical position of the projected coordinates, given in meters.
+    pub fn inverse(&self, position: Position) -> Position {
+        let sign = self.sign();
+        let (x, y) = (sign * position.x, sign * position.y);
+        let rho = x.hypot(y);
+        let t = rho / self.rho_per_t();
+
+        // Latitude converges within a few iterations, starting from the conformal one.
+        let mut phi = FRAC_PI_2 - 2. * t.atan();
+        for _ in 0..8 {
+            let esin = E * phi.sin();
+            phi = FRAC_PI_2 - 2. * (t * ((1. - esin) / (1. + esin)).powf(E / 2.)).atan();
+        }
+
+        let lon = sign * x.atan2(-y).to_degrees() + self.central_meridian;
+        Position {
+            x: (lon + 180.).rem_euclid(360.) - 180.,
+            y: sign * phi.to_degrees(),
+        }
+    }
+
+    /// Ratio of the projected distance to the one on the ground, at given latitude.
+    pub fn scale_factor(&self, lat: f64) -> f64 {
+        let phi = (self.sign() * lat).to_radians();
+        if (phi - FRAC_PI_2).abs() < 1e-10 {
+            // Limit of the formula below, which is 0/0 at the pole itself.
+            self.rho_per_t() * ((1. + E).powf(1. + E) * (1. - E).powf(1. - E)).sqrt() / (2. * A)
+        } else {
+            self.rho(phi) / (A * m(phi))
+        }
+    }
+
+    /// Tile of this projection's grid, which contains the position.
+    pub fn tile_id(&self, position: Position, zoom: u8) -> TileId {
+        crate::units::tile_id_of_normalized(self.normalize(position), zoom, 0)
+    }
+
+    fn sign(&self) -> f64 {
+        match self.pole {
+            Pole::North => 1.,
+            Pole::South => -1.,
+        }
+    }
+
+    fn rho(&self, phi: f64) -> f64 {
+        self.rho_per_t() * t(phi)
+    }
+
+    /// Distance from the pole per unit of `t`, which depends only on the standard parallel.
+    fn rho_per_t(&self) -> f64 {
+        let phi_c = (self.sign() * self.standard_parallel).to_radians();
+        if (phi_c - FRAC_PI_2).abs() < 1e-10 {
+            2. * A / ((1. + E).powf(1. + E) * (1. - E).powf(1. - E)).sqrt()
+        } else {
+            A * m(phi_c) / t(phi_c)
+        }
+    }
+}
+
+impl Projection for PolarStereographic {
+    fn normalize(&self, position: Position) -> (f64, f64) {
+        let projected = self.forward(position);
+        (
+            (projected.x + self.extent) / (2. * self.extent),
+            (self.extent - projected.y) / (2. * self.extent),
+        )
+    }
+
+    fn denormalize(&self, x: f64, y: f64) -> Position {
+        self.inverse(Position {
+            x: x * 2. * self.extent - self.extent,
+            y: self.extent - y * 2. * self.extent,
+        })
+    }
+
+    fn ground_width(&self, position: Position) -> f64 {
+        2. * self.extent / self.scale_factor(position.y)
+    }
+}
+
+fn t(phi: f64) -> f64 {
+    let esin = E * phi.sin();
+    (FRAC_PI_4 - phi / 2.).tan() / ((1. - esin) / (1. + esin)).powf(E / 2.)
+}
+
+fn m(phi: f64) -> f64 {
+    phi.cos() / (1. - (E * phi.sin()).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Position, b: Position, tolerance: f64) {
+        assert!(
+            (a.x - b.x).abs() < tolerance && (a.y - b.y).abs() < tolerance,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for projection in [
+            PolarStereographic::epsg3413(),
+            PolarStereographic::epsg3031(),
+        ] {
+            let sign = projection.sign();
+            for (lon, lat) in [
+                (0., 89.9),
+                (-45., 70.),
+                (120., 60.),
+                (-170., 45.),
+                (179., 80.),
+            ] {
+                let position = Position {
+                    x: lon,
+                    y: sign * lat,
+                };
+                let projected = projection.forward(position);
+                assert_near(projection.inverse(projected), position, 1e-9);
+
+                let (x, y) = projection.normalize(position);
+                assert_near(projection.denormalize(x, y), position, 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn south_pole_reference() {
+        // Example of Polar Stereographic (variant B) from EPSG Guidance Note 7-2, which only
+        // differs from EPSG:3031 by the central meridian of 70°E, and false easting and northing
+        // of 6000000 m.
+        let projection = PolarStereographic {
+            central_meridian: 70.,
+            ..PolarStereographic::epsg3031()
+        };
+        let projected = projection.forward(Position { x: 120., y: -75. });
+        assert_near(
+            projected,
+            Position {
+                x: 1_255_380.79,
+                y: 1_053_389.56,
+            },
+            0.01,
+        );
+
+        // Same offset from the central meridian in EPSG:3031 itself.
+        let projected = PolarStereographic::epsg3031().forward(Position { x: 50., y: -75. });
+        assert_near(
+            projected,
+            Position {
+                x: 1_255_380.79,
+                y: 1_053_389.56,
+            },
+            0.01,
+        );
+    }
+
+    #[test]
+    fn north_pole_reference() {
+        // Corners of NSIDC's polar stereographic north grid, as published to two decimals.
+        let projection = PolarStereographic::epsg3413();
+        assert_near(
+            projection.inverse(Position {
+                x: -3_850_000.,
+                y: 5_850_000.,
+            }),
+            Position {
+                x: 168.35,
+                y: 30.98,
+            },
+            0.01,
+        );
+        assert_near(
+            projection.inverse(Position {
+                x: 3_750_000.,
+                y: -5_350_000.,
+            }),
+            Position {
+                x: 350.03 - 360.,
+                y: 34.35,
+            },
+            0.01,
+        );
+
+        assert_near(
+            projection.forward(Position { x: 0., y: 90. }),
+            Position { x: 0., y: 0. },
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn scale_factor() {
+        let north = PolarStereographic::epsg3413();
+        assert!((north.scale_factor(70.) - 1.).abs() < 1e-12);
+        // Larger than 1 away from the true-scale latitude, smaller than 1 towards the pole.
+        assert!(north.scale_factor(60.) > 1.);
+        assert!(north.scale_factor(90.) < 1.);
+
+        let south = PolarStereographic::epsg3031();
+        assert!((south.scale_factor(-71.) - 1.).abs() < 1e-12);
+
+        // True at the pole itself.
+        let pole = PolarStereographic {
+            standard_parallel: 90.,
+            ..north
+        };
+        assert!((pole.scale_factor(90.) - 1.).abs() < 1e-12);
+        assert!(pole.scale_factor(80.) > 1.);
+    }
+}
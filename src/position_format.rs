@@ -0,0 +1,156 @@
+use crate::Position;
+
+/// How positions are shown to the user, e.g. in readouts of plugins. Set it once with
+/// [`crate::Map::position_format`] and use [`crate::Projector::format_position`] to honor it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PositionFormat {
+    /// `52.22977, 21.01178`
+    #[default]
+    DecimalDegrees,
+
+    /// `52°13'47"N 21°0'42"E`
+    DegreesMinutesSeconds,
+
+    /// Universal Transverse Mercator, e.g. `34U 500805 5786594`. Falls back to decimal degrees
+    /// near the poles, which UTM does not cover.
+    Utm,
+
+    /// Military Grid Reference System with 1 m precision, e.g. `34U EC 00804 86594`. Falls back
+    /// to decimal degrees near the poles.
+    Mgrs,
+
+    /// Plain `x, y`, as used by local maps.
+    Local,
+}
+
+impl PositionFormat {
+    pub fn format(&self, position: Position) -> String {
+        match self {
+            PositionFormat::DecimalDegrees => decimal_degrees(position),
+            PositionFormat::DegreesMinutesSeconds => format!(
+                "{} {}",
+                dms(position.y, ['N', 'S']),
+                dms(position.x, ['E', 'W'])
+            ),
+            PositionFormat::Utm => utm(position)
+                .map(|utm| {
+                    format!(
+                        "{}{} {:.0} {:.0}",
+                        utm.zone, utm.band, utm.easting, utm.northing
+                    )
+                })
+                .unwrap_or_else(|| decimal_degrees(position)),
+            PositionFormat::Mgrs => utm(position)
+                .map(|utm| mgrs(&utm))
+                .unwrap_or_else(|| decimal_degrees(position)),
+            PositionFormat::Local => format!("{:.2}, {:.2}", position.x, position.y),
+        }
+    }
+}
+
+fn decimal_degrees(position: Position) -> String {
+    format!("{:.5}, {:.5}", position.y, position.x)
+}
+
+fn dms(degrees: f64, hemispheres: [char; 2]) -> String {
+    let hemisphere = if degrees < 0. {
+        hemispheres[1]
+    } else {
+        hemispheres[0]
+    };
+    let total_seconds = (degrees.abs() * 3600.).round() as u64;
+    format!(
+        "{}°{}'{}\"{}",
+        total_seconds / 3600,
+        total_seconds / 60 % 60,
+        total_seconds % 60,
+        hemisphere
+    )
+}
+
+struct Utm {
+    zone: u8,
+    band: char,
+    easting: f64,
+    northing: f64,
+}
+
+fn utm(position: Position) -> Option<Utm> {
+    let band = crate::utm::band(position.y)?;
+    let zone = crate::utm::zone(position);
+    let (easting, northing, _) = crate::utm::forward(position, zone);
+
+    Some(Utm {
+        zone,
+        band,
+        easting,
+        northing: crate::utm::with_false_northing(northing),
+    })
+}
+
+fn mgrs(utm: &Utm) -> String {
+    const COLUMNS: [&[u8]; 3] = [b"STUVWXYZ", b"ABCDEFGH", b"JKLMNPQR"];
+    const ROWS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+
+    let (easting, northing) = (utm.easting.floor() as u64, utm.northing.floor() as u64);
+    let columns = COLUMNS[utm.zone as usize % 3];
+    let column = columns[((easting / 100_000) as usize).clamp(1, 8) - 1] as char;
+    let row_offset = if utm.zone % 2 == 0 { 5 } else { 0 };
+    let row = ROWS[(northing / 100_000 % 20 + row_offset) as usize % 20] as char;
+
+    format!(
+        "{}{} {}{} {:05} {:05}",
+        utm.zone,
+        utm.band,
+        column,
+        row,
+        easting % 100_000,
+        northing % 100_000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lat_lon;
+
+    #[test]
+    fn formats() {
+        let warsaw = pos_from_lat_lon(52.22977, 21.01178);
+        let format = |format: PositionFormat| format.format(warsaw);
+
+        assert_eq!(format(PositionFormat::DecimalDegrees), "52.22977, 21.01178");
+        assert_eq!(
+            format(PositionFormat::DegreesMinutesSeconds),
+            "52°13'47\"N 21°0'42\"E"
+        );
+        assert_eq!(format(PositionFormat::Utm), "34U 500805 5786594");
+        assert_eq!(format(PositionFormat::Mgrs), "34U EC 00804 86594");
+        assert_eq!(format(PositionFormat::Local), "21.01, 52.23");
+    }
+
+    #[test]
+    fn southern_and_western_hemispheres() {
+        let position = pos_from_lat_lon(-33.5, -70.75);
+        assert_eq!(
+            PositionFormat::DegreesMinutesSeconds.format(position),
+            "33°30'0\"S 70°45'0\"W"
+        );
+        // Seconds rounding up carry over to minutes and degrees.
+        assert_eq!(dms(9.99999, ['N', 'S']), "10°0'0\"N");
+    }
+
+    #[test]
+    fn mgrs_grid_squares() {
+        // CN Tower, at 17T 630084 4833438.
+        let tower = pos_from_lat_lon(43.642567, -79.387139);
+        assert_eq!(PositionFormat::Mgrs.format(tower), "17T PJ 30084 33438");
+    }
+
+    #[test]
+    fn polar_fallback() {
+        let pole = pos_from_lat_lon(89., 10.);
+        assert_eq!(PositionFormat::Utm.format(pole), "89.00000, 10.00000");
+        assert_eq!(PositionFormat::Mgrs.format(pole), "89.00000, 10.00000");
+    }
+}
@@ -1,8 +1,121 @@
+use std::{cell::OnceCell, sync::Arc};
+
 use crate::{
-    map_memory::MapMemory,
-    units::{AdjustedPosition, Position, PositionTrait},
+    camera,
+    labels::LabelSlots,
+    map_memory::{MapMemory, ScreenTransform},
+    time::TimeWindow,
+    units::{AdjustedPosition, BoundingBox, Pixel, Position, PositionTrait},
+    TileId,
 };
 
+/// Projection of geographical positions onto a square map, set with [`crate::Map::projection`],
+/// e.g. a country-specific grid or [`crate::PolarStereographic`]. The map is zoomed and tiled
+/// the same way as Web Mercator is: it is 256 points wide at zoom 0, twice as wide with each
+/// level, and tiles split it into a grid of `2^zoom` by `2^zoom`.
+///
+/// Positions given to and returned by [`Projector`] stay geographical.
+pub trait Projection: Send + Sync {
+    /// Position on the map, as fractions of its width and height, from 0 to 1 within the map,
+    /// with y going down.
+    fn normalize(&self, position: Position) -> (f64, f64);
+
+    /// Inverse of [`Projection::normalize`].
+    fn denormalize(&self, x: f64, y: f64) -> Position;
+
+    /// Width of the whole map in meters on the ground, at given position, which is used for
+    /// [`Projector::scale_pixel_per_meter`].
+    fn ground_width(&self, position: Position) -> f64;
+
+    /// Whether the tile covers any part of the world. Other tiles are neither downloaded nor
+    /// drawn.
+    fn has_tile(&self, _tile_id: TileId) -> bool {
+        true
+    }
+}
+
+/// Affine transformation of [`crate::LocalMap`] coordinates, set with
+/// [`crate::LocalMap::transform`], for plans whose coordinate system is rotated or scaled
+/// differently along its axes. Positions are scaled first, then rotated and finally translated,
+/// into the map's frame, in which one unit is one point at zoom 16 and y goes up. The default is
+/// the identity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocalTransform {
+    pub scale_x: f64,
+    pub scale_y: f64,
+
+    /// Counterclockwise, in degrees.
+    pub rotation: f64,
+
+    pub translation: Position,
+}
+
+impl Default for LocalTransform {
+    fn default() -> Self {
+        Self {
+            scale_x: 1.,
+            scale_y: 1.,
+            rotation: 0.,
+            translation: Position { x: 0., y: 0. },
+        }
+    }
+}
+
+impl LocalTransform {
+    /// Position in the map's frame.
+    pub fn apply(&self, position: Position) -> Position {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (x, y) = (position.x * self.scale_x, position.y * self.scale_y);
+        Position {
+            x: x * cos - y * sin + self.translation.x,
+            y: x * sin + y * cos + self.translation.y,
+        }
+    }
+
+    /// Inverse of [`LocalTransform::apply`]. Degenerate transforms, with a zero scale, give
+    /// non-finite positions.
+    pub fn invert(&self, position: Position) -> Position {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (x, y) = (
+            position.x - self.translation.x,
+            position.y - self.translation.y,
+        );
+        Position {
+            x: (x * cos + y * sin) / self.scale_x,
+            y: (-x * sin + y * cos) / self.scale_y,
+        }
+    }
+
+    /// How many times longer distances get in the map's frame. With different scales of the
+    /// axes, it is their geometric mean.
+    pub fn scale(&self) -> f64 {
+        (self.scale_x * self.scale_y).abs().sqrt()
+    }
+}
+
+/// Point halfway along the great circle between the positions, with the longitude kept next to
+/// theirs. None if they are antipodal, as there is no single great circle then.
+fn great_circle_midpoint(a: Position, b: Position) -> Option<Position> {
+    let vector = |p: Position| {
+        let (lat, lon) = (p.y.to_radians(), p.x.to_radians());
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    };
+    let (va, vb) = (vector(a), vector(b));
+    let sum = [va[0] + vb[0], va[1] + vb[1], va[2] + vb[2]];
+    let length = (sum[0] * sum[0] + sum[1] * sum[1] + sum[2] * sum[2]).sqrt();
+    if length < 1e-9 {
+        return None;
+    }
+
+    let lat = (sum[2] / length).asin().to_degrees();
+    let lon = sum[1].atan2(sum[0]).to_degrees();
+    let reference = (a.x + b.x) / 2.;
+    Some(Position {
+        x: lon - 360. * ((lon - reference) / 360.).round(),
+        y: lat,
+    })
+}
+
 /// A Projector relates Positions to screen coordinates
 /// two projectors are supported.
 #[derive(Default, Clone)]
@@ -13,91 +126,416 @@ pub enum ProjectorType {
     Global,
     /// Local is used for local coordinates were Positions are euclidean x and y values in
     /// some arbitrary units and the projection is an affine transformation
-    Local,
+    Local(LocalTransform),
+    /// Custom is used for maps where Positions are latitude and longitude, projected by the
+    /// given [`Projection`], e.g. polar ones
+    Custom(Arc<dyn Projection>),
 }
 
 pub struct Projector<'a> {
     clip_rect: egui::Rect,
     memory: &'a mut MapMemory,
     my_position: Position,
+    labels: LabelSlots,
+    visible_bounds: OnceCell<BoundingBox>,
 }
 
 impl<'a> Projector<'a> {
     pub fn new(memory: &'a mut MapMemory, rect: egui::Rect, my_position: Position) -> Self {
+        memory.viewport_height = rect.height();
         Self {
             clip_rect: rect,
+            labels: LabelSlots::new(memory.label_budget()),
             memory,
             my_position,
+            visible_bounds: OnceCell::new(),
         }
     }
 
     pub fn project(&self, pos: Position) -> egui::Pos2 {
+        let center = self.clip_rect.center();
+        center
+            + self
+                .memory
+                .screen_transform()
+                .apply(self.project_flat(pos) - center)
+    }
+
+    /// Like [`Projector::project`], but onto the flat, north-up map, before the bearing and tilt
+    /// are applied. See [`ScreenTransform::transform_mesh`].
+    pub(crate) fn project_flat(&self, pos: Position) -> egui::Pos2 {
         let zoom = self.memory.zoom();
-        match self.memory.projection_type {
+        let center = self.center();
+        let shift = match &self.memory.projection_type {
             ProjectorType::Global => {
-                let bm_pos = pos.global_bitmap_project(zoom);
+                pos.global_bitmap_project(zoom) - center.global_bitmap_project(zoom)
+            }
+            ProjectorType::Local(transform) => {
+                pos.local_bitmap_project(zoom, transform)
+                    - center.local_bitmap_project(zoom, transform)
+            }
+            ProjectorType::Custom(projection) => {
+                pos.custom_bitmap_project(zoom, projection.as_ref())
+                    - center.custom_bitmap_project(zoom, projection.as_ref())
+            }
+        };
+        self.clip_rect.center() + egui::Vec2::new(shift.x as f32, shift.y as f32)
+    }
 
-                let map_center_projected_position = self
-                    .memory
-                    .center_mode
-                    .global_position(self.my_position, zoom)
-                    .global_bitmap_project(zoom);
+    pub fn unproject(&self, screen_pos: egui::Pos2) -> Position {
+        let screen_pos = screen_pos - self.clip_rect.center();
+        let center = AdjustedPosition::from(self.center())
+            .shift(-self.memory.screen_transform().invert(screen_pos));
+        camera::unadjusted_position(&center, &self.memory.projection_type, self.memory.zoom())
+    }
 
-                let shift = bm_pos - map_center_projected_position;
+    /// Position on the map at zoom 0, which unlike the screen does not change as the map is
+    /// zoomed or moved. See [`Projector::project_bitmap`].
+    pub(crate) fn bitmap(&self, pos: Position) -> Pixel {
+        match &self.memory.projection_type {
+            ProjectorType::Global => pos.global_bitmap_project(0.),
+            ProjectorType::Local(transform) => pos.local_bitmap_project(0., transform),
+            ProjectorType::Custom(projection) => pos.custom_bitmap_project(0., projection.as_ref()),
+        }
+    }
 
-                self.clip_rect.center() + egui::Vec2::new(shift.x as f32, shift.y as f32)
-            }
-            ProjectorType::Local => {
-                let bm_pos = pos.local_bitmap_project(zoom);
+    /// Length of a meter at the position, in units of [`Projector::bitmap`].
+    pub(crate) fn bitmap_per_meter(&self, pos: Position) -> f64 {
+        self.memory.scale_pixel_per_meter_at(pos, 0.) as f64
+    }
 
-                let map_center_projected_position = self
-                    .memory
-                    .center_mode
-                    .local_position(self.my_position, zoom)
-                    .local_bitmap_project(zoom);
+    /// Screen position of a point of [`Projector::bitmap`].
+    pub(crate) fn project_bitmap(&self, bitmap: Pixel) -> egui::Pos2 {
+        let zoom = self.memory.zoom();
+        let center = self.center();
 
-                let shift = bm_pos - map_center_projected_position;
+        // Bitmaps of all projections grow twice with each zoom level.
+        let shift = (bitmap - self.bitmap(center)) * 2f64.powf(zoom);
+        self.clip_rect.center()
+            + self
+                .memory
+                .screen_transform()
+                .apply(egui::Vec2::new(shift.x as f32, shift.y as f32))
+    }
 
-                self.clip_rect.center() + egui::Vec2::new(shift.x as f32, shift.y as f32)
-            }
+    /// View of this projector for positions in UTM coordinates of given zone. Only meaningful for
+    /// global maps.
+    pub fn utm(&self, zone: UtmZone) -> UtmProjector<'_, 'a> {
+        UtmProjector {
+            projector: self,
+            zone,
         }
     }
 
-    pub fn unproject(&self, screen_pos: egui::Pos2) -> Position {
-        let screen_pos = screen_pos - self.clip_rect.center();
+    /// Rotation of the map's content on the screen. Geometry which should turn along with the
+    /// map (e.g. heading cones) needs it, while labels and icons should stay screen-aligned and
+    /// can be drawn at [`Projector::project`]ed positions as they are.
+    pub fn rotation(&self) -> egui::emath::Rot2 {
+        self.memory.screen_rotation()
+    }
 
-        let zoom = self.memory.zoom();
-        match self.memory.projection_type {
-            ProjectorType::Global => {
-                let center = self
-                    .memory
-                    .center_mode
-                    .global_position(self.my_position, zoom);
-
-                AdjustedPosition {
-                    position: center,
-                    offset: Default::default(),
-                }
-                .shift(-screen_pos)
-                .global_unadjusted_position(zoom)
+    pub(crate) fn screen_transform(&self) -> ScreenTransform {
+        self.memory.screen_transform()
+    }
+
+    /// See [`MapMemory::tilt`].
+    pub fn tilt(&self) -> f64 {
+        self.memory.tilt()
+    }
+
+    /// See [`MapMemory::bearing`].
+    pub fn bearing(&self) -> f64 {
+        self.memory.bearing()
+    }
+
+    /// Screen position of the offset from the position, given in points but in the map's frame,
+    /// so that it turns along with the map.
+    pub fn project_with_rotation(&self, pos: Position, offset: egui::Vec2) -> egui::Pos2 {
+        self.project(pos) + self.rotation() * offset
+    }
+
+    /// Screen points along the great circle from `a` to `b`, e.g. for drawing a flight route with
+    /// [`egui::Shape::line`]. The segment is split until the line between points is at most
+    /// `max_error` points away from the true path. Longitudes are unwrapped, so a path crossing the
+    /// antimeridian stays continuous. On local maps, it is simply the straight segment.
+    pub fn project_geodesic(&self, a: Position, b: Position, max_error: f32) -> Vec<egui::Pos2> {
+        let start = self.project(a);
+        let mut points = vec![start];
+        if self.memory.is_global() {
+            let b = Position {
+                x: b.x - 360. * ((b.x - a.x) / 360.).round(),
+                ..b
+            };
+            self.densify_geodesic(
+                (a, start),
+                (b, self.project(b)),
+                max_error.max(0.1),
+                16,
+                &mut points,
+            );
+        } else {
+            points.push(self.project(b));
+        }
+        points
+    }
+
+    /// Push points of the great circle after `a`, up to and including `b`.
+    fn densify_geodesic(
+        &self,
+        (a, a_screen): (Position, egui::Pos2),
+        (b, b_screen): (Position, egui::Pos2),
+        max_error: f32,
+        depth: u8,
+        points: &mut Vec<egui::Pos2>,
+    ) {
+        if let Some(middle) = great_circle_midpoint(a, b).filter(|_| depth > 0) {
+            let middle_screen = self.project(middle);
+            if middle_screen.is_finite()
+                && middle_screen.distance(a_screen.lerp(b_screen, 0.5)) > max_error
+            {
+                self.densify_geodesic(
+                    (a, a_screen),
+                    (middle, middle_screen),
+                    max_error,
+                    depth - 1,
+                    points,
+                );
+                self.densify_geodesic(
+                    (middle, middle_screen),
+                    (b, b_screen),
+                    max_error,
+                    depth - 1,
+                    points,
+                );
+                return;
             }
-            ProjectorType::Local => {
-                let center = self
-                    .memory
-                    .center_mode
-                    .local_position(self.my_position, zoom);
-
-                AdjustedPosition {
-                    position: center,
-                    offset: Default::default(),
+        }
+        points.push(b_screen);
+    }
+
+    /// Unit vector pointing on the screen towards given direction, in degrees clockwise from
+    /// north (or from the y axis, for local maps).
+    pub fn screen_direction(&self, degrees: f64) -> egui::Vec2 {
+        let angle = (degrees - self.bearing()).to_radians() as f32;
+        egui::Vec2::angled(angle - std::f32::consts::FRAC_PI_2)
+    }
+
+    pub fn scale_pixel_per_meter(&self, pos: Position) -> f32 {
+        self.memory.scale_pixel_per_meter(pos)
+    }
+
+    pub(crate) fn memory(&self) -> &MapMemory {
+        self.memory
+    }
+
+    /// Position at the map's center. See [`crate::Camera::center`].
+    pub(crate) fn center(&self) -> Position {
+        camera::center(self.memory, self.my_position)
+    }
+
+    pub(crate) fn clip_rect(&self) -> egui::Rect {
+        self.clip_rect
+    }
+
+    /// Bounding box of positions visible on the screen, in the map's coordinates (longitude and
+    /// latitude, or local ones). For a rotated or tilted map, it is larger than the view itself.
+    /// It is computed once per frame, so it can be used to cheaply skip geometry which is off the
+    /// screen, see [`Projector::is_visible`].
+    pub fn visible_bounds(&self) -> BoundingBox {
+        *self.visible_bounds.get_or_init(|| {
+            // Edges of the screen are not straight lines in every projection, so walk along them.
+            const STEPS: usize = 8;
+            let rect = self.clip_rect;
+            let mut points: Vec<Position> = (0..STEPS)
+                .flat_map(|i| {
+                    let t = i as f32 / STEPS as f32;
+                    [
+                        rect.lerp_inside(egui::vec2(t, 0.)),
+                        rect.lerp_inside(egui::vec2(1., t)),
+                        rect.lerp_inside(egui::vec2(1. - t, 1.)),
+                        rect.lerp_inside(egui::vec2(0., 1. - t)),
+                    ]
+                })
+                .map(|corner| self.unproject(corner))
+                .collect();
+
+            // A pole within the view of a custom projection (e.g. a polar one) makes all
+            // longitudes visible.
+            if let ProjectorType::Custom(_) = &self.memory.projection_type {
+                for lat in [90., -90.] {
+                    if rect.contains(self.project(Position { x: 0., y: lat })) {
+                        points.push(Position { x: -180., y: lat });
+                        points.push(Position { x: 180., y: lat });
+                    }
                 }
-                .shift(-screen_pos)
-                .local_unadjusted_position(zoom)
             }
+
+            let (min, max) = points
+                .iter()
+                .filter(|p| p.x.is_finite() && p.y.is_finite())
+                .fold(
+                    (
+                        Position {
+                            x: f64::INFINITY,
+                            y: f64::INFINITY,
+                        },
+                        Position {
+                            x: f64::NEG_INFINITY,
+                            y: f64::NEG_INFINITY,
+                        },
+                    ),
+                    |(min, max), p| {
+                        (
+                            Position {
+                                x: min.x.min(p.x),
+                                y: min.y.min(p.y),
+                            },
+                            Position {
+                                x: max.x.max(p.x),
+                                y: max.y.max(p.y),
+                            },
+                        )
+                    },
+                );
+            BoundingBox::new(min, max)
+        })
+    }
+
+    /// Whether any part of the bounding box, in the map's coordinates, might be visible on the
+    /// screen. See [`Projector::visible_bounds`].
+    pub fn is_visible(&self, bounds: &BoundingBox) -> bool {
+        let visible = self.visible_bounds();
+        bounds.min().x <= visible.max().x
+            && bounds.max().x >= visible.min().x
+            && bounds.min().y <= visible.max().y
+            && bounds.max().y >= visible.min().y
+    }
+
+    /// Whether a label anchored at given screen position fits within the
+    /// [`crate::LabelBudget`]. If so, the space is taken for the rest of the frame, so this
+    /// should be called only for labels which are about to be drawn, in the order of priority.
+    pub fn place_label(&self, anchor: egui::Pos2) -> bool {
+        self.labels.claim(anchor)
+    }
+
+    /// Position formatted as set with [`crate::Map::position_format`], for readouts and labels.
+    pub fn format_position(&self, pos: Position) -> String {
+        self.memory.position_format().format(pos)
+    }
+
+    /// See [`MapMemory::time_window`].
+    pub fn time_window(&self) -> Option<TimeWindow> {
+        self.memory.time_window()
+    }
+
+    /// Whether a feature stamped with given time should be shown. Features are always shown when
+    /// there is no time window.
+    pub fn shows_time(&self, time: f64) -> bool {
+        self.time_window()
+            .map_or(true, |time_window| time_window.contains(time))
+    }
+}
+
+/// Number of a UTM zone outside of 1 to 60.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("invalid UTM zone {0}")]
+pub struct InvalidUtmZone(pub u8);
+
+/// Zone of the Universal Transverse Mercator grid, e.g. 33N.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UtmZone {
+    /// Number of the zone, from 1 to 60.
+    pub zone: u8,
+    pub northern: bool,
+}
+
+impl UtmZone {
+    /// Zone of the northern hemisphere, numbered from 1 to 60.
+    pub fn north(zone: u8) -> Result<Self, InvalidUtmZone> {
+        Self::new(zone, true)
+    }
+
+    /// Zone of the southern hemisphere, numbered from 1 to 60.
+    pub fn south(zone: u8) -> Result<Self, InvalidUtmZone> {
+        Self::new(zone, false)
+    }
+
+    fn new(zone: u8, northern: bool) -> Result<Self, InvalidUtmZone> {
+        if (1..=60).contains(&zone) {
+            Ok(Self { zone, northern })
+        } else {
+            Err(InvalidUtmZone(zone))
         }
     }
 
+    /// Zone in which given geographical position lies.
+    pub fn containing(position: Position) -> Self {
+        Self {
+            zone: crate::utm::zone(position),
+            northern: position.y >= 0.,
+        }
+    }
+}
+
+/// [`Projector`] for positions given as easting (x) and northing (y) in meters, within a
+/// [`UtmZone`], e.g. survey data. See [`Projector::utm`].
+pub struct UtmProjector<'p, 'a> {
+    projector: &'p Projector<'a>,
+    zone: UtmZone,
+}
+
+impl UtmProjector<'_, '_> {
+    pub fn project(&self, pos: Position) -> egui::Pos2 {
+        self.projector.project(self.to_geographic(pos))
+    }
+
+    pub fn unproject(&self, screen_pos: egui::Pos2) -> Position {
+        self.from_geographic(self.projector.unproject(screen_pos))
+    }
+
+    /// Pixels per meter of the UTM grid, which differs from the meter on the ground by the
+    /// grid's scale factor, from 0.9996 on the central meridian up to about 1.001 at the zone's
+    /// edges.
     pub fn scale_pixel_per_meter(&self, pos: Position) -> f32 {
-        self.memory.scale_pixel_per_meter(pos)
+        let geographic = self.to_geographic(pos);
+        let (_, _, scale) = crate::utm::forward(geographic, self.zone.zone);
+        (self.projector.scale_pixel_per_meter(geographic) as f64 / scale) as f32
+    }
+
+    /// Latitude and longitude of the UTM position.
+    pub fn to_geographic(&self, pos: Position) -> Position {
+        crate::utm::inverse(self.zone.zone, self.zone.northern, pos.x, pos.y)
+    }
+
+    /// UTM position of the geographical one, extending the zone past its edges if needed.
+    pub fn from_geographic(&self, pos: Position) -> Position {
+        let (easting, northing, _) = crate::utm::forward(pos, self.zone.zone);
+        Position {
+            x: easting,
+            y: if self.zone.northern {
+                northing
+            } else {
+                northing + crate::utm::FALSE_NORTHING
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utm_zones() {
+        assert_eq!(
+            UtmZone::north(33),
+            Ok(UtmZone {
+                zone: 33,
+                northern: true
+            })
+        );
+        assert_eq!(UtmZone::south(60).map(|zone| zone.northern), Ok(false));
+        assert_eq!(UtmZone::north(0), Err(InvalidUtmZone(0)));
+        assert_eq!(UtmZone::south(61), Err(InvalidUtmZone(61)));
     }
 }
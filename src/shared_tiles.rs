@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{sources::Attribution, BoundingBox, HttpTiles, TextureWithUv, TileId, Tiles};
+
+/// Handle to [`Tiles`] which can be used by many maps, e.g. in different panels or viewports,
+/// sharing their cache and downloads. Each map gets its own clone of the handle:
+///
+/// ```ignore
+/// let tiles = SharedTiles::new(HttpTiles::new(OpenStreetMap, ctx.clone()));
+/// // Later, on each frame:
+/// ui.add(Map::new(Some(&mut tiles.clone()), &mut memory, position));
+/// ui.add(Map::new(Some(&mut tiles.clone()), &mut overview_memory, position));
+/// ```
+///
+/// To have maps fully isolated instead, give each of them its own [`HttpTiles`], which comes with
+/// its own cache and IO thread. Such maps can still share the storage of downloaded tiles through
+/// [`crate::HttpOptions::tile_cache`].
+///
+/// Errors reported by [`Tiles::take_errors`] go to whichever map takes them first.
+pub struct SharedTiles<T = HttpTiles>(Arc<Mutex<T>>);
+
+impl<T> Clone for SharedTiles<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Tiles> SharedTiles<T> {
+    pub fn new(tiles: T) -> Self {
+        Self(Arc::new(Mutex::new(tiles)))
+    }
+
+    /// Access the tiles, e.g. to call [`HttpTiles::set_parameter`].
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        // Tiles hold no invariants which a panic could break.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Tiles> Tiles for SharedTiles<T> {
+    fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+        self.lock().at(tile_id)
+    }
+
+    fn attribution(&self) -> Attribution {
+        self.lock().attribution()
+    }
+
+    fn tile_size(&self) -> u32 {
+        self.lock().tile_size()
+    }
+
+    fn zoom_offset(&self) -> u8 {
+        self.lock().zoom_offset()
+    }
+
+    fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        self.lock().take_errors()
+    }
+
+    fn source_id(&self) -> String {
+        self.lock().source_id()
+    }
+
+    fn attributions(&self, visible: BoundingBox) -> Vec<Attribution> {
+        self.lock().attributions(visible)
+    }
+}
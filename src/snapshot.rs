@@ -0,0 +1,452 @@
+//! Headless rendering of the map into an image, e.g. for golden-image tests, and driving it with
+//! input events, e.g. for integration tests.
+
+use std::collections::HashMap;
+
+use egui::{
+    epaint::{ClippedPrimitive, ClippedShape, ImageDelta, Primitive, Vertex},
+    Color32, ColorImage, Context, Event, FullOutput, ImageData, Modifiers, MouseWheelUnit,
+    PointerButton, Pos2, RawInput, Rect, TextureId, Ui, Vec2,
+};
+
+/// Renders egui output into a [`ColorImage`] using a simple software rasterizer, without any
+/// window or GPU. Combined with [`crate::DebugTiles`], rendering is fully deterministic.
+///
+/// ```ignore
+/// let mut snapshot = Snapshot::new(egui::vec2(512., 512.));
+/// let mut tiles = DebugTiles::new(snapshot.context().clone());
+/// let image = snapshot.render(|ui| {
+///     ui.add(Map::new(Some(&mut tiles), &mut memory, position).with_plugin(places));
+/// });
+/// ```
+///
+/// Between renders, the map can be driven like by a user, with [`Snapshot::drag`],
+/// [`Snapshot::scroll`], [`Snapshot::click`], or any events passed to [`Snapshot::step`].
+pub struct Snapshot {
+    ctx: Context,
+    size: Vec2,
+    textures: HashMap<TextureId, ColorImage>,
+    time: f64,
+}
+
+/// Upper limit of frames rendered while waiting for the content to settle.
+const MAX_PASSES: usize = 16;
+
+impl Snapshot {
+    /// Create a renderer producing images of given size, in points. Pixels per point is 1.
+    pub fn new(size: Vec2) -> Self {
+        Self {
+            ctx: Context::default(),
+            size,
+            textures: HashMap::new(),
+            time: 0.,
+        }
+    }
+
+    /// Context to be used for creating [`crate::Tiles`] and textures shown in the snapshot.
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    /// Run `add_contents` in a central panel, repeating it until no more repaints are requested
+    /// (e.g. all tiles are loaded), and return the image of the last frame.
+    pub fn render(&mut self, add_contents: impl FnMut(&mut Ui)) -> ColorImage {
+        let (shapes, pixels_per_point) = self.run(add_contents);
+        let primitives = self.ctx.tessellate(shapes, pixels_per_point);
+
+        let mut image = ColorImage::new(
+            [self.size.x.round() as usize, self.size.y.round() as usize],
+            Color32::TRANSPARENT,
+        );
+
+        for primitive in primitives {
+            self.paint(&mut image, &primitive);
+        }
+
+        image
+    }
+
+    /// Run a single frame with given input events, e.g. [`Event::Key`]. Positions are in points,
+    /// relative to the top left corner of the snapshot.
+    pub fn step(&mut self, events: Vec<Event>, mut add_contents: impl FnMut(&mut Ui)) {
+        let output = self.frame(events, &mut add_contents);
+        for id in output.textures_delta.free {
+            self.textures.remove(&id);
+        }
+    }
+
+    /// Drag with the primary mouse button, e.g. to pan the map, moving the pointer over a few
+    /// frames.
+    pub fn drag(&mut self, from: Pos2, to: Pos2, mut add_contents: impl FnMut(&mut Ui)) {
+        const STEPS: usize = 8;
+
+        let button = |pos, pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+
+        self.step(
+            vec![Event::PointerMoved(from), button(from, true)],
+            &mut add_contents,
+        );
+        for i in 1..=STEPS {
+            let pos = from.lerp(to, i as f32 / STEPS as f32);
+            self.step(vec![Event::PointerMoved(pos)], &mut add_contents);
+        }
+        self.step(vec![button(to, false)], &mut add_contents);
+    }
+
+    /// Turn the mouse wheel over the position, while holding the modifiers, e.g.
+    /// [`Modifiers::CTRL`] to zoom the map. Positive `lines` scroll up.
+    pub fn scroll(
+        &mut self,
+        position: Pos2,
+        lines: f32,
+        modifiers: Modifiers,
+        add_contents: impl FnMut(&mut Ui),
+    ) {
+        self.step(
+            vec![
+                Event::PointerMoved(position),
+                Event::MouseWheel {
+                    unit: MouseWheelUnit::Line,
+                    delta: Vec2::new(0., lines),
+                    modifiers,
+                },
+            ],
+            add_contents,
+        );
+    }
+
+    /// Click with the primary mouse button.
+    pub fn click(&mut self, position: Pos2, mut add_contents: impl FnMut(&mut Ui)) {
+        let button = |pressed| Event::PointerButton {
+            pos: position,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        self.step(
+            vec![Event::PointerMoved(position), button(true)],
+            &mut add_contents,
+        );
+        self.step(vec![button(false)], &mut add_contents);
+    }
+
+    /// Run frames until the content settles, and return shapes of the last one.
+    pub(crate) fn run(
+        &mut self,
+        mut add_contents: impl FnMut(&mut Ui),
+    ) -> (Vec<ClippedShape>, f32) {
+        for pass in 1.. {
+            let output = self.frame(Vec::new(), &mut add_contents);
+
+            let repaint = output
+                .viewport_output
+                .values()
+                .any(|viewport| viewport.repaint_delay.is_zero());
+
+            // Textures are freed after the frame is painted, so these are still needed.
+            if !repaint || pass == MAX_PASSES {
+                return (output.shapes, output.pixels_per_point);
+            }
+
+            for id in output.textures_delta.free {
+                self.textures.remove(&id);
+            }
+        }
+
+        unreachable!()
+    }
+
+    /// Run one frame, and take the textures it uploaded.
+    fn frame(&mut self, events: Vec<Event>, add_contents: &mut impl FnMut(&mut Ui)) -> FullOutput {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, self.size)),
+            time: Some(self.time),
+            events,
+            ..Default::default()
+        };
+
+        let mut output = self.ctx.run(input, |ctx| {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::none())
+                .show(ctx, |ui| add_contents(ui));
+        });
+
+        // Same as in eframe, assuming the frame takes 1/60s.
+        self.time += 1. / 60.;
+
+        for (id, delta) in std::mem::take(&mut output.textures_delta.set) {
+            self.set_texture(id, delta);
+        }
+
+        output
+    }
+
+    #[cfg(feature = "export")]
+    pub(crate) fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    /// Texture, as uploaded by egui.
+    #[cfg(feature = "export")]
+    pub(crate) fn texture(&self, id: TextureId) -> Option<&ColorImage> {
+        self.textures.get(&id)
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: ImageDelta) {
+        let patch = match delta.image {
+            ImageData::Color(image) => (*image).clone(),
+            ImageData::Font(font) => ColorImage {
+                size: font.size,
+                pixels: font.srgba_pixels(None).collect(),
+            },
+        };
+
+        match delta.pos {
+            None => {
+                self.textures.insert(id, patch);
+            }
+            Some([x, y]) => {
+                if let Some(texture) = self.textures.get_mut(&id) {
+                    for py in 0..patch.height() {
+                        for px in 0..patch.width() {
+                            texture[(x + px, y + py)] = patch[(px, py)];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn paint(&self, image: &mut ColorImage, primitive: &ClippedPrimitive) {
+        let Primitive::Mesh(mesh) = &primitive.primitive else {
+            // Paint callbacks need a real GPU.
+            return;
+        };
+
+        let Some(texture) = self.textures.get(&mesh.texture_id) else {
+            log::warn!("Unknown texture {:?}.", mesh.texture_id);
+            return;
+        };
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            paint_triangle(image, texture, primitive.clip_rect, [a, b, c]);
+        }
+    }
+}
+
+fn paint_triangle(image: &mut ColorImage, texture: &ColorImage, clip: Rect, v: [Vertex; 3]) {
+    let [a, b, c] = v.map(|v| v.pos);
+    let area = (b - a).x * (c - a).y - (b - a).y * (c - a).x;
+    if area == 0. {
+        return;
+    }
+
+    let bounds = Rect::from_points(&[a, b, c])
+        .intersect(clip)
+        .intersect(Rect::from_min_size(
+            Pos2::ZERO,
+            Vec2::new(image.width() as f32, image.height() as f32),
+        ));
+    if !bounds.is_positive() {
+        return;
+    }
+
+    for y in bounds.min.y.floor() as usize..bounds.max.y.ceil() as usize {
+        for x in bounds.min.x.floor() as usize..bounds.max.x.ceil() as usize {
+            let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+            // Barycentric coordinates.
+            let wa = ((b - p).x * (c - p).y - (b - p).y * (c - p).x) / area;
+            let wb = ((c - p).x * (a - p).y - (c - p).y * (a - p).x) / area;
+            let wc = 1. - wa - wb;
+            if wa < 0. || wb < 0. || wc < 0. || !clip.contains(p) {
+                continue;
+            }
+
+            let uv = v[0].uv.to_vec2() * wa + v[1].uv.to_vec2() * wb + v[2].uv.to_vec2() * wc;
+            let texel = texture[(
+                ((uv.x * texture.width() as f32) as usize).min(texture.width() - 1),
+                ((uv.y * texture.height() as f32) as usize).min(texture.height() - 1),
+            )];
+
+            let color = lerp_color(v.map(|v| v.color), [wa, wb, wc]);
+            let src = multiply(texel, color);
+            image[(x, y)] = blend(src, image[(x, y)]);
+        }
+    }
+}
+
+fn lerp_color(colors: [Color32; 3], weights: [f32; 3]) -> [f32; 4] {
+    let mut result = [0.; 4];
+    for (color, weight) in colors.iter().zip(weights) {
+        for (channel, value) in result.iter_mut().zip(color.to_array()) {
+            *channel += value as f32 * weight;
+        }
+    }
+    result
+}
+
+/// Multiply premultiplied texel by premultiplied vertex color.
+fn multiply(texel: Color32, color: [f32; 4]) -> [f32; 4] {
+    let texel = texel.to_array();
+    std::array::from_fn(|i| texel[i] as f32 * color[i] / 255.)
+}
+
+/// Premultiplied "over" blending.
+fn blend(src: [f32; 4], dst: Color32) -> Color32 {
+    let dst = dst.to_array();
+    let inv_alpha = 1. - src[3] / 255.;
+    let [r, g, b, a]: [u8; 4] =
+        std::array::from_fn(|i| (src[i] + dst[i] as f32 * inv_alpha).round().clamp(0., 255.) as u8);
+    Color32::from_rgba_premultiplied(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pos_from_lon_lat, sources::Attribution, DebugTiles, Map, MapEvent, MapMemory,
+        TextureWithUv, TileId, Tiles,
+    };
+
+    #[test]
+    fn renders_tiles() {
+        let mut snapshot = Snapshot::new(Vec2::splat(256.));
+        let mut tiles = DebugTiles::new(snapshot.context().clone());
+        let mut memory = MapMemory::default();
+
+        let image = snapshot.render(|ui| {
+            ui.add(Map::new(
+                Some(&mut tiles),
+                &mut memory,
+                pos_from_lon_lat(0., 0.),
+            ));
+        });
+
+        assert_eq!(image.size, [256, 256]);
+        assert!(image.pixels.iter().all(|pixel| pixel.a() == 255));
+    }
+
+    #[test]
+    fn drag_pans_the_map() {
+        let mut snapshot = Snapshot::new(Vec2::splat(256.));
+        let mut tiles = DebugTiles::new(snapshot.context().clone());
+        let mut memory = MapMemory::default();
+        let mut add_contents = |ui: &mut Ui| {
+            ui.add(Map::new(
+                Some(&mut tiles),
+                &mut memory,
+                pos_from_lon_lat(0., 0.),
+            ));
+        };
+
+        snapshot.render(&mut add_contents);
+        snapshot.drag(
+            Pos2::new(200., 128.),
+            Pos2::new(100., 128.),
+            &mut add_contents,
+        );
+        snapshot.render(&mut add_contents);
+
+        // Dragging the map to the left shows what is east of the initial position.
+        let center = memory.detached().expect("map should be detached");
+        assert!(center.x > 0., "{center:?}");
+        assert!(center.y.abs() < 1., "{center:?}");
+    }
+
+    #[test]
+    fn scroll_zooms_the_map() {
+        let mut snapshot = Snapshot::new(Vec2::splat(256.));
+        let mut tiles = DebugTiles::new(snapshot.context().clone());
+        let mut memory = MapMemory::default();
+        let zoom = memory.zoom();
+        let mut add_contents = |ui: &mut Ui| {
+            ui.add(Map::new(
+                Some(&mut tiles),
+                &mut memory,
+                pos_from_lon_lat(0., 0.),
+            ));
+        };
+
+        snapshot.render(&mut add_contents);
+        snapshot.scroll(
+            Pos2::new(128., 128.),
+            5.,
+            Modifiers::CTRL,
+            &mut add_contents,
+        );
+        snapshot.render(&mut add_contents);
+
+        assert!(memory.zoom() > zoom, "{} <= {}", memory.zoom(), zoom);
+    }
+
+    /// Debug tiles, told apart by the name.
+    struct Named(DebugTiles, &'static str);
+
+    impl Tiles for Named {
+        fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+            self.0.at(tile_id)
+        }
+
+        fn attribution(&self) -> Attribution {
+            self.0.attribution()
+        }
+
+        fn tile_size(&self) -> u32 {
+            self.0.tile_size()
+        }
+
+        fn source_id(&self) -> String {
+            self.1.to_owned()
+        }
+    }
+
+    #[test]
+    fn source_switched() {
+        let mut snapshot = Snapshot::new(Vec2::splat(256.));
+        let mut first = Named(DebugTiles::new(snapshot.context().clone()), "first");
+        let mut second = Named(DebugTiles::new(snapshot.context().clone()), "second");
+        let mut memory = MapMemory::default();
+        let mut switches = 0;
+
+        for use_second in [false, false, true, true] {
+            let tiles: &mut dyn Tiles = if use_second { &mut second } else { &mut first };
+            snapshot.render(|ui| {
+                ui.add(
+                    Map::new(Some(tiles), &mut memory, pos_from_lon_lat(0., 0.))
+                        .with_event_listener(|event| {
+                            if matches!(event, MapEvent::SourceSwitched { .. }) {
+                                switches += 1;
+                            }
+                        }),
+                );
+            });
+        }
+
+        // Not for the first tiles, nor when they stay the same.
+        assert_eq!(switches, 1);
+    }
+
+    #[test]
+    fn click() {
+        let mut snapshot = Snapshot::new(Vec2::splat(256.));
+        let clicked = std::cell::Cell::new(false);
+        let mut add_contents = |ui: &mut Ui| {
+            if ui.button("Click me").clicked() {
+                clicked.set(true);
+            }
+        };
+
+        snapshot.render(&mut add_contents);
+        snapshot.click(Pos2::new(200., 200.), &mut add_contents);
+        assert!(!clicked.get());
+        snapshot.click(Pos2::new(10., 8.), &mut add_contents);
+        assert!(clicked.get());
+    }
+}
@@ -0,0 +1,46 @@
+pub use reqwest::Request;
+
+/// Future returned by [`Authorization::authorize`]. It needs not be `Send` in WASM, so it can
+/// await the browser's APIs.
+#[cfg(not(target_arch = "wasm32"))]
+pub type AuthorizeFuture<'a> = futures::future::BoxFuture<'a, Result<Request, String>>;
+
+/// Future returned by [`Authorization::authorize`]. It needs not be `Send` in WASM, so it can
+/// await the browser's APIs.
+#[cfg(target_arch = "wasm32")]
+pub type AuthorizeFuture<'a> = futures::future::LocalBoxFuture<'a, Result<Request, String>>;
+
+/// Credentials of sources which need more than a static API key in the URL, e.g. short-lived
+/// bearer tokens or signed URLs. See [`super::TileSource::authorization`].
+///
+/// ```
+/// use walkers::sources::{Authorization, AuthorizeFuture, Request};
+/// use std::sync::Mutex;
+///
+/// struct BearerToken(Mutex<Option<String>>);
+///
+/// impl Authorization for BearerToken {
+///     fn authorize(&self, mut request: Request, refresh: bool) -> AuthorizeFuture<'_> {
+///         Box::pin(async move {
+///             let mut token = self.0.lock().unwrap();
+///             if refresh || token.is_none() {
+///                 // Fetch a new token from the provider here.
+///                 *token = Some("secret".to_owned());
+///             }
+///             let value = format!("Bearer {}", token.as_deref().unwrap_or_default());
+///             request.headers_mut().insert(
+///                 "Authorization",
+///                 value.parse().map_err(|_| "invalid token".to_owned())?,
+///             );
+///             Ok(request)
+///         })
+///     }
+/// }
+/// ```
+pub trait Authorization: Send + Sync {
+    /// Add credentials to the request, e.g. an `Authorization` header or a signature in the
+    /// URL. It is called before each download, so credentials should be kept between calls.
+    /// `refresh` is set when the server rejected the previous ones with 401 Unauthorized, in
+    /// which case new ones should be obtained. The request is then retried once.
+    fn authorize(&self, request: Request, refresh: bool) -> AuthorizeFuture<'_>;
+}
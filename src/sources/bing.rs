@@ -0,0 +1,113 @@
+use crate::tiles::TileId;
+
+use super::{subdomain, Attribution, TileSource};
+
+/// Imagery sets of [`Bing`].
+#[derive(Clone, Copy, Default)]
+pub enum BingImagery {
+    #[default]
+    Aerial,
+    AerialWithLabels,
+    Road,
+}
+
+impl BingImagery {
+    fn prefix(&self) -> &'static str {
+        match self {
+            Self::Aerial => "a",
+            Self::AerialWithLabels => "h",
+            Self::Road => "r",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Aerial | Self::AerialWithLabels => "jpeg",
+            Self::Road => "png",
+        }
+    }
+}
+
+/// Bing Maps tiles, addressed by quadkeys.
+/// <https://learn.microsoft.com/en-us/bingmaps/articles/bing-maps-tile-system>
+#[derive(Default)]
+pub struct Bing {
+    pub imagery: BingImagery,
+}
+
+impl TileSource for Bing {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        format!(
+            "https://ecn.t{}.tiles.virtualearth.net/tiles/{}{}.{}?g=1",
+            subdomain(&["0", "1", "2", "3"], tile_id),
+            self.imagery.prefix(),
+            tile_id.to_quadkey(),
+            self.imagery.extension(),
+        )
+    }
+
+    fn cache_id(&self) -> Option<String> {
+        Some(format!("bing-{}", self.imagery.prefix()))
+    }
+
+    fn attribution(&self) -> Attribution {
+        Attribution {
+            text: "© Microsoft",
+            url: "https://www.microsoft.com/maps/product/terms",
+            logo_light: None,
+            logo_dark: None,
+        }
+    }
+}
+
+/// Source of any service addressed by quadkeys. The URL template's `{quadkey}` placeholder is
+/// replaced by the tile's quadkey, and `{subdomain}` by one of the subdomains, which spreads the
+/// requests between servers.
+///
+/// ```
+/// use walkers::sources::Quadkey;
+///
+/// let source = Quadkey::new(
+///     "https://t{subdomain}.example.com/tiles/{quadkey}.png",
+///     &["0", "1", "2", "3"],
+///     "© Example",
+///     "https://example.com/",
+/// );
+/// ```
+pub struct Quadkey {
+    template: String,
+    subdomains: Vec<String>,
+    attribution: (&'static str, &'static str),
+}
+
+impl Quadkey {
+    pub fn new(
+        template: impl Into<String>,
+        subdomains: &[&str],
+        attribution_text: &'static str,
+        attribution_url: &'static str,
+    ) -> Self {
+        Self {
+            template: template.into(),
+            subdomains: subdomains.iter().map(|s| s.to_string()).collect(),
+            attribution: (attribution_text, attribution_url),
+        }
+    }
+}
+
+impl TileSource for Quadkey {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        self.template
+            .replace("{quadkey}", &tile_id.to_quadkey())
+            .replace("{subdomain}", subdomain(&self.subdomains, tile_id))
+    }
+
+    fn attribution(&self) -> Attribution {
+        Attribution {
+            text: self.attribution.0,
+            url: self.attribution.1,
+            logo_light: None,
+            logo_dark: None,
+        }
+    }
+}
@@ -57,6 +57,14 @@ impl TileSource for Mapbox {
         )
     }
 
+    fn cache_id(&self) -> Option<String> {
+        Some(format!(
+            "mapbox-{}{}",
+            self.style.api_slug(),
+            if self.high_resolution { "@2x" } else { "" }
+        ))
+    }
+
     fn attribution(&self) -> Attribution {
         // TODO: Proper linking (https://docs.mapbox.com/help/getting-started/attribution/))
         Attribution {
@@ -1,13 +1,45 @@
 //! Some common HTTP tile sources. Make sure you follow terms of usage of the particular source.
 
+mod auth;
+mod bing;
 mod geoportal;
 mod mapbox;
+mod mosaic;
+mod openseamap;
 mod openstreetmap;
+mod template;
+mod wms;
 
-use crate::tiles::TileId;
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{tiles::TileId, BoundingBox};
+pub use auth::{Authorization, AuthorizeFuture, Request};
+pub use bing::{Bing, BingImagery, Quadkey};
 pub use geoportal::Geoportal;
 pub use mapbox::{Mapbox, MapboxStyle};
+pub use mosaic::MosaicSource;
+pub use openseamap::OpenSeaMap;
 pub use openstreetmap::OpenStreetMap;
+pub use template::UrlTemplate;
+pub use wms::{WmsCrs, WmsSource};
+
+/// Tile size which is not 256 multiplied by a power of two.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("invalid tile size")]
+pub struct InvalidTileSize;
+
+/// Check whether tiles of this size can be drawn. See [`TileSource::tile_size`].
+pub fn validate_tile_size(tile_size: u32) -> Result<(), InvalidTileSize> {
+    if tile_size % crate::TILE_SIZE == 0 && (tile_size / crate::TILE_SIZE).is_power_of_two() {
+        Ok(())
+    } else {
+        Err(InvalidTileSize)
+    }
+}
+
+pub(crate) fn zoom_offset(tile_size: u32) -> u8 {
+    (tile_size / crate::TILE_SIZE).max(1).ilog2() as u8
+}
 
 #[derive(Clone)]
 pub struct Attribution {
@@ -17,17 +49,183 @@ pub struct Attribution {
     pub logo_dark: Option<egui::ImageSource<'static>>,
 }
 
+/// Where the tile grid's rows start counting from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TileYOrigin {
+    /// From the north, as in the usual XYZ ("slippy map") scheme.
+    #[default]
+    Xyz,
+
+    /// From the south, as in the Tile Map Service specification.
+    Tms,
+}
+
+/// Part of the map in which tiles come from another source than elsewhere. See
+/// [`TileSource::regions`].
+#[derive(Clone)]
+pub struct SourceRegion {
+    /// In degrees.
+    pub bounds: BoundingBox,
+
+    /// Zoom of the deepest tiles lying entirely within the region.
+    pub max_zoom: u8,
+
+    pub attribution: Attribution,
+}
+
+/// Runtime parameters of a source, like `language` or `style`. See
+/// [`TileSource::tile_url_with_parameters`].
+pub type SourceParameters = BTreeMap<String, String>;
+
 /// Remote tile server definition, source for the [`crate::HttpTiles`].
 pub trait TileSource {
     fn tile_url(&self, tile_id: TileId) -> String;
     fn attribution(&self) -> Attribution;
 
-    /// Size of each tile, should be a multiple of 256.
+    /// URL of the tile, given runtime parameters set by [`crate::HttpTiles::set_parameter`].
+    /// By default, each `{name}` placeholder in the [`TileSource::tile_url`] is replaced by the
+    /// value of the `name` parameter.
+    fn tile_url_with_parameters(&self, tile_id: TileId, parameters: &SourceParameters) -> String {
+        parameters
+            .iter()
+            .fold(self.tile_url(tile_id), |url, (name, value)| {
+                url.replace(&format!("{{{name}}}"), value)
+            })
+    }
+
+    /// Stable identity of the source, e.g. `mapbox-streets-v12`, which keys its tiles in the
+    /// [`crate::TileCache`] instead of their URLs. Otherwise, secrets embedded in URLs, like API
+    /// keys, split the cache each time they are rotated.
+    fn cache_id(&self) -> Option<String> {
+        None
+    }
+
+    /// Size of each tile, must be 256 multiplied by a power of two, e.g. 512 or 1024.
     fn tile_size(&self) -> u32 {
         256
     }
 
+    /// How many zoom levels the tiles are shifted relative to the 256px ones. For example, 512px
+    /// tile at zoom 1 covers the same area as four 256px tiles at zoom 2. By default, it is
+    /// derived from [`TileSource::tile_size`]. Override it for sources which serve high-DPI
+    /// (e.g. @2x) images using the regular tile grid.
+    fn zoom_offset(&self) -> u8 {
+        zoom_offset(self.tile_size())
+    }
+
     fn max_zoom(&self) -> u8 {
         19
     }
+
+    /// Row numbering of the source's tiles. For [`TileYOrigin::Tms`], the `y` of tiles passed to
+    /// [`TileSource::tile_url`] is flipped, so that URLs can be built as usual.
+    fn y_origin(&self) -> TileYOrigin {
+        TileYOrigin::Xyz
+    }
+
+    /// Credentials added to each tile request, for sources which need tokens or signed URLs.
+    /// Not used when downloading with [`crate::HttpOptions::fetch`].
+    fn authorization(&self) -> Option<Arc<dyn Authorization>> {
+        None
+    }
+
+    /// Regions in which tiles come from other sources, e.g. those of a [`MosaicSource`]. Tiles
+    /// lying entirely within a region are downloaded up to its zoom, while elsewhere only up to
+    /// [`TileSource::max_zoom`]. Attributions of visible regions are added to
+    /// [`crate::MapMemory::attributions`].
+    fn regions(&self) -> Vec<SourceRegion> {
+        Vec::new()
+    }
+}
+
+pub(crate) fn contains(outer: BoundingBox, inner: BoundingBox) -> bool {
+    outer.min().x <= inner.min().x
+        && outer.min().y <= inner.min().y
+        && inner.max().x <= outer.max().x
+        && inner.max().y <= outer.max().y
+}
+
+pub(crate) fn intersects(a: BoundingBox, b: BoundingBox) -> bool {
+    a.min().x <= b.max().x
+        && b.min().x <= a.max().x
+        && a.min().y <= b.max().y
+        && b.min().y <= a.max().y
+}
+
+/// Tile as numbered by the source, which is what its URLs are built from. `None` if the source
+/// numbers rows from the south, and the tile is not on the map.
+pub(crate) fn source_tile_id(
+    source: &(impl TileSource + ?Sized),
+    tile_id: TileId,
+) -> Option<TileId> {
+    match source.y_origin() {
+        TileYOrigin::Xyz => Some(tile_id),
+        TileYOrigin::Tms => Some(TileId {
+            y: 1u32
+                .checked_shl(tile_id.zoom.into())?
+                .checked_sub(1)?
+                .checked_sub(tile_id.y)?,
+            ..tile_id
+        }),
+    }
+}
+
+/// Subdomain for the tile. It is always the same for a given tile, so that its URL, and hence
+/// its place in the cache, does not change.
+pub(crate) fn subdomain<S: AsRef<str>>(subdomains: &[S], tile_id: TileId) -> &str {
+    if subdomains.is_empty() {
+        return "";
+    }
+    let index = (tile_id.x as usize + tile_id.y as usize) % subdomains.len();
+    subdomains[index].as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Source(TileYOrigin);
+
+    impl TileSource for Source {
+        fn tile_url(&self, tile_id: TileId) -> String {
+            format!("{}/{}/{}", tile_id.zoom, tile_id.x, tile_id.y)
+        }
+
+        fn attribution(&self) -> Attribution {
+            Attribution {
+                text: "",
+                url: "",
+                logo_light: None,
+                logo_dark: None,
+            }
+        }
+
+        fn y_origin(&self) -> TileYOrigin {
+            self.0
+        }
+    }
+
+    fn tile(zoom: u8, x: u32, y: u32) -> TileId {
+        TileId { x, y, zoom }
+    }
+
+    #[test]
+    fn xyz_rows() {
+        let source = Source(TileYOrigin::Xyz);
+        assert_eq!(source_tile_id(&source, tile(3, 2, 1)), Some(tile(3, 2, 1)));
+    }
+
+    #[test]
+    fn tms_rows() {
+        let source = Source(TileYOrigin::Tms);
+        assert_eq!(source_tile_id(&source, tile(0, 0, 0)), Some(tile(0, 0, 0)));
+        assert_eq!(source_tile_id(&source, tile(3, 2, 1)), Some(tile(3, 2, 6)));
+        assert_eq!(
+            source_tile_id(&source, tile(31, 0, 0)),
+            Some(tile(31, 0, (1 << 31) - 1))
+        );
+        assert_eq!(source_tile_id(&source, tile(3, 2, 8)), None);
+        assert_eq!(source_tile_id(&source, tile(32, 0, 0)), None);
+        assert_eq!(source_tile_id(&source, tile(255, 0, 0)), None);
+    }
 }
@@ -0,0 +1,312 @@
+use std::sync::Arc;
+
+use crate::{tiles::TileId, BoundingBox};
+
+use super::{
+    contains, intersects, source_tile_id, Attribution, Authorization, AuthorizeFuture,
+    InvalidTileSize, Request, SourceParameters, SourceRegion, TileSource,
+};
+
+struct Region {
+    bounds: BoundingBox,
+    source: Box<dyn TileSource + Send>,
+}
+
+/// Source which takes tiles from different sources depending on where they are, e.g. national
+/// high-resolution imagery within a country and a global source elsewhere.
+///
+/// Only tiles lying entirely within a region are taken from its source, so that tiles on the
+/// region's boundary do not show the blank margins of regional imagery. Instead, they come from
+/// the fallback source, as do tiles beyond the regional source's maximum zoom. Beyond the
+/// fallback source's maximum zoom, tiles outside of regions are made of its deepest ones.
+///
+/// ```
+/// use walkers::{pos_from_lon_lat, sources::{Geoportal, MosaicSource, OpenStreetMap}, BoundingBox};
+///
+/// let poland = BoundingBox::new(pos_from_lon_lat(14.1, 49.0), pos_from_lon_lat(24.2, 54.9));
+/// let source = MosaicSource::new(OpenStreetMap).region(poland, Geoportal).unwrap();
+/// ```
+pub struct MosaicSource {
+    fallback: Box<dyn TileSource + Send>,
+    regions: Vec<Region>,
+}
+
+impl MosaicSource {
+    /// Mosaic taking all tiles from the `fallback` source, until regions are added. Tile size
+    /// of the mosaic is that of the fallback source, and the regional ones must match it.
+    pub fn new(fallback: impl TileSource + Send + 'static) -> Self {
+        Self {
+            fallback: Box::new(fallback),
+            regions: Vec::new(),
+        }
+    }
+
+    /// Take tiles within the bounds (in degrees) from the source. Regions added earlier take
+    /// precedence where they overlap. Fails if the source's tiles are not of the same size and
+    /// zoom offset as the fallback source's, as they would not be aligned.
+    pub fn region(
+        mut self,
+        bounds: BoundingBox,
+        source: impl TileSource + Send + 'static,
+    ) -> Result<Self, InvalidTileSize> {
+        if source.tile_size() != self.fallback.tile_size()
+            || source.zoom_offset() != self.fallback.zoom_offset()
+        {
+            return Err(InvalidTileSize);
+        }
+
+        self.regions.push(Region {
+            bounds,
+            source: Box::new(source),
+        });
+        Ok(self)
+    }
+
+    /// Attributions of sources whose tiles may be visible within the bounds, e.g.
+    /// [`crate::Projector::visible_bounds`], starting with the fallback source. The map puts
+    /// them in [`crate::MapMemory::attributions`].
+    pub fn attributions(&self, visible: BoundingBox) -> Vec<Attribution> {
+        std::iter::once(self.fallback.attribution())
+            .chain(
+                self.regions
+                    .iter()
+                    .filter(|region| intersects(region.bounds, visible))
+                    .map(|region| region.source.attribution()),
+            )
+            .collect()
+    }
+
+    fn source(&self, tile_id: TileId) -> &dyn TileSource {
+        let tile_bounds = tile_id.bounds();
+        self.regions
+            .iter()
+            .find(|region| {
+                tile_id.zoom <= region.source.max_zoom() && contains(region.bounds, tile_bounds)
+            })
+            .map_or(self.fallback.as_ref(), |region| region.source.as_ref())
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &dyn TileSource> {
+        self.regions
+            .iter()
+            .map(|region| region.source.as_ref() as &dyn TileSource)
+            .chain(std::iter::once(self.fallback.as_ref() as &dyn TileSource))
+    }
+}
+
+impl TileSource for MosaicSource {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        self.tile_url_with_parameters(tile_id, &SourceParameters::new())
+    }
+
+    fn tile_url_with_parameters(&self, tile_id: TileId, parameters: &SourceParameters) -> String {
+        let source = self.source(tile_id);
+        // Tiles which the region's source cannot number have no URL, so their download fails.
+        source_tile_id(source, tile_id).map_or_else(String::new, |tile_id| {
+            source.tile_url_with_parameters(tile_id, parameters)
+        })
+    }
+
+    fn attribution(&self) -> Attribution {
+        self.fallback.attribution()
+    }
+
+    /// Made of the sources' ids and the regions' bounds, as they decide which source each
+    /// tile comes from. None unless all sources have one.
+    fn cache_id(&self) -> Option<String> {
+        let mut id = format!("mosaic+{}", self.fallback.cache_id()?);
+        for region in &self.regions {
+            let (min, max) = (region.bounds.min(), region.bounds.max());
+            id += &format!(
+                "+{}@{},{},{},{}",
+                region.source.cache_id()?,
+                min.x,
+                min.y,
+                max.x,
+                max.y
+            );
+        }
+        Some(id)
+    }
+
+    fn tile_size(&self) -> u32 {
+        self.fallback.tile_size()
+    }
+
+    fn zoom_offset(&self) -> u8 {
+        self.fallback.zoom_offset()
+    }
+
+    fn max_zoom(&self) -> u8 {
+        self.fallback.max_zoom()
+    }
+
+    fn regions(&self) -> Vec<SourceRegion> {
+        self.regions
+            .iter()
+            .map(|region| SourceRegion {
+                bounds: region.bounds,
+                max_zoom: region.source.max_zoom(),
+                attribution: region.source.attribution(),
+            })
+            .collect()
+    }
+
+    fn authorization(&self) -> Option<Arc<dyn Authorization>> {
+        let mut authorizations = Vec::new();
+        for source in self.sources() {
+            if let Some(authorization) = source.authorization() {
+                authorizations.push((origins(source), authorization));
+            }
+        }
+        (!authorizations.is_empty())
+            .then(|| Arc::new(MosaicAuthorization { authorizations }) as Arc<dyn Authorization>)
+    }
+}
+
+/// Scheme, host and port of the source's URLs. They are taken from a few tiles, so that each
+/// of the source's subdomains is seen.
+fn origins(source: &dyn TileSource) -> Vec<String> {
+    let mut origins = Vec::new();
+    for x in 0..4 {
+        for y in 0..4 {
+            let url = source.tile_url(TileId { x, y, zoom: 2 });
+            if let Some(origin) = origin(&url) {
+                if !origins.contains(&origin) {
+                    origins.push(origin);
+                }
+            }
+        }
+    }
+    origins
+}
+
+fn origin(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()
+        .map(|url| url.origin().ascii_serialization())
+}
+
+/// Credentials of the mosaic's sources, each used for requests to the servers of its source.
+struct MosaicAuthorization {
+    authorizations: Vec<(Vec<String>, Arc<dyn Authorization>)>,
+}
+
+impl Authorization for MosaicAuthorization {
+    fn authorize(&self, request: Request, refresh: bool) -> AuthorizeFuture<'_> {
+        let origin = request.url().origin().ascii_serialization();
+        match self
+            .authorizations
+            .iter()
+            .find(|(origins, _)| origins.contains(&origin))
+        {
+            Some((_, authorization)) => authorization.authorize(request, refresh),
+            None => Box::pin(async move { Ok(request) }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lon_lat;
+
+    struct Source {
+        url: &'static str,
+        max_zoom: u8,
+        tile_size: u32,
+    }
+
+    impl TileSource for Source {
+        fn tile_url(&self, tile_id: TileId) -> String {
+            format!(
+                "{}/{}/{}/{}?key={{key}}",
+                self.url, tile_id.zoom, tile_id.x, tile_id.y
+            )
+        }
+
+        fn attribution(&self) -> Attribution {
+            Attribution {
+                text: self.url,
+                url: self.url,
+                logo_light: None,
+                logo_dark: None,
+            }
+        }
+
+        fn tile_size(&self) -> u32 {
+            self.tile_size
+        }
+
+        fn max_zoom(&self) -> u8 {
+            self.max_zoom
+        }
+
+        fn cache_id(&self) -> Option<String> {
+            Some(self.url.trim_start_matches("https://").to_owned())
+        }
+    }
+
+    fn source(url: &'static str, max_zoom: u8) -> Source {
+        Source {
+            url,
+            max_zoom,
+            tile_size: 256,
+        }
+    }
+
+    /// Western hemisphere, up to the polar regions.
+    fn west() -> BoundingBox {
+        BoundingBox::new(pos_from_lon_lat(-180., -85.), pos_from_lon_lat(0., 85.))
+    }
+
+    fn mosaic() -> MosaicSource {
+        MosaicSource::new(source("https://global", 10))
+            .region(west(), source("https://regional", 15))
+            .unwrap()
+    }
+
+    #[test]
+    fn urls_with_parameters() {
+        let mosaic = mosaic();
+        let parameters = SourceParameters::from([("key".to_owned(), "secret".to_owned())]);
+        let url = |x, y, zoom| mosaic.tile_url_with_parameters(TileId { x, y, zoom }, &parameters);
+
+        // Tile lying within the region.
+        assert_eq!(url(1, 3, 3), "https://regional/3/1/3?key=secret");
+        // Crossing the region's boundary.
+        assert_eq!(url(0, 0, 0), "https://global/0/0/0?key=secret");
+        // Beyond the regional source's zoom.
+        assert_eq!(url(1, 1, 16), "https://global/16/1/1?key=secret");
+    }
+
+    #[test]
+    fn zooms_and_regions() {
+        let mosaic = mosaic();
+        assert_eq!(mosaic.max_zoom(), 10);
+
+        let regions = mosaic.regions();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].max_zoom, 15);
+        assert_eq!(regions[0].attribution.text, "https://regional");
+    }
+
+    #[test]
+    fn cache_id() {
+        assert_eq!(
+            mosaic().cache_id().as_deref(),
+            Some("mosaic+global+regional@-180,-85,0,85")
+        );
+    }
+
+    #[test]
+    fn tile_sizes_must_match() {
+        let large = Source {
+            tile_size: 512,
+            ..source("https://large", 10)
+        };
+        assert!(MosaicSource::new(source("https://global", 10))
+            .region(west(), large)
+            .is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use super::{Attribution, TileSource};
+use crate::tiles::TileId;
+
+/// Seamarks, lights, harbours and other nautical features from OpenSeaMap, on a transparent
+/// background. Meant as an overlay drawn with [`crate::extras::TileLayer`] over a base map,
+/// e.g. [`super::OpenStreetMap`].
+/// <https://www.openseamap.org>
+pub struct OpenSeaMap;
+
+impl TileSource for OpenSeaMap {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        format!(
+            "https://tiles.openseamap.org/seamark/{}/{}/{}.png",
+            tile_id.zoom, tile_id.x, tile_id.y
+        )
+    }
+
+    fn attribution(&self) -> Attribution {
+        Attribution {
+            text: "© OpenSeaMap contributors",
+            url: "https://www.openseamap.org",
+            logo_light: None,
+            logo_dark: None,
+        }
+    }
+
+    fn max_zoom(&self) -> u8 {
+        18
+    }
+}
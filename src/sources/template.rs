@@ -0,0 +1,91 @@
+use super::{subdomain, Attribution, TileSource};
+use crate::tiles::TileId;
+
+/// Source of any XYZ tile service, given its URL template. Placeholders `{z}`, `{x}` and `{y}`
+/// are replaced by the tile's coordinates, and alternatives such as `{a|b|c}` by one of them,
+/// which spreads the requests between the provider's servers.
+///
+/// ```
+/// use walkers::sources::UrlTemplate;
+///
+/// let source = UrlTemplate::new(
+///     "https://{a|b|c}.tile.example.com/{z}/{x}/{y}.png",
+///     "© Example",
+///     "https://example.com/",
+/// );
+/// ```
+pub struct UrlTemplate {
+    template: String,
+    /// Placeholder of the alternatives, along with them.
+    subdomains: Option<(String, Vec<String>)>,
+    attribution: (&'static str, &'static str),
+    max_zoom: u8,
+}
+
+impl UrlTemplate {
+    pub fn new(
+        template: impl Into<String>,
+        attribution_text: &'static str,
+        attribution_url: &'static str,
+    ) -> Self {
+        let template = template.into();
+        let subdomains = alternatives(&template);
+        Self {
+            template,
+            subdomains,
+            attribution: (attribution_text, attribution_url),
+            max_zoom: 19,
+        }
+    }
+
+    /// Highest zoom level served by the provider. Default is 19.
+    pub fn max_zoom(mut self, max_zoom: u8) -> Self {
+        self.max_zoom = max_zoom;
+        self
+    }
+}
+
+/// First placeholder with alternatives separated by `|`, e.g. `{a|b|c}`.
+fn alternatives(template: &str) -> Option<(String, Vec<String>)> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        let inner = &rest[start + 1..end];
+        if inner.contains('|') {
+            let subdomains = inner.split('|').map(str::to_owned).collect();
+            return Some((rest[start..=end].to_owned(), subdomains));
+        }
+        rest = &rest[end + 1..];
+    }
+    None
+}
+
+impl TileSource for UrlTemplate {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        let url = self
+            .template
+            .replace("{z}", &tile_id.zoom.to_string())
+            .replace("{x}", &tile_id.x.to_string())
+            .replace("{y}", &tile_id.y.to_string());
+
+        match &self.subdomains {
+            Some((placeholder, subdomains)) => {
+                url.replace(placeholder, subdomain(subdomains, tile_id))
+            }
+            None => url,
+        }
+    }
+
+    fn attribution(&self) -> Attribution {
+        Attribution {
+            text: self.attribution.0,
+            url: self.attribution.1,
+            logo_light: None,
+            logo_dark: None,
+        }
+    }
+
+    fn max_zoom(&self) -> u8 {
+        self.max_zoom
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Attribution, TileSource};
+use crate::{tiles::TileId, PlateCarree};
+
+/// Half of the Web Mercator world's side, in meters.
+const WEB_MERCATOR_EXTENT: f64 = 20_037_508.342_789_244;
+
+/// Coordinate reference system in which [`WmsSource`] requests images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WmsCrs {
+    /// Web Mercator, matching the regular tile grid.
+    #[default]
+    WebMercator,
+
+    /// Geographical coordinates, matching the tile grid of [`PlateCarree`], which should then be
+    /// set as the map's projection.
+    Wgs84,
+}
+
+impl WmsCrs {
+    fn code(&self) -> &'static str {
+        match self {
+            WmsCrs::WebMercator => "EPSG:3857",
+            WmsCrs::Wgs84 => "EPSG:4326",
+        }
+    }
+}
+
+/// Source requesting tiles from a Web Map Service (WMS 1.3.0) with GetMap requests, one image
+/// per tile.
+///
+/// ```
+/// use walkers::sources::WmsSource;
+///
+/// let source = WmsSource::new("https://example.com/wms", "orthophoto")
+///     .style("default")
+///     .attribution("© Example", "https://example.com/");
+/// ```
+#[derive(Clone, Debug)]
+pub struct WmsSource {
+    url: String,
+    layers: String,
+    styles: String,
+    crs: WmsCrs,
+    format: String,
+    transparent: bool,
+    attribution: (&'static str, &'static str),
+}
+
+impl WmsSource {
+    /// Source of given layers (comma-separated) of the service at given base URL, which may
+    /// already contain query parameters, e.g. an API key.
+    pub fn new(url: impl Into<String>, layers: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            layers: layers.into(),
+            styles: String::new(),
+            crs: WmsCrs::default(),
+            format: "image/png".to_owned(),
+            transparent: false,
+            attribution: ("", ""),
+        }
+    }
+
+    /// Styles of the layers (comma-separated). Default is empty, meaning the server's default
+    /// ones.
+    pub fn style(mut self, styles: impl Into<String>) -> Self {
+        self.styles = styles.into();
+        self
+    }
+
+    /// Default is [`WmsCrs::WebMercator`].
+    pub fn crs(mut self, crs: WmsCrs) -> Self {
+        self.crs = crs;
+        self
+    }
+
+    /// MIME type of the images. Default is `image/png`.
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = format.into();
+        self
+    }
+
+    /// Request images with transparent background, for overlays. Default is false.
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn attribution(mut self, text: &'static str, url: &'static str) -> Self {
+        self.attribution = (text, url);
+        self
+    }
+
+    /// Bounding box of the tile in the CRS, formatted as WMS 1.3.0 expects, that is in the
+    /// CRS's axis order, which is latitude first for EPSG:4326.
+    fn bbox(&self, tile_id: TileId) -> String {
+        match self.crs {
+            WmsCrs::WebMercator => {
+                let size = 2. * WEB_MERCATOR_EXTENT / 2f64.powi(tile_id.zoom as i32);
+                let min_x = -WEB_MERCATOR_EXTENT + tile_id.x as f64 * size;
+                let max_y = WEB_MERCATOR_EXTENT - tile_id.y as f64 * size;
+                format!("{},{},{},{}", min_x, max_y - size, min_x + size, max_y)
+            }
+            WmsCrs::Wgs84 => {
+                let bounds = PlateCarree.tile_bounds(tile_id);
+                let (min, max) = (bounds.min(), bounds.max());
+                format!("{},{},{},{}", min.y, min.x, max.y, max.x)
+            }
+        }
+    }
+}
+
+impl TileSource for WmsSource {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        let size = self.tile_size();
+        format!(
+            "{}{separator}SERVICE=WMS\
+            &REQUEST=GetMap\
+            &VERSION=1.3.0\
+            &LAYERS={}\
+            &STYLES={}\
+            &CRS={}\
+            &BBOX={}\
+            &WIDTH={size}\
+            &HEIGHT={size}\
+            &FORMAT={}\
+            &TRANSPARENT={}",
+            self.url,
+            encode(&self.layers),
+            encode(&self.styles),
+            self.crs.code(),
+            self.bbox(tile_id),
+            encode(&self.format),
+            if self.transparent { "TRUE" } else { "FALSE" },
+        )
+    }
+
+    fn attribution(&self) -> Attribution {
+        Attribution {
+            text: self.attribution.0,
+            url: self.attribution.1,
+            logo_light: None,
+            logo_dark: None,
+        }
+    }
+}
+
+/// Percent-encode the value of a query parameter. Commas, which separate layers and styles,
+/// colons, common in layer names, and slashes of MIME types are kept, as they are valid in
+/// queries.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b','
+            | b':'
+            | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode("topp:states,roads"), "topp:states,roads");
+        assert_eq!(encode("image/png"), "image/png");
+        assert_eq!(encode("Land & Sea"), "Land%20%26%20Sea");
+        assert_eq!(encode("a+b=c#d?"), "a%2Bb%3Dc%23d%3F");
+        assert_eq!(encode("Łódź"), "%C5%81%C3%B3d%C5%BA");
+    }
+
+    #[test]
+    fn url() {
+        let source = WmsSource::new("https://example.com/wms?key=secret", "Roads & Rails")
+            .style("dark+bold")
+            .format("image/png; mode=8bit");
+        let url = source.tile_url(TileId {
+            x: 0,
+            y: 0,
+            zoom: 0,
+        });
+        assert!(url.starts_with("https://example.com/wms?key=secret&SERVICE=WMS&"));
+        assert!(url.contains("&LAYERS=Roads%20%26%20Rails&"));
+        assert!(url.contains("&STYLES=dark%2Bbold&"));
+        assert!(url.contains("&FORMAT=image/png%3B%20mode%3D8bit&"));
+    }
+}
@@ -1,16 +1,33 @@
-use std::collections::{hash_map::Entry, HashMap};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
 
-use egui::{pos2, Color32, Context, Mesh, Pos2, Rect, Vec2};
+use egui::{pos2, vec2, Color32, Context, Mesh, Pos2, Rect, TextureOptions, Vec2};
 use egui::{ColorImage, TextureHandle};
-use futures::channel::mpsc::{channel, Receiver, Sender, TrySendError};
+use futures::channel::{
+    mpsc::{channel, Receiver, Sender, TryRecvError},
+    oneshot,
+};
 use image::ImageError;
 use lru::LruCache;
 
-use crate::units::Pixel;
+use crate::units::{tile_id_of_normalized, BoundingBox, Pixel, Position, PositionTrait};
 use crate::{
-    download::{download_continuously, HttpOptions, MAX_PARALLEL_DOWNLOADS},
+    cache::TileCache,
+    download::{
+        download_continuously, HttpOptions, Request, SharedParameters, TileInfo, TileOrigin,
+        TileResult, UploadBudget, MAX_PARALLEL_DOWNLOADS,
+    },
     io::Runtime,
-    sources::{Attribution, TileSource},
+    map_memory::ScreenTransform,
+    placeholder::Placeholder,
+    projector::ProjectorType,
+    sources::{
+        contains, intersects, validate_tile_size, zoom_offset, Attribution, SourceRegion,
+        TileSource,
+    },
+    validation::{Validation, ValidationError},
 };
 
 pub(crate) fn rect(screen_position: Pos2, tile_size: f64) -> Rect {
@@ -20,25 +37,34 @@ pub(crate) fn rect(screen_position: Pos2, tile_size: f64) -> Rect {
 #[derive(Clone)]
 pub struct Texture(TextureHandle);
 
+/// Decode the image into egui's [`ColorImage`]. Does not need egui's [`Context`], so it can be
+/// done outside of the UI thread.
+pub(crate) fn decode(image: &[u8]) -> Result<ColorImage, ImageError> {
+    let image = image::load_from_memory(image)?.to_rgba8();
+    let pixels = image.as_flat_samples();
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [image.width() as _, image.height() as _],
+        pixels.as_slice(),
+    ))
+}
+
 impl Texture {
     pub fn new(image: &[u8], ctx: &Context) -> Result<Self, ImageError> {
-        let image = image::load_from_memory(image)?.to_rgba8();
-        let pixels = image.as_flat_samples();
-        let image = ColorImage::from_rgba_unmultiplied(
-            [image.width() as _, image.height() as _],
-            pixels.as_slice(),
-        );
-
-        Ok(Self::from_color_image(image, ctx))
+        Ok(Self::from_color_image(decode(image)?, ctx))
     }
 
     /// Load the texture from egui's [`ColorImage`].
     pub fn from_color_image(color_image: ColorImage, ctx: &Context) -> Self {
-        Self(ctx.load_texture("image", color_image, Default::default()))
+        Self::from_color_image_with_options(color_image, ctx, Default::default())
     }
 
-    pub(crate) fn mesh_with_uv(&self, screen_position: Pos2, tile_size: f64, uv: Rect) -> Mesh {
-        self.mesh_with_rect_and_uv(rect(screen_position, tile_size), uv)
+    /// Like [`Texture::from_color_image`], but with given filtering and wrapping.
+    pub fn from_color_image_with_options(
+        color_image: ColorImage,
+        ctx: &Context,
+        options: TextureOptions,
+    ) -> Self {
+        Self(ctx.load_texture("image", color_image, options))
     }
 
     pub(crate) fn mesh_with_rect_and_uv(&self, rect: Rect, uv: Rect) -> Mesh {
@@ -47,6 +73,23 @@ impl Texture {
         mesh
     }
 
+    /// Like [`Texture::mesh_with_rect_and_uv`], but made of `n` by `n` quads.
+    pub(crate) fn mesh_with_rect_and_uv_grid(&self, rect: Rect, uv: Rect, n: usize) -> Mesh {
+        let mut mesh = Mesh::with_texture(self.0.id());
+        let cell = |r: Rect, i: usize, j: usize| {
+            Rect::from_min_max(
+                r.lerp_inside(vec2(i as f32, j as f32) / n as f32),
+                r.lerp_inside(vec2(i as f32 + 1., j as f32 + 1.) / n as f32),
+            )
+        };
+        for i in 0..n {
+            for j in 0..n {
+                mesh.add_rect_with_uv(cell(rect, i, j), cell(uv, i, j), Color32::WHITE);
+            }
+        }
+        mesh
+    }
+
     pub(crate) fn size(&self) -> Vec2 {
         self.0.size_vec2()
     }
@@ -62,6 +105,12 @@ impl Texture {
     }
 }
 
+impl From<TextureHandle> for Texture {
+    fn from(handle: TextureHandle) -> Self {
+        Self(handle)
+    }
+}
+
 /// Texture with UV coordinates.
 pub struct TextureWithUv {
     pub texture: Texture,
@@ -72,26 +121,149 @@ pub trait Tiles {
     fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv>;
     fn attribution(&self) -> Attribution;
     fn tile_size(&self) -> u32;
+
+    /// How many zoom levels the tiles are shifted relative to the 256px ones.
+    /// See [`TileSource::zoom_offset`].
+    fn zoom_offset(&self) -> u8 {
+        zoom_offset(self.tile_size())
+    }
+
+    /// Take tiles which failed to load since the last call, along with the reason.
+    fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        Vec::new()
+    }
+
+    /// Identifies where the tiles come from, so that the map can tell when it is given tiles of
+    /// another source. Must differ between sources, even ones with the same attribution, e.g. the
+    /// URL template of the tiles.
+    fn source_id(&self) -> String;
+
+    /// Attributions of the sources whose tiles may be visible within the bounds, in degrees.
+    /// Default is just [`Tiles::attribution`]. See [`TileSource::regions`].
+    fn attributions(&self, _visible: BoundingBox) -> Vec<Attribution> {
+        vec![self.attribution()]
+    }
+}
+
+/// Bookkeeping of tiles loaded in the background, shared by [`HttpTiles`] and other [`Tiles`]:
+/// the ones which are loaded, being loaded, or failed, along with the reasons of failures.
+///
+/// Failed tiles are not retried, unless they get evicted from the cache or it gets cleared.
+pub(crate) struct LoadedTiles<T> {
+    /// `None` for tiles which are being loaded, or failed.
+    cache: LruCache<TileId, Option<T>>,
+
+    /// Tiles which failed to load since the last call to [`LoadedTiles::take_errors`].
+    errors: Vec<(TileId, String)>,
+}
+
+impl<T: Clone> LoadedTiles<T> {
+    pub fn new() -> Self {
+        // Just arbitrary value which seemed right.
+        #[allow(clippy::unwrap_used)]
+        let cache_size = std::num::NonZeroUsize::new(256).unwrap();
+
+        Self {
+            cache: LruCache::new(cache_size),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Loaded tile, if any.
+    pub fn get(&mut self, tile_id: TileId) -> Option<T> {
+        self.cache.get(&tile_id).cloned().flatten()
+    }
+
+    /// Like [`LoadedTiles::get`], but if the tile was not requested yet, `request` is called. It
+    /// returns whether the tile was requested, if not, e.g. because a queue is full, it will be
+    /// requested again by one of the next calls.
+    pub fn get_or_request(&mut self, tile_id: TileId, request: impl FnOnce() -> bool) -> Option<T> {
+        match self.cache.get(&tile_id) {
+            Some(tile) => tile.clone(),
+            None => {
+                if request() {
+                    self.cache.put(tile_id, None);
+                }
+                None
+            }
+        }
+    }
+
+    pub fn loaded(&mut self, tile_id: TileId, tile: T) {
+        self.cache.put(tile_id, Some(tile));
+    }
+
+    /// Record the failure. Tile stays as if being loaded, so that it is not requested again.
+    pub fn failed(&mut self, tile_id: TileId, error: String) {
+        self.errors.push((tile_id, error));
+    }
+
+    pub fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Forget all tiles, so that they are requested again.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
 }
 
-/// Downloads the tiles via HTTP. It must persist between frames.
+/// Downloads the tiles via HTTP. It must persist between frames. Each instance has its own cache
+/// and IO thread, see [`crate::SharedTiles`] for using it in many maps.
 pub struct HttpTiles {
     attribution: Attribution,
 
-    cache: LruCache<TileId, Option<Texture>>,
+    tiles: LoadedTiles<Texture>,
+
+    /// How the tiles in the cache were obtained.
+    info: LruCache<TileId, TileInfo>,
+
+    stats: TileStats,
 
     /// Tiles to be downloaded by the IO thread.
-    request_tx: Sender<TileId>,
+    request_tx: Sender<Request>,
 
-    /// Tiles that got downloaded and should be put in the cache.
-    tile_rx: Receiver<(TileId, Texture)>,
+    /// Tiles that got downloaded and decoded, and should be uploaded and put in the cache.
+    tile_rx: Receiver<TileResult>,
 
     #[allow(dead_code)] // Significant Drop
     runtime: Runtime,
 
     tile_size: u32,
 
+    zoom_offset: u8,
+
     max_zoom: u8,
+
+    /// See [`TileSource::regions`].
+    regions: Vec<SourceRegion>,
+
+    upload_budget: UploadBudget,
+
+    egui_ctx: Context,
+
+    /// See [`HttpOptions::texture_filter`].
+    texture_options: TextureOptions,
+
+    /// Frame in which downloaded tiles were last put in the cache.
+    last_pass: Option<u64>,
+
+    parameters: SharedParameters,
+
+    /// Generation of the [`SharedParameters`] which tiles in the cache were downloaded with.
+    generation: u64,
+
+    placeholder: Placeholder,
+
+    /// Texture of the placeholder, unless it is drawn from other tiles, or not at all.
+    placeholder_texture: Option<Texture>,
+
+    /// See [`HttpOptions::tile_cache`] and [`TileSource::cache_id`].
+    tile_cache: Option<(Arc<dyn TileCache>, String)>,
+
+    /// Source's [`TileSource::cache_id`], or its URL of the whole world, which tell sources
+    /// apart. See [`Tiles::source_id`].
+    source_id: String,
 }
 
 impl HttpTiles {
@@ -115,75 +287,216 @@ impl HttpTiles {
         let (tile_tx, tile_rx) = channel(channel_size);
         let attribution = source.attribution();
         let tile_size = source.tile_size();
+        if let Err(error) = validate_tile_size(tile_size) {
+            log::error!("{}: {}, tiles will not be aligned.", error, tile_size);
+        }
+        let zoom_offset = source.zoom_offset();
         let max_zoom = source.max_zoom();
+        let regions = source.regions();
+        let upload_budget = http_options.upload_budget;
+        let texture_options = TextureOptions {
+            magnification: http_options.texture_filter,
+            minification: http_options.texture_filter,
+            ..Default::default()
+        };
+        let tile_cache = http_options.tile_cache.clone().zip(source.cache_id());
+        let source_id = source.cache_id().unwrap_or_else(|| {
+            source.tile_url(TileId {
+                x: 0,
+                y: 0,
+                zoom: 0,
+            })
+        });
+
+        // IO thread does not touch egui, other than waking it up.
+        let repaint = {
+            let egui_ctx = egui_ctx.clone();
+            Box::new(move || egui_ctx.request_repaint())
+        };
+
+        let parameters = SharedParameters::default();
 
         let runtime = Runtime::new(download_continuously(
             source,
             http_options,
             request_rx,
             tile_tx,
-            egui_ctx,
+            parameters.clone(),
+            repaint,
         ));
 
-        // Just arbitrary value which seemed right.
+        // Same as in LoadedTiles.
         #[allow(clippy::unwrap_used)]
         let cache_size = std::num::NonZeroUsize::new(256).unwrap();
 
         Self {
             attribution,
-            cache: LruCache::new(cache_size),
+            tiles: LoadedTiles::new(),
+            info: LruCache::new(cache_size),
+            stats: TileStats::default(),
             request_tx,
             tile_rx,
             runtime,
             tile_size,
+            zoom_offset,
             max_zoom,
+            regions,
+            upload_budget,
+            egui_ctx,
+            texture_options,
+            last_pass: None,
+            parameters,
+            generation: 0,
+            placeholder: Placeholder::default(),
+            placeholder_texture: None,
+            tile_cache,
+            source_id,
         }
     }
 
-    fn put_single_downloaded_tile_in_cache(&mut self) {
-        // This is called every frame, so take just one at the time.
-        match self.tile_rx.try_next() {
-            Ok(Some((tile_id, tile))) => {
-                self.cache.put(tile_id, Some(tile));
-            }
-            Err(_) => {
-                // Just ignore. It means that no new tile was downloaded.
+    /// Set a runtime parameter of the source, such as `language`, which is then used to build
+    /// tile URLs. See [`TileSource::tile_url_with_parameters`]. Tiles downloaded with
+    /// previous parameter values are discarded.
+    pub fn set_parameter(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.generation = self.parameters.set(name.into(), value.into());
+        self.tiles.clear();
+        self.info.clear();
+    }
+
+    /// Discard the tiles of this source, both loaded ones and those in the
+    /// [`HttpOptions::tile_cache`], so that they are downloaded again. The tile cache is cleared
+    /// only if the source has a [`TileSource::cache_id`].
+    pub fn clear_cache(&mut self) {
+        self.tiles.clear();
+        self.info.clear();
+        if let Some((tile_cache, source_id)) = &self.tile_cache {
+            tile_cache.invalidate_source(source_id);
+        }
+    }
+
+    /// Where the tile came from and how old it is, if it was loaded recently. Useful to verify
+    /// that caches are configured correctly.
+    pub fn tile_info(&self, tile_id: TileId) -> Option<TileInfo> {
+        self.info.peek(&tile_id).copied()
+    }
+
+    /// Check whether the source works, by downloading its top-level tile while bypassing the
+    /// caches. Useful for showing a clear configuration error, e.g. an invalid API key, instead
+    /// of a blank map.
+    pub fn validate(&mut self) -> Validation {
+        let (result_tx, result_rx) = oneshot::channel();
+        match self.request_tx.try_send(Request::Validate(result_tx)) {
+            Ok(()) => Validation::new(result_rx),
+            Err(_) => Validation::failed(ValidationError::DownloadThread),
+        }
+    }
+
+    /// Number of tiles loaded so far, by their origin.
+    pub fn stats(&self) -> TileStats {
+        self.stats
+    }
+
+    /// Set what is drawn in place of tiles which are not downloaded yet. Default is
+    /// [`Placeholder::Parent`].
+    pub fn set_placeholder(&mut self, placeholder: Placeholder) {
+        self.placeholder_texture = placeholder.texture(&self.egui_ctx);
+        self.placeholder = placeholder;
+    }
+
+    /// Put downloaded tiles in the cache, but no more than the [`UploadBudget`] allows. The rest
+    /// is left for the subsequent frames.
+    fn put_downloaded_tiles_in_cache(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let started = std::time::Instant::now();
+
+        for _ in 0..self.upload_budget.max_tiles {
+            #[cfg(not(target_arch = "wasm32"))]
+            if self
+                .upload_budget
+                .max_duration
+                .is_some_and(|max_duration| started.elapsed() >= max_duration)
+            {
+                break;
             }
-            Ok(None) => {
-                log::error!("IO thread is dead")
+
+            match self.tile_rx.try_recv() {
+                Ok(tile) if tile.generation != self.generation => {
+                    log::trace!("Dropping outdated tile: {:?}", tile.tile_id);
+                }
+                Ok(TileResult {
+                    tile_id,
+                    result: Ok((image, info)),
+                    ..
+                }) => {
+                    let tile = Texture::from_color_image_with_options(
+                        image,
+                        &self.egui_ctx,
+                        self.texture_options,
+                    );
+                    self.tiles.loaded(tile_id, tile);
+                    self.info.put(tile_id, info);
+                    self.stats.count(info.origin);
+                }
+                Ok(TileResult {
+                    tile_id,
+                    result: Err(error),
+                    ..
+                }) => {
+                    self.tiles.failed(tile_id, error);
+                    self.stats.failed += 1;
+                }
+                Err(TryRecvError::Empty) => {
+                    // No more tiles were downloaded.
+                    return;
+                }
+                Err(TryRecvError::Closed) => {
+                    log::error!("IO thread is dead");
+                    return;
+                }
             }
         }
+
+        // Budget is exhausted, but there might be more tiles waiting.
+        self.egui_ctx.request_repaint();
     }
 
     fn make_sure_is_downloaded(&mut self, tile_id: TileId) {
-        if self
-            .cache
-            .try_get_or_insert(
-                tile_id,
-                || -> Result<Option<Texture>, TrySendError<TileId>> {
-                    self.request_tx.try_send(tile_id)?;
+        let request_tx = &mut self.request_tx;
+        self.tiles.get_or_request(tile_id, || {
+            match request_tx.try_send(Request::Tile(tile_id)) {
+                Ok(()) => {
                     log::trace!("Requested tile: {:?}", tile_id);
-                    Ok(None)
-                },
-            )
-            .is_err()
-        {
-            log::debug!("Request queue is full.");
-        }
+                    true
+                }
+                Err(_) => {
+                    log::debug!("Request queue is full.");
+                    false
+                }
+            }
+        });
     }
 
     /// Get at tile, or interpolate it from lower zoom levels.
-    fn get_or_interpolate(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
+    fn get_or_interpolate(&mut self, tile_id: TileId, max_zoom: u8) -> Option<TextureWithUv> {
         let mut zoom_candidate = tile_id.zoom;
 
         loop {
             let (zoomed_tile_id, uv) = interpolate_higher_zoom(tile_id, zoom_candidate);
 
-            if let Some(Some(texture)) = self.cache.get(&zoomed_tile_id) {
-                break Some(TextureWithUv {
-                    texture: texture.clone(),
-                    uv,
-                });
+            if let Some(texture) = self.tiles.get(zoomed_tile_id) {
+                break Some(TextureWithUv { texture, uv });
+            }
+
+            // Beyond the source's max zoom, tiles are always made of its deepest ones. Other than
+            // that, lower zoom levels are only a placeholder, which might not be desired.
+            if zoom_candidate <= max_zoom && !matches!(self.placeholder, Placeholder::Parent) {
+                break self
+                    .placeholder_texture
+                    .clone()
+                    .map(|texture| TextureWithUv {
+                        texture,
+                        uv: Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+                    });
             }
 
             // Keep zooming out until we find a donor or there is no more zoom levels.
@@ -192,6 +505,23 @@ impl HttpTiles {
     }
 }
 
+/// Zoom of the deepest tile of the source which covers the tile. See [`TileSource::regions`].
+fn max_zoom_at(tile_id: TileId, max_zoom: u8, regions: &[SourceRegion]) -> u8 {
+    let mut zoom = tile_id.zoom;
+    while zoom > max_zoom {
+        let (covering, _) = interpolate_higher_zoom(tile_id, zoom);
+        let bounds = covering.bounds();
+        if regions
+            .iter()
+            .any(|region| zoom <= region.max_zoom && contains(region.bounds, bounds))
+        {
+            break;
+        }
+        zoom -= 1;
+    }
+    zoom
+}
+
 /// Take a piece of a tile with higher zoom level and use it as a tile with lower zoom level.
 fn interpolate_higher_zoom(tile_id: TileId, available_zoom: u8) -> (TileId, Rect) {
     assert!(tile_id.zoom >= available_zoom);
@@ -224,22 +554,64 @@ impl Tiles for HttpTiles {
         self.attribution.clone()
     }
 
+    fn source_id(&self) -> String {
+        self.source_id.clone()
+    }
+
+    fn attributions(&self, visible: BoundingBox) -> Vec<Attribution> {
+        std::iter::once(self.attribution.clone())
+            .chain(
+                self.regions
+                    .iter()
+                    .filter(|region| intersects(region.bounds, visible))
+                    .map(|region| region.attribution.clone()),
+            )
+            .collect()
+    }
+
     /// Return a tile if already in cache, schedule a download otherwise.
     fn at(&mut self, tile_id: TileId) -> Option<TextureWithUv> {
-        self.put_single_downloaded_tile_in_cache();
-
-        self.make_sure_is_downloaded(if tile_id.zoom > self.max_zoom {
-            interpolate_higher_zoom(tile_id, self.max_zoom).0
-        } else {
-            tile_id
-        });
+        // This is called for each visible tile, but the cache is updated once per frame.
+        let pass = self.egui_ctx.cumulative_pass_nr();
+        if self.last_pass.replace(pass) != Some(pass) {
+            self.put_downloaded_tiles_in_cache();
+        }
 
-        self.get_or_interpolate(tile_id)
+        let max_zoom = max_zoom_at(tile_id, self.max_zoom, &self.regions);
+        self.make_sure_is_downloaded(interpolate_higher_zoom(tile_id, max_zoom).0);
+        self.get_or_interpolate(tile_id, max_zoom)
     }
 
     fn tile_size(&self) -> u32 {
         self.tile_size
     }
+
+    fn zoom_offset(&self) -> u8 {
+        self.zoom_offset
+    }
+
+    fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        self.tiles.take_errors()
+    }
+}
+
+/// Number of tiles loaded by [`HttpTiles`], by their origin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileStats {
+    pub tile_cache: usize,
+    pub http_cache: usize,
+    pub network: usize,
+    pub failed: usize,
+}
+
+impl TileStats {
+    fn count(&mut self, origin: TileOrigin) {
+        match origin {
+            TileOrigin::TileCache => self.tile_cache += 1,
+            TileOrigin::HttpCache => self.http_cache += 1,
+            TileOrigin::Network => self.network += 1,
+        }
+    }
 }
 
 /// Coordinates of the OSM-like tile.
@@ -265,6 +637,90 @@ impl TileId {
         }
     }
 
+    /// Tile containing given geographical position at given zoom level.
+    pub fn from_position(position: Position, zoom: u8) -> TileId {
+        position.tile_id(zoom, 0)
+    }
+
+    /// Geographical position of the north-west corner of the tile with given numbers, which may
+    /// be one past the last tile of the zoom level.
+    fn north_west(x: u64, y: u64, zoom: u8) -> Position {
+        let number_of_tiles = 2f64.powi(zoom as i32);
+        let lon = x as f64 / number_of_tiles * 360. - 180.;
+        let lat = (std::f64::consts::PI * (1. - 2. * y as f64 / number_of_tiles))
+            .sinh()
+            .atan()
+            .to_degrees();
+        crate::pos_from_lon_lat(lon, lat)
+    }
+
+    /// Geographical area covered by this tile.
+    pub fn bounds(&self) -> BoundingBox {
+        let (x, y) = (u64::from(self.x), u64::from(self.y));
+        BoundingBox::new(
+            Self::north_west(x, y, self.zoom),
+            // South-east corner, which is the north-west one of the next tile.
+            Self::north_west(x + 1, y + 1, self.zoom),
+        )
+    }
+
+    /// Tile one zoom level lower, which contains this one.
+    pub fn parent(&self) -> Option<TileId> {
+        Some(TileId {
+            x: self.x / 2,
+            y: self.y / 2,
+            zoom: self.zoom.checked_sub(1)?,
+        })
+    }
+
+    /// Four tiles one zoom level higher, covering this one. Ordered as north-west, north-east,
+    /// south-west, south-east. `None` if their numbers do not fit.
+    pub fn children(&self) -> Option<[TileId; 4]> {
+        let zoom = self.zoom.checked_add(1)?;
+        let (x, y) = (self.x.checked_mul(2)?, self.y.checked_mul(2)?);
+        let (east, south) = (x.checked_add(1)?, y.checked_add(1)?);
+        Some([
+            TileId { x, y, zoom },
+            TileId { x: east, y, zoom },
+            TileId { x, y: south, zoom },
+            TileId {
+                x: east,
+                y: south,
+                zoom,
+            },
+        ])
+    }
+
+    /// [Quadkey](https://learn.microsoft.com/en-us/bingmaps/articles/bing-maps-tile-system)
+    /// of this tile, as used by Bing Maps.
+    pub fn to_quadkey(&self) -> String {
+        (1..=self.zoom)
+            .rev()
+            .map(|level| {
+                // Levels beyond the width of the numbers are leading zeros.
+                let bit = |n: u32| n.checked_shr(u32::from(level - 1)).unwrap_or(0) & 1;
+                let digit = bit(self.x) as u8 + 2 * bit(self.y) as u8;
+                char::from(b'0' + digit)
+            })
+            .collect()
+    }
+
+    /// Parse the quadkey. Returns `None` if it contains anything other than digits 0-3, or if the
+    /// tile's numbers do not fit.
+    pub fn from_quadkey(quadkey: &str) -> Option<TileId> {
+        let zoom = u8::try_from(quadkey.len()).ok()?;
+        let mut tile_id = TileId { x: 0, y: 0, zoom };
+
+        let shift = |n: u32, bit: u32| n.checked_mul(2)?.checked_add(bit);
+        for c in quadkey.chars() {
+            let digit = c.to_digit(4)?;
+            tile_id.x = shift(tile_id.x, digit & 1)?;
+            tile_id.y = shift(tile_id.y, digit >> 1)?;
+        }
+
+        Some(tile_id)
+    }
+
     pub fn east(&self) -> Option<TileId> {
         Some(TileId {
             x: self.x + 1,
@@ -298,28 +754,81 @@ impl TileId {
     }
 }
 
+/// Zoom level of the tiles to draw at the map's `zoom`, such that each pixel of a tile covers
+/// about one physical pixel of the display. This fetches one level deeper on a 2x display, and
+/// one level shallower for @2x tiles on a regular one.
+pub(crate) fn detail_zoom(zoom: f64, pixels_per_point: f32, tiles: &dyn Tiles) -> u8 {
+    // Pixels of a tile per point of a map at its zoom level, e.g. 2 for @2x tiles.
+    let tile_density = tiles.tile_size() as f64
+        / (crate::TILE_SIZE as f64 * 2f64.powi(tiles.zoom_offset() as i32));
+    let bias = (pixels_per_point as f64 / tile_density).log2();
+    (zoom + bias).round().clamp(0., u8::MAX as f64) as u8
+}
+
+/// Tile containing the map's center and the center's position in the bitmap made of tiles,
+/// which is where [`flood_fill_tiles`] starts.
+pub(crate) fn center_tile(
+    projection: &ProjectorType,
+    map_center: Position,
+    zoom: f64,
+    tile_zoom: u8,
+    zoom_offset: u8,
+) -> (TileId, Pixel) {
+    match projection {
+        ProjectorType::Custom(projection) => (
+            tile_id_of_normalized(projection.normalize(map_center), tile_zoom, zoom_offset),
+            map_center.custom_bitmap_project(zoom, projection.as_ref()),
+        ),
+        _ => (
+            map_center.tile_id(tile_zoom, zoom_offset),
+            map_center.global_bitmap_project(zoom),
+        ),
+    }
+}
+
 /// Use simple [flood fill algorithm](https://en.wikipedia.org/wiki/Flood_fill) to draw tiles on the map.
+/// Tiles are rotated and tilted about the viewport's center by `transform`, see
+/// [`crate::MapMemory::set_bearing`] and [`crate::MapMemory::set_tilt`].
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn flood_fill_tiles(
     viewport: Rect,
+    transform: ScreenTransform,
+    projection: &ProjectorType,
     tile_id: TileId,
     map_center_projected_position: Pixel,
     zoom: f64,
     tiles: &mut dyn Tiles,
     meshes: &mut HashMap<TileId, Option<Mesh>>,
 ) {
-    // We need to make up the difference between integer and floating point zoom levels.
-    let corrected_tile_size = tiles.tile_size() as f64 * 2f64.powf(zoom - zoom.round());
+    let fill_area = transform.visible_area(viewport);
+
+    // We need to make up the difference between the map's zoom level and the one of the tiles,
+    // which is not only fractional, but may also be shifted by the display's pixel density.
+    let corrected_tile_size = crate::TILE_SIZE as f64 * 2f64.powf(zoom - tile_id.zoom as f64);
     let tile_projected = tile_id.tile_pos(corrected_tile_size) - map_center_projected_position;
 
     let tile_screen_position =
         viewport.center() + egui::Vec2::new(tile_projected.x as f32, tile_projected.y as f32);
 
-    if viewport.intersects(rect(tile_screen_position, corrected_tile_size)) {
+    if fill_area.intersects(rect(tile_screen_position, corrected_tile_size)) {
         if let Entry::Vacant(entry) = meshes.entry(tile_id) {
+            let has_tile = match projection {
+                ProjectorType::Custom(projection) => projection.has_tile(tile_id),
+                _ => true,
+            };
+
             // It's still OK to insert an empty one, as we need to mark the spot for the filling algorithm.
-            let tile = tiles.at(tile_id).map(|tile| {
-                tile.texture
-                    .mesh_with_uv(tile_screen_position, corrected_tile_size, tile.uv)
+            let tile = has_tile.then(|| tiles.at(tile_id)).flatten().map(|tile| {
+                let rect = rect(tile_screen_position, corrected_tile_size);
+                let mut mesh = if transform.is_tilted() {
+                    // Perspective bends straight lines within a quad, so split it.
+                    tile.texture
+                        .mesh_with_rect_and_uv_grid(rect, tile.uv, transform.subdivisions)
+                } else {
+                    tile.texture.mesh_with_rect_and_uv(rect, tile.uv)
+                };
+                transform.transform_mesh(&mut mesh, viewport.center());
+                mesh
             });
 
             entry.insert(tile);
@@ -335,6 +844,8 @@ pub(crate) fn flood_fill_tiles(
             {
                 flood_fill_tiles(
                     viewport,
+                    transform,
+                    projection,
                     *next_tile_id,
                     map_center_projected_position,
                     zoom,
@@ -345,3 +856,163 @@ pub(crate) fn flood_fill_tiles(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(zoom: u8, x: u32, y: u32) -> TileId {
+        TileId { x, y, zoom }
+    }
+
+    #[test]
+    fn max_zoom_of_regions() {
+        let west = SourceRegion {
+            bounds: BoundingBox::new(
+                crate::pos_from_lon_lat(-180., -85.),
+                crate::pos_from_lon_lat(0., 85.),
+            ),
+            max_zoom: 15,
+            attribution: Attribution {
+                text: "",
+                url: "",
+                logo_light: None,
+                logo_dark: None,
+            },
+        };
+        let regions = [west];
+
+        // Within the region, down to its zoom.
+        assert_eq!(max_zoom_at(tile(12, 100, 1000), 10, &regions), 12);
+        assert_eq!(max_zoom_at(tile(18, 100, 1000), 10, &regions), 15);
+        // Elsewhere, down to the source's zoom.
+        assert_eq!(max_zoom_at(tile(12, 3000, 1000), 10, &regions), 10);
+        // Tiles straddling the region's boundary at deeper zooms are made of ones from outside.
+        assert_eq!(max_zoom_at(tile(12, 2048, 1000), 10, &regions), 10);
+        assert_eq!(max_zoom_at(tile(12, 2047, 1000), 10, &regions), 12);
+        assert_eq!(max_zoom_at(tile(8, 10, 10), 10, &[]), 8);
+    }
+
+    #[test]
+    fn loaded_tiles_are_requested_once() {
+        let mut tiles = LoadedTiles::new();
+        let requests = std::cell::Cell::new(0);
+        let request = |accepted| {
+            requests.set(requests.get() + 1);
+            accepted
+        };
+
+        // Rejected requests are made again.
+        assert_eq!(tiles.get_or_request(tile(1, 0, 0), || request(false)), None);
+        assert_eq!(tiles.get_or_request(tile(1, 0, 0), || request(true)), None);
+        assert_eq!(tiles.get_or_request(tile(1, 0, 0), || request(true)), None);
+        assert_eq!(requests.get(), 2);
+
+        tiles.loaded(tile(1, 0, 0), 'a');
+        assert_eq!(
+            tiles.get_or_request(tile(1, 0, 0), || request(true)),
+            Some('a')
+        );
+        assert_eq!(tiles.get(tile(1, 0, 0)), Some('a'));
+        assert_eq!(requests.get(), 2);
+    }
+
+    #[test]
+    fn failed_tiles_are_not_retried() {
+        let mut tiles = LoadedTiles::<char>::new();
+        assert_eq!(tiles.get_or_request(tile(1, 0, 0), || true), None);
+        tiles.failed(tile(1, 0, 0), "broken".to_owned());
+
+        assert_eq!(tiles.get_or_request(tile(1, 0, 0), || panic!()), None);
+        assert_eq!(
+            tiles.take_errors(),
+            vec![(tile(1, 0, 0), "broken".to_owned())]
+        );
+        assert!(tiles.take_errors().is_empty());
+
+        // Unless forgotten.
+        tiles.clear();
+        let mut requested = false;
+        tiles.get_or_request(tile(1, 0, 0), || {
+            requested = true;
+            true
+        });
+        assert!(requested);
+    }
+
+    #[test]
+    fn children_and_parent() {
+        for tile_id in [
+            tile(0, 0, 0),
+            tile(5, 17, 3),
+            tile(30, (1 << 30) - 1, 12345),
+        ] {
+            let children = tile_id.children().unwrap();
+            for child in children {
+                assert_eq!(child.zoom, tile_id.zoom + 1);
+                assert_eq!(child.parent(), Some(tile_id));
+            }
+        }
+        assert_eq!(
+            tile(1, 1, 0).children(),
+            Some([tile(2, 2, 0), tile(2, 3, 0), tile(2, 2, 1), tile(2, 3, 1)])
+        );
+    }
+
+    #[test]
+    fn children_overflow() {
+        assert_eq!(tile(255, 0, 0).children(), None);
+        assert_eq!(tile(31, 1 << 31, 0).children(), None);
+        assert_eq!(tile(31, 0, u32::MAX).children(), None);
+        assert_eq!(tile(0, 0, 0).parent(), None);
+    }
+
+    #[test]
+    fn bounds() {
+        let world = tile(0, 0, 0).bounds();
+        assert_eq!(world.min().x, -180.);
+        assert_eq!(world.max().x, 180.);
+        assert!((world.max().y - 85.0511).abs() < 1e-4);
+
+        // South-east tile of the deepest zoom which numbers fit in.
+        let last = tile(32, u32::MAX, u32::MAX).bounds();
+        assert_eq!(last.max().x, 180.);
+        assert!((last.min().y + 85.0511).abs() < 1e-4);
+        assert!(last.min().x < last.max().x);
+        assert!(last.min().y < last.max().y);
+    }
+
+    #[test]
+    fn quadkeys() {
+        // Example from Bing Maps' documentation.
+        assert_eq!(tile(3, 3, 5).to_quadkey(), "213");
+        assert_eq!(TileId::from_quadkey("213"), Some(tile(3, 3, 5)));
+        assert_eq!(tile(0, 0, 0).to_quadkey(), "");
+        assert_eq!(TileId::from_quadkey(""), Some(tile(0, 0, 0)));
+
+        for tile_id in [
+            tile(1, 1, 0),
+            tile(12, 2047, 1361),
+            tile(32, u32::MAX, 0),
+            tile(32, 123456789, u32::MAX),
+        ] {
+            let quadkey = tile_id.to_quadkey();
+            assert_eq!(quadkey.len(), tile_id.zoom as usize);
+            assert_eq!(TileId::from_quadkey(&quadkey), Some(tile_id));
+        }
+    }
+
+    #[test]
+    fn deep_quadkeys() {
+        // Beyond the width of the numbers, levels are leading zeros.
+        let quadkey = tile(40, 3, 1).to_quadkey();
+        assert_eq!(quadkey, format!("{}13", "0".repeat(38)));
+        assert_eq!(TileId::from_quadkey(&quadkey), Some(tile(40, 3, 1)));
+        assert_eq!(tile(255, 0, 0).to_quadkey().len(), 255);
+
+        // Numbers which do not fit.
+        assert_eq!(TileId::from_quadkey(&"1".repeat(33)), None);
+        assert_eq!(TileId::from_quadkey("104"), None);
+        assert_eq!(TileId::from_quadkey(&"0".repeat(256)), None);
+    }
+}
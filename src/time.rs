@@ -0,0 +1,59 @@
+/// Period of time which temporal layers should show data from. Time is expressed in seconds,
+/// relative to whatever epoch the application uses (e.g. UNIX time), as long as all layers agree
+/// on it. See [`crate::MapMemory::set_time_window`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeWindow {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl TimeWindow {
+    pub fn new(start: f64, end: f64) -> Self {
+        Self {
+            start: start.min(end),
+            end: start.max(end),
+        }
+    }
+
+    /// Whether the moment is within the window, inclusive.
+    pub fn contains(&self, time: f64) -> bool {
+        (self.start..=self.end).contains(&time)
+    }
+
+    /// Whether the period, e.g. duration of a track, overlaps with the window.
+    pub fn overlaps(&self, start: f64, end: f64) -> bool {
+        start <= self.end && end >= self.start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversed_bounds_are_swapped() {
+        assert_eq!(TimeWindow::new(20., 10.), TimeWindow::new(10., 20.));
+    }
+
+    #[test]
+    fn contains() {
+        let window = TimeWindow::new(10., 20.);
+        assert!(window.contains(10.));
+        assert!(window.contains(15.));
+        assert!(window.contains(20.));
+        assert!(!window.contains(9.9));
+        assert!(!window.contains(20.1));
+        assert!(!window.contains(f64::NAN));
+    }
+
+    #[test]
+    fn overlaps() {
+        let window = TimeWindow::new(10., 20.);
+        assert!(window.overlaps(0., 10.));
+        assert!(window.overlaps(12., 18.));
+        assert!(window.overlaps(0., 30.));
+        assert!(window.overlaps(20., 30.));
+        assert!(!window.overlaps(0., 9.));
+        assert!(!window.overlaps(21., 30.));
+    }
+}
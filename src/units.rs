@@ -1,10 +1,16 @@
 use std::f64::consts::PI;
 
-use crate::TileId;
+use crate::{
+    projector::{LocalTransform, Projection},
+    TileId,
+};
 
 /// Position in some coordinates, either latitude and longitude or local projected coordinate system.
 pub type Position = geo_types::Coord;
 
+/// Rectangular area, e.g. in geographical coordinates.
+pub type BoundingBox = geo_types::Rect;
+
 /// Construct from latitude and longitude.
 pub fn pos_from_lat_lon(lat: f64, lon: f64) -> Position {
     Position::new(lon, lat)
@@ -16,12 +22,143 @@ pub fn pos_from_lon_lat(lon: f64, lat: f64) -> Position {
     Position::new(lon, lat)
 }
 
+/// Construct from latitude and longitude, checking that they are within -90..=90 and
+/// -180..=180 degrees respectively.
+pub fn try_pos_from_lat_lon(lat: f64, lon: f64) -> Result<Position, InvalidCoordinates> {
+    if (-90. ..=90.).contains(&lat) && (-180. ..=180.).contains(&lon) {
+        Ok(pos_from_lat_lon(lat, lon))
+    } else {
+        Err(InvalidCoordinates)
+    }
+}
+
+/// Checked counterpart of [`pos_from_lon_lat`]. See [`try_pos_from_lat_lon`].
+pub fn try_pos_from_lon_lat(lon: f64, lat: f64) -> Result<Position, InvalidCoordinates> {
+    try_pos_from_lat_lon(lat, lon)
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("invalid coordinates")]
+pub struct InvalidCoordinates;
+
+/// Parse coordinates typed by a human. Accepted are decimal degrees (`52.23, 21.01`), degrees with
+/// hemispheres (`52.23N 21.01E`, `E21.01 N52.23`) and degrees, minutes and seconds
+/// (`52°13'48"N 21°0'36"E`). Latitude goes first, unless hemispheres say otherwise.
+pub fn parse_coordinates(text: &str) -> Result<Position, InvalidCoordinates> {
+    let (first, second) = split_coordinates(text.trim()).ok_or(InvalidCoordinates)?;
+    let first = parse_angle(first).ok_or(InvalidCoordinates)?;
+    let second = parse_angle(second).ok_or(InvalidCoordinates)?;
+
+    let (lat, lon) = match (first.1, second.1) {
+        (Some(Axis::Longitude), None | Some(Axis::Latitude)) => (second.0, first.0),
+        (None | Some(Axis::Latitude), None | Some(Axis::Longitude)) => (first.0, second.0),
+        _ => return Err(InvalidCoordinates),
+    };
+
+    try_pos_from_lat_lon(lat, lon)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Axis {
+    Latitude,
+    Longitude,
+}
+
+fn hemisphere(c: char) -> Option<(Axis, f64)> {
+    match c.to_ascii_uppercase() {
+        'N' => Some((Axis::Latitude, 1.)),
+        'S' => Some((Axis::Latitude, -1.)),
+        'E' => Some((Axis::Longitude, 1.)),
+        'W' => Some((Axis::Longitude, -1.)),
+        _ => None,
+    }
+}
+
+/// Split the text into latitude and longitude parts, in whichever order they were written.
+fn split_coordinates(text: &str) -> Option<(&str, &str)> {
+    if let Some(split) = text.split_once([',', ';']) {
+        return Some(split);
+    }
+
+    let letters: Vec<_> = text
+        .char_indices()
+        .filter(|(_, c)| hemisphere(*c).is_some())
+        .collect();
+
+    match letters.as_slice() {
+        // Leading hemispheres, e.g. "N52.23 E21.01".
+        [(0, _), (second, _)] => Some(text.split_at(*second)),
+        // Trailing hemispheres, e.g. "52.23N 21.01E".
+        [(first, c), _] => Some(text.split_at(first + c.len_utf8())),
+        [] => {
+            let mut parts = text.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(first), Some(second), None) => Some((first, second)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parse a single angle, either in decimal degrees or degrees, minutes and seconds, optionally
+/// with a hemisphere letter at the beginning or the end.
+fn parse_angle(text: &str) -> Option<(f64, Option<Axis>)> {
+    let text = text.trim();
+    let (text, hemisphere) = match (text.chars().next(), text.chars().last()) {
+        (Some(c), _) if hemisphere(c).is_some() => (&text[c.len_utf8()..], hemisphere(c)),
+        (_, Some(c)) if hemisphere(c).is_some() => {
+            (&text[..text.len() - c.len_utf8()], hemisphere(c))
+        }
+        _ => (text, None),
+    };
+
+    let values = text
+        .split(|c: char| c.is_whitespace() || "°'\"′″".contains(c))
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let (degrees, minutes, seconds) = match values.as_slice() {
+        [d] => (*d, 0., 0.),
+        [d, m] => (*d, *m, 0.),
+        [d, m, s] => (*d, *m, *s),
+        _ => return None,
+    };
+
+    if !(0. ..60.).contains(&minutes) || !(0. ..60.).contains(&seconds) {
+        return None;
+    }
+
+    let sign = match hemisphere {
+        // Negative degrees with a hemisphere are ambiguous.
+        Some(_) if degrees.is_sign_negative() => return None,
+        Some((_, sign)) => sign,
+        None => degrees.signum(),
+    };
+
+    let angle = sign * (degrees.abs() + minutes / 60. + seconds / 3600.);
+    Some((angle, hemisphere.map(|(axis, _)| axis)))
+}
+
+/// Mean radius of the Earth, in meters.
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Great-circle distance in meters.
+pub(crate) fn haversine(a: Position, b: Position) -> f64 {
+    let (lat_a, lat_b) = (a.y.to_radians(), b.y.to_radians());
+    let h = ((lat_b - lat_a) / 2.).sin().powi(2)
+        + lat_a.cos() * lat_b.cos() * ((b.x - a.x).to_radians() / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * h.sqrt().asin()
+}
+
 pub(crate) trait PositionTrait {
     fn new(x: f64, y: f64) -> Self;
     fn mercator_normalized(&self) -> (f64, f64);
     fn global_bitmap_project(&self, zoom: f64) -> Pixel;
-    fn local_bitmap_project(&self, zoom: f64) -> Pixel;
-    fn tile_id(&self, zoom: u8, source_tile_size: u32) -> TileId;
+    fn local_bitmap_project(&self, zoom: f64, transform: &LocalTransform) -> Pixel;
+    fn custom_bitmap_project(&self, zoom: f64, projection: &dyn Projection) -> Pixel;
+    fn tile_id(&self, zoom: u8, zoom_offset: u8) -> TileId;
 }
 
 impl PositionTrait for Position {
@@ -46,26 +183,37 @@ impl PositionTrait for Position {
         Pixel::new(x * total_pixels, y * total_pixels)
     }
 
-    fn local_bitmap_project(&self, zoom: f64) -> Pixel {
+    fn local_bitmap_project(&self, zoom: f64, transform: &LocalTransform) -> Pixel {
         let units_per_point = crate::local_units_per_point(zoom);
+        let position = transform.apply(*self);
 
-        Pixel::new(self.x / units_per_point, -self.y / units_per_point)
+        Pixel::new(position.x / units_per_point, -position.y / units_per_point)
     }
 
-    fn tile_id(&self, mut zoom: u8, source_tile_size: u32) -> TileId {
-        let (x, y) = self.mercator_normalized();
+    fn custom_bitmap_project(&self, zoom: f64, projection: &dyn Projection) -> Pixel {
+        let total_pixels = crate::total_pixels(zoom);
+        let (x, y) = projection.normalize(*self);
+        Pixel::new(x * total_pixels, y * total_pixels)
+    }
 
-        // Some sources provide larger tiles, effectively bundling e.g. 4 256px tiles in one
-        // 512px one. Walkers uses 256px internally, so we need to adjust the zoom level.
-        zoom -= (source_tile_size as f64 / crate::TILE_SIZE as f64).log2() as u8;
+    fn tile_id(&self, zoom: u8, zoom_offset: u8) -> TileId {
+        tile_id_of_normalized(self.mercator_normalized(), zoom, zoom_offset)
+    }
+}
 
-        // Map that into a big bitmap made out of web tiles.
-        let number_of_tiles = 2u32.pow(zoom as u32) as f64;
-        let x = (x * number_of_tiles).floor() as u32;
-        let y = (y * number_of_tiles).floor() as u32;
+/// Tile containing the position, given as fractions of the whole map in both axes.
+pub(crate) fn tile_id_of_normalized((x, y): (f64, f64), zoom: u8, zoom_offset: u8) -> TileId {
+    // Some sources provide larger tiles, effectively bundling e.g. 4 256px tiles in one
+    // 512px one. Walkers uses 256px internally, so we need to adjust the zoom level. There
+    // are no tiles below zoom 0, so such tile gets stretched instead.
+    let zoom = zoom.saturating_sub(zoom_offset);
 
-        TileId { x, y, zoom }
-    }
+    // Map that into a big bitmap made out of web tiles.
+    let number_of_tiles = 2u32.pow(zoom as u32) as f64;
+    let x = (x * number_of_tiles).floor() as u32;
+    let y = (y * number_of_tiles).floor() as u32;
+
+    TileId { x, y, zoom }
 }
 
 /// Location projected on the screen or an abstract bitmap.
@@ -73,7 +221,8 @@ pub(crate) type Pixel = geo_types::Coord;
 
 trait PixelTrait {
     fn global_bitmap_unproject(&self, zoom: f64) -> Position;
-    fn local_bitmap_unproject(&self, zoom: f64) -> Position;
+    fn local_bitmap_unproject(&self, zoom: f64, transform: &LocalTransform) -> Position;
+    fn custom_bitmap_unproject(&self, zoom: f64, projection: &dyn Projection) -> Position;
 }
 
 impl PixelTrait for Pixel {
@@ -93,10 +242,18 @@ impl PixelTrait for Pixel {
         pos_from_lon_lat(lon, lat)
     }
 
-    fn local_bitmap_unproject(&self, zoom: f64) -> Position {
+    fn local_bitmap_unproject(&self, zoom: f64, transform: &LocalTransform) -> Position {
         let units_per_point = crate::local_units_per_point(zoom);
 
-        Position::new(self.x * units_per_point, -self.y * units_per_point)
+        transform.invert(Position::new(
+            self.x * units_per_point,
+            -self.y * units_per_point,
+        ))
+    }
+
+    fn custom_bitmap_unproject(&self, zoom: f64, projection: &dyn Projection) -> Position {
+        let total_pixels = crate::total_pixels(zoom);
+        projection.denormalize(self.x / total_pixels, self.y / total_pixels)
     }
 }
 
@@ -131,22 +288,22 @@ impl AdjustedPosition {
         (self.position.global_bitmap_project(zoom) - self.offset).global_bitmap_unproject(zoom)
     }
 
-    pub(crate) fn local_unadjusted_position(&self, zoom: f64) -> Position {
-        (self.position.local_bitmap_project(zoom) - self.offset).local_bitmap_unproject(zoom)
-    }
-
-    pub(crate) fn global_zero_offset(self, zoom: f64) -> Self {
-        Self {
-            position: self.global_unadjusted_position(zoom),
-            offset: Default::default(),
-        }
+    pub(crate) fn local_unadjusted_position(
+        &self,
+        zoom: f64,
+        transform: &LocalTransform,
+    ) -> Position {
+        (self.position.local_bitmap_project(zoom, transform) - self.offset)
+            .local_bitmap_unproject(zoom, transform)
     }
 
-    pub(crate) fn local_zero_offset(self, zoom: f64) -> Self {
-        Self {
-            position: self.local_unadjusted_position(zoom),
-            offset: Default::default(),
-        }
+    pub(crate) fn custom_unadjusted_position(
+        &self,
+        zoom: f64,
+        projection: &dyn Projection,
+    ) -> Position {
+        (self.position.custom_bitmap_project(zoom, projection) - self.offset)
+            .custom_bitmap_unproject(zoom, projection)
     }
 }
 
@@ -158,3 +315,43 @@ impl From<Position> for AdjustedPosition {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> (f64, f64) {
+        let position = parse_coordinates(text).unwrap();
+        (
+            (position.y * 1e4).round() / 1e4,
+            (position.x * 1e4).round() / 1e4,
+        )
+    }
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(parsed("52.23, 21.01"), (52.23, 21.01));
+        assert_eq!(parsed("52.23 21.01"), (52.23, 21.01));
+        assert_eq!(parsed("-33.86; 151.21"), (-33.86, 151.21));
+        assert_eq!(parsed("52.23N 21.01E"), (52.23, 21.01));
+        assert_eq!(parsed("E21.01 N52.23"), (52.23, 21.01));
+        assert_eq!(parsed("33.86S 70.65W"), (-33.86, -70.65));
+        assert_eq!(parsed("52°13'48\"N 21°0'36\"E"), (52.23, 21.01));
+        assert_eq!(parsed("21°0′36″E, 52°13′48″N"), (52.23, 21.01));
+    }
+
+    #[test]
+    fn invalid_coordinates() {
+        for text in [
+            "",
+            "52.23",
+            "52.23 21.01 5",
+            "N52.23 N21.01",
+            "95, 21",
+            "52, 200",
+            "abc, def",
+        ] {
+            assert_eq!(parse_coordinates(text), Err(InvalidCoordinates), "{text}");
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Transverse Mercator formulas of the UTM grid, after Snyder's "Map Projections: A Working
+//! Manual", accurate to well below a meter within a zone.
+
+use crate::Position;
+
+const A: f64 = 6378137.;
+const E2: f64 = 0.006_694_379_990_14;
+const K0: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500_000.;
+pub(crate) const FALSE_NORTHING: f64 = 10_000_000.;
+
+/// Latitude bands, each 8° tall (the last one 12°), starting at 80°S.
+const BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWX";
+
+/// Latitude band letter, or `None` outside of the area covered by UTM.
+pub(crate) fn band(lat: f64) -> Option<char> {
+    (-80. ..=84.)
+        .contains(&lat)
+        .then(|| BANDS[(((lat + 80.) / 8.) as usize).min(BANDS.len() - 1)] as char)
+}
+
+/// Zone containing given position, including the exceptions around Norway and Svalbard.
+pub(crate) fn zone(position: Position) -> u8 {
+    let lon = normalized_longitude(position.x);
+    match (band(position.y), lon) {
+        (Some('V'), 3.0..12.0) => 32,
+        (Some('X'), 0.0..9.0) => 31,
+        (Some('X'), 9.0..21.0) => 33,
+        (Some('X'), 21.0..33.0) => 35,
+        (Some('X'), 33.0..42.0) => 37,
+        _ => (((lon + 180.) / 6.) as u8).min(59) + 1,
+    }
+}
+
+fn normalized_longitude(lon: f64) -> f64 {
+    (lon + 180.).rem_euclid(360.) - 180.
+}
+
+fn central_meridian(zone: u8) -> f64 {
+    (zone as f64 * 6. - 183.).to_radians()
+}
+
+/// Easting, northing and the point scale factor of the position in given zone. Northing is
+/// negative in the southern hemisphere, i.e. without the false northing.
+pub(crate) fn forward(position: Position, zone: u8) -> (f64, f64, f64) {
+    let ep2 = E2 / (1. - E2);
+    let (e4, e6) = (E2 * E2, E2 * E2 * E2);
+
+    let phi = position.y.to_radians();
+    let lon = normalized_longitude(position.x).to_radians();
+    let mut delta = lon - central_meridian(zone);
+    if delta > std::f64::consts::PI {
+        delta -= std::f64::consts::TAU;
+    } else if delta < -std::f64::consts::PI {
+        delta += std::f64::consts::TAU;
+    }
+
+    let n = A / (1. - E2 * phi.sin().powi(2)).sqrt();
+    let t = phi.tan().powi(2);
+    let c = ep2 * phi.cos().powi(2);
+    let a = phi.cos() * delta;
+    let m = A
+        * ((1. - E2 / 4. - 3. * e4 / 64. - 5. * e6 / 256.) * phi
+            - (3. * E2 / 8. + 3. * e4 / 32. + 45. * e6 / 1024.) * (2. * phi).sin()
+            + (15. * e4 / 256. + 45. * e6 / 1024.) * (4. * phi).sin()
+            - (35. * e6 / 3072.) * (6. * phi).sin());
+
+    let easting = K0
+        * n
+        * (a + (1. - t + c) * a.powi(3) / 6.
+            + (5. - 18. * t + t * t + 72. * c - 58. * ep2) * a.powi(5) / 120.)
+        + FALSE_EASTING;
+    let northing = K0
+        * (m + n
+            * phi.tan()
+            * (a * a / 2.
+                + (5. - t + 9. * c + 4. * c * c) * a.powi(4) / 24.
+                + (61. - 58. * t + t * t + 600. * c - 330. * ep2) * a.powi(6) / 720.));
+    let scale = K0
+        * (1.
+            + (1. + c) * a * a / 2.
+            + (5. - 4. * t + 42. * c + 13. * c * c - 28. * ep2) * a.powi(4) / 24.
+            + (61. - 148. * t + 16. * t * t) * a.powi(6) / 720.);
+
+    (easting, northing, scale)
+}
+
+/// Position with given easting and northing in the zone. Northing is expected to include the
+/// false northing in the southern hemisphere.
+pub(crate) fn inverse(zone: u8, northern: bool, easting: f64, northing: f64) -> Position {
+    let ep2 = E2 / (1. - E2);
+    let (e4, e6) = (E2 * E2, E2 * E2 * E2);
+    let e1 = (1. - (1. - E2).sqrt()) / (1. + (1. - E2).sqrt());
+
+    let x = easting - FALSE_EASTING;
+    let y = if northern {
+        northing
+    } else {
+        northing - FALSE_NORTHING
+    };
+
+    let mu = y / K0 / (A * (1. - E2 / 4. - 3. * e4 / 64. - 5. * e6 / 256.));
+    let phi1 = mu
+        + (3. * e1 / 2. - 27. * e1.powi(3) / 32.) * (2. * mu).sin()
+        + (21. * e1 * e1 / 16. - 55. * e1.powi(4) / 32.) * (4. * mu).sin()
+        + (151. * e1.powi(3) / 96.) * (6. * mu).sin()
+        + (1097. * e1.powi(4) / 512.) * (8. * mu).sin();
+
+    let sin2 = phi1.sin().powi(2);
+    let c1 = ep2 * phi1.cos().powi(2);
+    let t1 = phi1.tan().powi(2);
+    let n1 = A / (1. - E2 * sin2).sqrt();
+    let r1 = A * (1. - E2) / (1. - E2 * sin2).powf(1.5);
+    let d = x / (n1 * K0);
+
+    let lat = phi1
+        - (n1 * phi1.tan() / r1)
+            * (d * d / 2. - (5. + 3. * t1 + 10. * c1 - 4. * c1 * c1 - 9. * ep2) * d.powi(4) / 24.
+                + (61. + 90. * t1 + 298. * c1 + 45. * t1 * t1 - 252. * ep2 - 3. * c1 * c1)
+                    * d.powi(6)
+                    / 720.);
+    let lon = central_meridian(zone)
+        + (d - (1. + 2. * t1 + c1) * d.powi(3) / 6.
+            + (5. - 2. * c1 + 28. * t1 - 3. * c1 * c1 + 8. * ep2 + 24. * t1 * t1) * d.powi(5)
+                / 120.)
+            / phi1.cos();
+
+    Position {
+        x: normalized_longitude(lon.to_degrees()),
+        y: lat.to_degrees(),
+    }
+}
+
+/// Northing as written in UTM coordinates, i.e. with the false northing in the south.
+pub(crate) fn with_false_northing(northing: f64) -> f64 {
+    if northing < 0. {
+        northing + FALSE_NORTHING
+    } else {
+        northing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos_from_lat_lon;
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn forward_projection() {
+        // CN Tower, 43°38'33.24"N 79°23'13.7"W, is at 17T 630084 4833438.
+        let tower = pos_from_lat_lon(
+            43. + 38. / 60. + 33.24 / 3600.,
+            -(79. + 23. / 60. + 13.7 / 3600.),
+        );
+        assert_eq!(zone(tower), 17);
+        assert_eq!(band(tower.y), Some('T'));
+        let (easting, northing, _) = forward(tower, 17);
+        assert_near(easting, 630084., 1.);
+        assert_near(northing, 4833438., 1.);
+
+        // Central meridian on the equator.
+        let (easting, northing, scale) = forward(pos_from_lat_lon(0., 3.), 31);
+        assert_near(easting, FALSE_EASTING, 1e-6);
+        assert_near(northing, 0., 1e-6);
+        assert_near(scale, K0, 1e-9);
+    }
+
+    #[test]
+    fn round_trip() {
+        for (lat, lon) in [
+            (52.23, 21.01),
+            (-33.86, 151.21),
+            (-54.8, -68.3),
+            (78.2, 15.6),
+        ] {
+            let position = pos_from_lat_lon(lat, lon);
+            let zone = zone(position);
+            let (easting, northing, _) = forward(position, zone);
+            let back = inverse(zone, lat >= 0., easting, with_false_northing(northing));
+            assert_near(back.y, lat, 1e-7);
+            assert_near(back.x, lon, 1e-7);
+        }
+    }
+
+    #[test]
+    fn zones_and_bands() {
+        assert_eq!(zone(pos_from_lat_lon(0., -180.)), 1);
+        assert_eq!(zone(pos_from_lat_lon(0., 179.9)), 60);
+        assert_eq!(zone(pos_from_lat_lon(0., 180.)), 1);
+        // Norway and Svalbard.
+        assert_eq!(zone(pos_from_lat_lon(60., 5.)), 32);
+        assert_eq!(zone(pos_from_lat_lon(78., 15.)), 33);
+        assert_eq!(zone(pos_from_lat_lon(78., 8.)), 31);
+
+        assert_eq!(band(-80.), Some('C'));
+        assert_eq!(band(0.), Some('N'));
+        assert_eq!(band(83.9), Some('X'));
+        assert_eq!(band(84.1), None);
+        assert_eq!(band(-80.1), None);
+    }
+}
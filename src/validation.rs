@@ -0,0 +1,144 @@
+//! Checking whether a tile source is configured correctly, see [`crate::HttpTiles::validate`].
+
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use reqwest::{
+    header::{HeaderValue, CACHE_CONTROL, CONTENT_TYPE, USER_AGENT},
+    StatusCode,
+};
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::{io::Stopwatch, tiles::decode};
+
+/// Outcome of a successful [`crate::HttpTiles::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceReport {
+    /// Time between sending the request and receiving the whole tile.
+    pub latency: Duration,
+
+    /// `Content-Type` of the tile, if the server sent one.
+    pub content_type: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("tile server is unreachable: {0}")]
+    Unreachable(String),
+
+    #[error("tile server rejected the credentials (HTTP {0})")]
+    Unauthorized(u16),
+
+    #[error("tile server responded with HTTP {0}")]
+    Status(u16),
+
+    #[error("tile server responded with '{0}' instead of an image")]
+    NotAnImage(String),
+
+    #[error("tile could not be decoded: {0}")]
+    InvalidImage(String),
+
+    #[error("download thread is busy or not running")]
+    DownloadThread,
+}
+
+pub(crate) type ValidationResult = Result<SourceReport, ValidationError>;
+
+/// Pending or finished [`crate::HttpTiles::validate`]. Keep it between frames and poll it with
+/// [`Validation::result`]; the map gets repainted when the result arrives.
+pub struct Validation {
+    rx: Option<oneshot::Receiver<ValidationResult>>,
+    result: Option<ValidationResult>,
+}
+
+impl Validation {
+    pub(crate) fn new(rx: oneshot::Receiver<ValidationResult>) -> Self {
+        Self {
+            rx: Some(rx),
+            result: None,
+        }
+    }
+
+    pub(crate) fn failed(error: ValidationError) -> Self {
+        Self {
+            rx: None,
+            result: Some(Err(error)),
+        }
+    }
+
+    /// Result of the validation, or `None` if it is still in progress.
+    pub fn result(&mut self) -> Option<&ValidationResult> {
+        if let Some(rx) = &mut self.rx {
+            match rx.try_recv() {
+                Ok(Some(result)) => {
+                    self.result = Some(result);
+                    self.rx = None;
+                }
+                Ok(None) => {}
+                Err(oneshot::Canceled) => {
+                    self.result = Some(Err(ValidationError::DownloadThread));
+                    self.rx = None;
+                }
+            }
+        }
+
+        self.result.as_ref()
+    }
+}
+
+/// Download the tile, bypassing caches, and check whether it is a valid image.
+pub(crate) async fn validate(
+    client: &ClientWithMiddleware,
+    user_agent: Option<&HeaderValue>,
+    url: &str,
+) -> ValidationResult {
+    log::debug!("Validating the source with '{}'.", url);
+    let stopwatch = Stopwatch::start();
+
+    let mut request = client
+        .get(url)
+        .header(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+    if let Some(user_agent) = user_agent {
+        request = request.header(USER_AGENT, user_agent);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|error| ValidationError::Unreachable(error.to_string()))?;
+
+    match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            return Err(ValidationError::Unauthorized(response.status().as_u16()))
+        }
+        status if !status.is_success() => return Err(ValidationError::Status(status.as_u16())),
+        _ => {}
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    let image = response
+        .bytes()
+        .await
+        .map_err(|error| ValidationError::Unreachable(error.to_string()))?;
+
+    let latency = stopwatch.elapsed();
+
+    if let Some(content_type) = &content_type {
+        if !content_type.starts_with("image/") {
+            return Err(ValidationError::NotAnImage(content_type.clone()));
+        }
+    }
+
+    decode(&image).map_err(|error| ValidationError::InvalidImage(error.to_string()))?;
+
+    Ok(SourceReport {
+        latency,
+        content_type,
+    })
+}
@@ -0,0 +1,509 @@
+use egui::Color32;
+
+use super::mvt::{Feature, Geometry, Layer, PropertyValue};
+
+/// Value which depends on the map's zoom, e.g. the width of roads growing as the map zooms in.
+/// Plain values convert into [`Zoomed::Constant`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Zoomed<T> {
+    Constant(T),
+    /// Interpolated between the stops of `(zoom, value)`, sorted by zoom. Base of 1 is linear,
+    /// higher ones change faster towards the higher stop. Beyond the stops, the nearest one is
+    /// used.
+    Interpolate {
+        base: f64,
+        stops: Vec<(f64, T)>,
+    },
+    /// Value of the last stop at or below the zoom, or of the first one, if all are above it.
+    Step(Vec<(f64, T)>),
+}
+
+impl<T> From<T> for Zoomed<T> {
+    fn from(value: T) -> Self {
+        Zoomed::Constant(value)
+    }
+}
+
+/// Values which [`Zoomed::Interpolate`] can blend.
+pub(crate) trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Color32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self.lerp_to_gamma(other, t)
+    }
+}
+
+impl<T: Copy> Zoomed<T> {
+    pub(crate) fn map<U>(&self, f: impl Fn(T) -> U) -> Zoomed<U> {
+        match self {
+            Zoomed::Constant(value) => Zoomed::Constant(f(*value)),
+            Zoomed::Interpolate { base, stops } => Zoomed::Interpolate {
+                base: *base,
+                stops: stops
+                    .iter()
+                    .map(|(zoom, value)| (*zoom, f(*value)))
+                    .collect(),
+            },
+            Zoomed::Step(stops) => Zoomed::Step(
+                stops
+                    .iter()
+                    .map(|(zoom, value)| (*zoom, f(*value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Value of the highest stop, for uses which do not follow the zoom.
+    pub(crate) fn last(&self) -> Option<T> {
+        match self {
+            Zoomed::Constant(value) => Some(*value),
+            Zoomed::Interpolate { stops, .. } | Zoomed::Step(stops) => {
+                stops.last().map(|(_, value)| *value)
+            }
+        }
+    }
+
+    /// Value at given zoom, or `None` if there are no stops.
+    pub(crate) fn at(&self, zoom: f64) -> Option<T>
+    where
+        T: Lerp,
+    {
+        match self {
+            Zoomed::Constant(value) => Some(*value),
+            Zoomed::Interpolate { base, stops } => {
+                let above = stops.iter().position(|(stop, _)| *stop > zoom);
+                match above {
+                    Some(0) => stops.first().map(|(_, value)| *value),
+                    Some(i) => {
+                        let ((z0, v0), (z1, v1)) = (stops[i - 1], stops[i]);
+                        Some(v0.lerp(v1, progress(*base, zoom - z0, z1 - z0)))
+                    }
+                    None => stops.last().map(|(_, value)| *value),
+                }
+            }
+            Zoomed::Step(stops) => stops
+                .iter()
+                .rev()
+                .find(|(stop, _)| *stop <= zoom)
+                .or(stops.first())
+                .map(|(_, value)| *value),
+        }
+    }
+}
+
+/// How far along the range the value is, from 0 to 1, growing exponentially with the base.
+fn progress(base: f64, value: f64, range: f64) -> f32 {
+    if range <= 0. {
+        return 0.;
+    }
+    let t = if (base - 1.).abs() < 1e-9 {
+        value / range
+    } else {
+        (base.powf(value) - 1.) / (base.powf(range) - 1.)
+    };
+    t.clamp(0., 1.) as f32
+}
+
+/// Value which depends on the feature's properties along with the zoom, e.g. roads of class
+/// `motorway` being wider than the others. Plain and [`Zoomed`] values convert into
+/// [`Styled::Zoomed`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Styled<T> {
+    Zoomed(Zoomed<T>),
+    /// Value of the first case whose filter matches the feature, or of the fallback if none
+    /// does.
+    Case(Vec<(Filter, Zoomed<T>)>, Zoomed<T>),
+}
+
+impl<T> From<T> for Styled<T> {
+    fn from(value: T) -> Self {
+        Styled::Zoomed(Zoomed::Constant(value))
+    }
+}
+
+impl<T> From<Zoomed<T>> for Styled<T> {
+    fn from(value: Zoomed<T>) -> Self {
+        Styled::Zoomed(value)
+    }
+}
+
+impl<T: Copy> Styled<T> {
+    pub(crate) fn map<U>(&self, f: impl Fn(T) -> U) -> Styled<U> {
+        match self {
+            Styled::Zoomed(value) => Styled::Zoomed(value.map(f)),
+            Styled::Case(cases, fallback) => Styled::Case(
+                cases
+                    .iter()
+                    .map(|(filter, value)| (filter.clone(), value.map(&f)))
+                    .collect(),
+                fallback.map(f),
+            ),
+        }
+    }
+
+    /// Value at given zoom, which is left to be picked for each feature.
+    pub(crate) fn at(&self, zoom: f64) -> FeatureValue<T>
+    where
+        T: Lerp,
+    {
+        match self {
+            Styled::Zoomed(value) => FeatureValue {
+                cases: Vec::new(),
+                fallback: value.at(zoom),
+            },
+            Styled::Case(cases, fallback) => FeatureValue {
+                cases: cases
+                    .iter()
+                    .map(|(filter, value)| (filter.at(zoom), value.at(zoom)))
+                    .collect(),
+                fallback: fallback.at(zoom),
+            },
+        }
+    }
+}
+
+/// [`Styled`] value at a zoom, so that only its cases are left to be matched against each
+/// feature.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FeatureValue<T> {
+    cases: Vec<(Filter, Option<T>)>,
+    fallback: Option<T>,
+}
+
+impl<T: Copy> FeatureValue<T> {
+    /// Value for the feature, or `None` if there are no stops.
+    pub(crate) fn of(&self, layer: &Layer, feature: &Feature) -> Option<T> {
+        self.cases
+            .iter()
+            .find(|(filter, _)| filter.matches(layer, feature))
+            .map_or(self.fallback, |(_, value)| *value)
+    }
+}
+
+/// How a property is compared in a [`Filter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Whether the property compares to the value. Missing properties are only
+    /// [`Comparison::NotEqual`].
+    fn holds(self, property: Option<&PropertyValue>, value: &PropertyValue) -> bool {
+        let Some(property) = property else {
+            return self == Comparison::NotEqual;
+        };
+        let ordering = match (property, value) {
+            (PropertyValue::Number(a), PropertyValue::Number(b)) => a.partial_cmp(b),
+            (PropertyValue::String(a), PropertyValue::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match self {
+            Comparison::Equal => property == value,
+            Comparison::NotEqual => property != value,
+            Comparison::Less => ordering.is_some_and(|o| o.is_lt()),
+            Comparison::LessOrEqual => ordering.is_some_and(|o| o.is_le()),
+            Comparison::Greater => ordering.is_some_and(|o| o.is_gt()),
+            Comparison::GreaterOrEqual => ordering.is_some_and(|o| o.is_ge()),
+        }
+    }
+}
+
+/// Which features of a layer are drawn, see [`super::LayerStyle::filter`]. Key `$type` stands
+/// for the feature's geometry type: `Point`, `LineString` or `Polygon`, and `$zoom` for the
+/// map's zoom.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// All of the filters match. True if there are none.
+    All(Vec<Filter>),
+    /// Any of the filters matches. False if there are none.
+    Any(Vec<Filter>),
+    Not(Box<Filter>),
+    /// Feature has the property.
+    Has(String),
+    /// Feature's property compares to the value. Features without the property only match
+    /// [`Comparison::NotEqual`]. Ordering compares numbers with numbers and strings with
+    /// strings.
+    Compare(String, Comparison, PropertyValue),
+    /// Feature's property is one of the values.
+    In(String, Vec<PropertyValue>),
+}
+
+impl Filter {
+    /// Filter with the comparisons of the zoom resolved, so that they need not be repeated for
+    /// each feature.
+    pub(crate) fn at(&self, zoom: f64) -> Filter {
+        let current = PropertyValue::Number(zoom);
+        let resolved = |matches: bool| {
+            if matches {
+                Filter::All(Vec::new())
+            } else {
+                Filter::Any(Vec::new())
+            }
+        };
+        match self {
+            Filter::All(filters) => Filter::All(filters.iter().map(|f| f.at(zoom)).collect()),
+            Filter::Any(filters) => Filter::Any(filters.iter().map(|f| f.at(zoom)).collect()),
+            Filter::Not(filter) => Filter::Not(Box::new(filter.at(zoom))),
+            Filter::Has(key) if key == "$zoom" => resolved(true),
+            Filter::Compare(key, comparison, value) if key == "$zoom" => {
+                resolved(comparison.holds(Some(&current), value))
+            }
+            Filter::In(key, values) if key == "$zoom" => resolved(values.contains(&current)),
+            filter => filter.clone(),
+        }
+    }
+
+    pub(crate) fn matches(&self, layer: &Layer, feature: &Feature) -> bool {
+        let property = |key: &str| {
+            if key == "$type" {
+                let geometry_type = match feature.geometry {
+                    Geometry::Points(_) => "Point",
+                    Geometry::Lines(_) => "LineString",
+                    Geometry::Polygons(_) => "Polygon",
+                };
+                Some(PropertyValue::String(geometry_type.to_owned()))
+            } else {
+                layer.property(feature, key).cloned()
+            }
+        };
+
+        match self {
+            Filter::All(filters) => filters.iter().all(|f| f.matches(layer, feature)),
+            Filter::Any(filters) => filters.iter().any(|f| f.matches(layer, feature)),
+            Filter::Not(filter) => !filter.matches(layer, feature),
+            Filter::Has(key) => property(key).is_some(),
+            Filter::Compare(key, comparison, value) => {
+                comparison.holds(property(key).as_ref(), value)
+            }
+            Filter::In(key, values) => property(key).is_some_and(|p| values.contains(&p)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn point() -> Geometry {
+        Geometry::Points(vec![Position { x: 0., y: 0. }])
+    }
+
+    fn matches(filter: &Filter, layer: &Layer) -> bool {
+        filter.matches(layer, &layer.features[0])
+    }
+
+    #[test]
+    fn constant() {
+        assert_eq!(Zoomed::from(2.0f32).at(5.), Some(2.));
+    }
+
+    #[test]
+    fn linear_interpolation() {
+        let width = Zoomed::Interpolate {
+            base: 1.,
+            stops: vec![(10., 1.0f32), (14., 5.)],
+        };
+        assert_eq!(width.at(5.), Some(1.));
+        assert_eq!(width.at(10.), Some(1.));
+        assert_eq!(width.at(12.), Some(3.));
+        assert_eq!(width.at(20.), Some(5.));
+    }
+
+    #[test]
+    fn exponential_interpolation() {
+        let width = Zoomed::Interpolate {
+            base: 2.,
+            stops: vec![(0., 0.0f32), (2., 3.)],
+        };
+        // (2^1 - 1) / (2^2 - 1) of the way.
+        assert_eq!(width.at(1.), Some(1.));
+    }
+
+    #[test]
+    fn color_interpolation() {
+        let color = Zoomed::Interpolate {
+            base: 1.,
+            stops: vec![(0., Color32::BLACK), (10., Color32::WHITE)],
+        };
+        assert_eq!(color.at(0.), Some(Color32::BLACK));
+        assert_eq!(color.at(10.), Some(Color32::WHITE));
+    }
+
+    #[test]
+    fn steps() {
+        let radius = Zoomed::Step(vec![(f64::NEG_INFINITY, 1.0f32), (10., 2.), (12., 4.)]);
+        assert_eq!(radius.at(3.), Some(1.));
+        assert_eq!(radius.at(10.), Some(2.));
+        assert_eq!(radius.at(11.9), Some(2.));
+        assert_eq!(radius.at(15.), Some(4.));
+    }
+
+    #[test]
+    fn no_stops() {
+        let width: Zoomed<f32> = Zoomed::Step(Vec::new());
+        assert_eq!(width.at(3.), None);
+    }
+
+    #[test]
+    fn feature_values() {
+        let class = |value: &str| PropertyValue::String(value.to_owned());
+        let width = Styled::Case(
+            vec![(
+                Filter::In("class".to_owned(), vec![class("motorway")]),
+                Zoomed::Interpolate {
+                    base: 1.,
+                    stops: vec![(10., 2.0f32), (14., 6.)],
+                },
+            )],
+            Zoomed::Constant(1.),
+        );
+        let motorway = Layer::single(point(), &[("class", class("motorway"))]);
+        let track = Layer::single(point(), &[("class", class("track"))]);
+
+        let at = |zoom, layer: &Layer| width.at(zoom).of(layer, &layer.features[0]);
+        assert_eq!(at(12., &motorway), Some(4.));
+        assert_eq!(at(14., &motorway), Some(6.));
+        assert_eq!(at(12., &track), Some(1.));
+        assert_eq!(
+            Styled::from(3.0f32).at(0.).of(&track, &track.features[0]),
+            Some(3.)
+        );
+    }
+
+    #[test]
+    fn zoom_filters() {
+        let layer = Layer::single(point(), &[]);
+        let from_zoom = |zoom| {
+            Filter::Compare(
+                "$zoom".to_owned(),
+                Comparison::GreaterOrEqual,
+                PropertyValue::Number(zoom),
+            )
+        };
+
+        assert!(matches(&from_zoom(12.).at(12.), &layer));
+        assert!(!matches(&from_zoom(12.).at(11.5), &layer));
+        assert!(matches(
+            &Filter::Not(Box::new(from_zoom(12.))).at(11.5),
+            &layer
+        ));
+        assert_eq!(
+            Filter::Has("name".to_owned()).at(3.),
+            Filter::Has("name".to_owned())
+        );
+    }
+
+    #[test]
+    fn comparisons() {
+        let layer = Layer::single(
+            point(),
+            &[
+                ("class", PropertyValue::String("river".to_owned())),
+                ("rank", PropertyValue::Number(3.)),
+            ],
+        );
+        let compare = |key: &str, comparison, value| {
+            matches(&Filter::Compare(key.to_owned(), comparison, value), &layer)
+        };
+        let river = || PropertyValue::String("river".to_owned());
+
+        assert!(compare("class", Comparison::Equal, river()));
+        assert!(!compare("class", Comparison::NotEqual, river()));
+        assert!(compare("rank", Comparison::Less, PropertyValue::Number(4.)));
+        assert!(compare(
+            "rank",
+            Comparison::LessOrEqual,
+            PropertyValue::Number(3.)
+        ));
+        assert!(!compare(
+            "rank",
+            Comparison::Greater,
+            PropertyValue::Number(3.)
+        ));
+        assert!(compare(
+            "rank",
+            Comparison::GreaterOrEqual,
+            PropertyValue::Number(3.)
+        ));
+        // Numbers do not order against strings.
+        assert!(!compare("rank", Comparison::Less, river()));
+
+        // Missing properties.
+        assert!(!compare("name", Comparison::Equal, river()));
+        assert!(compare("name", Comparison::NotEqual, river()));
+        assert!(!compare(
+            "name",
+            Comparison::Less,
+            PropertyValue::Number(1.)
+        ));
+    }
+
+    #[test]
+    fn combinations() {
+        let layer = Layer::single(
+            point(),
+            &[("class", PropertyValue::String("river".to_owned()))],
+        );
+        let has = |key: &str| Filter::Has(key.to_owned());
+
+        assert!(matches(&has("class"), &layer));
+        assert!(!matches(&has("name"), &layer));
+        assert!(matches(&Filter::All(Vec::new()), &layer));
+        assert!(!matches(&Filter::Any(Vec::new()), &layer));
+        assert!(matches(
+            &Filter::Any(vec![has("name"), has("class")]),
+            &layer
+        ));
+        assert!(!matches(
+            &Filter::All(vec![has("name"), has("class")]),
+            &layer
+        ));
+        assert!(matches(&Filter::Not(Box::new(has("name"))), &layer));
+
+        let classes = |values: &[&str]| {
+            Filter::In(
+                "class".to_owned(),
+                values
+                    .iter()
+                    .map(|v| PropertyValue::String(v.to_string()))
+                    .collect(),
+            )
+        };
+        assert!(matches(&classes(&["canal", "river"]), &layer));
+        assert!(!matches(&classes(&["canal"]), &layer));
+    }
+
+    #[test]
+    fn geometry_type() {
+        let is = |geometry_type: &str| {
+            Filter::Compare(
+                "$type".to_owned(),
+                Comparison::Equal,
+                PropertyValue::String(geometry_type.to_owned()),
+            )
+        };
+        let points = Layer::single(point(), &[]);
+        let lines = Layer::single(Geometry::Lines(Vec::new()), &[]);
+        let polygons = Layer::single(Geometry::Polygons(Vec::new()), &[]);
+
+        assert!(matches(&is("Point"), &points));
+        assert!(matches(&is("LineString"), &lines));
+        assert!(matches(&is("Polygon"), &polygons));
+        assert!(!matches(&is("Polygon"), &lines));
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use egui::{vec2, Align2, Color32, FontId, Pos2, Rect, Stroke, Ui};
+
+use super::mvt::{Feature, Geometry, Layer, PropertyValue};
+use crate::{extras::galley_with_halo, Projector};
+
+/// How to label features of a layer, see [`super::LayerStyle::label`].
+#[derive(Clone, Debug)]
+pub struct LabelStyle {
+    pub(super) field: String,
+    pub(super) font: FontId,
+    pub(super) color: Color32,
+    pub(super) halo: Option<Stroke>,
+    pub(super) priority: i32,
+    pub(super) sort_key: Option<String>,
+    repeat_distance: f32,
+}
+
+impl LabelStyle {
+    /// Label features with the value of given property, e.g. `name`. Features without it are
+    /// not labelled.
+    pub fn new(field: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            font: FontId::proportional(12.),
+            color: Color32::from_gray(40),
+            halo: Some(Stroke::new(1.5, Color32::WHITE)),
+            priority: 0,
+            sort_key: None,
+            repeat_distance: 200.,
+        }
+    }
+
+    pub fn font(mut self, font: FontId) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn color(mut self, color: Color32) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Outline around the text, see [`crate::extras::galley_with_halo`].
+    pub fn halo(mut self, halo: Option<Stroke>) -> Self {
+        self.halo = halo;
+        self
+    }
+
+    /// Labels of higher priority are placed first, so they win when labels overlap, e.g. cities
+    /// over villages. Default is 0.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Numeric property ordering labels of the same priority, lower values first, e.g. `rank`.
+    pub fn sort_key(mut self, field: impl Into<String>) -> Self {
+        self.sort_key = Some(field.into());
+        self
+    }
+
+    /// Minimum distance, in points, between labels of the same text, e.g. of a road crossing
+    /// several tiles. Default is 200.
+    pub fn repeat_distance(mut self, distance: f32) -> Self {
+        self.repeat_distance = distance;
+        self
+    }
+}
+
+/// Label which might be placed, if there is room for it.
+struct Candidate<'a> {
+    style: &'a LabelStyle,
+    sort_key: f64,
+    /// Order of the layer's style, so that ties keep the style's order.
+    order: usize,
+    text: String,
+    anchor: Pos2,
+    /// How far from the anchor the text starts, for labels beside points.
+    offset: Option<f32>,
+}
+
+/// Labels collected from the layers of all visible tiles, placed together so that they do not
+/// overlap, even when they come from different tiles.
+#[derive(Default)]
+pub(crate) struct Labels<'a> {
+    candidates: Vec<Candidate<'a>>,
+}
+
+impl<'a> Labels<'a> {
+    pub(crate) fn add(
+        &mut self,
+        projector: &Projector,
+        style: &'a LabelStyle,
+        order: usize,
+        point_radius: f32,
+        layer: &Layer,
+        feature: &Feature,
+    ) {
+        let Some(text) = layer
+            .property(feature, &style.field)
+            .map(ToString::to_string)
+        else {
+            return;
+        };
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let sort_key = match style
+            .sort_key
+            .as_ref()
+            .and_then(|key| layer.property(feature, key))
+        {
+            Some(PropertyValue::Number(value)) => *value,
+            _ => 0.,
+        };
+
+        let mut push = |anchor: Pos2, offset: Option<f32>| {
+            self.candidates.push(Candidate {
+                style,
+                sort_key,
+                order,
+                text: text.clone(),
+                anchor,
+                offset,
+            });
+        };
+
+        match &feature.geometry {
+            Geometry::Points(points) => {
+                for point in points {
+                    push(projector.project(*point), Some(point_radius + 3.));
+                }
+            }
+            Geometry::Lines(lines) => {
+                let longest = lines
+                    .iter()
+                    .map(|line| {
+                        line.iter()
+                            .map(|p| projector.project(*p))
+                            .collect::<Vec<_>>()
+                    })
+                    .max_by(|a, b| length(a).total_cmp(&length(b)));
+                if let Some(anchor) = longest.and_then(|line| halfway(&line)) {
+                    push(anchor, None);
+                }
+            }
+            Geometry::Polygons(polygons) => {
+                let largest = polygons
+                    .iter()
+                    .max_by_key(|polygon| polygon.rings.first().map_or(0, Vec::len));
+                if let Some(polygon) = largest {
+                    push(projector.project(polygon.label_anchor), None);
+                }
+            }
+        }
+    }
+
+    /// Draw the labels which fit, starting with those of the highest priority.
+    pub(crate) fn draw(mut self, ui: &Ui, projector: &Projector) {
+        self.candidates.sort_by(|a, b| {
+            b.style
+                .priority
+                .cmp(&a.style.priority)
+                .then(a.sort_key.total_cmp(&b.sort_key))
+                .then(a.order.cmp(&b.order))
+        });
+
+        let painter = ui.painter();
+        let clip = painter.clip_rect();
+        let mut placed: Vec<Rect> = Vec::new();
+        let mut texts: HashMap<&str, Vec<Pos2>> = HashMap::new();
+
+        for candidate in &self.candidates {
+            if !clip.contains(candidate.anchor) {
+                continue;
+            }
+
+            let style = candidate.style;
+            let repeated = texts.get(candidate.text.as_str()).is_some_and(|anchors| {
+                anchors
+                    .iter()
+                    .any(|anchor| anchor.distance(candidate.anchor) < style.repeat_distance)
+            });
+            if repeated {
+                continue;
+            }
+
+            let galley =
+                painter.layout_no_wrap(candidate.text.clone(), style.font.clone(), style.color);
+            let rect = match candidate.offset {
+                Some(offset) => Align2::LEFT_CENTER
+                    .anchor_size(candidate.anchor + vec2(offset, 0.), galley.size()),
+                None => Align2::CENTER_CENTER.anchor_size(candidate.anchor, galley.size()),
+            };
+            if placed.iter().any(|other| other.intersects(rect.expand(2.))) {
+                continue;
+            }
+
+            // Share the screen with labels of other plugins.
+            if !projector.place_label(candidate.anchor) {
+                continue;
+            }
+
+            placed.push(rect);
+            texts
+                .entry(candidate.text.as_str())
+                .or_default()
+                .push(candidate.anchor);
+
+            match style.halo {
+                Some(halo) => galley_with_halo(painter, rect.min, galley, halo),
+                None => painter.galley(rect.min, galley, style.color),
+            }
+        }
+    }
+}
+
+fn length(line: &[Pos2]) -> f32 {
+    line.windows(2).map(|w| w[0].distance(w[1])).sum()
+}
+
+/// Point halfway along the line.
+fn halfway(line: &[Pos2]) -> Option<Pos2> {
+    let mut remaining = length(line) / 2.;
+    for w in line.windows(2) {
+        let segment = w[0].distance(w[1]);
+        if segment >= remaining && segment > 0. {
+            return Some(w[0].lerp(w[1], remaining / segment));
+        }
+        remaining -= segment;
+    }
+    line.first().copied()
+}
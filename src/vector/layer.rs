@@ -0,0 +1,346 @@
+use std::{
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use egui::{Color32, Mesh, Response, Shape, Stroke, Ui};
+
+use super::{
+    expression::{FeatureValue, Filter, Styled},
+    labels::{LabelStyle, Labels},
+    mvt::Geometry,
+    VectorTiles,
+};
+use crate::{Plugin, Projector, TileId};
+
+/// Upper limit of tiles drawn at once. When more are visible, e.g. when the map is tilted, tiles
+/// of lower zoom are used.
+const MAX_TILES: usize = 64;
+
+/// How to draw one of the tile's layers.
+#[derive(Clone, Debug)]
+pub struct LayerStyle {
+    pub(super) source_layer: String,
+    pub(super) fill: Option<Styled<Color32>>,
+    pub(super) stroke_color: Styled<Color32>,
+    pub(super) stroke_width: Styled<f32>,
+    pub(super) point_radius: Styled<f32>,
+    pub(super) zoom_range: RangeInclusive<f64>,
+    pub(super) filter: Option<Filter>,
+    pub(super) label: Option<LabelStyle>,
+}
+
+impl LayerStyle {
+    /// Style of the layer of given name in the vector tiles, e.g. `water` or `roads`. By
+    /// default, it is drawn with a thin gray stroke and no fill.
+    pub fn new(source_layer: impl Into<String>) -> Self {
+        Self {
+            source_layer: source_layer.into(),
+            fill: None,
+            stroke_color: Color32::GRAY.into(),
+            stroke_width: 1.0.into(),
+            point_radius: 3.0.into(),
+            zoom_range: 0.0..=f64::INFINITY,
+            filter: None,
+            label: None,
+        }
+    }
+
+    /// Color of polygons and points.
+    pub fn fill(mut self, color: impl Into<Styled<Color32>>) -> Self {
+        self.fill = Some(color.into());
+        self
+    }
+
+    /// Stroke of lines and polygons' outlines.
+    pub fn stroke(mut self, stroke: Stroke) -> Self {
+        self.stroke_color = stroke.color.into();
+        self.stroke_width = stroke.width.into();
+        self
+    }
+
+    /// Color of the stroke, see [`Self::stroke`].
+    pub fn stroke_color(mut self, color: impl Into<Styled<Color32>>) -> Self {
+        self.stroke_color = color.into();
+        self
+    }
+
+    /// Width of the stroke, see [`Self::stroke`].
+    pub fn stroke_width(mut self, width: impl Into<Styled<f32>>) -> Self {
+        self.stroke_width = width.into();
+        self
+    }
+
+    pub fn point_radius(mut self, radius: impl Into<Styled<f32>>) -> Self {
+        self.point_radius = radius.into();
+        self
+    }
+
+    /// Map's zoom levels at which the layer is drawn.
+    pub fn zoom_range(mut self, zoom_range: RangeInclusive<f64>) -> Self {
+        self.zoom_range = zoom_range;
+        self
+    }
+
+    /// Draw only the features which match the filter. Together with [`Self::zoom_range`], it
+    /// sets the visibility of the features, as the filter may compare the `$zoom` too.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Label the features. Labels of all layers are drawn above them, and ones which would
+    /// overlap labels of higher priority are left out.
+    pub fn label(mut self, label: LabelStyle) -> Self {
+        self.label = Some(label);
+        self
+    }
+}
+
+impl LayerStyle {
+    /// Style at given zoom, or `None` if the layer is hidden at it.
+    fn at(&self, zoom: f64) -> Option<LayerAtZoom> {
+        self.zoom_range.contains(&zoom).then(|| LayerAtZoom {
+            filter: self.filter.as_ref().map(|filter| filter.at(zoom)),
+            fill: self.fill.as_ref().map(|fill| fill.at(zoom)),
+            stroke_color: self.stroke_color.at(zoom),
+            stroke_width: self.stroke_width.at(zoom),
+            point_radius: self.point_radius.at(zoom),
+        })
+    }
+}
+
+/// [`LayerStyle`] evaluated at a zoom, which is left to be applied to each feature.
+#[derive(Debug)]
+struct LayerAtZoom {
+    filter: Option<Filter>,
+    fill: Option<FeatureValue<Color32>>,
+    stroke_color: FeatureValue<Color32>,
+    stroke_width: FeatureValue<f32>,
+    point_radius: FeatureValue<f32>,
+}
+
+/// Styles of the layers at the zoom they were evaluated at.
+type Evaluated = (f64, Arc<Vec<Option<LayerAtZoom>>>);
+
+/// Style of vector tiles. Only the listed layers are drawn, in order, so the first one ends up
+/// at the bottom.
+#[derive(Debug, Default)]
+pub struct VectorStyle {
+    pub(super) layers: Vec<LayerStyle>,
+
+    /// Layers evaluated once per change of the zoom, rather than in each frame.
+    evaluated: Mutex<Option<Evaluated>>,
+}
+
+impl Clone for VectorStyle {
+    fn clone(&self) -> Self {
+        Self {
+            layers: self.layers.clone(),
+            evaluated: Mutex::default(),
+        }
+    }
+}
+
+impl VectorStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_layer(mut self, layer: LayerStyle) -> Self {
+        self.layers.push(layer);
+        *self
+            .evaluated
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+        self
+    }
+
+    /// Styles of the layers at given zoom, or `None` for the hidden ones.
+    fn at(&self, zoom: f64) -> Arc<Vec<Option<LayerAtZoom>>> {
+        let mut evaluated = self
+            .evaluated
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match &*evaluated {
+            Some((at, layers)) if *at == zoom => layers.clone(),
+            _ => {
+                let layers = Arc::new(self.layers.iter().map(|layer| layer.at(zoom)).collect());
+                *evaluated = Some((zoom, Arc::clone(&layers)));
+                layers
+            }
+        }
+    }
+}
+
+/// [`Plugin`] which draws [`VectorTiles`] in given style. Local maps are not supported.
+pub struct VectorTileLayer<'a> {
+    tiles: &'a mut VectorTiles,
+    style: &'a VectorStyle,
+}
+
+impl<'a> VectorTileLayer<'a> {
+    pub fn new(tiles: &'a mut VectorTiles, style: &'a VectorStyle) -> Self {
+        Self { tiles, style }
+    }
+}
+
+/// Tiles covering the visible part of the map.
+fn visible_tiles(projector: &Projector, max_zoom: u8) -> Vec<TileId> {
+    // Tiles do not reach the poles.
+    const MAX_LATITUDE: f64 = 85.05;
+
+    let bounds = projector.visible_bounds();
+    let (min, max) = (bounds.min(), bounds.max());
+    let north_west = crate::Position {
+        x: min.x.max(-180.),
+        y: max.y.min(MAX_LATITUDE),
+    };
+    let south_east = crate::Position {
+        x: max.x.min(180.),
+        y: min.y.max(-MAX_LATITUDE),
+    };
+
+    let mut zoom = (projector.memory().zoom().floor() as u8).min(max_zoom);
+    loop {
+        let (from, to) = (
+            TileId::from_position(north_west, zoom),
+            TileId::from_position(south_east, zoom),
+        );
+        let last = (1u32 << zoom) - 1;
+        let (xs, ys) = (from.x..=to.x.min(last), from.y..=to.y.min(last));
+        if xs.clone().count() * ys.clone().count() <= MAX_TILES || zoom == 0 {
+            return xs
+                .flat_map(|x| ys.clone().map(move |y| TileId { x, y, zoom }))
+                .collect();
+        }
+        zoom -= 1;
+    }
+}
+
+impl Plugin for VectorTileLayer<'_> {
+    fn run(self: Box<Self>, ui: &mut Ui, _response: &Response, projector: &Projector) {
+        if !projector.memory().is_global() {
+            return;
+        }
+
+        let tiles: Vec<_> = visible_tiles(projector, self.tiles.max_zoom())
+            .into_iter()
+            .filter_map(|tile_id| self.tiles.at(tile_id))
+            .collect();
+
+        let zoom = projector.memory().zoom();
+        let painter = ui.painter();
+        let mut labels = Labels::default();
+
+        let evaluated = self.style.at(zoom);
+        let layers = self.style.layers.iter().zip(evaluated.iter());
+
+        for (order, (style, at_zoom)) in layers.enumerate() {
+            let Some(at_zoom) = at_zoom else {
+                continue;
+            };
+
+            let features = tiles
+                .iter()
+                .flat_map(|tile| &tile.layers)
+                .filter(|layer| layer.name == style.source_layer)
+                .flat_map(|layer| layer.features.iter().map(move |feature| (layer, feature)));
+
+            for (layer, feature) in features {
+                if at_zoom
+                    .filter
+                    .as_ref()
+                    .is_some_and(|filter| !filter.matches(layer, feature))
+                {
+                    continue;
+                }
+
+                let fill = at_zoom
+                    .fill
+                    .as_ref()
+                    .and_then(|fill| fill.of(layer, feature));
+                let stroke = Stroke::new(
+                    at_zoom.stroke_width.of(layer, feature).unwrap_or(0.),
+                    at_zoom
+                        .stroke_color
+                        .of(layer, feature)
+                        .unwrap_or(Color32::TRANSPARENT),
+                );
+                let point_radius = at_zoom.point_radius.of(layer, feature).unwrap_or(0.);
+
+                if let Some(label) = &style.label {
+                    labels.add(projector, label, order, point_radius, layer, feature);
+                }
+
+                match &feature.geometry {
+                    Geometry::Points(points) => {
+                        let color = fill.unwrap_or(stroke.color);
+                        if point_radius > 0. {
+                            for point in points {
+                                painter.circle_filled(
+                                    projector.project(*point),
+                                    point_radius,
+                                    color,
+                                );
+                            }
+                        }
+                    }
+                    Geometry::Lines(lines) => {
+                        if !stroke.is_empty() {
+                            for line in lines {
+                                let points = line.iter().map(|p| projector.project(*p)).collect();
+                                painter.add(Shape::line(points, stroke));
+                            }
+                        }
+                    }
+                    Geometry::Polygons(polygons) => {
+                        for polygon in polygons {
+                            if let Some(fill) = fill {
+                                let mut mesh = Mesh::default();
+                                for vertex in &polygon.vertices {
+                                    mesh.colored_vertex(projector.project(*vertex), fill);
+                                }
+                                mesh.indices.clone_from(&polygon.indices);
+                                painter.add(mesh);
+                            }
+
+                            if !stroke.is_empty() {
+                                for ring in &polygon.rings {
+                                    let points =
+                                        ring.iter().map(|p| projector.project(*p)).collect();
+                                    painter.add(Shape::closed_line(points, stroke));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        labels.draw(ui, projector);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluated_once_per_zoom() {
+        let style = VectorStyle::new()
+            .with_layer(LayerStyle::new("roads").zoom_range(10.0..=f64::INFINITY))
+            .with_layer(LayerStyle::new("water"));
+
+        let layers = style.at(8.);
+        assert!(layers[0].is_none());
+        assert!(layers[1].is_some());
+
+        assert!(Arc::ptr_eq(&layers, &style.at(8.)));
+        assert!(!Arc::ptr_eq(&layers, &style.at(12.)));
+        assert!(style.at(12.)[0].is_some());
+
+        // Clones do not share the evaluated layers.
+        assert!(style.clone().evaluated.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,909 @@
+use egui::{Color32, FontId, Stroke};
+use serde_json::Value as Json;
+
+use super::{
+    expression::{Comparison, Filter, Styled, Zoomed},
+    labels::LabelStyle,
+    layer::{LayerStyle, VectorStyle},
+    mvt::PropertyValue,
+};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid style: {reason}")]
+pub struct InvalidStyle {
+    pub reason: String,
+}
+
+/// Vector source of a MapLibre style, along with the style of its layers. Draw it with
+/// [`super::VectorTiles`] of the tiles' URL, and [`super::VectorTileLayer`] of the style.
+#[derive(Clone, Debug)]
+pub struct StyleSource {
+    /// Name by which the style's layers refer to the source.
+    pub name: String,
+    /// URL templates of the tiles, with `{z}`, `{x}` and `{y}` placeholders, e.g. for
+    /// [`crate::sources::UrlTemplate`]. Empty when the source is given by a TileJSON URL.
+    pub tiles: Vec<String>,
+    /// URL of the source's TileJSON, which lists the tiles' URL templates. It is not fetched.
+    pub url: Option<String>,
+    pub attribution: Option<String>,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub style: VectorStyle,
+}
+
+/// Parse a [MapLibre style](https://maplibre.org/maplibre-style-spec/) into the styles of its
+/// vector sources. Supported is a subset: fill, line, circle and symbol layers, with their
+/// colors, opacities, widths and text labels, zoom functions, `match` and `case` expressions of
+/// feature properties, and filters comparing properties or the zoom.
+/// Other layers, e.g. background or raster ones, and layers using unsupported expressions, are
+/// left out with a warning. Text properties which depend on the zoom use their highest stop.
+pub fn parse_maplibre_style(json: &str) -> Result<Vec<StyleSource>, InvalidStyle> {
+    let style: Json = serde_json::from_str(json).map_err(|error| invalid(error.to_string()))?;
+
+    let mut sources: Vec<StyleSource> = style
+        .get("sources")
+        .and_then(Json::as_object)
+        .ok_or_else(|| invalid("missing sources"))?
+        .iter()
+        .filter(|(_, source)| string(source, "type") == Some("vector"))
+        .map(|(name, source)| StyleSource {
+            name: name.clone(),
+            tiles: source
+                .get("tiles")
+                .and_then(Json::as_array)
+                .map(|tiles| {
+                    tiles
+                        .iter()
+                        .filter_map(Json::as_str)
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            url: string(source, "url").map(str::to_owned),
+            attribution: string(source, "attribution").map(str::to_owned),
+            min_zoom: zoom_level(source, "minzoom").unwrap_or(0),
+            max_zoom: zoom_level(source, "maxzoom").unwrap_or(22),
+            style: VectorStyle::new(),
+        })
+        .collect();
+
+    let layers = style
+        .get("layers")
+        .and_then(Json::as_array)
+        .ok_or_else(|| invalid("missing layers"))?;
+
+    for (index, layer) in layers.iter().enumerate() {
+        let id = string(layer, "id").unwrap_or_default();
+        let source = string(layer, "source")
+            .and_then(|name| sources.iter_mut().find(|source| source.name == name));
+        let Some(source) = source else {
+            log::debug!("Leaving out layer {id}, which is not of a vector source.");
+            continue;
+        };
+
+        match parse_layer(layer, index) {
+            Ok(Some(style)) => source.style = std::mem::take(&mut source.style).with_layer(style),
+            Ok(None) => {}
+            Err(reason) => log::warn!("Leaving out layer {id}: {reason}."),
+        }
+    }
+
+    Ok(sources)
+}
+
+fn invalid(reason: impl Into<String>) -> InvalidStyle {
+    InvalidStyle {
+        reason: reason.into(),
+    }
+}
+
+fn string<'a>(object: &'a Json, key: &str) -> Option<&'a str> {
+    object.get(key).and_then(Json::as_str)
+}
+
+fn zoom_level(object: &Json, key: &str) -> Option<u8> {
+    object
+        .get(key)
+        .and_then(Json::as_f64)
+        .map(|zoom| zoom.clamp(0., 30.) as u8)
+}
+
+fn paint<'a>(layer: &'a Json, key: &str) -> Option<&'a Json> {
+    layer.get("paint").and_then(|paint| paint.get(key))
+}
+
+fn layout<'a>(layer: &'a Json, key: &str) -> Option<&'a Json> {
+    layer.get("layout").and_then(|layout| layout.get(key))
+}
+
+/// Style of a layer, or `None` if it is hidden. Index of the layer gives priority to labels of
+/// the layers above, like MapLibre does.
+fn parse_layer(layer: &Json, index: usize) -> Result<Option<LayerStyle>, String> {
+    let kind = string(layer, "type").ok_or("missing type")?;
+    if !matches!(kind, "fill" | "line" | "circle" | "symbol") {
+        return Err(format!("{kind} layers are not supported"));
+    }
+    if layout(layer, "visibility").and_then(Json::as_str) == Some("none") {
+        return Ok(None);
+    }
+
+    let source_layer = string(layer, "source-layer").ok_or("missing source-layer")?;
+    let min_zoom = layer.get("minzoom").and_then(Json::as_f64).unwrap_or(0.);
+    let max_zoom = layer
+        .get("maxzoom")
+        .and_then(Json::as_f64)
+        .unwrap_or(f64::INFINITY);
+    let mut style = LayerStyle::new(source_layer)
+        .stroke_width(0.)
+        .point_radius(0.)
+        .zoom_range(min_zoom..=max_zoom);
+
+    if let Some(filter) = layer.get("filter") {
+        style = style.filter(parse_filter(filter)?);
+    }
+
+    let color = |value| styled_color(value).map(|color| color.unwrap_or(Color32::BLACK.into()));
+
+    match kind {
+        "fill" => {
+            style = style.fill(with_opacity(
+                color(paint(layer, "fill-color"))?,
+                number_property(paint(layer, "fill-opacity"))?,
+            ));
+            if let Some(outline) = styled_color(paint(layer, "fill-outline-color"))? {
+                style = style.stroke_color(outline).stroke_width(1.);
+            }
+        }
+        "line" => {
+            style = style
+                .stroke_color(with_opacity(
+                    color(paint(layer, "line-color"))?,
+                    number_property(paint(layer, "line-opacity"))?,
+                ))
+                .stroke_width(styled_number(paint(layer, "line-width"))?.unwrap_or(1.0.into()));
+        }
+        "circle" => {
+            style = style
+                .fill(with_opacity(
+                    color(paint(layer, "circle-color"))?,
+                    number_property(paint(layer, "circle-opacity"))?,
+                ))
+                .point_radius(styled_number(paint(layer, "circle-radius"))?.unwrap_or(5.0.into()));
+        }
+        _ => style = style.label(parse_label(layer, index)?),
+    }
+
+    Ok(Some(style))
+}
+
+fn parse_label(layer: &Json, index: usize) -> Result<LabelStyle, String> {
+    let field = layout(layer, "text-field").ok_or("only text labels are supported")?;
+    let size = number_property(layout(layer, "text-size"))?
+        .and_then(|value| value.last())
+        .unwrap_or(16.);
+    let color = color_property(paint(layer, "text-color"))?
+        .and_then(|value| value.last())
+        .unwrap_or(Color32::BLACK);
+    let halo_width = number_property(paint(layer, "text-halo-width"))?
+        .and_then(|value| value.last())
+        .unwrap_or(0.);
+    let halo_color = color_property(paint(layer, "text-halo-color"))?
+        .and_then(|value| value.last())
+        .unwrap_or(Color32::TRANSPARENT);
+    let halo = Stroke::new(halo_width, halo_color);
+
+    let mut label = LabelStyle::new(text_field(field)?)
+        .font(FontId::proportional(size))
+        .color(color)
+        .halo((!halo.is_empty()).then_some(halo))
+        .priority(index.try_into().unwrap_or(i32::MAX));
+    if let Some(sort_key) = layout(layer, "symbol-sort-key") {
+        label = label.sort_key(get(sort_key).ok_or("unsupported symbol-sort-key")?);
+    }
+    Ok(label)
+}
+
+/// Property shown by a label. Text mixing properties with other text is not supported.
+fn text_field(value: &Json) -> Result<String, String> {
+    let unsupported = || format!("unsupported text-field {value}");
+    if let Some(text) = value.as_str() {
+        let field = text
+            .trim()
+            .strip_prefix('{')
+            .and_then(|text| text.strip_suffix('}'))
+            .filter(|field| !field.contains(['{', '}']));
+        return field.map(str::to_owned).ok_or_else(unsupported);
+    }
+    match value.as_array().map(Vec::as_slice) {
+        Some([op, first, ..])
+            if matches!(op.as_str(), Some("to-string" | "coalesce" | "format")) =>
+        {
+            text_field(first)
+        }
+        _ => get(value).ok_or_else(unsupported),
+    }
+}
+
+/// Property read by a `["get", key]` expression.
+fn get(value: &Json) -> Option<String> {
+    match value.as_array().map(Vec::as_slice) {
+        Some([op, key]) if op.as_str() == Some("get") => key.as_str().map(str::to_owned),
+        _ => None,
+    }
+}
+
+/// Property compared by a filter, either by name, as in legacy filters, or by an expression.
+fn key(value: &Json) -> Option<String> {
+    match value {
+        Json::String(key) => Some(key.clone()),
+        Json::Array(expression) if expression.len() == 1 => match expression[0].as_str() {
+            Some("geometry-type") => Some("$type".to_owned()),
+            Some("zoom") => Some("$zoom".to_owned()),
+            _ => None,
+        },
+        _ => get(value),
+    }
+}
+
+fn literal(value: &Json) -> Option<PropertyValue> {
+    match value {
+        Json::String(value) => Some(PropertyValue::String(value.clone())),
+        Json::Number(value) => value.as_f64().map(PropertyValue::Number),
+        Json::Bool(value) => Some(PropertyValue::Bool(*value)),
+        _ => None,
+    }
+}
+
+/// Legacy filters, e.g. `["==", "class", "river"]`, and expressions of the same comparisons,
+/// e.g. `["==", ["get", "class"], "river"]`, along with `match` expressions of booleans.
+fn parse_filter(filter: &Json) -> Result<Filter, String> {
+    if let Some(value) = filter.as_bool() {
+        return Ok(if value {
+            Filter::All(Vec::new())
+        } else {
+            Filter::Any(Vec::new())
+        });
+    }
+
+    let unsupported = || format!("unsupported filter {filter}");
+    let Some([op, args @ ..]) = filter.as_array().map(Vec::as_slice) else {
+        return Err(unsupported());
+    };
+    let filters = |args: &[Json]| args.iter().map(parse_filter).collect::<Result<_, _>>();
+    let key = |value: &Json| key(value).ok_or_else(unsupported);
+    let literal = |value: &Json| literal(value).ok_or_else(unsupported);
+    let literals = |values: &[Json]| values.iter().map(literal).collect::<Result<_, _>>();
+    let not = |filter| Filter::Not(Box::new(filter));
+
+    Ok(match (op.as_str().ok_or_else(unsupported)?, args) {
+        ("all", args) => Filter::All(filters(args)?),
+        ("any", args) => Filter::Any(filters(args)?),
+        ("none", args) => not(Filter::Any(filters(args)?)),
+        ("!", [filter]) => not(parse_filter(filter)?),
+        ("has", [property]) => Filter::Has(key(property)?),
+        ("!has", [property]) => not(Filter::Has(key(property)?)),
+        ("==", [property, value]) => {
+            Filter::Compare(key(property)?, Comparison::Equal, literal(value)?)
+        }
+        ("!=", [property, value]) => {
+            Filter::Compare(key(property)?, Comparison::NotEqual, literal(value)?)
+        }
+        ("<", [property, value]) => {
+            Filter::Compare(key(property)?, Comparison::Less, literal(value)?)
+        }
+        ("<=", [property, value]) => {
+            Filter::Compare(key(property)?, Comparison::LessOrEqual, literal(value)?)
+        }
+        (">", [property, value]) => {
+            Filter::Compare(key(property)?, Comparison::Greater, literal(value)?)
+        }
+        (">=", [property, value]) => {
+            Filter::Compare(key(property)?, Comparison::GreaterOrEqual, literal(value)?)
+        }
+        // Expression, e.g. `["in", ["get", "class"], ["literal", ["river", "canal"]]]`.
+        ("in", [property @ Json::Array(_), list]) => {
+            let values = match list.as_array().map(Vec::as_slice) {
+                Some([op, Json::Array(values)]) if op.as_str() == Some("literal") => values,
+                _ => return Err(unsupported()),
+            };
+            Filter::In(key(property)?, literals(values)?)
+        }
+        ("in", [property, values @ ..]) => Filter::In(key(property)?, literals(values)?),
+        ("!in", [property, values @ ..]) => not(Filter::In(key(property)?, literals(values)?)),
+        ("match", [property, arms @ .., fallback]) if arms.len() % 2 == 0 => {
+            let fallback = fallback.as_bool().ok_or_else(unsupported)?;
+            let (mut matching, mut others) = (Vec::new(), Vec::new());
+            for arm in arms.chunks_exact(2) {
+                let values = match &arm[0] {
+                    Json::Array(values) => literals(values)?,
+                    value => vec![literal(value)?],
+                };
+                if arm[1].as_bool().ok_or_else(unsupported)? {
+                    matching.extend(values);
+                } else {
+                    others.extend(values);
+                }
+            }
+            if fallback {
+                not(Filter::In(key(property)?, others))
+            } else {
+                Filter::In(key(property)?, matching)
+            }
+        }
+        _ => return Err(unsupported()),
+    })
+}
+
+fn color_property(value: Option<&Json>) -> Result<Option<Zoomed<Color32>>, String> {
+    value
+        .map(|value| zoomed(value, &|leaf| leaf.as_str().and_then(parse_color)))
+        .transpose()
+}
+
+fn number_property(value: Option<&Json>) -> Result<Option<Zoomed<f32>>, String> {
+    value.map(|value| zoomed(value, &number)).transpose()
+}
+
+fn styled_color(value: Option<&Json>) -> Result<Option<Styled<Color32>>, String> {
+    value
+        .map(|value| styled(value, &|leaf| leaf.as_str().and_then(parse_color)))
+        .transpose()
+}
+
+fn styled_number(value: Option<&Json>) -> Result<Option<Styled<f32>>, String> {
+    value.map(|value| styled(value, &number)).transpose()
+}
+
+fn number(value: &Json) -> Option<f32> {
+    value.as_f64().map(|number| number as f32)
+}
+
+/// Value of [`zoomed`], or one which depends on the feature's properties, given by `match` and
+/// `case` expressions of zoomed values.
+fn styled<T: Copy>(value: &Json, leaf: &dyn Fn(&Json) -> Option<T>) -> Result<Styled<T>, String> {
+    let unsupported = || format!("unsupported value {value}");
+    let arms = |arms: &[Json], filter: &dyn Fn(&Json) -> Result<Filter, String>| {
+        arms.chunks_exact(2)
+            .map(|arm| Ok((filter(&arm[0])?, zoomed(&arm[1], leaf)?)))
+            .collect::<Result<Vec<_>, String>>()
+    };
+
+    match value.as_array().map(Vec::as_slice) {
+        Some([op, input, cases @ .., fallback])
+            if op.as_str() == Some("match") && cases.len() % 2 == 0 =>
+        {
+            let key = key(input).ok_or_else(unsupported)?;
+            let cases = arms(cases, &|labels| {
+                let values = match labels {
+                    Json::Array(values) => values.iter().map(literal).collect(),
+                    value => literal(value).map(|value| vec![value]),
+                };
+                Ok(Filter::In(key.clone(), values.ok_or_else(unsupported)?))
+            })?;
+            Ok(Styled::Case(cases, zoomed(fallback, leaf)?))
+        }
+        Some([op, cases @ .., fallback]) if op.as_str() == Some("case") && cases.len() % 2 == 0 => {
+            Ok(Styled::Case(
+                arms(cases, &parse_filter)?,
+                zoomed(fallback, leaf)?,
+            ))
+        }
+        _ => zoomed(value, leaf).map(Styled::Zoomed),
+    }
+}
+
+/// Constant value, or one which depends on the zoom, given by legacy `stops` or by
+/// `interpolate` and `step` expressions.
+fn zoomed<T: Copy>(value: &Json, leaf: &dyn Fn(&Json) -> Option<T>) -> Result<Zoomed<T>, String> {
+    let unsupported = || format!("unsupported value {value}");
+    let stops = |values: &[Json]| -> Result<Vec<(f64, T)>, String> {
+        let mut stops = Vec::new();
+        for pair in values.chunks(2) {
+            match pair {
+                [zoom, value] => stops.push((
+                    zoom.as_f64().ok_or_else(unsupported)?,
+                    leaf(value).ok_or_else(unsupported)?,
+                )),
+                _ => return Err(unsupported()),
+            }
+        }
+        Ok(stops)
+    };
+    let is_zoom = |input: &Json| input.as_array().is_some_and(|input| input == &["zoom"]);
+
+    if let Some(function) = value.as_object() {
+        if function.contains_key("property") {
+            return Err(format!("property functions are not supported: {value}"));
+        }
+        let pairs: Vec<Json> = function
+            .get("stops")
+            .and_then(Json::as_array)
+            .ok_or_else(unsupported)?
+            .iter()
+            .flat_map(|stop| stop.as_array().cloned().unwrap_or_default())
+            .collect();
+        let stops = stops(&pairs)?;
+        return Ok(match function.get("type").and_then(Json::as_str) {
+            Some("interval") => Zoomed::Step(stops),
+            _ => Zoomed::Interpolate {
+                base: function.get("base").and_then(Json::as_f64).unwrap_or(1.),
+                stops,
+            },
+        });
+    }
+
+    match value.as_array().map(Vec::as_slice) {
+        Some([op, interpolation, input, values @ ..])
+            if op.as_str() == Some("interpolate") && is_zoom(input) =>
+        {
+            let base = match interpolation.as_array().map(Vec::as_slice) {
+                Some([kind, base]) if kind.as_str() == Some("exponential") => {
+                    base.as_f64().ok_or_else(unsupported)?
+                }
+                _ => 1.,
+            };
+            Ok(Zoomed::Interpolate {
+                base,
+                stops: stops(values)?,
+            })
+        }
+        Some([op, input, first, values @ ..]) if op.as_str() == Some("step") && is_zoom(input) => {
+            let mut all = vec![(f64::NEG_INFINITY, leaf(first).ok_or_else(unsupported)?)];
+            all.extend(stops(values)?);
+            Ok(Zoomed::Step(all))
+        }
+        _ => leaf(value).map(Zoomed::Constant).ok_or_else(unsupported),
+    }
+}
+
+/// Color with the opacity applied. Both depending on the zoom, or the opacity depending on it
+/// while the color depends on the feature, is not supported, in which case the opacity is left
+/// out.
+fn with_opacity(color: Styled<Color32>, opacity: Option<Zoomed<f32>>) -> Styled<Color32> {
+    match (color, opacity) {
+        (color, None) => color,
+        (color, Some(Zoomed::Constant(opacity))) => color.map(|c| c.gamma_multiply(opacity)),
+        (Styled::Zoomed(Zoomed::Constant(color)), Some(opacity)) => {
+            opacity.map(|o| color.gamma_multiply(o)).into()
+        }
+        (color, Some(_)) => {
+            log::warn!("Leaving out opacity, which depends on the zoom along with the color.");
+            color
+        }
+    }
+}
+
+/// CSS color: hex, `rgb()`, `rgba()`, `hsl()`, `hsla()` or one of the basic names.
+fn parse_color(text: &str) -> Option<Color32> {
+    let text = text.trim().to_ascii_lowercase();
+    if text.starts_with('#') {
+        return Color32::from_hex(&text).ok();
+    }
+
+    if let Some((function, args)) = text.strip_suffix(')').and_then(|text| text.split_once('(')) {
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        let alpha = match args.get(3) {
+            Some(alpha) => (alpha.parse::<f32>().ok()?.clamp(0., 1.) * 255.).round() as u8,
+            None => 255,
+        };
+        let [r, g, b] = match (function.trim(), &args[..]) {
+            ("rgb" | "rgba", [r, g, b, ..]) if args.len() <= 4 => {
+                let channel = |c: &str| {
+                    c.parse::<f32>()
+                        .ok()
+                        .map(|c| c.clamp(0., 255.).round() as u8)
+                };
+                [channel(r)?, channel(g)?, channel(b)?]
+            }
+            ("hsl" | "hsla", [h, s, l, ..]) if args.len() <= 4 => {
+                let percent = |v: &str| {
+                    v.strip_suffix('%')?
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .map(|v| v / 100.)
+                };
+                hsl_to_rgb(h.parse().ok()?, percent(s)?, percent(l)?)
+            }
+            _ => return None,
+        };
+        return Some(Color32::from_rgba_unmultiplied(r, g, b, alpha));
+    }
+
+    let [r, g, b] = match text.as_str() {
+        "transparent" => return Some(Color32::TRANSPARENT),
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "red" => [255, 0, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "orange" => [255, 165, 0],
+        _ => return None,
+    };
+    Some(Color32::from_rgb(r, g, b))
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    let (saturation, lightness) = (saturation.clamp(0., 1.), lightness.clamp(0., 1.));
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let sector = hue.rem_euclid(360.) / 60.;
+    let x = chroma * (1. - (sector % 2. - 1.).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let m = lightness - chroma / 2.;
+    [r, g, b].map(|c| ((c + m) * 255.).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STYLE: &str = r##"{
+        "version": 8,
+        "sources": {
+            "openmaptiles": {
+                "type": "vector",
+                "tiles": ["https://tiles.example.com/{z}/{x}/{y}.pbf"],
+                "maxzoom": 14,
+                "attribution": "© Example"
+            },
+            "hillshade": {"type": "raster", "tiles": ["https://example.com/{z}/{x}/{y}.png"]}
+        },
+        "layers": [
+            {"id": "background", "type": "background", "paint": {"background-color": "#fff"}},
+            {"id": "shading", "type": "raster", "source": "hillshade"},
+            {
+                "id": "water",
+                "type": "fill",
+                "source": "openmaptiles",
+                "source-layer": "water",
+                "filter": ["all", ["==", "$type", "Polygon"], ["!=", "intermittent", 1]],
+                "paint": {"fill-color": "rgb(0, 0, 255)", "fill-opacity": 0.5}
+            },
+            {
+                "id": "roads",
+                "type": "line",
+                "source": "openmaptiles",
+                "source-layer": "transportation",
+                "minzoom": 5,
+                "filter": ["in", ["get", "class"], ["literal", ["primary", "secondary"]]],
+                "paint": {
+                    "line-color": "#ff0000",
+                    "line-width": ["interpolate", ["exponential", 1.5], ["zoom"], 5, 1, 15, 8]
+                }
+            },
+            {
+                "id": "hidden",
+                "type": "line",
+                "source": "openmaptiles",
+                "source-layer": "boundary",
+                "layout": {"visibility": "none"}
+            },
+            {
+                "id": "extruded",
+                "type": "fill-extrusion",
+                "source": "openmaptiles",
+                "source-layer": "building"
+            },
+            {
+                "id": "places",
+                "type": "symbol",
+                "source": "openmaptiles",
+                "source-layer": "place",
+                "layout": {
+                    "text-field": "{name}",
+                    "text-size": {"stops": [[4, 10], [10, 14]]},
+                    "symbol-sort-key": ["get", "rank"]
+                },
+                "paint": {"text-color": "#333", "text-halo-color": "#fff", "text-halo-width": 1}
+            }
+        ]
+    }"##;
+
+    #[test]
+    fn style() {
+        let sources = parse_maplibre_style(STYLE).unwrap();
+        assert_eq!(sources.len(), 1);
+
+        let source = &sources[0];
+        assert_eq!(source.name, "openmaptiles");
+        assert_eq!(source.tiles, ["https://tiles.example.com/{z}/{x}/{y}.pbf"]);
+        assert_eq!(source.attribution.as_deref(), Some("© Example"));
+        assert_eq!((source.min_zoom, source.max_zoom), (0, 14));
+
+        let layers = &source.style.layers;
+        let names: Vec<_> = layers.iter().map(|l| l.source_layer.as_str()).collect();
+        assert_eq!(names, ["water", "transportation", "place"]);
+
+        let water = &layers[0];
+        assert_eq!(
+            water.fill,
+            Some(Styled::from(
+                Color32::from_rgb(0, 0, 255).gamma_multiply(0.5)
+            ))
+        );
+        assert_eq!(water.stroke_width, Styled::from(0.));
+        assert_eq!(
+            water.filter,
+            Some(Filter::All(vec![
+                Filter::Compare(
+                    "$type".to_owned(),
+                    Comparison::Equal,
+                    PropertyValue::String("Polygon".to_owned())
+                ),
+                Filter::Compare(
+                    "intermittent".to_owned(),
+                    Comparison::NotEqual,
+                    PropertyValue::Number(1.)
+                ),
+            ]))
+        );
+
+        let roads = &layers[1];
+        assert_eq!(roads.fill, None);
+        assert_eq!(roads.stroke_color, Styled::from(Color32::RED));
+        assert_eq!(
+            roads.stroke_width,
+            Styled::Zoomed(Zoomed::Interpolate {
+                base: 1.5,
+                stops: vec![(5., 1.), (15., 8.)]
+            })
+        );
+        assert_eq!(*roads.zoom_range.start(), 5.);
+        assert_eq!(
+            roads.filter,
+            Some(Filter::In(
+                "class".to_owned(),
+                vec![
+                    PropertyValue::String("primary".to_owned()),
+                    PropertyValue::String("secondary".to_owned())
+                ]
+            ))
+        );
+
+        let label = layers[2].label.as_ref().unwrap();
+        assert_eq!(label.field, "name");
+        assert_eq!(label.font, FontId::proportional(14.));
+        assert_eq!(label.color, Color32::from_rgb(0x33, 0x33, 0x33));
+        assert_eq!(label.halo, Some(Stroke::new(1., Color32::WHITE)));
+        assert_eq!(label.sort_key.as_deref(), Some("rank"));
+        // Above the other layers, including the left out ones.
+        assert_eq!(label.priority, 6);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_maplibre_style("{").is_err());
+        assert!(parse_maplibre_style(r#"{"layers": []}"#).is_err());
+        assert!(parse_maplibre_style(r#"{"sources": {}}"#).is_err());
+        assert!(parse_maplibre_style(r#"{"sources": {}, "layers": []}"#)
+            .unwrap()
+            .is_empty());
+    }
+
+    fn filter(json: &str) -> Result<Filter, String> {
+        parse_filter(&serde_json::from_str(json).unwrap())
+    }
+
+    fn class(value: &str) -> PropertyValue {
+        PropertyValue::String(value.to_owned())
+    }
+
+    #[test]
+    fn legacy_filters() {
+        assert_eq!(filter("true"), Ok(Filter::All(Vec::new())));
+        assert_eq!(
+            filter(r#"["has", "name"]"#),
+            Ok(Filter::Has("name".to_owned()))
+        );
+        assert_eq!(
+            filter(r#"["!has", "name"]"#),
+            Ok(Filter::Not(Box::new(Filter::Has("name".to_owned()))))
+        );
+        assert_eq!(
+            filter(r#"[">=", "rank", 3]"#),
+            Ok(Filter::Compare(
+                "rank".to_owned(),
+                Comparison::GreaterOrEqual,
+                PropertyValue::Number(3.)
+            ))
+        );
+        assert_eq!(
+            filter(r#"["!in", "class", "river", "canal"]"#),
+            Ok(Filter::Not(Box::new(Filter::In(
+                "class".to_owned(),
+                vec![class("river"), class("canal")]
+            ))))
+        );
+        assert_eq!(
+            filter(r#"["none", ["has", "a"]]"#),
+            Ok(Filter::Not(Box::new(Filter::Any(vec![Filter::Has(
+                "a".to_owned()
+            )]))))
+        );
+    }
+
+    #[test]
+    fn expression_filters() {
+        assert_eq!(
+            filter(r#"["==", ["geometry-type"], "Point"]"#),
+            Ok(Filter::Compare(
+                "$type".to_owned(),
+                Comparison::Equal,
+                class("Point")
+            ))
+        );
+        assert_eq!(
+            filter(r#"[">=", ["zoom"], 12]"#),
+            Ok(Filter::Compare(
+                "$zoom".to_owned(),
+                Comparison::GreaterOrEqual,
+                PropertyValue::Number(12.)
+            ))
+        );
+        assert_eq!(
+            filter(r#"["!", ["<", ["get", "rank"], 2]]"#),
+            Ok(Filter::Not(Box::new(Filter::Compare(
+                "rank".to_owned(),
+                Comparison::Less,
+                PropertyValue::Number(2.)
+            ))))
+        );
+        assert_eq!(
+            filter(
+                r#"["match", ["get", "class"], ["river", "canal"], true, "lake", false, false]"#
+            ),
+            Ok(Filter::In(
+                "class".to_owned(),
+                vec![class("river"), class("canal")]
+            ))
+        );
+        assert_eq!(
+            filter(r#"["match", ["get", "class"], "lake", false, true]"#),
+            Ok(Filter::Not(Box::new(Filter::In(
+                "class".to_owned(),
+                vec![class("lake")]
+            ))))
+        );
+
+        assert!(filter(r#"["within", {}]"#).is_err());
+        assert!(filter(r#"["==", ["get", "a"], ["get", "b"]]"#).is_err());
+        assert!(filter(r#"["match", ["get", "class"], "lake", "blue", "red"]"#).is_err());
+    }
+
+    fn number(json: &str) -> Result<Option<Zoomed<f32>>, String> {
+        number_property(Some(&serde_json::from_str(json).unwrap()))
+    }
+
+    #[test]
+    fn zoom_functions() {
+        assert_eq!(number("2"), Ok(Some(Zoomed::Constant(2.))));
+        assert_eq!(
+            number(r#"{"base": 1.2, "stops": [[5, 1], [10, 4]]}"#),
+            Ok(Some(Zoomed::Interpolate {
+                base: 1.2,
+                stops: vec![(5., 1.), (10., 4.)]
+            }))
+        );
+        assert_eq!(
+            number(r#"{"type": "interval", "stops": [[5, 1], [10, 4]]}"#),
+            Ok(Some(Zoomed::Step(vec![(5., 1.), (10., 4.)])))
+        );
+        assert_eq!(
+            number(r#"["interpolate", ["linear"], ["zoom"], 5, 1, 10, 4]"#),
+            Ok(Some(Zoomed::Interpolate {
+                base: 1.,
+                stops: vec![(5., 1.), (10., 4.)]
+            }))
+        );
+        assert_eq!(
+            number(r#"["step", ["zoom"], 1, 10, 4]"#),
+            Ok(Some(Zoomed::Step(vec![(f64::NEG_INFINITY, 1.), (10., 4.)])))
+        );
+
+        assert!(number(r#"{"property": "rank", "stops": [[1, 1]]}"#).is_err());
+        assert!(number(r#"["interpolate", ["linear"], ["get", "rank"], 1, 1]"#).is_err());
+        assert!(number(r#"["interpolate", ["linear"], ["zoom"], 1]"#).is_err());
+        assert!(number(r#""wide""#).is_err());
+    }
+
+    #[test]
+    fn opacity() {
+        let stops = Zoomed::Interpolate {
+            base: 1.,
+            stops: vec![(0., 0.), (10., 1.)],
+        };
+        assert_eq!(
+            with_opacity(Color32::WHITE.into(), Some(stops)),
+            Styled::Zoomed(Zoomed::Interpolate {
+                base: 1.,
+                stops: vec![(0., Color32::TRANSPARENT), (10., Color32::WHITE)]
+            })
+        );
+    }
+
+    fn styled_width(json: &str) -> Result<Option<Styled<f32>>, String> {
+        styled_number(Some(&serde_json::from_str(json).unwrap()))
+    }
+
+    #[test]
+    fn feature_expressions() {
+        assert_eq!(
+            styled_width(
+                r#"["match", ["get", "class"], "motorway", 4, ["primary", "trunk"], 2, 1]"#
+            ),
+            Ok(Some(Styled::Case(
+                vec![
+                    (
+                        Filter::In("class".to_owned(), vec![class("motorway")]),
+                        Zoomed::Constant(4.)
+                    ),
+                    (
+                        Filter::In("class".to_owned(), vec![class("primary"), class("trunk")]),
+                        Zoomed::Constant(2.)
+                    ),
+                ],
+                Zoomed::Constant(1.)
+            )))
+        );
+        assert_eq!(
+            styled_width(r#"["case", ["has", "bridge"], ["step", ["zoom"], 1, 12, 3], 1]"#),
+            Ok(Some(Styled::Case(
+                vec![(
+                    Filter::Has("bridge".to_owned()),
+                    Zoomed::Step(vec![(f64::NEG_INFINITY, 1.), (12., 3.)])
+                )],
+                Zoomed::Constant(1.)
+            )))
+        );
+        assert_eq!(styled_width("2"), Ok(Some(Styled::from(2.))));
+
+        assert!(styled_width(r#"["match", ["get", "class"], "motorway", 4]"#).is_err());
+        assert!(styled_width(r#"["case", ["within", {}], 4, 1]"#).is_err());
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#f00"), Some(Color32::RED));
+        assert_eq!(parse_color("#00FF00"), Some(Color32::from_rgb(0, 255, 0)));
+        assert_eq!(
+            parse_color("rgba(255, 255, 255, 0.5)"),
+            Some(Color32::from_rgba_unmultiplied(255, 255, 255, 128))
+        );
+        assert_eq!(parse_color("rgb(0,0,0)"), Some(Color32::BLACK));
+        assert_eq!(parse_color("hsl(0, 100%, 50%)"), Some(Color32::RED));
+        assert_eq!(
+            parse_color("hsla(240, 100%, 50%, 1)"),
+            Some(Color32::from_rgb(0, 0, 255))
+        );
+        assert_eq!(parse_color("hsl(120, 0%, 100%)"), Some(Color32::WHITE));
+        assert_eq!(parse_color("White"), Some(Color32::WHITE));
+        assert_eq!(parse_color("transparent"), Some(Color32::TRANSPARENT));
+
+        assert_eq!(parse_color("#12"), None);
+        assert_eq!(parse_color("rgb(1, 2)"), None);
+        assert_eq!(parse_color("hsl(1, 2, 3)"), None);
+        assert_eq!(parse_color("chartreuse-ish"), None);
+    }
+
+    #[test]
+    fn text_fields() {
+        let field = |json: &str| text_field(&serde_json::from_str(json).unwrap());
+        assert_eq!(field(r#""{name}""#), Ok("name".to_owned()));
+        assert_eq!(field(r#"["get", "name:en"]"#), Ok("name:en".to_owned()));
+        assert_eq!(
+            field(r#"["coalesce", ["get", "name:en"], ["get", "name"]]"#),
+            Ok("name:en".to_owned())
+        );
+        assert!(field(r#""{name} ({ele})""#).is_err());
+        assert!(field(r#""Summit""#).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Rendering of vector tiles, as opposed to the usual raster ones.
+
+mod expression;
+mod labels;
+mod layer;
+mod maplibre;
+mod mvt;
+
+use std::sync::Arc;
+
+use egui::Context;
+use futures::{
+    channel::mpsc::{channel, Receiver, Sender},
+    SinkExt, StreamExt,
+};
+
+use crate::{
+    download::{Fetcher, Repaint, MAX_PARALLEL_DOWNLOADS},
+    io::Runtime,
+    sources::{source_tile_id, Attribution, TileSource},
+    tiles::LoadedTiles,
+    HttpOptions, TileId,
+};
+
+pub use expression::{Comparison, Filter, Styled, Zoomed};
+pub use labels::LabelStyle;
+pub use layer::{LayerStyle, VectorStyle, VectorTileLayer};
+pub use maplibre::{parse_maplibre_style, InvalidStyle, StyleSource};
+pub use mvt::PropertyValue;
+pub(crate) use mvt::VectorTile;
+
+type TileResult = (TileId, Result<VectorTile, String>);
+
+/// Downloads and decodes [Mapbox Vector Tiles](https://github.com/mapbox/vector-tile-spec) of
+/// the source, which are then drawn with [`VectorTileLayer`]. Like [`crate::HttpTiles`], it must
+/// persist between frames, and has its own cache and IO thread.
+pub struct VectorTiles {
+    attribution: Attribution,
+    max_zoom: u8,
+
+    tiles: LoadedTiles<Arc<VectorTile>>,
+    request_tx: Sender<TileId>,
+    tile_rx: Receiver<TileResult>,
+
+    #[allow(dead_code)] // Significant Drop
+    runtime: Runtime,
+}
+
+impl VectorTiles {
+    /// Construct new [`VectorTiles`] with default [`HttpOptions`].
+    pub fn new<S>(source: S, egui_ctx: Context) -> Self
+    where
+        S: TileSource + Send + 'static,
+    {
+        Self::with_options(source, HttpOptions::default(), egui_ctx)
+    }
+
+    /// Construct new [`VectorTiles`] with supplied [`HttpOptions`]. Options specific to raster
+    /// tiles, like `post_process`, are ignored.
+    pub fn with_options<S>(source: S, http_options: HttpOptions, egui_ctx: Context) -> Self
+    where
+        S: TileSource + Send + 'static,
+    {
+        let (request_tx, request_rx) = channel(MAX_PARALLEL_DOWNLOADS);
+        let (tile_tx, tile_rx) = channel(MAX_PARALLEL_DOWNLOADS);
+        let attribution = source.attribution();
+        let max_zoom = source.max_zoom();
+
+        // IO thread does not touch egui, other than waking it up.
+        let ctx = egui_ctx.clone();
+        let repaint: Repaint = Box::new(move || ctx.request_repaint());
+
+        let runtime = Runtime::new(download_continuously(
+            source,
+            http_options,
+            request_rx,
+            tile_tx,
+            repaint,
+        ));
+
+        Self {
+            attribution,
+            max_zoom,
+            tiles: LoadedTiles::new(),
+            request_tx,
+            tile_rx,
+            runtime,
+        }
+    }
+
+    pub fn attribution(&self) -> Attribution {
+        self.attribution.clone()
+    }
+
+    pub fn max_zoom(&self) -> u8 {
+        self.max_zoom
+    }
+
+    /// Take tiles which failed to load since the last call, along with the reason.
+    pub fn take_errors(&mut self) -> Vec<(TileId, String)> {
+        self.tiles.take_errors()
+    }
+
+    /// Decoded tile, if already downloaded. Otherwise, it gets requested.
+    pub(crate) fn at(&mut self, tile_id: TileId) -> Option<Arc<VectorTile>> {
+        self.receive();
+
+        let request_tx = &mut self.request_tx;
+        self.tiles
+            .get_or_request(tile_id, || request_tx.try_send(tile_id).is_ok())
+    }
+
+    fn receive(&mut self) {
+        while let Ok((tile_id, result)) = self.tile_rx.try_recv() {
+            match result {
+                Ok(tile) => self.tiles.loaded(tile_id, Arc::new(tile)),
+                Err(error) => self.tiles.failed(tile_id, error),
+            }
+        }
+    }
+}
+
+async fn download_continuously<S>(
+    source: S,
+    http_options: HttpOptions,
+    request_rx: Receiver<TileId>,
+    tile_tx: Sender<TileResult>,
+    repaint: Repaint,
+) where
+    S: TileSource + Send + 'static,
+{
+    let mut fetcher = Fetcher::new(http_options);
+    fetcher.authorization = source.authorization();
+    let fetcher = &fetcher;
+
+    request_rx
+        .map(move |tile_id| {
+            let url = source_tile_id(&source, tile_id).map(|id| source.tile_url(id));
+            (tile_id, url)
+        })
+        .map(|(tile_id, url)| async move {
+            let Some(url) = url else {
+                return (tile_id, Err("tile is not on the map".to_owned()));
+            };
+            let result = match fetcher.download(&url).await {
+                Ok((data, _)) => mvt::decode(&data, tile_id).map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            if let Err(error) = &result {
+                log::warn!("{}: {}", url, error);
+            }
+            (tile_id, result)
+        })
+        .buffer_unordered(MAX_PARALLEL_DOWNLOADS)
+        .for_each(|result| {
+            let mut tile_tx = tile_tx.clone();
+            let repaint = &repaint;
+            async move {
+                // Main thread is gone if this fails, nothing to do about it.
+                let _ = tile_tx.send(result).await;
+                repaint();
+            }
+        })
+        .await;
+}
@@ -0,0 +1,745 @@
+//! Decoding of [Mapbox Vector Tiles](https://github.com/mapbox/vector-tile-spec).
+
+use std::f64::consts::PI;
+
+use egui::{pos2, Pos2};
+
+use crate::{
+    extras::{polylabel, triangulate},
+    Position, TileId,
+};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid vector tile: {0}")]
+pub(crate) struct InvalidVectorTile(&'static str);
+
+/// Decoded vector tile, with coordinates already converted to geographical positions.
+#[derive(Debug, Default)]
+pub(crate) struct VectorTile {
+    pub(crate) layers: Vec<Layer>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Layer {
+    pub(crate) name: String,
+    pub(crate) features: Vec<Feature>,
+    /// Names and values of properties, which features refer to by index.
+    keys: Vec<String>,
+    values: Vec<PropertyValue>,
+}
+
+impl Layer {
+    /// Value of the feature's property.
+    pub(crate) fn property(&self, feature: &Feature, key: &str) -> Option<&PropertyValue> {
+        feature
+            .tags
+            .iter()
+            .find(|(k, _)| self.keys.get(*k as usize).is_some_and(|k| k == key))
+            .and_then(|(_, v)| self.values.get(*v as usize))
+    }
+}
+
+#[cfg(test)]
+impl Layer {
+    /// Layer of a single feature, with given properties.
+    pub(crate) fn single(geometry: Geometry, properties: &[(&str, PropertyValue)]) -> Self {
+        Self {
+            name: "test".to_owned(),
+            features: vec![Feature {
+                geometry,
+                tags: (0..properties.len() as u32).map(|i| (i, i)).collect(),
+            }],
+            keys: properties.iter().map(|(key, _)| key.to_string()).collect(),
+            values: properties.iter().map(|(_, value)| value.clone()).collect(),
+        }
+    }
+}
+
+/// Value of a feature's property.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl std::fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyValue::String(value) => write!(f, "{value}"),
+            PropertyValue::Number(value) => write!(f, "{value}"),
+            PropertyValue::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Feature {
+    pub(crate) geometry: Geometry,
+    /// Indexes of the layer's keys and values.
+    tags: Vec<(u32, u32)>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Geometry {
+    Points(Vec<Position>),
+    Lines(Vec<Vec<Position>>),
+    Polygons(Vec<Polygon>),
+}
+
+/// Polygon with its holes, triangulated for filling.
+#[derive(Debug)]
+pub(crate) struct Polygon {
+    pub(crate) rings: Vec<Vec<Position>>,
+    pub(crate) vertices: Vec<Position>,
+    pub(crate) indices: Vec<u32>,
+    /// Where its label goes, inside the polygon.
+    pub(crate) label_anchor: Position,
+}
+
+/// Protobuf reader, just enough for vector tiles.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+/// Value of a protobuf field.
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// 32 or 64 bit value.
+    Fixed(u64),
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn varint(&mut self) -> Result<u64, InvalidVectorTile> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self
+                .data
+                .split_first()
+                .ok_or(InvalidVectorTile("truncated varint"))?;
+            self.data = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(InvalidVectorTile("varint too long"))
+    }
+
+    fn skip(&mut self, n: usize) -> Result<&'a [u8], InvalidVectorTile> {
+        if self.data.len() < n {
+            return Err(InvalidVectorTile("truncated field"));
+        }
+        let (skipped, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(skipped)
+    }
+
+    /// Little-endian value of given number of bytes.
+    fn fixed(&mut self, n: usize) -> Result<u64, InvalidVectorTile> {
+        Ok(self
+            .skip(n)?
+            .iter()
+            .rev()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+    }
+
+    /// Next field number along with its value.
+    fn field(&mut self) -> Option<Result<(u64, Field<'a>), InvalidVectorTile>> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        Some(self.next_field())
+    }
+
+    fn next_field(&mut self) -> Result<(u64, Field<'a>), InvalidVectorTile> {
+        let key = self.varint()?;
+        let value = match key & 7 {
+            0 => Field::Varint(self.varint()?),
+            1 => Field::Fixed(self.fixed(8)?),
+            2 => {
+                let length = self.varint()? as usize;
+                Field::Bytes(self.skip(length)?)
+            }
+            5 => Field::Fixed(self.fixed(4)?),
+            _ => return Err(InvalidVectorTile("unsupported wire type")),
+        };
+        Ok((key >> 3, value))
+    }
+
+    /// Packed repeated varints.
+    fn packed(data: &'a [u8]) -> Result<Vec<u32>, InvalidVectorTile> {
+        let mut reader = Reader::new(data);
+        let mut values = Vec::new();
+        while !reader.data.is_empty() {
+            values.push(reader.varint()? as u32);
+        }
+        Ok(values)
+    }
+}
+
+/// Decode the tile, placing its content within the area of given tile of the Web Mercator grid.
+pub(crate) fn decode(data: &[u8], tile_id: TileId) -> Result<VectorTile, InvalidVectorTile> {
+    let mut tile = VectorTile::default();
+    let mut reader = Reader::new(data);
+    while let Some(field) = reader.field() {
+        if let (3, Field::Bytes(layer)) = field? {
+            tile.layers.push(decode_layer(layer, tile_id)?);
+        }
+    }
+    Ok(tile)
+}
+
+fn decode_layer(data: &[u8], tile_id: TileId) -> Result<Layer, InvalidVectorTile> {
+    let mut name = String::new();
+    let mut extent = 4096;
+    let mut features = Vec::new();
+    let mut keys = Vec::new();
+    let mut values = Vec::new();
+
+    let mut reader = Reader::new(data);
+    while let Some(field) = reader.field() {
+        match field? {
+            (1, Field::Bytes(bytes)) => name = String::from_utf8_lossy(bytes).into_owned(),
+            (2, Field::Bytes(bytes)) => features.push(bytes),
+            (3, Field::Bytes(bytes)) => keys.push(String::from_utf8_lossy(bytes).into_owned()),
+            (4, Field::Bytes(bytes)) => values.push(decode_value(bytes)?),
+            (5, Field::Varint(value)) => extent = value as u32,
+            _ => {}
+        }
+    }
+
+    // Extent is only known once the whole layer is read.
+    let to_position = tile_to_position(tile_id, extent.max(1));
+    let features = features
+        .into_iter()
+        .filter_map(|feature| decode_feature(feature, &to_position).transpose())
+        .collect::<Result<_, _>>()?;
+
+    Ok(Layer {
+        name,
+        features,
+        keys,
+        values,
+    })
+}
+
+fn decode_value(data: &[u8]) -> Result<PropertyValue, InvalidVectorTile> {
+    let mut value = PropertyValue::String(String::new());
+    let mut reader = Reader::new(data);
+    while let Some(field) = reader.field() {
+        value = match field? {
+            (1, Field::Bytes(bytes)) => {
+                PropertyValue::String(String::from_utf8_lossy(bytes).into_owned())
+            }
+            (2, Field::Fixed(bits)) => PropertyValue::Number(f32::from_bits(bits as u32) as f64),
+            (3, Field::Fixed(bits)) => PropertyValue::Number(f64::from_bits(bits)),
+            (4, Field::Varint(int)) => PropertyValue::Number(int as i64 as f64),
+            (5, Field::Varint(uint)) => PropertyValue::Number(uint as f64),
+            (6, Field::Varint(sint)) => {
+                PropertyValue::Number(((sint >> 1) as i64 ^ -((sint & 1) as i64)) as f64)
+            }
+            (7, Field::Varint(bool)) => PropertyValue::Bool(bool != 0),
+            _ => continue,
+        };
+    }
+    Ok(value)
+}
+
+/// Function converting coordinates within the tile to geographical positions.
+fn tile_to_position(tile_id: TileId, extent: u32) -> impl Fn(Pos2) -> Position {
+    let scale = 2f64.powi(tile_id.zoom as i32) * extent as f64;
+    let (x0, y0) = (
+        tile_id.x as f64 * extent as f64,
+        tile_id.y as f64 * extent as f64,
+    );
+    move |point| {
+        let (x, y) = ((x0 + point.x as f64) / scale, (y0 + point.y as f64) / scale);
+        Position {
+            x: x * 360. - 180.,
+            y: (PI * (1. - 2. * y)).sinh().atan().to_degrees(),
+        }
+    }
+}
+
+fn decode_feature(
+    data: &[u8],
+    to_position: &impl Fn(Pos2) -> Position,
+) -> Result<Option<Feature>, InvalidVectorTile> {
+    let mut kind = 0;
+    let mut geometry = Vec::new();
+    let mut tags = Vec::new();
+
+    let mut reader = Reader::new(data);
+    while let Some(field) = reader.field() {
+        match field? {
+            (2, Field::Bytes(bytes)) => {
+                tags = Reader::packed(bytes)?
+                    .chunks_exact(2)
+                    .map(|tag| (tag[0], tag[1]))
+                    .collect();
+            }
+            (3, Field::Varint(value)) => kind = value,
+            (4, Field::Bytes(bytes)) => geometry = Reader::packed(bytes)?,
+            _ => {}
+        }
+    }
+
+    let rings = decode_geometry(&geometry)?;
+    let positions =
+        |ring: &[Pos2]| -> Vec<Position> { ring.iter().map(|p| to_position(*p)).collect() };
+
+    let geometry = match kind {
+        1 => Geometry::Points(rings.iter().flatten().map(|p| to_position(*p)).collect()),
+        2 => Geometry::Lines(rings.iter().map(|r| positions(r)).collect()),
+        3 => Geometry::Polygons(
+            group_polygons(rings)
+                .into_iter()
+                .map(|(exterior, holes)| {
+                    let mut all_rings = vec![positions(&exterior)];
+                    all_rings.extend(holes.iter().map(|hole| positions(hole)));
+                    let outline = bridge_holes(exterior, holes);
+                    let indices = if outline.len() >= 3 {
+                        triangulate(&outline)
+                    } else {
+                        Vec::new()
+                    };
+                    Polygon {
+                        label_anchor: label_anchor(&all_rings),
+                        rings: all_rings,
+                        vertices: positions(&outline),
+                        indices,
+                    }
+                })
+                .collect(),
+        ),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Feature { geometry, tags }))
+}
+
+/// Pole of inaccessibility of the polygon, found with a precision relative to its size.
+fn label_anchor(rings: &[Vec<Position>]) -> Position {
+    let Some((exterior, holes)) = rings.split_first() else {
+        return Position::default();
+    };
+    let (min, max) = exterior.iter().fold(
+        ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+        |(min, max), p| {
+            (
+                (min.0.min(p.x), min.1.min(p.y)),
+                (max.0.max(p.x), max.1.max(p.y)),
+            )
+        },
+    );
+    let precision = ((max.0 - min.0).max(max.1 - min.1) / 50.).max(f64::EPSILON);
+    let polygon = geo_types::Polygon::new(
+        geo_types::LineString(exterior.clone()),
+        holes
+            .iter()
+            .map(|hole| geo_types::LineString(hole.clone()))
+            .collect(),
+    );
+    polylabel(&polygon, precision)
+}
+
+/// Run the geometry commands, returning the drawn rings or lines, in tile coordinates.
+fn decode_geometry(commands: &[u32]) -> Result<Vec<Vec<Pos2>>, InvalidVectorTile> {
+    const MOVE_TO: u32 = 1;
+    const LINE_TO: u32 = 2;
+    const CLOSE_PATH: u32 = 7;
+
+    let zigzag = |value: u32| ((value >> 1) as i32) ^ -((value & 1) as i32);
+
+    let mut parts: Vec<Vec<Pos2>> = Vec::new();
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut commands = commands.iter();
+
+    while let Some(command) = commands.next() {
+        let (id, count) = (command & 7, command >> 3);
+        match id {
+            MOVE_TO | LINE_TO => {
+                for _ in 0..count {
+                    let (dx, dy) = (
+                        commands
+                            .next()
+                            .ok_or(InvalidVectorTile("truncated geometry"))?,
+                        commands
+                            .next()
+                            .ok_or(InvalidVectorTile("truncated geometry"))?,
+                    );
+                    x = x.wrapping_add(zigzag(*dx));
+                    y = y.wrapping_add(zigzag(*dy));
+                    if id == MOVE_TO {
+                        parts.push(Vec::new());
+                    }
+                    parts
+                        .last_mut()
+                        .ok_or(InvalidVectorTile("line without a start"))?
+                        .push(pos2(x as f32, y as f32));
+                }
+            }
+            CLOSE_PATH => {}
+            _ => return Err(InvalidVectorTile("unknown geometry command")),
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Twice the signed area of the ring. Positive for exterior rings, which go clockwise in tile
+/// coordinates, where y points down.
+fn signed_area(ring: &[Pos2]) -> f32 {
+    (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum()
+}
+
+/// Split rings into polygons, each being an exterior ring followed by its holes.
+fn group_polygons(rings: Vec<Vec<Pos2>>) -> Vec<(Vec<Pos2>, Vec<Vec<Pos2>>)> {
+    let mut polygons: Vec<(Vec<Pos2>, Vec<Vec<Pos2>>)> = Vec::new();
+    for ring in rings {
+        let area = signed_area(&ring);
+        if area > 0. {
+            polygons.push((ring, Vec::new()));
+        } else if area < 0. {
+            if let Some((_, holes)) = polygons.last_mut() {
+                holes.push(ring);
+            }
+        }
+    }
+    polygons
+}
+
+/// Join the holes to the exterior ring by zero-width cuts, producing a single ring which can be
+/// triangulated.
+fn bridge_holes(mut outline: Vec<Pos2>, mut holes: Vec<Vec<Pos2>>) -> Vec<Pos2> {
+    // Bridging the rightmost holes first keeps cuts from crossing each other.
+    let rightmost = |ring: &[Pos2]| {
+        (0..ring.len())
+            .max_by(|&a, &b| ring[a].x.total_cmp(&ring[b].x))
+            .unwrap_or(0)
+    };
+    holes.retain(|hole| !hole.is_empty());
+    holes.sort_by(|a, b| b[rightmost(b)].x.total_cmp(&a[rightmost(a)].x));
+
+    for hole in holes {
+        let start = rightmost(&hole);
+        let point = hole[start];
+        let Some(nearest) = (0..outline.len())
+            .filter(|&i| outline[i].x >= point.x)
+            .min_by(|&a, &b| {
+                outline[a]
+                    .distance_sq(point)
+                    .total_cmp(&outline[b].distance_sq(point))
+            })
+        else {
+            continue;
+        };
+
+        let mut bridged = Vec::with_capacity(outline.len() + hole.len() + 2);
+        bridged.extend_from_slice(&outline[..=nearest]);
+        bridged.extend(hole[start..].iter().chain(&hole[..=start]));
+        bridged.extend_from_slice(&outline[nearest..]);
+        outline = bridged;
+    }
+
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(buffer: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            buffer.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        buffer.push(value as u8);
+    }
+
+    fn bytes_field(buffer: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+        varint(buffer, field << 3 | 2);
+        varint(buffer, bytes.len() as u64);
+        buffer.extend_from_slice(bytes);
+    }
+
+    fn varint_field(buffer: &mut Vec<u8>, field: u64, value: u64) {
+        varint(buffer, field << 3);
+        varint(buffer, value);
+    }
+
+    fn zigzag(value: i32) -> u32 {
+        ((value << 1) ^ (value >> 31)) as u32
+    }
+
+    fn command(id: u32, count: u32) -> u32 {
+        id | count << 3
+    }
+
+    /// Geometry commands drawing the rings, each given by absolute coordinates.
+    fn geometry(parts: &[&[(i32, i32)]], close: bool) -> Vec<u32> {
+        let mut commands = Vec::new();
+        let mut cursor = (0, 0);
+        for part in parts {
+            for (i, &(x, y)) in part.iter().enumerate() {
+                if i == 0 {
+                    commands.push(command(1, 1));
+                } else if i == 1 {
+                    commands.push(command(2, part.len() as u32 - 1));
+                }
+                commands.extend([zigzag(x - cursor.0), zigzag(y - cursor.1)]);
+                cursor = (x, y);
+            }
+            if close {
+                commands.push(command(7, 1));
+            }
+        }
+        commands
+    }
+
+    fn packed(values: &[u32]) -> Vec<u8> {
+        let mut packed = Vec::new();
+        for value in values {
+            varint(&mut packed, *value as u64);
+        }
+        packed
+    }
+
+    fn feature(kind: u64, commands: &[u32]) -> Vec<u8> {
+        feature_with_tags(kind, commands, &[])
+    }
+
+    fn feature_with_tags(kind: u64, commands: &[u32], tags: &[u32]) -> Vec<u8> {
+        let packed = packed(commands);
+        let mut feature = Vec::new();
+        if !tags.is_empty() {
+            bytes_field(&mut feature, 2, &self::packed(tags));
+        }
+        varint_field(&mut feature, 3, kind);
+        bytes_field(&mut feature, 4, &packed);
+        feature
+    }
+
+    const SQUARE: [(i32, i32); 4] = [(0, 0), (100, 0), (100, 100), (0, 100)];
+    const HOLE: [(i32, i32); 4] = [(25, 25), (25, 75), (75, 75), (75, 25)];
+
+    /// Tile with one layer holding a point, a line and a polygon with a hole.
+    fn tile() -> Vec<u8> {
+        let mut layer = Vec::new();
+        bytes_field(&mut layer, 1, b"test");
+        bytes_field(
+            &mut layer,
+            2,
+            &feature_with_tags(1, &geometry(&[&[(2048, 2048)]], false), &[0, 0, 1, 1, 2, 2]),
+        );
+        bytes_field(
+            &mut layer,
+            2,
+            &feature(2, &geometry(&[&[(0, 0), (4096, 0), (4096, 4096)]], false)),
+        );
+        bytes_field(
+            &mut layer,
+            2,
+            &feature(3, &geometry(&[&SQUARE, &HOLE], true)),
+        );
+        for key in ["name", "rank", "capital"] {
+            bytes_field(&mut layer, 3, key.as_bytes());
+        }
+        let mut name = Vec::new();
+        bytes_field(&mut name, 1, "Null Island".as_bytes());
+        bytes_field(&mut layer, 4, &name);
+        let mut rank = Vec::new();
+        varint_field(&mut rank, 6, zigzag(-2) as u64);
+        bytes_field(&mut layer, 4, &rank);
+        let mut capital = Vec::new();
+        varint_field(&mut capital, 7, 1);
+        bytes_field(&mut layer, 4, &capital);
+        varint_field(&mut layer, 5, 4096);
+
+        let mut tile = Vec::new();
+        bytes_field(&mut tile, 3, &layer);
+        tile
+    }
+
+    /// Total area of the triangles.
+    fn triangles_area(points: &[Pos2], indices: &[u32]) -> f32 {
+        indices
+            .chunks(3)
+            .map(|t| {
+                let (a, b, c) = (
+                    points[t[0] as usize],
+                    points[t[1] as usize],
+                    points[t[2] as usize],
+                );
+                ((b - a).x * (c - a).y - (b - a).y * (c - a).x).abs() / 2.
+            })
+            .sum()
+    }
+
+    #[test]
+    fn reads_varints() {
+        let mut reader = Reader::new(&[0xac, 0x02, 0x01]);
+        assert_eq!(reader.varint(), Ok(300));
+        assert_eq!(reader.varint(), Ok(1));
+        assert!(reader.varint().is_err());
+        assert!(Reader::new(&[0x80]).varint().is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_fields() {
+        let mut data = Vec::new();
+        bytes_field(&mut data, 3, b"layer");
+        data.pop();
+        assert!(decode(
+            &data,
+            TileId {
+                x: 0,
+                y: 0,
+                zoom: 0
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn decodes_tile() {
+        let tile = decode(
+            &tile(),
+            TileId {
+                x: 0,
+                y: 0,
+                zoom: 0,
+            },
+        )
+        .unwrap();
+        assert_eq!(tile.layers.len(), 1);
+        let layer = &tile.layers[0];
+        assert_eq!(layer.name, "test");
+        assert_eq!(layer.features.len(), 3);
+
+        let Geometry::Points(points) = &layer.features[0].geometry else {
+            panic!("expected points");
+        };
+        assert_eq!(points.len(), 1);
+        assert!(points[0].x.abs() < 1e-9 && points[0].y.abs() < 1e-9);
+
+        let Geometry::Lines(lines) = &layer.features[1].geometry else {
+            panic!("expected lines");
+        };
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].len(), 3);
+        assert_eq!(lines[0][1].x, 180.);
+
+        let Geometry::Polygons(polygons) = &layer.features[2].geometry else {
+            panic!("expected polygons");
+        };
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].rings.len(), 2);
+        // Both rings, joined by a cut which repeats a vertex of each.
+        assert_eq!(polygons[0].vertices.len(), 10);
+        assert_eq!(polygons[0].indices.len(), 3 * 8);
+
+        // Label goes between the hole and the edge, rather than into the hole.
+        let anchor = polygons[0].label_anchor;
+        let (exterior, hole) = (&polygons[0].rings[0], &polygons[0].rings[1]);
+        let inside = |ring: &[Position]| {
+            let (min_x, max_x) = (
+                ring.iter().map(|p| p.x).fold(f64::MAX, f64::min),
+                ring.iter().map(|p| p.x).fold(f64::MIN, f64::max),
+            );
+            let (min_y, max_y) = (
+                ring.iter().map(|p| p.y).fold(f64::MAX, f64::min),
+                ring.iter().map(|p| p.y).fold(f64::MIN, f64::max),
+            );
+            (min_x..=max_x).contains(&anchor.x) && (min_y..=max_y).contains(&anchor.y)
+        };
+        assert!(inside(exterior) && !inside(hole));
+    }
+
+    #[test]
+    fn decodes_properties() {
+        let tile = decode(
+            &tile(),
+            TileId {
+                x: 0,
+                y: 0,
+                zoom: 0,
+            },
+        )
+        .unwrap();
+        let layer = &tile.layers[0];
+        let point = &layer.features[0];
+        assert_eq!(
+            layer.property(point, "name"),
+            Some(&PropertyValue::String("Null Island".to_owned()))
+        );
+        assert_eq!(
+            layer.property(point, "rank"),
+            Some(&PropertyValue::Number(-2.))
+        );
+        assert_eq!(
+            layer.property(point, "capital"),
+            Some(&PropertyValue::Bool(true))
+        );
+        assert_eq!(layer.property(point, "population"), None);
+        assert_eq!(layer.property(&layer.features[1], "name"), None);
+    }
+
+    #[test]
+    fn decodes_geometry() {
+        let parts = decode_geometry(&geometry(&[&SQUARE], true)).unwrap();
+        assert_eq!(
+            parts,
+            vec![SQUARE.map(|(x, y)| pos2(x as f32, y as f32)).to_vec()]
+        );
+
+        assert!(decode_geometry(&[command(1, 1), 2]).is_err());
+        assert!(decode_geometry(&[command(2, 1), 2, 2]).is_err());
+        assert!(decode_geometry(&[command(5, 1)]).is_err());
+    }
+
+    #[test]
+    fn groups_rings_into_polygons() {
+        let ring = |points: &[(i32, i32)], offset: i32| -> Vec<Pos2> {
+            points
+                .iter()
+                .map(|(x, y)| pos2((x + offset) as f32, *y as f32))
+                .collect()
+        };
+        let polygons = group_polygons(vec![ring(&SQUARE, 0), ring(&HOLE, 0), ring(&SQUARE, 200)]);
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[0].1.len(), 1);
+        assert!(polygons[1].1.is_empty());
+
+        // Hole comes before any exterior ring, so it is dropped.
+        assert!(group_polygons(vec![ring(&HOLE, 0)]).is_empty());
+    }
+
+    #[test]
+    fn bridges_holes() {
+        let square: Vec<Pos2> = SQUARE
+            .iter()
+            .map(|(x, y)| pos2(*x as f32, *y as f32))
+            .collect();
+        let hole: Vec<Pos2> = HOLE
+            .iter()
+            .map(|(x, y)| pos2(*x as f32, *y as f32))
+            .collect();
+        let outline = bridge_holes(square, vec![hole]);
+        assert_eq!(outline.len(), 10);
+        let area = triangles_area(&outline, &triangulate(&outline));
+        assert!((area - (100. * 100. - 50. * 50.)).abs() < 1e-3);
+    }
+}
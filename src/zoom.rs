@@ -1,4 +1,4 @@
-#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
 #[error("invalid zoom level")]
 pub struct InvalidZoom;
 